@@ -561,19 +561,37 @@ fn generate_bindings(config: &KAdm5Config, out_path: &Path) {
         .allowlist_function("kadm5_set_string")
         .allowlist_function("krb5_cc_close")
         .allowlist_function("krb5_cc_default")
+        .allowlist_function("krb5_cc_destroy")
+        .allowlist_function("krb5_cc_get_name")
         .allowlist_function("krb5_cc_get_principal")
+        .allowlist_function("krb5_cc_get_type")
+        .allowlist_function("krb5_cc_initialize")
+        .allowlist_function("krb5_cc_new_unique")
         .allowlist_function("krb5_cc_resolve")
+        .allowlist_function("krb5_cc_store_cred")
         .allowlist_function("krb5_enctype_to_string")
         .allowlist_function("krb5_free_context")
+        .allowlist_function("krb5_free_cred_contents")
         .allowlist_function("krb5_free_default_realm")
         .allowlist_function("krb5_free_error_message")
         .allowlist_function("krb5_free_principal")
         .allowlist_function("krb5_free_unparsed_name")
         .allowlist_function("krb5_get_default_realm")
         .allowlist_function("krb5_get_error_message")
+        .allowlist_function("krb5_get_init_creds_keytab")
+        .allowlist_function("krb5_get_init_creds_opt_alloc")
+        .allowlist_function("krb5_get_init_creds_opt_free")
+        .allowlist_function("krb5_get_init_creds_opt_set_renew_life")
+        .allowlist_function("krb5_get_init_creds_opt_set_tkt_life")
+        .allowlist_function("krb5_get_init_creds_password")
+        .allowlist_function("krb5_get_renewed_creds")
         .allowlist_function("krb5_init_context")
+        .allowlist_function("krb5_kt_close")
+        .allowlist_function("krb5_kt_default")
+        .allowlist_function("krb5_kt_resolve")
         .allowlist_function("krb5_parse_name")
         .allowlist_function("krb5_salttype_to_string")
+        .allowlist_function("krb5_set_default_realm")
         .allowlist_function("krb5_sname_to_principal")
         .allowlist_function("krb5_string_to_enctype")
         .allowlist_function("krb5_string_to_salttype")
@@ -596,6 +614,24 @@ fn generate_bindings(config: &KAdm5Config, out_path: &Path) {
             .allowlist_function("kadm5_randkey_principal_3");
     }
 
+    if config.variant.is_mit() {
+        builder = builder
+            .allowlist_function("kadm5_free_kadm5_key_data")
+            .allowlist_function("kadm5_get_principal_keys")
+            .allowlist_function("kadm5_setkey_principal_4")
+            .allowlist_var("KRB5_INIT_CONTEXT_.*")
+            .allowlist_function("krb5_get_init_creds_opt_set_fast_ccache_name")
+            .allowlist_function("krb5_init_context_profile")
+            .allowlist_function("krb5_init_secure_context")
+            .allowlist_function("krb5_set_trace_callback")
+            .allowlist_function("profile_init")
+            .allowlist_function("profile_release");
+    } else {
+        builder = builder
+            .allowlist_function("kadm5_setkey_principal_3")
+            .allowlist_function("krb5_set_config_files");
+    }
+
     for include_path in &config.include_paths {
         builder = builder.clang_arg(format!("-I{}", include_path.display()));
     }
@@ -44,10 +44,15 @@ class KAdmin:
     def list_principals(self, query: str | None = None) -> list[str]: ...
     def add_policy(self, name: str, **kwargs) -> Policy: ...
     def delete_policy(self, name: str) -> None: ...
+    def rename_policy(self, old_name: str, new_name: str) -> None: ...
+    def delete_policy_if_unused(self, name: str) -> None: ...
+    def policy_usage(self) -> dict[str, list[str]]: ...
+    def principals_using_policy(self, name: str) -> list[str]: ...
     def get_policy(self, name: str) -> Policy | None: ...
     def policy_exists(self, name: str) -> bool: ...
     def list_policies(self, query: str | None = None) -> list[str]: ...
     def get_privileges(self) -> int: ...
+    def with_timeout(self, timeout: datetime.timedelta | None) -> KAdmin: ...
     @staticmethod
     def with_password(
         variant: KAdm5Variant,
@@ -57,6 +62,7 @@ class KAdmin:
         db_args: DbArgs | None = None,
         api_version: KAdminApiVersion | None = None,
         library_path: str | None = None,
+        timeout: datetime.timedelta | None = None,
     ) -> KAdmin: ...
     @staticmethod
     def with_keytab(
@@ -67,6 +73,7 @@ class KAdmin:
         db_args: DbArgs | None = None,
         api_version: KAdminApiVersion | None = None,
         library_path: str | None = None,
+        timeout: datetime.timedelta | None = None,
     ) -> KAdmin: ...
     @staticmethod
     def with_ccache(
@@ -77,6 +84,7 @@ class KAdmin:
         db_args: DbArgs | None = None,
         api_version: KAdminApiVersion | None = None,
         library_path: str | None = None,
+        timeout: datetime.timedelta | None = None,
     ) -> KAdmin: ...
     @staticmethod
     def with_anonymous(
@@ -86,6 +94,7 @@ class KAdmin:
         db_args: DbArgs | None = None,
         api_version: KAdminApiVersion | None = None,
         library_path: str | None = None,
+        timeout: datetime.timedelta | None = None,
     ) -> KAdmin: ...
     @staticmethod
     def with_local(
@@ -94,6 +103,7 @@ class KAdmin:
         db_args: DbArgs | None = None,
         api_version: KAdminApiVersion | None = None,
         library_path: str | None = None,
+        timeout: datetime.timedelta | None = None,
     ) -> KAdmin: ...
 
 @final
@@ -182,6 +192,12 @@ class Policy:
 
     def modify(self, kadmin: KAdmin, **kwargs) -> Policy: ...
     def delete(self, kadmin: KAdmin) -> None: ...
+    def validate_password(
+        self,
+        password: str,
+        principal_name: str | None = None,
+        dict_file: str | None = None,
+    ) -> None: ...
 
 @final
 class Params:
@@ -195,6 +211,28 @@ class Params:
         acl_file: str | None = None,
         dict_file: str | None = None,
         stash_file: str | None = None,
+        mkey_name: str | None = None,
+        mkey_from_kbd: bool | None = None,
+        enctype: EncryptionType | None = None,
+        max_life: datetime.timedelta | None = None,
+        max_rlife: datetime.timedelta | None = None,
+        expiration: datetime.datetime | None = None,
+        flags: int | None = None,
+        keysalts: KeySalts | None = None,
+        kvno: int | None = None,
+        iprop_enabled: bool | None = None,
+        iprop_ulogsize: int | None = None,
+        iprop_poll_time: datetime.timedelta | None = None,
+        iprop_logfile: str | None = None,
+        iprop_port: int | None = None,
+        iprop_resync_timeout: datetime.timedelta | None = None,
+        kadmind_listen: str | None = None,
+        kpasswd_listen: str | None = None,
+        iprop_listen: str | None = None,
+        kdc_ports: str | None = None,
+        kdc_tcp_ports: str | None = None,
+        readonly_admin_server: str | None = None,
+        readonly_kadmind_port: int | None = None,
     ): ...
 
 @final
@@ -0,0 +1,453 @@
+//! [`Batch`] of operations applied with rollback
+
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::policy::{PolicyBuilder, PolicyModifier};
+use crate::{
+    error::{Error, Result},
+    kadmin::KAdminImpl,
+    keysalt::Key,
+    principal::{PrincipalBuilder, PrincipalModifier},
+};
+
+/// Operation queued in a [`Batch`]
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum BatchOperation {
+    /// See [`KAdminImpl::add_principal`]. Undone by deleting the principal
+    AddPrincipal(PrincipalBuilder),
+    /// See [`KAdminImpl::modify_principal`]. Undone by restoring the modified fields from a
+    /// snapshot of the principal taken before the modification
+    ModifyPrincipal(PrincipalModifier),
+    /// See [`KAdminImpl::rename_principal`]. Undone by renaming the principal back
+    RenamePrincipal {
+        /// Current name of the principal
+        old_name: String,
+        /// New name of the principal
+        new_name: String,
+    },
+    /// See [`KAdminImpl::delete_principal`]. Cannot be undone, as the keys of the principal are
+    /// lost
+    DeletePrincipal(String),
+    /// See [`KAdminImpl::principal_change_password`]. Cannot be undone
+    ChangePassword {
+        /// Name of the principal
+        name: String,
+        /// New password, zeroed on drop and left out of the `Debug` output
+        password: Zeroizing<String>,
+    },
+    /// See [`KAdminImpl::principal_randkey`]. Cannot be undone
+    Randkey(String),
+    /// See [`KAdminImpl::principal_set_keys`]. Cannot be undone
+    SetKeys {
+        /// Name of the principal
+        name: String,
+        /// Whether to keep the existing keys
+        keepold: bool,
+        /// New keys
+        keys: Vec<Key>,
+    },
+    #[cfg(any(mit_client, mit_server))]
+    /// See [`KAdminImpl::principal_set_string`]. Undone by restoring the previous value of the
+    /// string
+    ///
+    /// Only available for MIT variants
+    SetString {
+        /// Name of the principal
+        name: String,
+        /// Key of the string attribute
+        key: String,
+        /// New value of the string attribute, `None` to remove it
+        value: Option<String>,
+    },
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// See [`KAdminImpl::add_policy`]. Undone by deleting the policy
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    AddPolicy(PolicyBuilder),
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// See [`KAdminImpl::modify_policy`]. Undone by restoring the modified fields from a snapshot
+    /// of the policy taken before the modification
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    ModifyPolicy(PolicyModifier),
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// See [`KAdminImpl::delete_policy`]. Undone by re-creating the policy from a snapshot taken
+    /// before the deletion
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    DeletePolicy(String),
+}
+
+impl fmt::Debug for BatchOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddPrincipal(builder) => f.debug_tuple("AddPrincipal").field(builder).finish(),
+            Self::ModifyPrincipal(modifier) => {
+                f.debug_tuple("ModifyPrincipal").field(modifier).finish()
+            }
+            Self::RenamePrincipal { old_name, new_name } => f
+                .debug_struct("RenamePrincipal")
+                .field("old_name", old_name)
+                .field("new_name", new_name)
+                .finish(),
+            Self::DeletePrincipal(name) => f.debug_tuple("DeletePrincipal").field(name).finish(),
+            Self::ChangePassword { name, .. } => f
+                .debug_struct("ChangePassword")
+                .field("name", name)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::Randkey(name) => f.debug_tuple("Randkey").field(name).finish(),
+            Self::SetKeys {
+                name,
+                keepold,
+                keys,
+            } => f
+                .debug_struct("SetKeys")
+                .field("name", name)
+                .field("keepold", keepold)
+                .field("keys", keys)
+                .finish(),
+            #[cfg(any(mit_client, mit_server))]
+            Self::SetString { name, key, value } => f
+                .debug_struct("SetString")
+                .field("name", name)
+                .field("key", key)
+                .field("value", value)
+                .finish(),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::AddPolicy(builder) => f.debug_tuple("AddPolicy").field(builder).finish(),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::ModifyPolicy(modifier) => f.debug_tuple("ModifyPolicy").field(modifier).finish(),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::DeletePolicy(name) => f.debug_tuple("DeletePolicy").field(name).finish(),
+        }
+    }
+}
+
+impl BatchOperation {
+    /// Whether this operation can be undone if a later operation of the batch fails
+    pub fn is_reversible(&self) -> bool {
+        !matches!(
+            self,
+            Self::DeletePrincipal(_)
+                | Self::ChangePassword { .. }
+                | Self::Randkey(_)
+                | Self::SetKeys { .. }
+        )
+    }
+
+    /// Apply this operation, and return how to undo it
+    fn apply<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<Undo> {
+        Ok(match self {
+            Self::AddPrincipal(builder) => {
+                kadmin.add_principal(builder)?;
+                Undo::DeletePrincipal(builder.name.clone())
+            }
+            Self::ModifyPrincipal(modifier) => {
+                let snapshot = kadmin.get_principal(&modifier.name)?;
+                kadmin.modify_principal(modifier)?;
+                snapshot.map_or(Undo::Nothing, |snapshot| {
+                    Undo::ModifyPrincipal(modifier.restoring(&snapshot))
+                })
+            }
+            Self::RenamePrincipal { old_name, new_name } => {
+                kadmin.rename_principal(old_name, new_name)?;
+                Undo::RenamePrincipal {
+                    old_name: new_name.clone(),
+                    new_name: old_name.clone(),
+                }
+            }
+            Self::DeletePrincipal(name) => {
+                kadmin.delete_principal(name)?;
+                Undo::Irreversible
+            }
+            Self::ChangePassword { name, password } => {
+                kadmin.principal_change_password(
+                    name,
+                    password,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                )?;
+                Undo::Irreversible
+            }
+            Self::Randkey(name) => {
+                kadmin.principal_randkey(
+                    name,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                )?;
+                Undo::Irreversible
+            }
+            Self::SetKeys {
+                name,
+                keepold,
+                keys,
+            } => {
+                kadmin.principal_set_keys(name, *keepold, keys)?;
+                Undo::Irreversible
+            }
+            #[cfg(any(mit_client, mit_server))]
+            Self::SetString { name, key, value } => {
+                let previous = kadmin.principal_get_strings(name)?.remove(key);
+                kadmin.principal_set_string(name, key, value.as_deref())?;
+                Undo::SetString {
+                    name: name.clone(),
+                    key: key.clone(),
+                    value: previous,
+                }
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::AddPolicy(builder) => {
+                kadmin.add_policy(builder)?;
+                Undo::DeletePolicy(builder.name.clone())
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::ModifyPolicy(modifier) => {
+                let snapshot = kadmin.get_policy(&modifier.name)?;
+                kadmin.modify_policy(modifier)?;
+                snapshot.map_or(Undo::Nothing, |snapshot| {
+                    Undo::ModifyPolicy(modifier.restoring(&snapshot))
+                })
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::DeletePolicy(name) => {
+                let snapshot = kadmin.get_policy(name)?;
+                kadmin.delete_policy(name)?;
+                snapshot.map_or(Undo::Nothing, |snapshot| {
+                    Undo::AddPolicy(PolicyBuilder::from_policy(&snapshot))
+                })
+            }
+        })
+    }
+}
+
+/// Inverse of an applied [`BatchOperation`]
+enum Undo {
+    Nothing,
+    Irreversible,
+    DeletePrincipal(String),
+    ModifyPrincipal(PrincipalModifier),
+    RenamePrincipal {
+        old_name: String,
+        new_name: String,
+    },
+    #[cfg(any(mit_client, mit_server))]
+    SetString {
+        name: String,
+        key: String,
+        value: Option<String>,
+    },
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    AddPolicy(PolicyBuilder),
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    ModifyPolicy(PolicyModifier),
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    DeletePolicy(String),
+}
+
+impl Undo {
+    fn apply<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<()> {
+        match self {
+            Self::Nothing | Self::Irreversible => Ok(()),
+            Self::DeletePrincipal(name) => kadmin.delete_principal(name),
+            Self::ModifyPrincipal(modifier) => kadmin.modify_principal(modifier),
+            Self::RenamePrincipal { old_name, new_name } => {
+                kadmin.rename_principal(old_name, new_name)
+            }
+            #[cfg(any(mit_client, mit_server))]
+            Self::SetString { name, key, value } => {
+                kadmin.principal_set_string(name, key, value.as_deref())
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::AddPolicy(builder) => kadmin.add_policy(builder),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::ModifyPolicy(modifier) => kadmin.modify_policy(modifier),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::DeletePolicy(name) => kadmin.delete_policy(name),
+        }
+    }
+}
+
+/// Queue of principal and policy operations, applied in order
+///
+/// If an operation fails, the operations applied before it are undone in reverse order, and
+/// [`Error::BatchFailed`] is returned. Some operations cannot be undone, see
+/// [`BatchOperation::is_reversible`] and [`Batch::irreversible`] to check for them before applying
+/// the batch.
+///
+/// ```no_run
+/// # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant, Principal, Policy, batch::Batch};
+/// # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+/// #     .with_ccache(None, None)
+/// #     .unwrap();
+/// let batch = Batch::new()
+///     .add_policy(Policy::builder("team").password_min_length(12))
+///     .add_principal(Principal::builder("alice").policy(Some("team")))
+///     .add_principal(Principal::builder("bob").policy(Some("team")));
+/// assert!(batch.irreversible().is_empty());
+/// batch.apply(&kadmin).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    operations: Vec<BatchOperation>,
+}
+
+impl Batch {
+    /// Construct a new empty [`Batch`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an operation
+    pub fn push(mut self, operation: BatchOperation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Queue the creation of a principal
+    pub fn add_principal(self, builder: PrincipalBuilder) -> Self {
+        self.push(BatchOperation::AddPrincipal(builder))
+    }
+
+    /// Queue the modification of a principal
+    pub fn modify_principal(self, modifier: PrincipalModifier) -> Self {
+        self.push(BatchOperation::ModifyPrincipal(modifier))
+    }
+
+    /// Queue the renaming of a principal
+    pub fn rename_principal(self, old_name: &str, new_name: &str) -> Self {
+        self.push(BatchOperation::RenamePrincipal {
+            old_name: old_name.to_owned(),
+            new_name: new_name.to_owned(),
+        })
+    }
+
+    /// Queue the deletion of a principal. Cannot be undone
+    pub fn delete_principal(self, name: &str) -> Self {
+        self.push(BatchOperation::DeletePrincipal(name.to_owned()))
+    }
+
+    /// Queue a password change for a principal. Cannot be undone
+    pub fn change_password(self, name: &str, password: &str) -> Self {
+        self.push(BatchOperation::ChangePassword {
+            name: name.to_owned(),
+            password: Zeroizing::new(password.to_owned()),
+        })
+    }
+
+    /// Queue setting the key of a principal to a random value. Cannot be undone
+    pub fn randkey(self, name: &str) -> Self {
+        self.push(BatchOperation::Randkey(name.to_owned()))
+    }
+
+    /// Queue setting the keys of a principal. Cannot be undone
+    pub fn set_keys(self, name: &str, keepold: bool, keys: &[Key]) -> Self {
+        self.push(BatchOperation::SetKeys {
+            name: name.to_owned(),
+            keepold,
+            keys: keys.to_vec(),
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Queue setting a string attribute on a principal. Pass `None` as `value` to remove it
+    ///
+    /// Only available for MIT variants
+    pub fn set_string(self, name: &str, key: &str, value: Option<&str>) -> Self {
+        self.push(BatchOperation::SetString {
+            name: name.to_owned(),
+            key: key.to_owned(),
+            value: value.map(String::from),
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Queue the creation of a policy
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    pub fn add_policy(self, builder: PolicyBuilder) -> Self {
+        self.push(BatchOperation::AddPolicy(builder))
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Queue the modification of a policy
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    pub fn modify_policy(self, modifier: PolicyModifier) -> Self {
+        self.push(BatchOperation::ModifyPolicy(modifier))
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Queue the deletion of a policy
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    pub fn delete_policy(self, name: &str) -> Self {
+        self.push(BatchOperation::DeletePolicy(name.to_owned()))
+    }
+
+    /// Queued operations
+    pub fn operations(&self) -> &[BatchOperation] {
+        &self.operations
+    }
+
+    /// Queued operations that cannot be undone, with their index in the batch
+    pub fn irreversible(&self) -> Vec<(usize, &BatchOperation)> {
+        self.operations
+            .iter()
+            .enumerate()
+            .filter(|(_, operation)| !operation.is_reversible())
+            .collect()
+    }
+
+    /// Apply the queued operations in order
+    ///
+    /// If an operation fails, the ones applied before it are undone in reverse order and
+    /// [`Error::BatchFailed`] is returned
+    pub fn apply<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<()> {
+        let mut applied = Vec::with_capacity(self.operations.len());
+        for (index, operation) in self.operations.iter().enumerate() {
+            match operation.apply(kadmin) {
+                Ok(undo) => applied.push(undo),
+                Err(source) => {
+                    let mut rollback_errors = Vec::new();
+                    let mut irreversible = Vec::new();
+                    for (done, undo) in applied.iter().enumerate().rev() {
+                        if matches!(undo, Undo::Irreversible) {
+                            irreversible.push(done);
+                        } else if let Err(err) = undo.apply(kadmin) {
+                            rollback_errors.push((done, err));
+                        }
+                    }
+                    return Err(Error::BatchFailed {
+                        index,
+                        source: Box::new(source),
+                        rollback_errors,
+                        irreversible,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_password() {
+        let batch = Batch::new().change_password("alice", "hunter2");
+        let debug = format!("{batch:?}");
+        assert!(debug.contains("alice"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
@@ -0,0 +1,153 @@
+//! Helpers shared by the command-line tools
+
+use std::io::{self, IsTerminal, Write};
+
+#[cfg(any(mit_client, mit_server))]
+use kadmin::DbArgs;
+use kadmin::{KAdm5Variant, KAdmin, Params};
+use zeroize::Zeroizing;
+
+/// How to connect and authenticate to a realm
+#[derive(Default)]
+pub(crate) struct Connection {
+    /// Defaults to the first client variant available, or server variant when `local` is set
+    pub(crate) variant: Option<KAdm5Variant>,
+    pub(crate) realm: Option<String>,
+    pub(crate) admin_server: Option<String>,
+    pub(crate) principal: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) use_keytab: bool,
+    pub(crate) keytab: Option<String>,
+    pub(crate) ccache: Option<String>,
+    /// Whether to prompt for the password of `principal` when no password, keytab or credentials
+    /// cache is given, like kadmin
+    pub(crate) prompt_password: bool,
+    /// Whether to access the local database instead of authenticating. When unset, server-side
+    /// variants access it unless an authentication option is given
+    pub(crate) local: Option<bool>,
+    #[cfg(any(mit_client, mit_server))]
+    pub(crate) db_args: Vec<String>,
+}
+
+impl Connection {
+    pub(crate) fn connect(&self) -> Result<KAdmin, String> {
+        let variant = match self.variant {
+            Some(variant) => variant,
+            None => {
+                let candidates = if self.local == Some(true) {
+                    ["mit_server", "heimdal_server"]
+                } else {
+                    ["mit_client", "heimdal_client"]
+                };
+                candidates
+                    .iter()
+                    .find_map(|variant| variant.parse().ok())
+                    .ok_or("no default variant available")?
+            }
+        };
+        let mut builder = KAdmin::builder(variant);
+        if self.realm.is_some() || self.admin_server.is_some() {
+            let mut params = Params::new();
+            if let Some(realm) = &self.realm {
+                params = params.realm(realm);
+            }
+            if let Some(admin_server) = &self.admin_server {
+                params = params.admin_server(admin_server);
+            }
+            builder = builder.params(params);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(db_args) = db_args(&self.db_args).map_err(|err| err.to_string())? {
+            builder = builder.db_args(db_args);
+        }
+
+        let authenticate = self.password.is_some()
+            || self.use_keytab
+            || self.keytab.is_some()
+            || self.ccache.is_some()
+            || self.principal.is_some();
+        let local = self.local.unwrap_or(!authenticate && !variant.is_client());
+        let kadmin = if local {
+            builder.with_local()
+        } else if let Some(password) = &self.password {
+            let principal = self
+                .principal
+                .as_deref()
+                .ok_or("a password requires a principal")?;
+            builder.with_password(principal, password)
+        } else if self.use_keytab || self.keytab.is_some() {
+            builder.with_keytab(self.principal.as_deref(), self.keytab.as_deref())
+        } else if let (true, Some(principal), None) =
+            (self.prompt_password, &self.principal, &self.ccache)
+        {
+            let password = Zeroizing::new(
+                read_password(&format!("Password for {principal}: "))
+                    .map_err(|err| err.to_string())?,
+            );
+            builder.with_password(principal, &password)
+        } else {
+            builder.with_ccache(self.principal.as_deref(), self.ccache.as_deref())
+        };
+        kadmin.map_err(|err| err.to_string())
+    }
+}
+
+/// Read a line from the standard input, after printing `prompt` on the standard error
+pub(crate) fn read_line(prompt: &str) -> io::Result<String> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "unexpected end of input",
+        ));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+/// Like [`read_line`], without echoing what is typed if the standard input is a terminal
+pub(crate) fn read_password(prompt: &str) -> io::Result<String> {
+    if !io::stdin().is_terminal() {
+        return read_line(prompt);
+    }
+    let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: the standard input is a terminal, and termios is only read if tcgetattr filled it
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) } != 0 {
+        return read_line(prompt);
+    }
+    let saved = unsafe { termios.assume_init() };
+    let mut noecho = saved;
+    noecho.c_lflag &= !libc::ECHO;
+    let _restore = RestoreTermios(saved);
+    unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &noecho) };
+    let password = read_line(prompt);
+    eprintln!();
+    password
+}
+
+/// Terminal attributes of the standard input, restored when dropped
+struct RestoreTermios(libc::termios);
+
+impl Drop for RestoreTermios {
+    fn drop(&mut self) {
+        // SAFETY: the attributes were read from the standard input with tcgetattr
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0) };
+    }
+}
+
+/// Build database arguments from `NAME[=VALUE]` strings, `None` if there are none
+#[cfg(any(mit_client, mit_server))]
+pub(crate) fn db_args(args: &[String]) -> kadmin::error::Result<Option<DbArgs>> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = DbArgs::builder();
+    for arg in args {
+        builder = match arg.split_once('=') {
+            Some((name, value)) => builder.arg(name, Some(value)),
+            None => builder.arg(arg, None),
+        };
+    }
+    Ok(Some(builder.build()?))
+}
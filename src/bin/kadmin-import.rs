@@ -0,0 +1,147 @@
+//! Create or update principals from a CSV file
+//!
+//! Run `kadmin-import --help` for usage.
+
+use std::{
+    env,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use kadmin::{
+    KAdmin,
+    import::{Import, ImportAction},
+};
+
+use crate::common::Connection;
+
+mod common;
+
+const USAGE: &str = "\
+Usage: kadmin-import --variant VARIANT [OPTIONS] FILE
+
+Create principals from the CSV FILE, or from the standard input if FILE is -.
+
+The first line of FILE names its columns: name, and optionally password, policy, expire_time,
+attributes and strings.
+
+Connection options:
+  --variant VARIANT           mit_client, mit_server, heimdal_client or heimdal_server
+  --principal NAME            Principal to authenticate as
+  --keytab PATH               Authenticate with this keytab
+  --ccache NAME               Authenticate with this credentials cache
+  --password-env VAR          Authenticate with the password in this environment variable
+  --realm REALM               Realm to administer
+  --admin-server HOST         Admin server to connect to
+Without any authentication option, server-side variants access the local database and
+client-side ones use the default credentials cache.
+
+Import options:
+  --update                    Update principals that already exist
+  -h, --help                  Show this help
+";
+
+#[derive(Default)]
+struct Args {
+    connection: Connection,
+    password_env: Option<String>,
+    update: bool,
+    file: Option<String>,
+}
+
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut args = Args::default();
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        let mut value = || argv.next().ok_or_else(|| format!("{arg} requires a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--variant" => {
+                let value = value()?;
+                args.connection.variant = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("unknown or unsupported variant {value}"))?,
+                );
+            }
+            "--principal" => args.connection.principal = Some(value()?),
+            "--keytab" => args.connection.keytab = Some(value()?),
+            "--ccache" => args.connection.ccache = Some(value()?),
+            "--password-env" => args.password_env = Some(value()?),
+            "--realm" => args.connection.realm = Some(value()?),
+            "--admin-server" => args.connection.admin_server = Some(value()?),
+            "--update" => args.update = true,
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown argument {arg}"));
+            }
+            _ if args.file.is_some() => return Err(format!("unexpected argument {arg}")),
+            _ => args.file = Some(arg),
+        }
+    }
+    Ok(Some(args))
+}
+
+fn connect(mut args: Args) -> Result<KAdmin, String> {
+    if args.connection.variant.is_none() {
+        return Err("--variant is required".to_owned());
+    }
+    if let Some(var) = &args.password_env {
+        if args.connection.principal.is_none() {
+            return Err("--password-env requires --principal".to_owned());
+        }
+        let password = env::var(var).map_err(|err| format!("failed to read {var}: {err}"))?;
+        args.connection.password = Some(password);
+    }
+    args.connection
+        .connect()
+        .map_err(|err| format!("failed to connect: {err}"))
+}
+
+fn run(args: Args) -> Result<bool, String> {
+    let file = args.file.as_deref().ok_or("FILE is required")?;
+    let import = if file == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .map_err(|err| format!("failed to read the standard input: {err}"))?;
+        contents.parse::<Import>()
+    } else {
+        Import::from_file(file)
+    }
+    .map_err(|err| format!("failed to read {file}: {err}"))?
+    .update(args.update);
+
+    let kadmin = connect(args)?;
+    let report = import.run(&kadmin);
+    for row in &report.rows {
+        match &row.result {
+            Ok(ImportAction::Created) => println!("line {}: {}: created", row.line, row.name),
+            Ok(ImportAction::Updated) => println!("line {}: {}: updated", row.line, row.name),
+            Ok(_) => println!("line {}: {}: done", row.line, row.name),
+            Err(err) => eprintln!("line {}: {}: failed: {err}", row.line, row.name),
+        }
+    }
+    Ok(report.is_success())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("kadmin-import: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("kadmin-import: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Copy principals, keys and policies from a source realm to a destination realm
+//!
+//! Run `kadmin-migrate --help` for usage.
+
+use std::{env, fs, process::ExitCode};
+
+use kadmin::{KAdmin, migrate::Migration};
+
+use crate::common::Connection;
+
+mod common;
+
+const USAGE: &str = "\
+Usage: kadmin-migrate --source VARIANT --destination VARIANT [OPTIONS]
+
+Copy principals, keys and policies from a source realm to a destination realm.
+
+Connection options, where SIDE is either source or destination:
+  --SIDE VARIANT              mit_client, mit_server, heimdal_client or heimdal_server
+  --SIDE-principal NAME       Principal to authenticate as
+  --SIDE-keytab PATH          Authenticate with this keytab
+  --SIDE-ccache NAME          Authenticate with this credentials cache
+  --SIDE-password-env VAR     Authenticate with the password in this environment variable
+  --SIDE-realm REALM          Realm to administer
+  --SIDE-admin-server HOST    Admin server to connect to
+Without any authentication option, server-side variants access the local database and
+client-side ones use the default credentials cache.
+
+Migration options:
+  --include GLOB              Only copy principals matching GLOB, can be repeated
+  --exclude GLOB              Don't copy principals matching GLOB, can be repeated
+  --internal                  Also copy realm-internal principals, like krbtgt/*
+  --no-policies               Don't copy policies
+  --no-keys                   Don't copy keys, give every principal random ones
+  --state FILE                Record progress in FILE, and resume from it
+  --reissue FILE              Write the principals needing new credentials to FILE
+  -h, --help                  Show this help
+";
+
+/// How to connect to one of the realms
+#[derive(Default)]
+struct Side {
+    connection: Connection,
+    password_env: Option<String>,
+}
+
+impl Side {
+    /// Handle `--<side>` and `--<side>-<option>` arguments, returning whether `option` was one
+    fn parse(
+        &mut self,
+        option: &str,
+        value: impl FnOnce() -> Result<String, String>,
+    ) -> Result<bool, String> {
+        match option {
+            "" => {
+                let value = value()?;
+                self.connection.variant = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("unknown or unsupported variant {value}"))?,
+                );
+            }
+            "-principal" => self.connection.principal = Some(value()?),
+            "-keytab" => self.connection.keytab = Some(value()?),
+            "-ccache" => self.connection.ccache = Some(value()?),
+            "-password-env" => self.password_env = Some(value()?),
+            "-realm" => self.connection.realm = Some(value()?),
+            "-admin-server" => self.connection.admin_server = Some(value()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn connect(mut self, name: &str) -> Result<KAdmin, String> {
+        if self.connection.variant.is_none() {
+            return Err(format!("--{name} is required"));
+        }
+        if let Some(var) = &self.password_env {
+            if self.connection.principal.is_none() {
+                return Err(format!("--{name}-password-env requires --{name}-principal"));
+            }
+            let password =
+                env::var(var).map_err(|err| format!("failed to read {var} for {name}: {err}"))?;
+            self.connection.password = Some(password);
+        }
+        self.connection
+            .connect()
+            .map_err(|err| format!("failed to connect to the {name}: {err}"))
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    source: Side,
+    destination: Side,
+    migration: Migration,
+    reissue: Option<String>,
+}
+
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut args = Args::default();
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        let mut value = || argv.next().ok_or_else(|| format!("{arg} requires a value"));
+        if let Some(option) = arg.strip_prefix("--source") {
+            if args.source.parse(option, &mut value)? {
+                continue;
+            }
+        } else if let Some(option) = arg.strip_prefix("--destination") {
+            if args.destination.parse(option, &mut value)? {
+                continue;
+            }
+        }
+        args.migration = match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--include" => args.migration.include(&value()?),
+            "--exclude" => args.migration.exclude(&value()?),
+            "--internal" => args.migration.internal(true),
+            "--no-policies" => args.migration.policies(false),
+            "--no-keys" => args.migration.keys(false),
+            "--state" => args.migration.state_file(value()?),
+            "--reissue" => {
+                args.reissue = Some(value()?);
+                args.migration
+            }
+            _ => return Err(format!("unknown argument {arg}")),
+        };
+    }
+    Ok(Some(args))
+}
+
+fn run(args: Args) -> Result<bool, String> {
+    let source = args.source.connect("source")?;
+    let destination = args.destination.connect("destination")?;
+    let report = args
+        .migration
+        .run(&source, &destination)
+        .map_err(|err| format!("migration aborted: {err}"))?;
+
+    println!(
+        "Copied {} policies and {} principals, skipped {} already copied",
+        report.policies.len(),
+        report.principals.len(),
+        report.skipped
+    );
+    if !report.reissue.is_empty() {
+        println!(
+            "{} principals got random keys and need new credentials",
+            report.reissue.len()
+        );
+    }
+    if let Some(path) = &args.reissue {
+        let mut contents = report.reissue.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        fs::write(path, contents).map_err(|err| format!("failed to write {path}: {err}"))?;
+    } else {
+        for name in &report.reissue {
+            println!("reissue: {name}");
+        }
+    }
+    for (name, err) in &report.failed {
+        eprintln!("failed: {name}: {err}");
+    }
+    Ok(report.is_complete())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("kadmin-migrate: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("kadmin-migrate: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,1285 @@
+//! kadmin-compatible command-line interface, behaving the same against MIT and Heimdal realms
+//!
+//! Run `kadmin-rs --help` for usage.
+
+use std::{
+    collections::HashSet,
+    env,
+    ffi::c_long,
+    io::{self, IsTerminal, Write},
+    process::ExitCode,
+    slice::Iter,
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use kadmin::Policy;
+use kadmin::{
+    KAdm5Variant, KAdmin, KAdminImpl, KeySalts, Principal,
+    principal::{PrincipalBuilder, PrincipalBuilderKey, apply_attribute_flags, attribute_flags},
+    profile,
+};
+use serde_json::{Value, json};
+
+use crate::common::{Connection, read_line, read_password};
+
+mod common;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+Usage: kadmin-rs [OPTIONS] [COMMAND [ARGS...]]
+
+Administer a Kerberos realm, with the same commands against MIT and Heimdal.
+
+Without COMMAND and -q, commands are read from the standard input.
+
+Options:
+  --variant VARIANT       mit_client, mit_server, heimdal_client or heimdal_server. Defaults
+                          to the first client variant available, or server variant with -l
+  -r REALM                Realm to administer
+  -s HOST[:PORT]          Admin server to connect to
+  -p PRINCIPAL            Principal to authenticate as. Its password is prompted for unless
+                          -w, -k or -c is given
+  -k                      Authenticate with a keytab
+  -t KEYTAB               Keytab to use with -k
+  -c CCACHE               Authenticate with this credentials cache
+  -w PASSWORD             Authenticate with this password
+  -l                      Access the local database, like kadmin.local
+  -x DB_ARG               Database specific argument, MIT only, can be repeated
+  -q QUERY                Run QUERY and exit
+  --json                  Print results as JSON
+  -h, --help              Show this help
+";
+
+const REQUESTS: &str = "\
+Available kadmin-rs requests:
+
+addprinc, add_principal, ank       Add principal
+delprinc, delete_principal         Delete principal
+modprinc, modify_principal         Modify principal
+renprinc, rename_principal         Rename principal
+cpw, change_password               Change password
+getprinc, get_principal            Get principal
+listprincs, list_principals        List principals
+addpol, add_policy                 Add policy
+modpol, modify_policy              Modify policy
+delpol, delete_policy              Delete policy
+getpol, get_policy                 Get policy
+listpols, list_policies            List policies
+getprivs, get_privs                Get privileges
+getstrs, get_strings               Show string attributes on a principal
+setstr, set_string                 Set a string attribute on a principal
+delstr, del_string                 Delete a string attribute on a principal
+quit, exit, q                      Exit program
+";
+
+/// Parse a duration, either `never` or one of the formats [`profile::parse_duration`] accepts,
+/// like a number of seconds, `[Nd][Nh][Nm][Ns]` or `N days HH:MM:SS`
+fn parse_duration(value: &str) -> Result<Option<Duration>> {
+    if ["never", "unlimited"].contains(&value.trim().to_ascii_lowercase().as_str()) {
+        return Ok(None);
+    }
+    profile::parse_duration(value)
+        .map(Some)
+        .ok_or_else(|| format!("invalid duration {value:?}").into())
+}
+
+/// Parse a time, either `never`, `now`, `now + DURATION`, RFC 3339, `YYYY-MM-DD[ hh:mm:ss]` or
+/// `YYYYMMDDhhmmss`
+fn parse_time(value: &str) -> Result<Option<DateTime<Utc>>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("never") {
+        return Ok(None);
+    }
+    if let Some(rest) = value.strip_prefix("now") {
+        let rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(Some(Utc::now()));
+        }
+        if let Some(delta) = rest.strip_prefix('+') {
+            let delta = parse_duration(delta)?.unwrap_or_default();
+            return Ok(Some(Utc::now() + chrono::Duration::from_std(delta)?));
+        }
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(Some(time.with_timezone(&Utc)));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y%m%d%H%M%S"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(Some(time.and_utc()));
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| Some(time.and_utc()))
+        .ok_or_else(|| format!("invalid time {value:?}").into())
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(
+        || "[never]".to_owned(),
+        |time| time.format("%a %b %d %H:%M:%S %Z %Y").to_string(),
+    )
+}
+
+fn format_duration(duration: Option<Duration>) -> String {
+    let secs = duration.unwrap_or_default().as_secs();
+    let days = secs / (24 * 3600);
+    format!(
+        "{days} {} {:02}:{:02}:{:02}",
+        if days == 1 { "day" } else { "days" },
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+fn json_time(time: Option<DateTime<Utc>>) -> Value {
+    time.map_or(Value::Null, |time| time.to_rfc3339().into())
+}
+
+fn json_duration(duration: Option<Duration>) -> Value {
+    duration.map_or(Value::Null, |duration| duration.as_secs().into())
+}
+
+/// Split a request line into words, handling quotes and backslashes like a shell
+fn split_line(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".into());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Prompt for a new password twice, like kadmin
+fn prompt_new_password(name: &str) -> Result<String> {
+    let password = read_password(&format!("Enter password for principal \"{name}\": "))?;
+    let again = read_password(&format!("Re-enter password for principal \"{name}\": "))?;
+    if password != again {
+        return Err("passwords don't match".into());
+    }
+    Ok(password)
+}
+
+/// Ask for confirmation, unless `force` is set
+fn confirm(force: bool, question: &str) -> Result<bool> {
+    Ok(force || read_line(&format!("{question} (yes/no): "))? == "yes")
+}
+
+/// Value of `option`, the next argument
+fn value<'a>(args: &mut Iter<'a, String>, option: &str) -> Result<&'a str> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("missing value for {option}").into())
+}
+
+/// Options shared by addprinc and modprinc
+#[derive(Default)]
+struct PrincipalOptions {
+    expire_time: Option<Option<DateTime<Utc>>>,
+    password_expiration: Option<Option<DateTime<Utc>>>,
+    max_life: Option<Option<Duration>>,
+    max_renewable_life: Option<Option<Duration>>,
+    policy: Option<Option<String>>,
+    kvno: Option<u32>,
+    flags: Vec<String>,
+    #[cfg(any(mit_client, mit_server))]
+    db_args: Vec<String>,
+}
+
+impl PrincipalOptions {
+    /// Handle `arg`, returning whether it was one of these options
+    fn parse(&mut self, arg: &str, args: &mut Iter<'_, String>) -> Result<bool> {
+        match arg {
+            "-expire" => self.expire_time = Some(parse_time(value(args, arg)?)?),
+            "-pwexpire" => self.password_expiration = Some(parse_time(value(args, arg)?)?),
+            "-maxlife" => self.max_life = Some(parse_duration(value(args, arg)?)?),
+            "-maxrenewlife" => self.max_renewable_life = Some(parse_duration(value(args, arg)?)?),
+            "-policy" => self.policy = Some(Some(value(args, arg)?.to_owned())),
+            "-clearpolicy" => self.policy = Some(None),
+            "-kvno" => self.kvno = Some(value(args, arg)?.parse()?),
+            #[cfg(any(mit_client, mit_server))]
+            "-x" => self.db_args.push(value(args, arg)?.to_owned()),
+            // Options taking a value that only addprinc handles, whose value would otherwise be
+            // mistaken for the principal name
+            "-pw" | "-e" => return Err(format!("{arg} isn't supported by this request").into()),
+            _ if arg.len() > 1 && (arg.starts_with('+') || arg.starts_with('-')) => {
+                // Attribute flags are validated when applied, unknown options end up there too
+                self.flags.push(arg.to_owned());
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Attributes to set, from `attributes` and the flags given
+    fn attributes(&self, attributes: i32, variant: KAdm5Variant) -> Result<Option<i32>> {
+        if self.flags.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(apply_attribute_flags(
+            attributes,
+            &self.flags.join(" "),
+            variant,
+        )?))
+    }
+}
+
+/// Set the fields of [`PrincipalOptions`] on a `PrincipalBuilder` or `PrincipalModifier`
+macro_rules! apply_principal_options {
+    ($doer:expr, $options:expr, $attributes:expr) => {{
+        let mut doer = $doer;
+        if let Some(expire_time) = $options.expire_time {
+            doer = doer.expire_time(expire_time);
+        }
+        if let Some(password_expiration) = $options.password_expiration {
+            doer = doer.password_expiration(password_expiration);
+        }
+        if let Some(max_life) = $options.max_life {
+            doer = doer.max_life(max_life);
+        }
+        if let Some(max_renewable_life) = $options.max_renewable_life {
+            doer = doer.max_renewable_life(max_renewable_life);
+        }
+        if let Some(policy) = &$options.policy {
+            doer = doer.policy(policy.as_deref());
+        }
+        if let Some(kvno) = $options.kvno {
+            doer = doer.kvno(kvno);
+        }
+        if let Some(attributes) = $attributes {
+            doer = doer.attributes(attributes);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(db_args) = common::db_args(&$options.db_args)? {
+            doer = doer.db_args(db_args);
+        }
+        doer
+    }};
+}
+
+/// Options shared by addpol and modpol
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+#[derive(Default)]
+struct PolicyOptions {
+    password_max_life: Option<Option<Duration>>,
+    password_min_life: Option<Option<Duration>>,
+    password_min_length: Option<c_long>,
+    password_min_classes: Option<c_long>,
+    password_history_num: Option<c_long>,
+    #[cfg(any(mit_client, mit_server))]
+    password_max_fail: Option<u32>,
+    #[cfg(any(mit_client, mit_server))]
+    password_failcount_interval: Option<Option<Duration>>,
+    #[cfg(any(mit_client, mit_server))]
+    password_lockout_duration: Option<Option<Duration>>,
+    #[cfg(any(mit_client, mit_server))]
+    allowed_keysalts: Option<Option<String>>,
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+impl PolicyOptions {
+    /// Handle `arg`, returning whether it was one of these options
+    fn parse(&mut self, arg: &str, args: &mut Iter<'_, String>) -> Result<bool> {
+        match arg {
+            "-maxlife" => self.password_max_life = Some(parse_duration(value(args, arg)?)?),
+            "-minlife" => self.password_min_life = Some(parse_duration(value(args, arg)?)?),
+            "-minlength" => self.password_min_length = Some(value(args, arg)?.parse()?),
+            "-minclasses" => self.password_min_classes = Some(value(args, arg)?.parse()?),
+            "-history" => self.password_history_num = Some(value(args, arg)?.parse()?),
+            #[cfg(any(mit_client, mit_server))]
+            "-maxfailure" => self.password_max_fail = Some(value(args, arg)?.parse()?),
+            #[cfg(any(mit_client, mit_server))]
+            "-failurecountinterval" => {
+                self.password_failcount_interval = Some(parse_duration(value(args, arg)?)?);
+            }
+            #[cfg(any(mit_client, mit_server))]
+            "-lockoutduration" => {
+                self.password_lockout_duration = Some(parse_duration(value(args, arg)?)?);
+            }
+            #[cfg(any(mit_client, mit_server))]
+            "-allowedkeysalts" => {
+                let keysalts = value(args, arg)?;
+                self.allowed_keysalts = Some((keysalts != "-").then(|| keysalts.to_owned()));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Set the fields of [`PolicyOptions`] on a `PolicyBuilder` or `PolicyModifier`
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+macro_rules! apply_policy_options {
+    ($doer:expr, $options:expr, $kadmin:expr) => {{
+        let mut doer = $doer;
+        if let Some(password_max_life) = $options.password_max_life {
+            doer = doer.password_max_life(password_max_life);
+        }
+        if let Some(password_min_life) = $options.password_min_life {
+            doer = doer.password_min_life(password_min_life);
+        }
+        if let Some(password_min_length) = $options.password_min_length {
+            doer = doer.password_min_length(password_min_length);
+        }
+        if let Some(password_min_classes) = $options.password_min_classes {
+            doer = doer.password_min_classes(password_min_classes);
+        }
+        if let Some(password_history_num) = $options.password_history_num {
+            doer = doer.password_history_num(password_history_num);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        {
+            if let Some(password_max_fail) = $options.password_max_fail {
+                doer = doer.password_max_fail(password_max_fail);
+            }
+            if let Some(interval) = $options.password_failcount_interval {
+                doer = doer.password_failcount_interval(interval);
+            }
+            if let Some(duration) = $options.password_lockout_duration {
+                doer = doer.password_lockout_duration(duration);
+            }
+            if let Some(keysalts) = &$options.allowed_keysalts {
+                doer = doer.allowed_keysalts(
+                    keysalts
+                        .as_deref()
+                        .map(|keysalts| $kadmin.parse_keysalts(keysalts))
+                        .transpose()?,
+                );
+            }
+        }
+        doer
+    }};
+}
+
+/// Runs requests against a realm
+struct Shell {
+    kadmin: KAdmin,
+    json: bool,
+}
+
+impl Shell {
+    fn execute(&self, argv: &[String]) -> Result<()> {
+        let Some((request, args)) = argv.split_first() else {
+            return Ok(());
+        };
+        match request.as_str() {
+            "addprinc" | "add_principal" | "ank" => self.addprinc(args),
+            "delprinc" | "delete_principal" => self.delprinc(args),
+            "modprinc" | "modify_principal" => self.modprinc(args),
+            "renprinc" | "rename_principal" => self.renprinc(args),
+            "cpw" | "change_password" => self.cpw(args),
+            "getprinc" | "get_principal" => self.getprinc(args),
+            "listprincs" | "list_principals" | "get_principals" | "getprincs" => {
+                self.list(args, "listprincs", |query| {
+                    self.kadmin.list_principals(query)
+                })
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            "addpol" | "add_policy" => self.addpol(args),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            "modpol" | "modify_policy" => self.modpol(args),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            "delpol" | "delete_policy" => self.delpol(args),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            "getpol" | "get_policy" => self.getpol(args),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            "listpols" | "list_policies" | "get_policies" | "getpols" => {
+                self.list(args, "listpols", |query| self.kadmin.list_policies(query))
+            }
+            #[cfg(not(any(mit_client, mit_server, heimdal_server)))]
+            "addpol" | "add_policy" | "modpol" | "modify_policy" | "delpol" | "delete_policy"
+            | "getpol" | "get_policy" | "listpols" | "list_policies" | "get_policies"
+            | "getpols" => Err("policies aren't supported by this build".into()),
+            "getprivs" | "get_privs" => self.getprivs(args),
+            #[cfg(any(mit_client, mit_server))]
+            "getstrs" | "get_strings" => self.getstrs(args),
+            #[cfg(any(mit_client, mit_server))]
+            "setstr" | "set_string" => self.setstr(args),
+            #[cfg(any(mit_client, mit_server))]
+            "delstr" | "del_string" => self.delstr(args),
+            #[cfg(not(any(mit_client, mit_server)))]
+            "getstrs" | "get_strings" | "setstr" | "set_string" | "delstr" | "del_string" => {
+                Err("string attributes aren't supported by this build".into())
+            }
+            "?" | "help" | "lr" | "list_requests" => {
+                print!("{REQUESTS}");
+                Ok(())
+            }
+            _ => {
+                Err(format!("unknown request \"{request}\", type \"?\" for a request list").into())
+            }
+        }
+    }
+
+    /// Report a successful change, with `message` or `details` in JSON
+    fn done(&self, message: String, details: Value) {
+        if self.json {
+            println!("{details}");
+        } else {
+            println!("{message}");
+        }
+    }
+
+    fn addprinc(&self, args: &[String]) -> Result<()> {
+        const USAGE: &str = "usage: addprinc [options] principal";
+        let mut options = PrincipalOptions::default();
+        let mut key = None;
+        let mut keysalts = None;
+        let mut name = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-randkey" => key = Some(PrincipalBuilderKey::RandKey),
+                "-nokey" => key = Some(PrincipalBuilderKey::NoKey),
+                "-pw" => {
+                    key = Some(PrincipalBuilderKey::Password(
+                        value(&mut args, arg)?.to_owned(),
+                    ));
+                }
+                "-e" => keysalts = Some(value(&mut args, arg)?),
+                _ if options.parse(arg, &mut args)? => {}
+                _ if name.is_none() => name = Some(arg.as_str()),
+                _ => return Err(USAGE.into()),
+            }
+        }
+        let name = name.ok_or(USAGE)?;
+        let key = match key {
+            Some(key) => key,
+            None => PrincipalBuilderKey::Password(prompt_new_password(name)?),
+        };
+
+        let attributes = options.attributes(0, self.kadmin.variant())?;
+        let builder =
+            apply_principal_options!(PrincipalBuilder::new(name), options, attributes).key(&key);
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        let builder = match keysalts {
+            Some(keysalts) => builder.keysalts(&self.kadmin.parse_keysalts(keysalts)?),
+            None => builder,
+        };
+        #[cfg(not(any(mit_client, mit_server, heimdal_server)))]
+        if let Some(keysalts) = keysalts {
+            return Err(format!("-e {keysalts} isn't supported by this build").into());
+        }
+        builder.create(&self.kadmin)?;
+        self.done(
+            format!("Principal \"{name}\" created."),
+            json!({ "principal": name, "action": "created" }),
+        );
+        Ok(())
+    }
+
+    fn modprinc(&self, args: &[String]) -> Result<()> {
+        const USAGE: &str = "usage: modprinc [options] principal";
+        let mut options = PrincipalOptions::default();
+        let mut unlock = false;
+        let mut name = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-unlock" => unlock = true,
+                _ if options.parse(arg, &mut args)? => {}
+                _ if name.is_none() => name = Some(arg.as_str()),
+                _ => return Err(USAGE.into()),
+            }
+        }
+        let name = name.ok_or(USAGE)?;
+
+        let principal = self.get_principal(name)?;
+        let attributes = options.attributes(principal.attributes(), self.kadmin.variant())?;
+        let mut modifier = apply_principal_options!(principal.modifier(), options, attributes);
+        if unlock {
+            modifier = modifier.fail_auth_count(0);
+        }
+        modifier.modify(&self.kadmin)?;
+        self.done(
+            format!("Principal \"{name}\" modified."),
+            json!({ "principal": name, "action": "modified" }),
+        );
+        Ok(())
+    }
+
+    fn delprinc(&self, args: &[String]) -> Result<()> {
+        let (force, [name]) = Self::force::<1>(args, "usage: delprinc [-force] principal")?;
+        if !confirm(
+            force,
+            &format!("Are you sure you want to delete the principal \"{name}\"?"),
+        )? {
+            return Err(format!("Principal \"{name}\" not deleted").into());
+        }
+        self.kadmin.delete_principal(name)?;
+        self.done(
+            format!("Principal \"{name}\" deleted."),
+            json!({ "principal": name, "action": "deleted" }),
+        );
+        Ok(())
+    }
+
+    fn renprinc(&self, args: &[String]) -> Result<()> {
+        let (force, [old_name, new_name]) =
+            Self::force::<2>(args, "usage: renprinc [-force] old_principal new_principal")?;
+        if !confirm(
+            force,
+            &format!(
+                "Are you sure you want to rename the principal \"{old_name}\" to \"{new_name}\"?"
+            ),
+        )? {
+            return Err(format!("Principal \"{old_name}\" not renamed").into());
+        }
+        self.kadmin.rename_principal(old_name, new_name)?;
+        self.done(
+            format!("Principal \"{old_name}\" renamed to \"{new_name}\"."),
+            json!({ "principal": old_name, "action": "renamed", "new_name": new_name }),
+        );
+        Ok(())
+    }
+
+    fn cpw(&self, args: &[String]) -> Result<()> {
+        const USAGE: &str =
+            "usage: cpw [-randkey] [-keepold] [-e keysaltlist] [-pw password] principal";
+        let mut randkey = false;
+        let mut keepold = false;
+        let mut keysalts = None;
+        let mut password = None;
+        let mut name = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-randkey" => randkey = true,
+                "-keepold" => keepold = true,
+                "-e" => keysalts = Some(value(&mut args, arg)?),
+                "-pw" => password = Some(value(&mut args, arg)?.to_owned()),
+                _ if name.is_none() && !arg.starts_with('-') => name = Some(arg.as_str()),
+                _ => return Err(USAGE.into()),
+            }
+        }
+        let name = name.ok_or(USAGE)?;
+
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        let keysalts = keysalts
+            .map(|keysalts| self.kadmin.parse_keysalts(keysalts))
+            .transpose()?;
+        #[cfg(not(any(mit_client, mit_server, heimdal_server)))]
+        if keepold || keysalts.is_some() {
+            return Err("-keepold and -e aren't supported by this build".into());
+        }
+
+        if randkey {
+            self.kadmin.principal_randkey(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                Some(keepold),
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts.as_ref(),
+            )?;
+            self.done(
+                format!("Key for \"{name}\" randomized."),
+                json!({ "principal": name, "action": "randkey" }),
+            );
+            return Ok(());
+        }
+        let password = match password {
+            Some(password) => password,
+            None => prompt_new_password(name)?,
+        };
+        self.kadmin.principal_change_password(
+            name,
+            &password,
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Some(keepold),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            keysalts.as_ref(),
+        )?;
+        self.done(
+            format!("Password for \"{name}\" changed."),
+            json!({ "principal": name, "action": "cpw" }),
+        );
+        Ok(())
+    }
+
+    fn getprinc(&self, args: &[String]) -> Result<()> {
+        let (terse, [name]) =
+            Self::flag::<1>(args, "-terse", "usage: getprinc [-terse] principal")?;
+        let principal = self.get_principal(name)?;
+        let keys = self.keys(name);
+        let variant = self.kadmin.variant();
+        if self.json {
+            println!(
+                "{}",
+                json!({
+                    "name": principal.name(),
+                    "expire_time": json_time(principal.expire_time()),
+                    "last_password_change": json_time(principal.last_password_change()),
+                    "password_expiration": json_time(principal.password_expiration()),
+                    "max_life": json_duration(principal.max_life()),
+                    "max_renewable_life": json_duration(principal.max_renewable_life()),
+                    "modified_at": json_time(principal.modified_at()),
+                    "modified_by": principal.modified_by(),
+                    "last_success": json_time(principal.last_success()),
+                    "last_failed": json_time(principal.last_failed()),
+                    "fail_auth_count": principal.fail_auth_count(),
+                    "kvno": principal.kvno(),
+                    "mkvno": principal.mkvno(),
+                    "attributes": principal.attributes(),
+                    "attribute_flags": attribute_flags(principal.attributes(), variant),
+                    "policy": principal.policy(),
+                    "keys": keys.as_ref().map(|keys| {
+                        keys.iter()
+                            .map(|(kvno, keysalt)| json!({ "kvno": kvno, "keysalt": keysalt }))
+                            .collect::<Vec<_>>()
+                    }),
+                })
+            );
+        } else if terse {
+            let timestamp = |time: Option<DateTime<Utc>>| time.map_or(0, |time| time.timestamp());
+            let seconds = |duration: Option<Duration>| duration.map_or(0, |d| d.as_secs());
+            let keys: String = keys
+                .iter()
+                .flatten()
+                .map(|(kvno, keysalt)| format!("\t{kvno}\t{keysalt}"))
+                .collect();
+            println!(
+                "\"{}\"\t{}\t{}\t{}\t{}\t\"{}\"\t{}\t{}\t{}\t{}\t\"{}\"\t{}\t{}\t{}\t{}\t{}{keys}",
+                principal.name(),
+                timestamp(principal.expire_time()),
+                timestamp(principal.last_password_change()),
+                timestamp(principal.password_expiration()),
+                seconds(principal.max_life()),
+                principal.modified_by().unwrap_or("[none]"),
+                timestamp(principal.modified_at()),
+                principal.attributes(),
+                principal.kvno(),
+                principal.mkvno(),
+                principal.policy().unwrap_or("[none]"),
+                seconds(principal.max_renewable_life()),
+                timestamp(principal.last_success()),
+                timestamp(principal.last_failed()),
+                principal.fail_auth_count(),
+                keys.as_ref().map_or(0, Vec::len),
+            );
+        } else {
+            println!("Principal: {}", principal.name());
+            println!("Expiration date: {}", format_time(principal.expire_time()));
+            println!(
+                "Last password change: {}",
+                format_time(principal.last_password_change())
+            );
+            println!(
+                "Password expiration date: {}",
+                format_time(principal.password_expiration())
+            );
+            println!(
+                "Maximum ticket life: {}",
+                format_duration(principal.max_life())
+            );
+            println!(
+                "Maximum renewable life: {}",
+                format_duration(principal.max_renewable_life())
+            );
+            println!(
+                "Last modified: {} ({})",
+                format_time(principal.modified_at()),
+                principal.modified_by().unwrap_or("[none]")
+            );
+            println!(
+                "Last successful authentication: {}",
+                format_time(principal.last_success())
+            );
+            println!(
+                "Last failed authentication: {}",
+                format_time(principal.last_failed())
+            );
+            println!("Failed password attempts: {}", principal.fail_auth_count());
+            println!("Key version number: {}", principal.kvno());
+            println!("MKey: vno {}", principal.mkvno());
+            println!(
+                "Attributes: {}",
+                attribute_flags(principal.attributes(), variant).join(" ")
+            );
+            if let Some(keys) = keys {
+                println!("Number of keys: {}", keys.len());
+                for (kvno, keysalt) in keys {
+                    println!("Key: vno {kvno}, {keysalt}");
+                }
+            }
+            println!("Policy: {}", principal.policy().unwrap_or("[none]"));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn addpol(&self, args: &[String]) -> Result<()> {
+        let (options, name) = Self::policy_options(args, "usage: addpol [options] policy")?;
+        apply_policy_options!(Policy::builder(name), options, self.kadmin).create(&self.kadmin)?;
+        self.done(
+            format!("Policy \"{name}\" created."),
+            json!({ "policy": name, "action": "created" }),
+        );
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn modpol(&self, args: &[String]) -> Result<()> {
+        let (options, name) = Self::policy_options(args, "usage: modpol [options] policy")?;
+        let policy = self
+            .kadmin
+            .get_policy(name)?
+            .ok_or_else(|| format!("Policy \"{name}\" does not exist"))?;
+        apply_policy_options!(policy.modifier(), options, self.kadmin).modify(&self.kadmin)?;
+        self.done(
+            format!("Policy \"{name}\" modified."),
+            json!({ "policy": name, "action": "modified" }),
+        );
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delpol(&self, args: &[String]) -> Result<()> {
+        let (force, [name]) = Self::force::<1>(args, "usage: delpol [-force] policy")?;
+        if !confirm(
+            force,
+            &format!("Are you sure you want to delete the policy \"{name}\"?"),
+        )? {
+            return Err(format!("Policy \"{name}\" not deleted").into());
+        }
+        self.kadmin.delete_policy(name)?;
+        self.done(
+            format!("Policy \"{name}\" deleted."),
+            json!({ "policy": name, "action": "deleted" }),
+        );
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn getpol(&self, args: &[String]) -> Result<()> {
+        let (terse, [name]) = Self::flag::<1>(args, "-terse", "usage: getpol [-terse] policy")?;
+        let policy = self
+            .kadmin
+            .get_policy(name)?
+            .ok_or_else(|| format!("Policy \"{name}\" does not exist"))?;
+        // Lockout and ticket settings are only filled by MIT
+        #[cfg(any(mit_client, mit_server))]
+        let mit = self.kadmin.variant().is_mit().then_some(&policy);
+        #[cfg(any(mit_client, mit_server))]
+        let allowed_keysalts = mit
+            .and_then(Policy::allowed_keysalts)
+            .map(|keysalts| self.kadmin.format_keysalts(keysalts))
+            .transpose()?;
+
+        if self.json {
+            #[allow(unused_mut)]
+            let mut value = json!({
+                "name": policy.name(),
+                "password_max_life": json_duration(policy.password_max_life()),
+                "password_min_life": json_duration(policy.password_min_life()),
+                "password_min_length": policy.password_min_length(),
+                "password_min_classes": policy.password_min_classes(),
+                "password_history_num": policy.password_history_num(),
+                "policy_refcnt": policy.policy_refcnt(),
+            });
+            #[cfg(any(mit_client, mit_server))]
+            if let Some(policy) = mit {
+                value["password_max_fail"] = policy.password_max_fail().into();
+                value["password_failcount_interval"] =
+                    json_duration(policy.password_failcount_interval());
+                value["password_lockout_duration"] =
+                    json_duration(policy.password_lockout_duration());
+                value["allowed_keysalts"] = allowed_keysalts.into();
+            }
+            println!("{value}");
+            return Ok(());
+        }
+
+        if terse {
+            #[allow(unused_mut)]
+            let mut fields = vec![
+                format!("\"{}\"", policy.name()),
+                policy
+                    .password_max_life()
+                    .unwrap_or_default()
+                    .as_secs()
+                    .to_string(),
+                policy
+                    .password_min_life()
+                    .unwrap_or_default()
+                    .as_secs()
+                    .to_string(),
+                policy.password_min_length().to_string(),
+                policy.password_min_classes().to_string(),
+                policy.password_history_num().to_string(),
+                policy.policy_refcnt().to_string(),
+            ];
+            #[cfg(any(mit_client, mit_server))]
+            if let Some(policy) = mit {
+                fields.extend([
+                    policy.password_max_fail().to_string(),
+                    policy
+                        .password_failcount_interval()
+                        .unwrap_or_default()
+                        .as_secs()
+                        .to_string(),
+                    policy
+                        .password_lockout_duration()
+                        .unwrap_or_default()
+                        .as_secs()
+                        .to_string(),
+                    allowed_keysalts.unwrap_or_else(|| "-".to_owned()),
+                ]);
+            }
+            println!("{}", fields.join("\t"));
+            return Ok(());
+        }
+
+        println!("Policy: {}", policy.name());
+        println!(
+            "Maximum password life: {}",
+            format_duration(policy.password_max_life())
+        );
+        println!(
+            "Minimum password life: {}",
+            format_duration(policy.password_min_life())
+        );
+        println!("Minimum password length: {}", policy.password_min_length());
+        println!(
+            "Minimum number of password character classes: {}",
+            policy.password_min_classes()
+        );
+        println!("Number of old keys kept: {}", policy.password_history_num());
+        println!("Reference count: {}", policy.policy_refcnt());
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(policy) = mit {
+            println!(
+                "Maximum password failures before lockout: {}",
+                policy.password_max_fail()
+            );
+            println!(
+                "Password failure count reset interval: {}",
+                format_duration(policy.password_failcount_interval())
+            );
+            println!(
+                "Password lockout duration: {}",
+                format_duration(policy.password_lockout_duration())
+            );
+            if let Some(allowed_keysalts) = allowed_keysalts {
+                println!("Allowed key/salt types: {allowed_keysalts}");
+            }
+        }
+        Ok(())
+    }
+
+    fn getprivs(&self, args: &[String]) -> Result<()> {
+        if !args.is_empty() {
+            return Err("usage: getprivs".into());
+        }
+        let privileges = self.kadmin.get_privileges()?;
+        let names: Vec<&str> = privileges.iter_names().map(|(name, _)| name).collect();
+        if self.json {
+            println!("{}", json!(names));
+        } else {
+            println!("current privileges: {}", names.join(" "));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn getstrs(&self, args: &[String]) -> Result<()> {
+        let [name] = args else {
+            return Err("usage: getstrs principal".into());
+        };
+        let mut strings: Vec<_> = self
+            .kadmin
+            .principal_get_strings(name)?
+            .into_iter()
+            .collect();
+        strings.sort();
+        if self.json {
+            println!(
+                "{}",
+                Value::Object(
+                    strings
+                        .into_iter()
+                        .map(|(key, value)| (key, value.into()))
+                        .collect()
+                )
+            );
+        } else if strings.is_empty() {
+            println!("(No string attributes.)");
+        } else {
+            for (key, value) in strings {
+                println!("{key}: {value}");
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn setstr(&self, args: &[String]) -> Result<()> {
+        let [name, key, value] = args else {
+            return Err("usage: setstr principal key value".into());
+        };
+        self.kadmin
+            .principal_set_string(name, key, Some(value.as_str()))?;
+        self.done(
+            format!("Attribute set for principal \"{name}\"."),
+            json!({ "principal": name, "action": "setstr", "key": key }),
+        );
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn delstr(&self, args: &[String]) -> Result<()> {
+        let [name, key] = args else {
+            return Err("usage: delstr principal key".into());
+        };
+        self.kadmin.principal_set_string(name, key, None)?;
+        self.done(
+            format!("Attribute removed from principal \"{name}\"."),
+            json!({ "principal": name, "action": "delstr", "key": key }),
+        );
+        Ok(())
+    }
+
+    fn list(
+        &self,
+        args: &[String],
+        request: &str,
+        list: impl FnOnce(Option<&str>) -> kadmin::error::Result<Vec<String>>,
+    ) -> Result<()> {
+        let query = match args {
+            [] => None,
+            [query] => Some(query.as_str()),
+            _ => return Err(format!("usage: {request} [expression]").into()),
+        };
+        let mut names = list(query)?;
+        names.sort();
+        if self.json {
+            println!("{}", json!(names));
+        } else {
+            for name in names {
+                println!("{name}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Keys of the principal `name` as `(kvno, enctype:salttype)`, newest first, or `None` if they
+    /// can't be read, like without the privilege to extract them
+    fn keys(&self, name: &str) -> Option<Vec<(u32, String)>> {
+        let mut keys = self.kadmin.principal_get_keys(name, None).ok()?;
+        keys.sort_by(|a, b| b.kvno.cmp(&a.kvno));
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                let keysalts = KeySalts {
+                    keysalts: HashSet::from([key.keysalt]),
+                };
+                let keysalt = self.kadmin.format_keysalts(&keysalts).unwrap_or_else(|_| {
+                    format!(
+                        "{}:{}",
+                        i32::from(key.keysalt.enctype),
+                        i32::from(key.keysalt.salttype)
+                    )
+                });
+                (key.kvno, keysalt)
+            })
+            .collect();
+        Some(keys)
+    }
+
+    fn get_principal(&self, name: &str) -> Result<Principal> {
+        self.kadmin
+            .get_principal(name)?
+            .ok_or_else(|| format!("Principal \"{name}\" does not exist").into())
+    }
+
+    /// Split `args` into whether `flag` was given first, and `N` names
+    fn flag<'a, const N: usize>(
+        args: &'a [String],
+        flag: &str,
+        usage: &str,
+    ) -> Result<(bool, [&'a str; N])> {
+        let (set, args) = match args.split_first() {
+            Some((first, rest)) if first == flag => (true, rest),
+            _ => (false, args),
+        };
+        let names: Vec<&str> = args.iter().map(String::as_str).collect();
+        Ok((set, names.try_into().map_err(|_| usage.to_owned())?))
+    }
+
+    fn force<'a, const N: usize>(args: &'a [String], usage: &str) -> Result<(bool, [&'a str; N])> {
+        Self::flag(args, "-force", usage)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn policy_options<'a>(args: &'a [String], usage: &str) -> Result<(PolicyOptions, &'a str)> {
+        let mut options = PolicyOptions::default();
+        let mut name = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                _ if options.parse(arg, &mut args)? => {}
+                _ if name.is_none() && !arg.starts_with('-') => name = Some(arg.as_str()),
+                _ => return Err(usage.into()),
+            }
+        }
+        Ok((options, name.ok_or(usage)?))
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    connection: Connection,
+    query: Option<String>,
+    json: bool,
+    request: Vec<String>,
+}
+
+fn parse_args() -> Result<Option<Args>> {
+    let mut args = Args::default();
+    let argv: Vec<String> = env::args().skip(1).collect();
+    let mut argv = argv.iter();
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--variant" => {
+                let variant = value(&mut argv, arg)?;
+                args.connection.variant = Some(
+                    variant
+                        .parse()
+                        .map_err(|_| format!("unknown or unsupported variant {variant}"))?,
+                );
+            }
+            "-r" => args.connection.realm = Some(value(&mut argv, arg)?.to_owned()),
+            "-s" => args.connection.admin_server = Some(value(&mut argv, arg)?.to_owned()),
+            "-p" => args.connection.principal = Some(value(&mut argv, arg)?.to_owned()),
+            "-k" => args.connection.use_keytab = true,
+            "-t" => args.connection.keytab = Some(value(&mut argv, arg)?.to_owned()),
+            "-c" => args.connection.ccache = Some(value(&mut argv, arg)?.to_owned()),
+            "-w" => args.connection.password = Some(value(&mut argv, arg)?.to_owned()),
+            "-l" => args.connection.local = Some(true),
+            #[cfg(any(mit_client, mit_server))]
+            "-x" => args
+                .connection
+                .db_args
+                .push(value(&mut argv, arg)?.to_owned()),
+            "-q" => args.query = Some(value(&mut argv, arg)?.to_owned()),
+            "--json" => args.json = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}").into()),
+            _ => {
+                args.request.push(arg.clone());
+                args.request.extend(argv.cloned());
+                break;
+            }
+        }
+    }
+    if args.query.is_some() && !args.request.is_empty() {
+        return Err("-q can't be used along with a request".into());
+    }
+    if args.connection.password.is_some() && args.connection.principal.is_none() {
+        return Err("-w requires -p".into());
+    }
+    // Authenticate with the default credentials cache unless -l is given, or with a prompted
+    // password for -p alone, like kadmin
+    args.connection.local.get_or_insert(false);
+    args.connection.prompt_password = true;
+    Ok(Some(args))
+}
+
+/// Run requests from the standard input until its end or `quit`, returning whether they all
+/// succeeded
+fn interactive(shell: &Shell) -> bool {
+    let prompt = io::stdin().is_terminal();
+    let mut success = true;
+    loop {
+        if prompt {
+            print!("kadmin-rs: ");
+            let _ = io::stdout().flush();
+        }
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("kadmin-rs: {err}");
+                return false;
+            }
+        }
+        let argv = match split_line(&line) {
+            Ok(argv) => argv,
+            Err(err) => {
+                eprintln!("kadmin-rs: {err}");
+                success = false;
+                continue;
+            }
+        };
+        if let Some("quit" | "exit" | "q") = argv.first().map(String::as_str) {
+            break;
+        }
+        if let Err(err) = shell.execute(&argv) {
+            eprintln!("{}: {err}", argv[0]);
+            success = false;
+        }
+    }
+    success
+}
+
+fn run(args: Args) -> Result<bool> {
+    let request = match &args.query {
+        Some(query) => split_line(query)?,
+        None => args.request.clone(),
+    };
+    let shell = Shell {
+        kadmin: args
+            .connection
+            .connect()
+            .map_err(|err| format!("failed to connect: {err}"))?,
+        json: args.json,
+    };
+    if request.is_empty() {
+        return Ok(interactive(&shell));
+    }
+    shell
+        .execute(&request)
+        .map_err(|err| format!("{}: {err}", request[0]))?;
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("kadmin-rs: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("kadmin-rs: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn durations() -> Result<()> {
+        assert_eq!(parse_duration("never")?, None);
+        assert_eq!(parse_duration("Unlimited")?, None);
+        assert_eq!(parse_duration("3600")?, Some(Duration::from_secs(3600)));
+        assert_eq!(
+            parse_duration("1w 2 days 3h")?,
+            Some(Duration::from_secs(9 * 86400 + 3 * 3600))
+        );
+        assert_eq!(
+            parse_duration("1 day 02:30:00")?,
+            Some(Duration::from_secs(86400 + 9000))
+        );
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("5124095576030432:00").is_err());
+        assert!(parse_duration("18446744073709551615s 1s").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn times() -> Result<()> {
+        assert_eq!(parse_time("never")?, None);
+        let expected = Some("2030-01-02T03:04:05Z".parse::<DateTime<Utc>>()?);
+        assert_eq!(parse_time("2030-01-02T03:04:05Z")?, expected);
+        assert_eq!(parse_time("2030-01-02 03:04:05")?, expected);
+        assert_eq!(parse_time("20300102030405")?, expected);
+        assert_eq!(
+            parse_time("2030-01-02")?,
+            Some("2030-01-02T00:00:00Z".parse::<DateTime<Utc>>()?)
+        );
+        let later = parse_time("now + 1h")?.unwrap();
+        assert!(later > Utc::now() + chrono::Duration::minutes(59));
+        assert!(parse_time("tomorrow").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn split() -> Result<()> {
+        assert_eq!(
+            split_line("addprinc -pw 'a b' \"c\\\"d\" e\\ f\n")?,
+            strings(&["addprinc", "-pw", "a b", "c\"d", "e f"])
+        );
+        assert_eq!(split_line("getprinc ''")?, strings(&["getprinc", ""]));
+        assert!(split_line("").unwrap().is_empty());
+        assert!(split_line("cpw 'alice").is_err());
+        assert!(split_line("cpw alice\\").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn principal_options() -> Result<()> {
+        let args = split_line("-maxlife 10h -kvno 3 +requires_preauth -clearpolicy alice")?;
+        let mut options = PrincipalOptions::default();
+        let mut args = args.iter();
+        let mut rest = Vec::new();
+        while let Some(arg) = args.next() {
+            if !options.parse(arg, &mut args)? {
+                rest.push(arg.as_str());
+            }
+        }
+        assert_eq!(rest, ["alice"]);
+        assert_eq!(options.max_life, Some(Some(Duration::from_secs(36000))));
+        assert_eq!(options.kvno, Some(3));
+        assert_eq!(options.policy, Some(None));
+        assert_eq!(options.flags, ["+requires_preauth"]);
+
+        let args = strings(&["-pw", "secret"]);
+        let mut args = args.iter();
+        let arg = args.next().unwrap();
+        assert!(PrincipalOptions::default().parse(arg, &mut args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn flag() -> Result<()> {
+        let (set, [name]) = Shell::flag::<1>(&strings(&["-terse", "alice"]), "-terse", "usage")?;
+        assert!(set);
+        assert_eq!(name, "alice");
+        let (set, [old, new]) = Shell::flag::<2>(&strings(&["alice", "bob"]), "-force", "usage")?;
+        assert!(!set);
+        assert_eq!((old, new), ("alice", "bob"));
+        assert!(Shell::flag::<1>(&strings(&["-terse"]), "-terse", "usage").is_err());
+        assert!(Shell::flag::<1>(&strings(&["alice", "bob"]), "-terse", "usage").is_err());
+        Ok(())
+    }
+}
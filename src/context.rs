@@ -3,7 +3,8 @@
 use std::{
     ffi::{CStr, CString, c_void},
     mem::MaybeUninit,
-    os::raw::c_char,
+    os::{raw::c_char, unix::ffi::OsStrExt},
+    path::{Path, PathBuf},
     ptr::null_mut,
     sync::Mutex,
 };
@@ -36,6 +37,61 @@ impl Context {
         ContextBuilder::default()
     }
 
+    /// Set the default realm of the underlying `krb5_context`, if provided
+    fn set_default_realm(&self, realm: Option<&str>) -> Result<()> {
+        let Some(realm) = realm else {
+            return Ok(());
+        };
+        let realm = CString::new(realm)?;
+        let code = library_match!(&self.library; |cont, lib| unsafe {
+            cont.krb5_set_default_realm(self.context as lib!(krb5_context), realm.as_ptr())
+        });
+        krb5_error_code_escape_hatch(self, code.into())
+    }
+
+    /// Register a trace callback forwarding libkrb5 trace messages to `tracing` if that feature
+    /// is enabled, to `log` otherwise
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    fn set_trace_callback(&self) -> Result<()> {
+        if self.library.is_heimdal() {
+            return Err(Error::LibraryMismatch(
+                "Trace callbacks are only supported by MIT krb5",
+            ));
+        }
+        let code = library_match!(
+            &self.library;
+            mit_client, mit_server => |cont, lib| {
+                unsafe extern "C" fn callback(
+                    _context: lib!(krb5_context),
+                    info: *const lib!(krb5_trace_info),
+                    _cb_data: *mut c_void,
+                ) {
+                    // Called with NULL when the context is freed
+                    if info.is_null() {
+                        return;
+                    }
+                    if let Ok(message) = c_string_to_string(unsafe { (*info).message }) {
+                        let message = redact_trace_message(message.trim_end());
+                        #[cfg(feature = "tracing")]
+                        tracing::trace!("{message}");
+                        #[cfg(not(feature = "tracing"))]
+                        log::trace!("{message}");
+                    }
+                }
+                unsafe {
+                    cont.krb5_set_trace_callback(
+                        self.context as lib!(krb5_context),
+                        Some(callback),
+                        null_mut(),
+                    )
+                }
+            },
+            // Rejected above
+            heimdal_client, heimdal_server => |_cont, _lib| 0
+        );
+        krb5_error_code_escape_hatch(self, code.into())
+    }
+
     /// Try to fill the `default_realm` field
     fn fill_default_realm(&mut self) {
         self.default_realm = {
@@ -92,6 +148,15 @@ impl Context {
 pub struct ContextBuilder {
     /// Optional [`krb5_context`] provided by the user
     context: Option<*mut c_void>,
+    /// Configuration files to use instead of the default ones
+    profile_files: Option<Vec<PathBuf>>,
+    /// Default realm to set on the context
+    default_realm: Option<String>,
+    /// Whether to ignore environment variables
+    secure: bool,
+    /// Whether to forward trace messages to `tracing` or `log`
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    trace: bool,
 }
 
 impl ContextBuilder {
@@ -102,11 +167,54 @@ impl ContextBuilder {
     /// Context will be freed with `krb5_free_context` when [`Context`] is dropped.
     ///
     /// Context must have been built with the same [`Library`] as passed to [`Self::build`]
+    ///
+    /// [`Self::default_realm`] and [`Self::trace`] are applied to it in place, so they also
+    /// affect any other user of the context.
     pub unsafe fn context(mut self, context: *mut c_void) -> Self {
         self.context = Some(context);
         self
     }
 
+    /// Load the configuration from these files instead of the default ones (`krb5.conf`, and
+    /// `kdc.conf` for server-side libraries)
+    ///
+    /// Ignored if a custom context is provided with [`Self::context`]
+    pub fn profile_files<P: AsRef<Path>>(mut self, files: &[P]) -> Self {
+        self.profile_files = Some(files.iter().map(|f| f.as_ref().to_path_buf()).collect());
+        self
+    }
+
+    /// Set the default realm of the context, overriding the one from the configuration
+    ///
+    /// A custom context provided with [`Self::context`] is modified in place.
+    pub fn default_realm(mut self, realm: &str) -> Self {
+        self.default_realm = Some(realm.to_owned());
+        self
+    }
+
+    /// Ignore environment variables such as `KRB5_CONFIG` when creating the context, like
+    /// `krb5_init_secure_context` does
+    ///
+    /// Only supported on MIT variants. Ignored if a custom context is provided with
+    /// [`Self::context`]
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Forward libkrb5 trace messages at trace level, with key material redacted
+    ///
+    /// Messages go to `tracing` if that feature is enabled, to `log` otherwise. This takes
+    /// precedence over the `KRB5_TRACE` environment variable.
+    ///
+    /// Only supported on MIT variants, [`Self::build`] fails on Heimdal ones when enabled. The
+    /// trace callback is registered in place on a custom context provided with [`Self::context`].
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
     /// Build a [`Context`] instance
     ///
     /// If no context was provided, a default one is created with `kadm5_init_krb5_context`
@@ -117,10 +225,40 @@ impl ContextBuilder {
                 context: ctx,
                 default_realm: None,
             };
+            context.set_default_realm(self.default_realm.as_deref())?;
+            #[cfg(any(feature = "log", feature = "tracing"))]
+            if self.trace {
+                context.set_trace_callback()?;
+            }
             context.fill_default_realm();
             return Ok(context);
         }
 
+        if self.secure && library.is_heimdal() {
+            return Err(Error::LibraryMismatch(
+                "Secure contexts are only supported by MIT krb5",
+            ));
+        }
+
+        let profile_files = self
+            .profile_files
+            .as_ref()
+            .map(|files| {
+                files
+                    .iter()
+                    .map(|f| CString::new(f.as_os_str().as_bytes()))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        // NULL-terminated list of pointers to the above strings
+        let profile_files_ptrs: Option<Vec<*mut c_char>> = profile_files.as_ref().map(|files| {
+            files
+                .iter()
+                .map(|f| f.as_ptr().cast_mut())
+                .chain(std::iter::once(null_mut()))
+                .collect()
+        });
+
         let _guard = CONTEXT_INIT_LOCK.lock().map_err(|_| Error::LockError)?;
 
         let (context, code) = library_match!(
@@ -128,14 +266,56 @@ impl ContextBuilder {
             mit_client, mit_server => |cont, lib| {
                 let mut context_ptr: MaybeUninit<lib!(krb5_context)> =
                     MaybeUninit::zeroed();
-                let code = unsafe { cont.kadm5_init_krb5_context(context_ptr.as_mut_ptr()) };
+                let code = if profile_files_ptrs.is_none() && !self.secure {
+                    unsafe { cont.kadm5_init_krb5_context(context_ptr.as_mut_ptr()) }
+                } else if profile_files_ptrs.is_none() && library.is_client() {
+                    unsafe { cont.krb5_init_secure_context(context_ptr.as_mut_ptr()) }
+                } else {
+                    let mut flags = 0;
+                    if self.secure {
+                        flags |= lib!(KRB5_INIT_CONTEXT_SECURE);
+                    }
+                    if library.is_server() {
+                        flags |= lib!(KRB5_INIT_CONTEXT_KDC);
+                    }
+                    let mut profile: lib!(profile_t) = null_mut();
+                    let code = if let Some(ptrs) = &profile_files_ptrs {
+                        let files = ptrs.as_ptr() as *mut *const c_char;
+                        unsafe { cont.profile_init(files, &mut profile) } as lib!(krb5_error_code)
+                    } else {
+                        0
+                    };
+                    if code != 0 {
+                        code
+                    } else {
+                        let code = unsafe {
+                            cont.krb5_init_context_profile(
+                                profile,
+                                flags as lib!(krb5_flags),
+                                context_ptr.as_mut_ptr(),
+                            )
+                        };
+                        // The context holds its own copy of the profile
+                        if !profile.is_null() {
+                            unsafe { cont.profile_release(profile) };
+                        }
+                        code
+                    }
+                };
                 (unsafe { context_ptr.assume_init() } as *mut c_void, code)
             },
             heimdal_client, heimdal_server => |cont, lib| {
                 let mut context_ptr: MaybeUninit<lib!(krb5_context)> =
                     MaybeUninit::zeroed();
                 let code = unsafe { cont.krb5_init_context(context_ptr.as_mut_ptr()) };
-                (unsafe { context_ptr.assume_init() } as *mut c_void, code)
+                let context = unsafe { context_ptr.assume_init() };
+                let code = match &profile_files_ptrs {
+                    Some(ptrs) if code == 0 => unsafe {
+                        cont.krb5_set_config_files(context, ptrs.as_ptr().cast_mut())
+                    },
+                    _ => code,
+                };
+                (context as *mut c_void, code)
             }
         );
 
@@ -146,6 +326,11 @@ impl ContextBuilder {
             default_realm: None,
         };
         krb5_error_code_escape_hatch(&context, code.into())?;
+        #[cfg(any(feature = "log", feature = "tracing"))]
+        if self.trace {
+            context.set_trace_callback()?;
+        }
+        context.set_default_realm(self.default_realm.as_deref())?;
         context.fill_default_realm();
         Ok(context)
     }
@@ -161,6 +346,40 @@ impl Drop for Context {
     }
 }
 
+/// Redact key material from a libkrb5 trace message
+///
+/// Keys are formatted as `enctype/fingerprint`, for instance `aes256-cts/1A2B`. Only the
+/// fingerprint is redacted.
+#[cfg(any(feature = "log", feature = "tracing"))]
+fn redact_trace_message(message: &str) -> String {
+    let is_enctype = |s: &str| {
+        s.starts_with(|c: char| c.is_ascii_lowercase())
+            && s.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    };
+    let is_fingerprint = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c))
+    };
+
+    message
+        .split(' ')
+        .map(|word| {
+            let token = word.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+            match token.split_once('/') {
+                Some((enctype, fingerprint))
+                    if is_enctype(enctype) && is_fingerprint(fingerprint) =>
+                {
+                    word.replacen(token, &format!("{enctype}/<redacted>"), 1)
+                }
+                _ => word.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,4 +468,121 @@ mod tests {
         assert!(message.starts_with("Unknown"));
         Ok(())
     }
+
+    #[cfg(mit_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn default_realm_mit() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::MitClient)?;
+        let context = Context::builder().default_realm("OTHER.ORG").build(lib)?;
+        assert_eq!(context.default_realm.as_deref(), Some("OTHER.ORG"));
+        Ok(())
+    }
+
+    #[cfg(heimdal_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn default_realm_heimdal() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::HeimdalClient)?;
+        let context = Context::builder().default_realm("OTHER.ORG").build(lib)?;
+        assert_eq!(context.default_realm.as_deref(), Some("OTHER.ORG"));
+        Ok(())
+    }
+
+    #[cfg(mit_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn profile_files_mit() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::MitClient)?;
+        let context = Context::builder()
+            .profile_files(&["tests/fixtures/profile/krb5.conf"])
+            .build(lib)?;
+        assert_eq!(context.default_realm.as_deref(), Some("EXAMPLE.ORG"));
+        Ok(())
+    }
+
+    #[cfg(heimdal_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn profile_files_heimdal() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::HeimdalClient)?;
+        let context = Context::builder()
+            .profile_files(&["tests/fixtures/profile/krb5.conf"])
+            .build(lib)?;
+        assert_eq!(context.default_realm.as_deref(), Some("EXAMPLE.ORG"));
+        Ok(())
+    }
+
+    #[cfg(mit_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn secure_mit_client() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::MitClient)?;
+        let context = Context::builder().secure(true).build(lib);
+        assert!(context.is_ok());
+        Ok(())
+    }
+
+    #[cfg(mit_server)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn secure_mit_server() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::MitServer)?;
+        let context = Context::builder()
+            .secure(true)
+            .profile_files(&["tests/fixtures/profile/krb5.conf"])
+            .build(lib)?;
+        assert_eq!(context.default_realm.as_deref(), Some("EXAMPLE.ORG"));
+        Ok(())
+    }
+
+    #[cfg(heimdal_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn secure_heimdal() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::HeimdalClient)?;
+        let context = Context::builder().secure(true).build(lib);
+        assert!(matches!(context, Err(Error::LibraryMismatch(_))));
+        Ok(())
+    }
+
+    #[cfg(all(any(feature = "log", feature = "tracing"), mit_client))]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn trace_mit() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::MitClient)?;
+        let context = Context::builder().trace(true).build(lib);
+        assert!(context.is_ok());
+        Ok(())
+    }
+
+    #[cfg(all(any(feature = "log", feature = "tracing"), heimdal_client))]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn trace_heimdal() -> Result<()> {
+        let lib = Library::from_variant(KAdm5Variant::HeimdalClient)?;
+        let context = Context::builder().trace(true).build(lib);
+        assert!(matches!(context, Err(Error::LibraryMismatch(_))));
+        Ok(())
+    }
+
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    #[test]
+    fn redact_trace_message_keys() {
+        assert_eq!(
+            redact_trace_message("AS key obtained from gak_fct: aes256-cts/1A2B"),
+            "AS key obtained from gak_fct: aes256-cts/<redacted>"
+        );
+        assert_eq!(
+            redact_trace_message(
+                "Decrypted AS reply; session key is: (camellia128-cts-cmac/09FF), rc4-hmac/E3"
+            ),
+            "Decrypted AS reply; session key is: (camellia128-cts-cmac/<redacted>), \
+             rc4-hmac/<redacted>"
+        );
+        assert_eq!(
+            redact_trace_message("Getting initial credentials for host/abc.example.org@ABC"),
+            "Getting initial credentials for host/abc.example.org@ABC"
+        );
+    }
 }
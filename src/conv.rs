@@ -27,6 +27,14 @@ pub(crate) fn c_string_to_string(c_string: *const c_char) -> Result<String> {
     }
 }
 
+/// Copy `length` bytes from `data`, which may be NULL when `length` is 0
+pub(crate) fn bytes_from_raw(data: *const c_void, length: usize) -> Vec<u8> {
+    if data.is_null() || length == 0 {
+        return Vec::new();
+    }
+    unsafe { std::slice::from_raw_parts(data as *const u8, length) }.to_vec()
+}
+
 /// Convert a [`krb5_timestamp`] to a [`DateTime<Utc>`]
 pub(crate) fn ts_to_dt(ts: i64) -> Result<Option<DateTime<Utc>>> {
     if ts == 0 {
@@ -133,3 +141,71 @@ impl Drop for ParsedName<'_> {
         });
     }
 }
+
+/// Match `name` against a kadm5 query, a shell-style glob expression that can contain `?`, `*`
+/// and `[]`
+///
+/// If the query doesn't contain an `@` character, it is matched against the name without its
+/// realm, like kadm5 does by appending the local realm to the query
+pub(crate) fn query_matches(query: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some(('[', rest)) => {
+                let Some(end) = rest.iter().skip(1).position(|c| *c == ']').map(|i| i + 1) else {
+                    return name.first() == Some(&'[') && matches(rest, &name[1..]);
+                };
+                let Some((c, name_rest)) = name.split_first() else {
+                    return false;
+                };
+                let (negated, set) = match rest[..end].split_first() {
+                    Some(('!' | '^', set)) => (true, set),
+                    _ => (false, &rest[..end]),
+                };
+                let mut found = false;
+                let mut i = 0;
+                while i < set.len() {
+                    if i + 2 < set.len() && set[i + 1] == '-' {
+                        found |= (set[i]..=set[i + 2]).contains(c);
+                        i += 3;
+                    } else {
+                        found |= set[i] == *c;
+                        i += 1;
+                    }
+                }
+                found != negated && matches(&rest[end + 1..], name_rest)
+            }
+            Some((p, rest)) => name.first() == Some(p) && matches(rest, &name[1..]),
+        }
+    }
+
+    let name = if query.contains('@') {
+        name
+    } else {
+        name.split_once('@').map_or(name, |(name, _)| name)
+    };
+    let query: Vec<char> = query.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&query, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_matching() {
+        assert!(query_matches("*", "alice@EXAMPLE.ORG"));
+        assert!(query_matches("ali?e", "alice@EXAMPLE.ORG"));
+        assert!(query_matches("host/*", "host/a.example.org@EXAMPLE.ORG"));
+        assert!(!query_matches("host/*", "alice@EXAMPLE.ORG"));
+        assert!(query_matches("*@EXAMPLE.ORG", "alice@EXAMPLE.ORG"));
+        assert!(!query_matches("alice@OTHER.ORG", "alice@EXAMPLE.ORG"));
+        assert!(query_matches("[a-c]lice", "alice"));
+        assert!(!query_matches("[!a]lice", "alice"));
+        assert!(query_matches("user[12]", "user2"));
+        assert!(!query_matches("user", "user2"));
+    }
+}
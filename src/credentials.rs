@@ -0,0 +1,549 @@
+//! Obtain initial [`Credentials`] into a memory credentials cache
+
+use std::{
+    ffi::{CString, c_void},
+    fmt,
+    ptr::{null, null_mut},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use zeroize::Zeroizing;
+
+use crate::{
+    context::Context,
+    conv::{c_string_to_string, dur_to_delta, parse_name, ts_to_dt},
+    error::{Error, Result, krb5_error_code_escape_hatch},
+    sys::{KAdm5Variant, Library, library_match},
+};
+
+/// Initial credentials for a client principal, stored in a `MEMORY:` credentials cache
+///
+/// Memory credentials caches are shared by the whole process, so [`Self::ccache_name`] can be
+/// passed to [`KAdminBuilder::with_ccache`][`crate::kadmin::KAdminBuilder::with_ccache`], even
+/// for a [`KAdmin`][`crate::kadmin::KAdmin`] built on another thread. This struct however holds a
+/// raw handle to the cache and is neither `Send` nor `Sync`: it must be renewed and dropped on the
+/// thread that created it. The cache is destroyed when this struct is dropped, and as such it must
+/// outlive any [`KAdmin`][`crate::kadmin::KAdmin`] instance initialized from it.
+///
+/// When obtained with a password, the password is kept in memory to get new credentials once the
+/// current ones can't be renewed anymore. It is zeroed when this struct is dropped.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use kadmin::{Credentials, KAdm5Variant, KAdmin, KAdminImpl};
+///
+/// let mut creds = Credentials::builder(KAdm5Variant::MitClient)
+///     .lifetime(Duration::from_secs(3600))
+///     .renewable_lifetime(Duration::from_secs(86400))
+///     .service("kadmin/admin")
+///     .with_password("user/admin@EXAMPLE.ORG", "vErYsEcUrE")
+///     .unwrap();
+/// let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+///     .with_ccache(Some(creds.client_name()), Some(creds.ccache_name()))
+///     .unwrap();
+/// dbg!("{}", kadmin.list_principals(None).unwrap());
+///
+/// creds.renew_if_needed(Duration::from_secs(300)).unwrap();
+/// ```
+pub struct Credentials {
+    context: Context,
+    ccache: *mut c_void,
+    ccache_name: String,
+    client_name: String,
+    /// Source of the credentials, to get new ones once they can't be renewed anymore
+    source: CredentialsSource,
+    options: CredentialsOptions,
+    end_time: Option<DateTime<Utc>>,
+    renew_till: Option<DateTime<Utc>>,
+}
+
+/// Where initial credentials are obtained from
+enum CredentialsSource {
+    /// Password, zeroed when dropped
+    Password(Zeroizing<CString>),
+    /// Keytab name, or the default keytab
+    Keytab(Option<CString>),
+}
+
+/// Options passed to `krb5_get_init_creds_*`
+#[derive(Debug, Default)]
+struct CredentialsOptions {
+    lifetime: Option<Duration>,
+    renewable_lifetime: Option<Duration>,
+    fast_ccache: Option<String>,
+    service: Option<String>,
+}
+
+/// Store `$creds` into `$ccache` for `$client` if `$code` is a success, then free `$creds`
+///
+/// Returns the resulting error code, along with the end and renew-till times of the credentials
+macro_rules! store_creds {
+    ($cont:ident, $context:ident, $ccache:expr, $client:expr, $creds:ident, $code:expr) => {{
+        let mut code = $code;
+        if code == 0 {
+            code = unsafe { $cont.krb5_cc_initialize($context, $ccache, $client) };
+        }
+        if code == 0 {
+            code = unsafe { $cont.krb5_cc_store_cred($context, $ccache, &mut $creds) };
+        }
+        let times = (
+            i64::from($creds.times.endtime),
+            i64::from($creds.times.renew_till),
+        );
+        unsafe { $cont.krb5_free_cred_contents($context, &mut $creds) };
+        (code, times)
+    }};
+}
+
+impl Credentials {
+    /// Construct a new [`CredentialsBuilder`]
+    pub fn builder(variant: KAdm5Variant) -> CredentialsBuilder {
+        CredentialsBuilder::new(variant)
+    }
+
+    /// Create an empty memory credentials cache
+    fn new(
+        context: Context,
+        client_name: &str,
+        source: CredentialsSource,
+        options: CredentialsOptions,
+    ) -> Result<Self> {
+        let (ccache, code) = library_match!(&context.library; |cont, lib| {
+            let mut ccache: lib!(krb5_ccache) = null_mut();
+            let code = unsafe {
+                cont.krb5_cc_new_unique(
+                    context.context as lib!(krb5_context),
+                    c"MEMORY".as_ptr(),
+                    null(),
+                    &mut ccache,
+                )
+            };
+            (ccache as *mut c_void, code)
+        });
+        let mut creds = Self {
+            context,
+            ccache,
+            ccache_name: String::new(),
+            client_name: client_name.to_owned(),
+            source,
+            options,
+            end_time: None,
+            renew_till: None,
+        };
+        krb5_error_code_escape_hatch(&creds.context, code.into())?;
+
+        let (cc_type, cc_name) = library_match!(&creds.context.library; |cont, lib| unsafe {
+            (
+                cont.krb5_cc_get_type(
+                    creds.context.context as lib!(krb5_context),
+                    creds.ccache as lib!(krb5_ccache),
+                ),
+                cont.krb5_cc_get_name(
+                    creds.context.context as lib!(krb5_context),
+                    creds.ccache as lib!(krb5_ccache),
+                ),
+            )
+        });
+        creds.ccache_name = format!(
+            "{}:{}",
+            c_string_to_string(cc_type)?,
+            c_string_to_string(cc_name)?
+        );
+        Ok(creds)
+    }
+
+    /// Name of the credentials cache, suitable for
+    /// [`KAdminBuilder::with_ccache`][`crate::kadmin::KAdminBuilder::with_ccache`]
+    pub fn ccache_name(&self) -> &str {
+        &self.ccache_name
+    }
+
+    /// Name of the client principal the credentials were obtained for
+    pub fn client_name(&self) -> &str {
+        &self.client_name
+    }
+
+    /// When the credentials expire
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        self.end_time
+    }
+
+    /// Until when the credentials can be renewed, if they are renewable
+    pub fn renew_till(&self) -> Option<DateTime<Utc>> {
+        self.renew_till
+    }
+
+    /// Whether the credentials expire within `margin`
+    pub fn needs_renewal(&self, margin: Duration) -> bool {
+        let margin = TimeDelta::from_std(margin).unwrap_or(TimeDelta::MAX);
+        self.end_time.map_or(true, |end_time| {
+            Utc::now()
+                .checked_add_signed(margin)
+                .map_or(true, |now| now >= end_time)
+        })
+    }
+
+    /// Renew the credentials
+    ///
+    /// Renewable credentials are renewed with `krb5_get_renewed_creds`. Once they can't be
+    /// renewed anymore, because they expired, are past their renewable lifetime or the KDC
+    /// refuses to renew them, new credentials are obtained with the password or keytab used
+    /// initially.
+    pub fn renew(&mut self) -> Result<()> {
+        let now = Utc::now();
+        let renewable = self.end_time.is_some_and(|end_time| end_time > now)
+            && self.renew_till.is_some_and(|renew_till| renew_till > now);
+        if renewable && self.get_renewed_creds().is_ok() {
+            return Ok(());
+        }
+        self.get_init_creds()
+    }
+
+    /// Renew the credentials with `krb5_get_renewed_creds`
+    fn get_renewed_creds(&mut self) -> Result<()> {
+        let client = parse_name(&self.context, &self.client_name)?;
+        let service = self
+            .options
+            .service
+            .as_deref()
+            .map(CString::new)
+            .transpose()?;
+        let service = service
+            .as_ref()
+            .map_or(null_mut(), |s| s.as_ptr().cast_mut());
+
+        let (code, (end_time, renew_till)) = library_match!(&self.context.library; |cont, lib| {
+            let context = self.context.context as lib!(krb5_context);
+            let ccache = self.ccache as lib!(krb5_ccache);
+            let client = client.raw as lib!(krb5_principal);
+            let mut creds: lib!(krb5_creds) = Default::default();
+            let code = unsafe {
+                cont.krb5_get_renewed_creds(context, &mut creds, client, ccache, service)
+            };
+            store_creds!(cont, context, ccache, client, creds, code)
+        });
+        krb5_error_code_escape_hatch(&self.context, code.into())?;
+        self.end_time = ts_to_dt(end_time)?;
+        self.renew_till = ts_to_dt(renew_till)?;
+        Ok(())
+    }
+
+    /// Renew the credentials if they expire within `margin`
+    ///
+    /// Returns whether the credentials were renewed
+    pub fn renew_if_needed(&mut self, margin: Duration) -> Result<bool> {
+        if !self.needs_renewal(margin) {
+            return Ok(false);
+        }
+        self.renew()?;
+        Ok(true)
+    }
+
+    /// Obtain initial credentials from the password or keytab they were created with
+    fn get_init_creds(&mut self) -> Result<()> {
+        let client = parse_name(&self.context, &self.client_name)?;
+        let opt = InitCredsOpt::new(&self.context, &self.options)?;
+        let service = self
+            .options
+            .service
+            .as_deref()
+            .map(CString::new)
+            .transpose()?;
+        let service = service
+            .as_ref()
+            .map_or(null_mut(), |s| s.as_ptr().cast_mut());
+        let (password, keytab_name) = match &self.source {
+            CredentialsSource::Password(password) => (Some(password.as_c_str()), null_mut()),
+            CredentialsSource::Keytab(keytab) => (
+                None,
+                keytab
+                    .as_ref()
+                    .map_or(null_mut(), |k| k.as_ptr().cast_mut()),
+            ),
+        };
+
+        let (code, (end_time, renew_till)) = library_match!(&self.context.library; |cont, lib| {
+            let context = self.context.context as lib!(krb5_context);
+            let ccache = self.ccache as lib!(krb5_ccache);
+            let client = client.raw as lib!(krb5_principal);
+            let opt = opt.raw as *mut lib!(krb5_get_init_creds_opt);
+            let mut creds: lib!(krb5_creds) = Default::default();
+            let code = if let Some(password) = password {
+                unsafe {
+                    cont.krb5_get_init_creds_password(
+                        context,
+                        &mut creds,
+                        client,
+                        password.as_ptr().cast_mut(),
+                        None,
+                        null_mut(),
+                        0,
+                        service,
+                        opt,
+                    )
+                }
+            } else {
+                let mut keytab: lib!(krb5_keytab) = null_mut();
+                let code = if keytab_name.is_null() {
+                    unsafe { cont.krb5_kt_default(context, &mut keytab) }
+                } else {
+                    unsafe { cont.krb5_kt_resolve(context, keytab_name, &mut keytab) }
+                };
+                if code == 0 {
+                    let code = unsafe {
+                        cont.krb5_get_init_creds_keytab(
+                            context,
+                            &mut creds,
+                            client,
+                            keytab,
+                            0,
+                            service,
+                            opt,
+                        )
+                    };
+                    unsafe { cont.krb5_kt_close(context, keytab) };
+                    code
+                } else {
+                    code
+                }
+            };
+            store_creds!(cont, context, ccache, client, creds, code)
+        });
+        krb5_error_code_escape_hatch(&self.context, code.into())?;
+        self.end_time = ts_to_dt(end_time)?;
+        self.renew_till = ts_to_dt(renew_till)?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match &self.source {
+            CredentialsSource::Password(_) => "password (redacted)".to_owned(),
+            CredentialsSource::Keytab(Some(keytab)) => format!("keytab {keytab:?}"),
+            CredentialsSource::Keytab(None) => "default keytab".to_owned(),
+        };
+        f.debug_struct("Credentials")
+            .field("ccache_name", &self.ccache_name)
+            .field("client_name", &self.client_name)
+            .field("source", &source)
+            .field("options", &self.options)
+            .field("end_time", &self.end_time)
+            .field("renew_till", &self.renew_till)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Credentials {
+    fn drop(&mut self) {
+        if self.ccache.is_null() {
+            return;
+        }
+        library_match!(&self.context.library; |cont, lib| unsafe {
+            cont.krb5_cc_destroy(
+                self.context.context as lib!(krb5_context),
+                self.ccache as lib!(krb5_ccache),
+            );
+        });
+    }
+}
+
+/// Wrapper around `krb5_get_init_creds_opt` that frees it when dropped
+struct InitCredsOpt<'a> {
+    raw: *mut c_void,
+    context: &'a Context,
+}
+
+impl<'a> InitCredsOpt<'a> {
+    fn new(context: &'a Context, options: &CredentialsOptions) -> Result<Self> {
+        let (raw, code) = library_match!(&context.library; |cont, lib| {
+            let mut raw: *mut lib!(krb5_get_init_creds_opt) = null_mut();
+            let code = unsafe {
+                cont.krb5_get_init_creds_opt_alloc(context.context as lib!(krb5_context), &mut raw)
+            };
+            (raw as *mut c_void, code)
+        });
+        let opt = Self { raw, context };
+        krb5_error_code_escape_hatch(context, code.into())?;
+
+        let lifetime = dur_to_delta(options.lifetime)?;
+        let renewable_lifetime = dur_to_delta(options.renewable_lifetime)?;
+        library_match!(&context.library; |cont, lib| {
+            let raw = opt.raw as *mut lib!(krb5_get_init_creds_opt);
+            if options.lifetime.is_some() {
+                unsafe { cont.krb5_get_init_creds_opt_set_tkt_life(raw, lifetime) };
+            }
+            if options.renewable_lifetime.is_some() {
+                unsafe { cont.krb5_get_init_creds_opt_set_renew_life(raw, renewable_lifetime) };
+            }
+        });
+
+        if let Some(fast_ccache) = &options.fast_ccache {
+            let fast_ccache = CString::new(fast_ccache.as_str())?;
+            let res: Result<()> = library_match!(
+                &context.library;
+                mit_client, mit_server => |cont, lib| {
+                    let code = unsafe {
+                        cont.krb5_get_init_creds_opt_set_fast_ccache_name(
+                            context.context as lib!(krb5_context),
+                            opt.raw as *mut lib!(krb5_get_init_creds_opt),
+                            fast_ccache.as_ptr(),
+                        )
+                    };
+                    krb5_error_code_escape_hatch(context, code.into())
+                },
+                heimdal_client, heimdal_server => |_cont, _lib| {
+                    Err(Error::LibraryMismatch(
+                        "FAST armor is only supported with MIT krb5",
+                    ))
+                }
+            );
+            res?;
+        }
+
+        Ok(opt)
+    }
+}
+
+impl Drop for InitCredsOpt<'_> {
+    fn drop(&mut self) {
+        if self.raw.is_null() {
+            return;
+        }
+        library_match!(&self.context.library; |cont, lib| unsafe {
+            cont.krb5_get_init_creds_opt_free(
+                self.context.context as lib!(krb5_context),
+                self.raw as *mut lib!(krb5_get_init_creds_opt),
+            );
+        });
+    }
+}
+
+/// [`Credentials`] builder
+pub struct CredentialsBuilder {
+    variant: KAdm5Variant,
+    library: Option<Library>,
+    context: Option<Context>,
+
+    options: CredentialsOptions,
+}
+
+impl CredentialsBuilder {
+    /// Create a new [`CredentialsBuilder`] instance
+    pub fn new(variant: KAdm5Variant) -> Self {
+        Self {
+            variant,
+            library: None,
+            context: None,
+
+            options: Default::default(),
+        }
+    }
+
+    /// Set the [`Library`] to use to obtain the credentials
+    pub fn library(mut self, library: Library) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    /// Set the [`Context`] to use to obtain the credentials
+    pub fn context(mut self, context: Context) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Requested lifetime of the credentials
+    pub fn lifetime(mut self, lifetime: Duration) -> Self {
+        self.options.lifetime = Some(lifetime);
+        self
+    }
+
+    /// Requested renewable lifetime of the credentials
+    pub fn renewable_lifetime(mut self, renewable_lifetime: Duration) -> Self {
+        self.options.renewable_lifetime = Some(renewable_lifetime);
+        self
+    }
+
+    /// Name of a credentials cache to use as FAST armor
+    ///
+    /// Only supported with MIT krb5
+    pub fn fast_ccache(mut self, fast_ccache: &str) -> Self {
+        self.options.fast_ccache = Some(fast_ccache.to_owned());
+        self
+    }
+
+    /// Service to get credentials for, instead of the TGS of the client realm
+    ///
+    /// For instance `kadmin/admin`
+    pub fn service(mut self, service: &str) -> Self {
+        self.options.service = Some(service.to_owned());
+        self
+    }
+
+    /// Get the [`Context`] to use from the builder inputs
+    fn get_context(self) -> Result<(Context, CredentialsOptions)> {
+        if self.library.is_some() && self.context.is_some() {
+            return Err(Error::LibraryMismatch(
+                "Both library and context cannot be set at the same time",
+            ));
+        }
+        if let Some(library) = &self.library {
+            if library.variant() != self.variant {
+                return Err(Error::LibraryMismatch(
+                    "Library variant and builder variant don't match",
+                ));
+            }
+        }
+        if let Some(context) = &self.context {
+            if context.library.variant() != self.variant {
+                return Err(Error::LibraryMismatch(
+                    "Context variant and builder variant don't match",
+                ));
+            }
+        }
+
+        let context = match self.context {
+            Some(context) => context,
+            None => Context::new(match self.library {
+                Some(library) => library,
+                None => Library::from_variant(self.variant)?,
+            })?,
+        };
+        Ok((context, self.options))
+    }
+
+    /// Obtain credentials for `client_name` using `password`
+    ///
+    /// The password is kept, zeroed on drop, to get new credentials once they can't be renewed
+    /// anymore
+    pub fn with_password(self, client_name: &str, password: &str) -> Result<Credentials> {
+        let password = Zeroizing::new(CString::new(password)?);
+        let (context, options) = self.get_context()?;
+        let mut creds = Credentials::new(
+            context,
+            client_name,
+            CredentialsSource::Password(password),
+            options,
+        )?;
+        creds.get_init_creds()?;
+        Ok(creds)
+    }
+
+    /// Obtain credentials for `client_name` from a keytab
+    ///
+    /// If no keytab is provided, the default keytab will be used
+    pub fn with_keytab(self, client_name: &str, keytab: Option<&str>) -> Result<Credentials> {
+        let keytab = keytab.map(CString::new).transpose()?;
+        let (context, options) = self.get_context()?;
+        let mut creds = Credentials::new(
+            context,
+            client_name,
+            CredentialsSource::Keytab(keytab),
+            options,
+        )?;
+        creds.get_init_creds()?;
+        Ok(creds)
+    }
+}
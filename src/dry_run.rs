@@ -0,0 +1,487 @@
+//! [`DryRun`] wrapper recording changes instead of applying them
+
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::HashMap,
+};
+
+use zeroize::Zeroizing;
+
+use crate::{
+    batch::{Batch, BatchOperation},
+    conv::query_matches,
+    error::{Error, KAdminErrorKind, Result, kadm5_error},
+    kadmin::KAdminImpl,
+    keysalt::Key,
+    principal::{Principal, PrincipalBuilder, PrincipalBuilderKey, PrincipalModifier},
+    privileges::Privileges,
+    sys::KAdm5Variant,
+};
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::{
+    keysalt::KeySalts,
+    policy::{Policy, PolicyBuilder, PolicyModifier},
+};
+
+/// Wrapper around a [`KAdminImpl`] that records changes instead of applying them
+///
+/// Read operations are passed through to the wrapped implementation. Write operations are checked
+/// like kadm5 would (for instance, adding a principal that already exists fails), recorded as
+/// [`BatchOperation`]s, and applied to an in-memory overlay so that later reads see their effects.
+/// Nothing is ever written to the realm.
+///
+/// The recorded changes can be reviewed with [`Self::changes`], and applied for real later on with
+/// [`Self::batch`]. The `keepold` and `keysalts` options of password changes and random keys are
+/// not recorded.
+///
+/// Keys set with [`KAdminImpl::principal_set_keys`] are returned by
+/// [`KAdminImpl::principal_get_keys`]. The keys of principals created, renamed, or given a new
+/// password or random key during the dry run aren't known until the changes are applied, and
+/// retrieving them fails with [`Error::DryRunKeys`].
+///
+/// Principals changed during the dry run are known by their realm-qualified name, like kadm5 lists
+/// them. The realm appended to names without one is the one of the wrapped implementation's
+/// `krbtgt` principals, so principal names are left as is if those can't be listed.
+///
+/// ```no_run
+/// # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant, Principal, dry_run::DryRun};
+/// # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+/// #     .with_ccache(None, None)
+/// #     .unwrap();
+/// let dry_run = DryRun::new(kadmin);
+/// Principal::builder("alice").create(&dry_run).unwrap();
+/// assert!(dry_run.principal_exists("alice").unwrap());
+/// assert!(!dry_run.inner().principal_exists("alice").unwrap());
+/// for change in dry_run.changes() {
+///     println!("{change:?}");
+/// }
+/// ```
+pub struct DryRun<K: KAdminImpl> {
+    inner: K,
+    changes: RefCell<Vec<BatchOperation>>,
+    /// Realm appended to principal names without one, looked up on first use
+    realm: OnceCell<Option<String>>,
+    /// Principals changed during the dry run, by realm-qualified name. `None` if deleted
+    principals: RefCell<HashMap<String, Option<Principal>>>,
+    /// Keys of the principals whose keys changed during the dry run, by principal name. `None` if
+    /// they are generated by kadm5
+    keys: RefCell<HashMap<String, Option<Vec<Key>>>>,
+    #[cfg(any(mit_client, mit_server))]
+    /// String attributes of the principals changed during the dry run, by principal name
+    strings: RefCell<HashMap<String, HashMap<String, String>>>,
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Policies changed during the dry run, by name. `None` if deleted
+    policies: RefCell<HashMap<String, Option<Policy>>>,
+}
+
+impl<K: KAdminImpl> DryRun<K> {
+    /// Wrap `inner` in a [`DryRun`]
+    pub fn new(inner: K) -> Self {
+        Self {
+            inner,
+            changes: RefCell::default(),
+            realm: OnceCell::new(),
+            principals: RefCell::default(),
+            keys: RefCell::default(),
+            #[cfg(any(mit_client, mit_server))]
+            strings: RefCell::default(),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            policies: RefCell::default(),
+        }
+    }
+
+    /// Wrapped implementation
+    pub fn inner(&self) -> &K {
+        &self.inner
+    }
+
+    /// Consume the [`DryRun`] and return the wrapped implementation
+    pub fn into_inner(self) -> K {
+        self.inner
+    }
+
+    /// Changes recorded so far, in order
+    pub fn changes(&self) -> Vec<BatchOperation> {
+        self.changes.borrow().clone()
+    }
+
+    /// Build a [`Batch`] applying the recorded changes
+    pub fn batch(&self) -> Batch {
+        self.changes
+            .borrow()
+            .iter()
+            .cloned()
+            .fold(Batch::new(), Batch::push)
+    }
+
+    /// Forget the recorded changes and their effects
+    pub fn reset(&self) {
+        self.changes.borrow_mut().clear();
+        self.principals.borrow_mut().clear();
+        self.keys.borrow_mut().clear();
+        #[cfg(any(mit_client, mit_server))]
+        self.strings.borrow_mut().clear();
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        self.policies.borrow_mut().clear();
+    }
+
+    fn record(&self, change: BatchOperation) {
+        self.changes.borrow_mut().push(change);
+    }
+
+    fn error(&self, kind: KAdminErrorKind) -> Error {
+        kadm5_error(self.inner.variant(), kind)
+    }
+
+    /// Append the local realm to `name` if it doesn't have one
+    ///
+    /// [`KAdminImpl`] doesn't expose the local realm, so it is taken from the `krbtgt` principals
+    /// listed by the wrapped implementation, as kadm5 appends the local realm to the query.
+    fn canonical(&self, name: &str) -> String {
+        if name.contains('@') {
+            return name.to_owned();
+        }
+        let realm = match self.realm.get() {
+            Some(realm) => realm,
+            None => {
+                let realm = self
+                    .inner
+                    .list_principals(Some("krbtgt/*"))
+                    .ok()
+                    .and_then(|names| {
+                        names.into_iter().find_map(|name| {
+                            name.rsplit_once('@').map(|(_, realm)| realm.to_owned())
+                        })
+                    });
+                self.realm.get_or_init(|| realm)
+            }
+        };
+        match realm {
+            Some(realm) => format!("{name}@{realm}"),
+            None => name.to_owned(),
+        }
+    }
+
+    /// Retrieve a principal, as changed by the dry run
+    fn principal(&self, name: &str) -> Result<Option<Principal>> {
+        if let Some(principal) = self.principals.borrow().get(&self.canonical(name)) {
+            return Ok(principal.clone());
+        }
+        let Some(principal) = self.inner.get_principal(name)? else {
+            return Ok(None);
+        };
+        Ok(match self.principals.borrow().get(principal.name()) {
+            Some(changed) => changed.clone(),
+            None => Some(principal),
+        })
+    }
+
+    /// Retrieve a principal that must exist
+    fn existing_principal(&self, name: &str) -> Result<Principal> {
+        self.principal(name)?
+            .ok_or_else(|| self.error(KAdminErrorKind::UnknownPrincipal))
+    }
+
+    fn store_keys(&self, principal: &Principal, keys: Option<Vec<Key>>) {
+        self.keys
+            .borrow_mut()
+            .insert(principal.name().to_owned(), keys);
+    }
+
+    fn store_principal(&self, principal: Principal) {
+        self.principals
+            .borrow_mut()
+            .insert(principal.name().to_owned(), Some(principal));
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn check_policy(&self, policy: Option<&Option<String>>) -> Result<()> {
+        if let Some(Some(policy)) = policy {
+            if self.get_policy(policy)?.is_none() {
+                return Err(self.error(KAdminErrorKind::UnknownPolicy));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn check_password(&self, principal: &Principal, password: &str) -> Result<()> {
+        if let Some(policy) = principal.policy() {
+            if let Some(policy) = self.get_policy(policy)? {
+                policy.validate_password(password, Some(principal.name()), None)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn policy(&self, name: &str) -> Result<Option<Policy>> {
+        if let Some(policy) = self.policies.borrow().get(name) {
+            return Ok(policy.clone());
+        }
+        self.inner.get_policy(name)
+    }
+}
+
+/// Merge the names listed by the wrapped implementation with the ones changed during the dry run
+fn list<T>(
+    listed: Vec<String>,
+    changed: &HashMap<String, Option<T>>,
+    query: Option<&str>,
+) -> Vec<String> {
+    let mut names: Vec<String> = listed
+        .into_iter()
+        .filter(|name| !changed.contains_key(name))
+        .collect();
+    let mut added: Vec<String> = changed
+        .iter()
+        .filter(|(name, entry)| {
+            entry.is_some() && query.map_or(true, |query| query_matches(query, name))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    added.sort();
+    names.extend(added);
+    names
+}
+
+impl<K: KAdminImpl> KAdminImpl for DryRun<K> {
+    fn variant(&self) -> KAdm5Variant {
+        self.inner.variant()
+    }
+
+    fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
+        if self.principal(&builder.name)?.is_some() {
+            return Err(self.error(KAdminErrorKind::DuplicatePrincipal));
+        }
+        let name = self.canonical(&builder.name);
+        let principal = Principal::from_builder(builder).renamed(&name);
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        {
+            self.check_policy(builder.policy.as_ref())?;
+            if let PrincipalBuilderKey::Password(password) = &builder.key {
+                self.check_password(&principal, password)?;
+            }
+        }
+        #[cfg(any(mit_client, mit_server))]
+        self.strings.borrow_mut().insert(name, HashMap::new());
+        let keys = matches!(builder.key, PrincipalBuilderKey::NoKey).then(Vec::new);
+        self.store_keys(&principal, keys);
+        self.store_principal(principal);
+        self.record(BatchOperation::AddPrincipal(builder.clone()));
+        Ok(())
+    }
+
+    fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
+        let mut principal = self.existing_principal(&modifier.name)?;
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        self.check_policy(modifier.policy.as_ref())?;
+        modifier.apply_to(&mut principal);
+        self.store_principal(principal);
+        self.record(BatchOperation::ModifyPrincipal(modifier.clone()));
+        Ok(())
+    }
+
+    fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let principal = self.existing_principal(old_name)?;
+        if self.principal(new_name)?.is_some() {
+            return Err(self.error(KAdminErrorKind::DuplicatePrincipal));
+        }
+        let canonical_name = self.canonical(new_name);
+        #[cfg(any(mit_client, mit_server))]
+        {
+            let strings = self.principal_get_strings(old_name)?;
+            let mut overlay = self.strings.borrow_mut();
+            overlay.remove(principal.name());
+            overlay.insert(canonical_name.clone(), strings);
+        }
+        // Keys set during the dry run are kept, the realm ones can't be retrieved under the new
+        // name
+        let keys = self.keys.borrow_mut().remove(principal.name()).flatten();
+        self.principals
+            .borrow_mut()
+            .insert(principal.name().to_owned(), None);
+        let principal = principal.renamed(&canonical_name);
+        self.store_keys(&principal, keys);
+        self.store_principal(principal);
+        self.record(BatchOperation::RenamePrincipal {
+            old_name: old_name.to_owned(),
+            new_name: new_name.to_owned(),
+        });
+        Ok(())
+    }
+
+    fn delete_principal(&self, name: &str) -> Result<()> {
+        let principal = self.existing_principal(name)?;
+        #[cfg(any(mit_client, mit_server))]
+        self.strings.borrow_mut().remove(principal.name());
+        self.keys.borrow_mut().remove(principal.name());
+        self.principals
+            .borrow_mut()
+            .insert(principal.name().to_owned(), None);
+        self.record(BatchOperation::DeletePrincipal(name.to_owned()));
+        Ok(())
+    }
+
+    fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
+        self.principal(name)
+    }
+
+    fn principal_change_password(
+        &self,
+        name: &str,
+        password: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        let mut principal = self.existing_principal(name)?;
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        self.check_password(&principal, password)?;
+        principal.rotate_keys();
+        self.store_keys(&principal, None);
+        self.store_principal(principal);
+        self.record(BatchOperation::ChangePassword {
+            name: name.to_owned(),
+            password: Zeroizing::new(password.to_owned()),
+        });
+        Ok(())
+    }
+
+    fn principal_randkey(
+        &self,
+        name: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        let mut principal = self.existing_principal(name)?;
+        principal.rotate_keys();
+        self.store_keys(&principal, None);
+        self.store_principal(principal);
+        self.record(BatchOperation::Randkey(name.to_owned()));
+        Ok(())
+    }
+
+    fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+        let principal = self.existing_principal(name)?;
+        match self.keys.borrow().get(principal.name()) {
+            Some(Some(keys)) => Ok(keys
+                .iter()
+                .filter(|key| kvno.map_or(true, |kvno| key.kvno == kvno))
+                .cloned()
+                .collect()),
+            Some(None) => Err(Error::DryRunKeys(principal.name().to_owned())),
+            None => self.inner.principal_get_keys(name, kvno),
+        }
+    }
+
+    fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+        let mut principal = self.existing_principal(name)?;
+        principal.set_keys(keys);
+        let keys = if keepold {
+            match self.keys.borrow().get(principal.name()) {
+                Some(Some(old)) => Some(old.iter().chain(keys).cloned().collect()),
+                // The kept keys are generated by kadm5 or only known to the realm
+                _ => None,
+            }
+        } else {
+            Some(keys.to_vec())
+        };
+        self.store_keys(&principal, keys);
+        self.store_principal(principal);
+        self.record(BatchOperation::SetKeys {
+            name: name.to_owned(),
+            keepold,
+            keys: keys.to_vec(),
+        });
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
+        let principal = self.existing_principal(name)?;
+        if let Some(strings) = self.strings.borrow().get(principal.name()) {
+            return Ok(strings.clone());
+        }
+        self.inner.principal_get_strings(name)
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_set_string(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let principal = self.existing_principal(name)?;
+        let mut strings = self.principal_get_strings(name)?;
+        match value {
+            Some(value) => strings.insert(key.to_owned(), value.to_owned()),
+            None => strings.remove(key),
+        };
+        self.strings
+            .borrow_mut()
+            .insert(principal.name().to_owned(), strings);
+        self.record(BatchOperation::SetString {
+            name: name.to_owned(),
+            key: key.to_owned(),
+            value: value.map(String::from),
+        });
+        Ok(())
+    }
+
+    fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
+        Ok(list(
+            self.inner.list_principals(query)?,
+            &self.principals.borrow(),
+            query,
+        ))
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
+        if self.policy(&builder.name)?.is_some() {
+            return Err(self.error(KAdminErrorKind::DuplicatePolicy));
+        }
+        self.policies
+            .borrow_mut()
+            .insert(builder.name.clone(), Some(Policy::from_builder(builder)));
+        self.record(BatchOperation::AddPolicy(builder.clone()));
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
+        let Some(mut policy) = self.policy(&modifier.name)? else {
+            return Err(self.error(KAdminErrorKind::UnknownPolicy));
+        };
+        modifier.apply_to(&mut policy);
+        self.policies
+            .borrow_mut()
+            .insert(modifier.name.clone(), Some(policy));
+        self.record(BatchOperation::ModifyPolicy(modifier.clone()));
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delete_policy(&self, name: &str) -> Result<()> {
+        if self.policy(name)?.is_none() {
+            return Err(self.error(KAdminErrorKind::UnknownPolicy));
+        }
+        self.policies.borrow_mut().insert(name.to_owned(), None);
+        self.record(BatchOperation::DeletePolicy(name.to_owned()));
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+        self.policy(name)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
+        Ok(list(
+            self.inner.list_policies(query)?,
+            &self.policies.borrow(),
+            query,
+        ))
+    }
+
+    fn get_privileges(&self) -> Result<Privileges> {
+        self.inner.get_privileges()
+    }
+}
@@ -0,0 +1,85 @@
+//! Text dumps of a KDC database
+//!
+//! Dumps contain the whole database, including principal keys, and can be edited and loaded back
+//! into a KDC. They are read and written without any Kerberos library, so they can be processed
+//! offline.
+//!
+//! * [`mit`]: MIT krb5 `kdb5_util dump` format
+//! * [`heimdal`]: Heimdal `kadmin -l dump` format, convertible to and from the MIT one
+
+use std::fmt;
+
+use crate::{
+    error::{Error, Result},
+    keysalt::{EncryptionType, SaltType},
+};
+
+pub mod heimdal;
+pub mod mit;
+
+/// A principal key, as stored in the KDC database
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct KeyData {
+    /// Key version number
+    pub kvno: u32,
+    /// Master key version number the key is encrypted with, if recorded with the key
+    ///
+    /// Heimdal records it for each key. MIT records it for the whole principal, see
+    /// [`mit::DumpPrincipal::mkvno`], and leaves it unset.
+    pub mkvno: Option<u32>,
+    /// Encryption type of the key
+    pub enctype: EncryptionType,
+    /// Key contents, encrypted with the master key
+    pub contents: Vec<u8>,
+    /// Salt used to derive the key from a password, if stored
+    pub salt: Option<Salt>,
+}
+
+/// Salt of a [`KeyData`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Salt {
+    /// Salt type
+    pub salttype: SaltType,
+    /// Salt value, empty for salt types which derive it from the principal name
+    pub contents: Vec<u8>,
+}
+
+/// Build an [`Error::DumpParse`] for line `line`
+fn parse_error(line: usize, message: impl fmt::Display) -> Error {
+    Error::DumpParse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Decode hexadecimal `data`
+pub(crate) fn from_hex(data: &str) -> Option<Vec<u8>> {
+    if data.len() % 2 != 0 || !data.is_ascii() {
+        return None;
+    }
+    (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&data[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Encode `data` as lowercase hexadecimal
+pub(crate) fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        assert_eq!(to_hex(&[0x00, 0x1f, 0xab]), "001fab");
+        assert_eq!(from_hex("001fAB"), Some(vec![0x00, 0x1f, 0xab]));
+        assert_eq!(from_hex(""), Some(vec![]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
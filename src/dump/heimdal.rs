@@ -0,0 +1,1067 @@
+//! Heimdal `kadmin -l dump` format
+//!
+//! Each line of the dump is a principal entry, written by Heimdal's `hdb_entry2string`. Heimdal
+//! doesn't dump policies.
+//!
+//! Records can be converted from [MIT records][`super::mit`] with [`From`], and to them with
+//! [`TryFrom`] as some values may not fit in MIT records. The conversion translates attribute
+//! flags, salt types and the TL-data MIT uses for what Heimdal stores in HDB extensions. It is
+//! lossy:
+//!
+//! * key contents are copied as-is. They are encrypted with the source KDC master key, so keys of
+//!   converted principals must be reset unless that master key is also used by the destination KDC
+//! * only the keys of the current key version number are converted
+//! * MIT records a single master key version number per principal, the one of the first Heimdal key
+//!   is used
+//! * MIT string attributes, authentication statistics and policies have no Heimdal equivalent
+//! * Heimdal HDB extensions other than the last password change and the policy have no MIT
+//!   equivalent
+//!
+//! ```no_run
+//! # use std::fs;
+//! # use crate::kadmin::dump::{heimdal, mit};
+//! let heimdal = heimdal::Dump::from_file("heimdal.dump").unwrap();
+//! let mit = mit::Dump::try_from(&heimdal).unwrap();
+//! fs::write("mit.dump", mit.to_text().unwrap()).unwrap();
+//! ```
+
+use std::{fmt, fs, path::Path, str::FromStr, time::Duration};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use super::{KeyData, Salt, from_hex, mit, parse_error, to_hex};
+use crate::{
+    conv::delta_to_dur,
+    error::{Error, Result},
+    keysalt::{Key, KeySalt},
+    principal::Principal,
+    sys::KAdm5Variant,
+};
+
+/// Format of timestamps, in UTC
+const TIME_FORMAT: &str = "%Y%m%d%H%M%S";
+/// Name written for events without a principal
+const UNKNOWN_PRINCIPAL: &str = "<unknown>";
+
+const HDB_INITIAL: u32 = 1 << 0;
+const HDB_FORWARDABLE: u32 = 1 << 1;
+const HDB_PROXIABLE: u32 = 1 << 2;
+const HDB_RENEWABLE: u32 = 1 << 3;
+const HDB_POSTDATE: u32 = 1 << 4;
+const HDB_SERVER: u32 = 1 << 5;
+const HDB_CLIENT: u32 = 1 << 6;
+const HDB_INVALID: u32 = 1 << 7;
+const HDB_REQUIRE_PREAUTH: u32 = 1 << 8;
+const HDB_CHANGE_PW: u32 = 1 << 9;
+const HDB_REQUIRE_HWAUTH: u32 = 1 << 10;
+const HDB_OK_AS_DELEGATE: u32 = 1 << 11;
+const HDB_TRUSTED_FOR_DELEGATION: u32 = 1 << 14;
+const HDB_ALLOW_DIGEST: u32 = 1 << 16;
+const HDB_REQUIRE_PWCHANGE: u32 = 1 << 18;
+const HDB_NO_AUTH_DATA_REQD: u32 = 1 << 23;
+
+/// HDB flags and the kadm5 attributes they translate to, as done by Heimdal's kadm5 library
+///
+/// Each item is the HDB flag, whether the attribute is set when the flag is unset, then the MIT
+/// and Heimdal attribute values, 0 when there is none.
+const ATTRIBUTES: [(u32, bool, i32, i32); 15] = [
+    (HDB_POSTDATE, true, 0x0000_0001, 0x0000_0001),
+    (HDB_FORWARDABLE, true, 0x0000_0002, 0x0000_0002),
+    (HDB_INITIAL, false, 0x0000_0004, 0x0000_0004),
+    (HDB_RENEWABLE, true, 0x0000_0008, 0x0000_0008),
+    (HDB_PROXIABLE, true, 0x0000_0010, 0x0000_0010),
+    (HDB_INVALID, false, 0x0000_0040, 0x0000_0040),
+    (HDB_REQUIRE_PREAUTH, false, 0x0000_0080, 0x0000_0080),
+    (HDB_REQUIRE_HWAUTH, false, 0x0000_0100, 0x0000_0100),
+    (HDB_REQUIRE_PWCHANGE, false, 0x0000_0200, 0x0000_0200),
+    (HDB_SERVER, true, 0x0000_1000, 0x0000_1000),
+    (HDB_CHANGE_PW, false, 0x0000_2000, 0x0000_2000),
+    (HDB_OK_AS_DELEGATE, false, 0x0010_0000, 0x0001_0000),
+    (HDB_TRUSTED_FOR_DELEGATION, false, 0x0020_0000, 0x0002_0000),
+    (HDB_ALLOW_DIGEST, false, 0, 0x0008_0000),
+    (HDB_NO_AUTH_DATA_REQD, false, 0x0040_0000, 0x0100_0000),
+];
+
+/// `hdb_pw_salt`
+const HDB_PW_SALT: i32 = 3;
+/// `hdb_afs3_salt`
+const HDB_AFS3_SALT: i32 = 10;
+
+/// `KRB5_KDB_SALTTYPE_V4`
+const MIT_SALTTYPE_V4: i32 = 1;
+/// `KRB5_KDB_SALTTYPE_NOREALM`
+const MIT_SALTTYPE_NOREALM: i32 = 2;
+/// `KRB5_KDB_SALTTYPE_ONLYREALM`
+const MIT_SALTTYPE_ONLYREALM: i32 = 3;
+/// `KRB5_KDB_SALTTYPE_SPECIAL`
+const MIT_SALTTYPE_SPECIAL: i32 = 4;
+/// `KRB5_KDB_SALTTYPE_AFS3`
+const MIT_SALTTYPE_AFS3: i32 = 5;
+
+/// `last-pw-change` choice of `HDB-extension`
+const EXTENSION_LAST_PW_CHANGE: u8 = 7;
+/// `policy` choice of `HDB-extension`
+const EXTENSION_POLICY: u8 = 12;
+
+/// A Heimdal database dump
+///
+/// It can be parsed with [`str::parse`] or [`Dump::from_file`], and written back with its
+/// [`Display`][`fmt::Display`] implementation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Dump {
+    /// Principal records
+    pub principals: Vec<DumpPrincipal>,
+}
+
+impl Dump {
+    /// Construct a new empty [`Dump`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a [`Dump`] from the file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+impl FromStr for Dump {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut dump = Self::new();
+        for (i, contents) in s.lines().enumerate() {
+            if contents.is_empty() {
+                continue;
+            }
+            dump.principals.push(DumpPrincipal::parse(i + 1, contents)?);
+        }
+        Ok(dump)
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for principal in &self.principals {
+            writeln!(f, "{principal}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&mit::Dump> for Dump {
+    fn from(dump: &mit::Dump) -> Self {
+        Self {
+            principals: dump.principals.iter().map(DumpPrincipal::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<&Dump> for mit::Dump {
+    type Error = Error;
+
+    fn try_from(dump: &Dump) -> Result<Self> {
+        let mut mit = Self::new();
+        mit.principals = dump
+            .principals
+            .iter()
+            .map(mit::DumpPrincipal::try_from)
+            .collect::<Result<_>>()?;
+        Ok(mit)
+    }
+}
+
+/// Creation or modification of a principal
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Event {
+    /// When the event occurred
+    pub time: DateTime<Utc>,
+    /// Principal that caused the event, if known
+    pub principal: Option<String>,
+}
+
+impl Event {
+    /// Event at the epoch by an unknown principal, for records which don't have any
+    fn unknown() -> Self {
+        Self {
+            time: DateTime::UNIX_EPOCH,
+            principal: None,
+        }
+    }
+}
+
+/// Generation number of a principal, used for replication
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Generation {
+    /// When the principal was last changed
+    pub time: DateTime<Utc>,
+    /// Microseconds part of `time`
+    pub usec: u32,
+    /// Generation number
+    pub number: u32,
+}
+
+/// Principal record of a [`Dump`]
+///
+/// [`DumpPrincipal::keys`] all have the principal [`DumpPrincipal::kvno`]. Their salt types are
+/// Heimdal's, and differ from MIT's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DumpPrincipal {
+    /// Principal name
+    pub name: String,
+    /// Current key version number
+    pub kvno: u32,
+    /// Keys of the principal
+    pub keys: Vec<KeyData>,
+    /// When and by whom the principal was created
+    pub created: Event,
+    /// When and by whom the principal was last modified
+    pub modified: Option<Event>,
+    /// When the principal becomes valid
+    pub valid_start: Option<DateTime<Utc>>,
+    /// When the principal expires
+    pub valid_end: Option<DateTime<Utc>>,
+    /// When the password expires
+    pub password_end: Option<DateTime<Utc>>,
+    /// Maximum ticket life
+    pub max_life: Option<Duration>,
+    /// Maximum renewable ticket life
+    pub max_renewable_life: Option<Duration>,
+    /// HDB flags
+    ///
+    /// Use [`DumpPrincipal::attributes`] for kadm5 attributes
+    pub flags: u32,
+    /// Generation number
+    pub generation: Option<Generation>,
+    /// DER-encoded HDB extensions
+    pub extensions: Vec<Vec<u8>>,
+}
+
+impl DumpPrincipal {
+    /// Construct a new [`DumpPrincipal`] without any key
+    ///
+    /// The principal is usable as a client and server, like Heimdal's kadmin would create it.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            kvno: 1,
+            keys: Vec::new(),
+            created: Event::unknown(),
+            modified: None,
+            valid_start: None,
+            valid_end: None,
+            password_end: None,
+            max_life: None,
+            max_renewable_life: None,
+            flags: HDB_FORWARDABLE
+                | HDB_PROXIABLE
+                | HDB_RENEWABLE
+                | HDB_POSTDATE
+                | HDB_SERVER
+                | HDB_CLIENT,
+            generation: None,
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Construct a [`DumpPrincipal`] from a [`Principal`] retrieved from a Heimdal server
+    ///
+    /// kadm5 doesn't expose principal keys, so the record doesn't have any.
+    pub fn from_principal(principal: &Principal) -> Self {
+        let modified = principal.modified_at().map(|time| Event {
+            time,
+            principal: principal.modified_by().map(String::from),
+        });
+        let mut record = Self {
+            kvno: principal.kvno(),
+            created: modified.clone().unwrap_or_else(Event::unknown),
+            modified,
+            valid_end: principal.expire_time(),
+            password_end: principal.password_expiration(),
+            max_life: principal.max_life(),
+            max_renewable_life: principal.max_renewable_life(),
+            ..Self::new(principal.name())
+        };
+        record.flags = attributes_to_flags(principal.attributes(), false);
+        if let Some(last_password_change) = principal.last_password_change() {
+            record.set_last_password_change(last_password_change);
+        }
+        record.set_policy(principal.policy());
+        record
+    }
+
+    /// Construct the [`Principal`] described by this record
+    pub fn to_principal(&self) -> Principal {
+        Principal::from_heimdal_dump(self)
+    }
+
+    /// Master key version number of the first key which records one
+    ///
+    /// This is the one Heimdal's kadm5 library reports for the principal.
+    pub fn mkvno(&self) -> Option<u32> {
+        self.keys.iter().find_map(|key| key.mkvno)
+    }
+
+    /// kadm5 attributes of the principal, as the kadm5 library of `variant` would return them
+    pub fn attributes(&self, variant: KAdm5Variant) -> i32 {
+        flags_to_attributes(self.flags, variant.is_mit())
+    }
+
+    /// kadm5 attributes of the principal, as Heimdal's kadm5 library would return them
+    pub(crate) fn heimdal_attributes(&self) -> i32 {
+        flags_to_attributes(self.flags, false)
+    }
+
+    /// Set the HDB flags from kadm5 attributes, as the kadm5 library of `variant` would set them
+    ///
+    /// HDB flags without a kadm5 attribute equivalent are kept.
+    pub fn set_attributes(&mut self, attributes: i32, variant: KAdm5Variant) {
+        let mapped = ATTRIBUTES
+            .iter()
+            .fold(0, |mapped, (flag, ..)| mapped | flag);
+        self.flags = (self.flags & !mapped) | attributes_to_flags(attributes, variant.is_mit());
+    }
+
+    /// When the password was last changed
+    pub fn last_password_change(&self) -> Option<DateTime<Utc>> {
+        let (tag, value) = der::read(self.extension(EXTENSION_LAST_PW_CHANGE)?)?;
+        if tag != der::GENERALIZED_TIME {
+            return None;
+        }
+        parse_time(std::str::from_utf8(value).ok()?.strip_suffix('Z')?)
+    }
+
+    /// Set when the password was last changed
+    pub fn set_last_password_change(&mut self, at: DateTime<Utc>) {
+        let time = format!("{}Z", at.format(TIME_FORMAT));
+        self.set_extension(
+            EXTENSION_LAST_PW_CHANGE,
+            Some(der::write(der::GENERALIZED_TIME, time.as_bytes())),
+        );
+    }
+
+    /// Associated policy
+    pub fn policy(&self) -> Option<String> {
+        let (tag, value) = der::read(self.extension(EXTENSION_POLICY)?)?;
+        if tag != der::UTF8_STRING {
+            return None;
+        }
+        String::from_utf8(value.to_vec()).ok()
+    }
+
+    /// Set the associated policy, or clear it with `None`
+    pub fn set_policy(&mut self, policy: Option<&str>) {
+        self.set_extension(
+            EXTENSION_POLICY,
+            policy.map(|policy| der::write(der::UTF8_STRING, policy.as_bytes())),
+        );
+    }
+
+    /// DER-encoded value of the extension with choice `choice`
+    fn extension(&self, choice: u8) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find_map(|extension| der::extension(extension).filter(|(c, _)| *c == choice))
+            .map(|(_, value)| value)
+    }
+
+    /// Replace the extension with choice `choice`, remove it with `None`
+    fn set_extension(&mut self, choice: u8, value: Option<Vec<u8>>) {
+        let position = self
+            .extensions
+            .iter()
+            .position(|extension| der::extension(extension).is_some_and(|(c, _)| c == choice));
+        match (position, value) {
+            (Some(position), Some(value)) => {
+                self.extensions[position] = der::write_extension(choice, &value)
+            }
+            (None, Some(value)) => self.extensions.push(der::write_extension(choice, &value)),
+            (Some(position), None) => {
+                self.extensions.remove(position);
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn parse(line: usize, contents: &str) -> Result<Self> {
+        let mut fields = split_fields(contents);
+        let mut next = |what: &str| {
+            fields
+                .next()
+                .ok_or_else(|| parse_error(line, format!("missing {what}")))
+        };
+
+        let mut principal = Self::new(next("name")?);
+
+        let mut keys = next("keys")?.split(':');
+        let kvno = keys.next().unwrap_or_default();
+        principal.kvno = kvno
+            .parse()
+            .map_err(|_| parse_error(line, format!("invalid key version number {kvno:?}")))?;
+        let keys: Vec<&str> = keys.collect();
+        if keys.len() % 4 != 0 {
+            return Err(parse_error(line, "invalid keys"));
+        }
+        for key in keys.chunks(4) {
+            let mkvno = match key[0] {
+                "" => None,
+                mkvno => Some(mkvno.parse().map_err(|_| {
+                    parse_error(line, format!("invalid master key version number {mkvno:?}"))
+                })?),
+            };
+            let enctype = key[1]
+                .parse::<i32>()
+                .map_err(|_| parse_error(line, format!("invalid encryption type {:?}", key[1])))?
+                .into();
+            let contents = decode_data(key[2]).ok_or_else(|| parse_error(line, "invalid key"))?;
+            let salt = match key[3] {
+                "-" => None,
+                salt => {
+                    let (salttype, contents) = salt
+                        .split_once('/')
+                        .ok_or_else(|| parse_error(line, "invalid salt"))?;
+                    Some(Salt {
+                        salttype: salttype
+                            .parse::<i32>()
+                            .map_err(|_| {
+                                parse_error(line, format!("invalid salt type {salttype:?}"))
+                            })?
+                            .into(),
+                        contents: decode_data(contents)
+                            .ok_or_else(|| parse_error(line, "invalid salt"))?,
+                    })
+                }
+            };
+            principal.keys.push(KeyData {
+                kvno: principal.kvno,
+                mkvno,
+                enctype,
+                contents,
+                salt,
+            });
+        }
+
+        principal.created = parse_event(line, next("creation")?)?
+            .ok_or_else(|| parse_error(line, "missing creation"))?;
+        principal.modified = parse_event(line, next("modification")?)?;
+        principal.valid_start = parse_optional_time(line, next("valid start")?)?;
+        principal.valid_end = parse_optional_time(line, next("valid end")?)?;
+        principal.password_end = parse_optional_time(line, next("password end")?)?;
+        principal.max_life = parse_delta(line, next("max life")?)?;
+        principal.max_renewable_life = parse_delta(line, next("max renewable life")?)?;
+        let flags = next("flags")?;
+        principal.flags = flags
+            .parse::<i64>()
+            .map_err(|_| parse_error(line, format!("invalid flags {flags:?}")))?
+            as u32;
+        principal.generation = match next("generation")? {
+            "-" => None,
+            generation => {
+                let mut parts = generation.split(':');
+                let invalid = || parse_error(line, format!("invalid generation {generation:?}"));
+                let time = parts.next().and_then(parse_time).ok_or_else(invalid)?;
+                let usec = parts
+                    .next()
+                    .and_then(|usec| usec.parse().ok())
+                    .ok_or_else(invalid)?;
+                let number = parts
+                    .next()
+                    .and_then(|number| number.parse().ok())
+                    .ok_or_else(invalid)?;
+                if parts.next().is_some() {
+                    return Err(invalid());
+                }
+                Some(Generation { time, usec, number })
+            }
+        };
+        principal.extensions = match next("extensions")? {
+            "-" => Vec::new(),
+            extensions => extensions
+                .split(':')
+                .map(|extension| {
+                    decode_data(extension).ok_or_else(|| parse_error(line, "invalid extension"))
+                })
+                .collect::<Result<_>>()?,
+        };
+        if fields.next().is_some() {
+            return Err(parse_error(line, "unexpected trailing fields"));
+        }
+        Ok(principal)
+    }
+}
+
+impl fmt::Display for DumpPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.kvno)?;
+        for key in &self.keys {
+            match key.mkvno {
+                Some(mkvno) => write!(f, ":{mkvno}:")?,
+                None => write!(f, "::")?,
+            }
+            write!(
+                f,
+                "{}:{}:",
+                i32::from(key.enctype),
+                encode_data(&key.contents)
+            )?;
+            match &key.salt {
+                Some(salt) => write!(
+                    f,
+                    "{}/{}",
+                    i32::from(salt.salttype),
+                    encode_data(&salt.contents)
+                )?,
+                None => write!(f, "-")?,
+            }
+        }
+        for event in [Some(&self.created), self.modified.as_ref()] {
+            match event {
+                Some(event) => write!(
+                    f,
+                    " {}:{}",
+                    event.time.format(TIME_FORMAT),
+                    event.principal.as_deref().unwrap_or(UNKNOWN_PRINCIPAL)
+                )?,
+                None => write!(f, " -")?,
+            }
+        }
+        for time in [self.valid_start, self.valid_end, self.password_end] {
+            match time {
+                Some(time) => write!(f, " {}", time.format(TIME_FORMAT))?,
+                None => write!(f, " -")?,
+            }
+        }
+        for delta in [self.max_life, self.max_renewable_life] {
+            match delta {
+                Some(delta) => write!(f, " {}", delta.as_secs())?,
+                None => write!(f, " -")?,
+            }
+        }
+        write!(f, " {}", self.flags as i32)?;
+        match &self.generation {
+            Some(generation) => write!(
+                f,
+                " {}:{}:{}",
+                generation.time.format(TIME_FORMAT),
+                generation.usec,
+                generation.number
+            )?,
+            None => write!(f, " -")?,
+        }
+        if self.extensions.is_empty() {
+            write!(f, " -")
+        } else {
+            let extensions: Vec<String> = self
+                .extensions
+                .iter()
+                .map(|extension| encode_data(extension))
+                .collect();
+            write!(f, " {}", extensions.join(":"))
+        }
+    }
+}
+
+impl From<&mit::DumpPrincipal> for DumpPrincipal {
+    fn from(principal: &mit::DumpPrincipal) -> Self {
+        let kvno = principal.kvno();
+        let modified = principal.modified_at().map(|time| Event {
+            time,
+            principal: principal.modified_by(),
+        });
+        let mut record = Self {
+            kvno,
+            keys: principal
+                .keys
+                .iter()
+                .filter(|key| key.kvno == kvno)
+                .map(|key| KeyData {
+                    mkvno: principal.mkvno(),
+                    salt: salt_from_mit(&principal.name, key.salt.as_ref()),
+                    ..key.clone()
+                })
+                .collect(),
+            created: modified.clone().unwrap_or_else(Event::unknown),
+            modified,
+            valid_end: principal.expire_time,
+            password_end: principal.password_expiration,
+            max_life: principal.max_life,
+            max_renewable_life: principal.max_renewable_life,
+            flags: attributes_to_flags(principal.attributes, true),
+            ..Self::new(&principal.name)
+        };
+        if let Some(last_password_change) = principal.last_password_change() {
+            record.set_last_password_change(last_password_change);
+        }
+        record.set_policy(principal.policy().as_deref());
+        record
+    }
+}
+
+impl TryFrom<&DumpPrincipal> for mit::DumpPrincipal {
+    type Error = Error;
+
+    fn try_from(principal: &DumpPrincipal) -> Result<Self> {
+        let mut record = Self::new(&principal.name);
+        record.attributes = flags_to_attributes(principal.flags, true);
+        record.max_life = principal.max_life;
+        record.max_renewable_life = principal.max_renewable_life;
+        record.expire_time = principal.valid_end;
+        record.password_expiration = principal.password_end;
+        record.keys = principal
+            .keys
+            .iter()
+            .map(|key| KeyData {
+                mkvno: None,
+                salt: salt_to_mit(&principal.name, key.salt.as_ref()),
+                ..key.clone()
+            })
+            .collect();
+        if let Some(last_password_change) = principal.last_password_change() {
+            record.set_last_password_change(last_password_change)?;
+        }
+        let modified = principal.modified.as_ref().unwrap_or(&principal.created);
+        if let Some(by) = &modified.principal {
+            record.set_modified(modified.time, by)?;
+        }
+        if let Some(mkvno) = principal.mkvno() {
+            record.set_mkvno(mkvno)?;
+        }
+        if let Some(policy) = principal.policy() {
+            record.set_policy(Some(&policy));
+        }
+        Ok(record)
+    }
+}
+
+fn flags_to_attributes(flags: u32, mit: bool) -> i32 {
+    ATTRIBUTES
+        .iter()
+        .filter(|(flag, inverted, ..)| (flags & flag != 0) != *inverted)
+        .fold(0, |attributes, (_, _, mit_attribute, heimdal_attribute)| {
+            attributes
+                | if mit {
+                    mit_attribute
+                } else {
+                    heimdal_attribute
+                }
+        })
+}
+
+fn attributes_to_flags(attributes: i32, mit: bool) -> u32 {
+    ATTRIBUTES
+        .iter()
+        .filter(|(_, inverted, mit_attribute, heimdal_attribute)| {
+            let attribute = if mit {
+                *mit_attribute
+            } else {
+                *heimdal_attribute
+            };
+            // Flags without an equivalent attribute are left unset
+            attribute != 0 && (attributes & attribute != 0) != *inverted
+        })
+        .fold(HDB_CLIENT, |flags, (flag, ..)| flags | flag)
+}
+
+/// Translate kadm5 attributes from the values of `from` to the values of `to`
+///
+/// Attributes without an equivalent in both are dropped.
+#[cfg_attr(not(feature = "migrate"), allow(dead_code))]
+pub(crate) fn translate_attributes(attributes: i32, from: KAdm5Variant, to: KAdm5Variant) -> i32 {
+    if from.is_mit() == to.is_mit() {
+        return attributes;
+    }
+    flags_to_attributes(attributes_to_flags(attributes, from.is_mit()), to.is_mit())
+}
+
+/// Translate the salt of a kadm5 key of `name` from the values of `from` to the values of `to`
+///
+/// Keys without a stored salt use the default salt of the principal with both.
+#[cfg_attr(not(feature = "migrate"), allow(dead_code))]
+pub(crate) fn translate_key(name: &str, key: &Key, from: KAdm5Variant, to: KAdm5Variant) -> Key {
+    if from.is_mit() == to.is_mit() {
+        return key.clone();
+    }
+    let salt = Salt {
+        salttype: key.keysalt.salttype,
+        contents: key.salt.clone(),
+    };
+    let unsalted = i32::from(salt.salttype) == 0 && salt.contents.is_empty();
+    let salt = if from.is_mit() {
+        salt_from_mit(name, Some(&salt)).unwrap_or_else(|| {
+            let (components, realm) = default_salt(name);
+            Salt {
+                salttype: HDB_PW_SALT.into(),
+                contents: [realm, components].concat(),
+            }
+        })
+    } else if unsalted {
+        // KRB5_KDB_SALTTYPE_NORMAL
+        salt
+    } else {
+        salt_to_mit(name, Some(&salt)).unwrap_or(Salt {
+            salttype: 0.into(),
+            contents: Vec::new(),
+        })
+    };
+    Key {
+        keysalt: KeySalt {
+            salttype: salt.salttype,
+            ..key.keysalt
+        },
+        salt: salt.contents,
+        ..key.clone()
+    }
+}
+
+/// Default salt of a principal, the realm followed by the name components
+fn default_salt(name: &str) -> (Vec<u8>, Vec<u8>) {
+    let (components, realm) = name.rsplit_once('@').unwrap_or((name, ""));
+    (
+        components.replace('/', "").into_bytes(),
+        realm.as_bytes().to_vec(),
+    )
+}
+
+fn salt_from_mit(name: &str, salt: Option<&Salt>) -> Option<Salt> {
+    let salt = salt?;
+    let (components, realm) = default_salt(name);
+    let (salttype, contents) = match i32::from(salt.salttype) {
+        MIT_SALTTYPE_V4 => (HDB_PW_SALT, Vec::new()),
+        MIT_SALTTYPE_NOREALM => (HDB_PW_SALT, components),
+        MIT_SALTTYPE_ONLYREALM => (HDB_PW_SALT, realm),
+        MIT_SALTTYPE_SPECIAL => (HDB_PW_SALT, salt.contents.clone()),
+        MIT_SALTTYPE_AFS3 => (HDB_AFS3_SALT, salt.contents.clone()),
+        // KRB5_KDB_SALTTYPE_NORMAL, which is Heimdal's default
+        0 => return None,
+        _ => return Some(salt.clone()),
+    };
+    Some(Salt {
+        salttype: salttype.into(),
+        contents,
+    })
+}
+
+fn salt_to_mit(name: &str, salt: Option<&Salt>) -> Option<Salt> {
+    let salt = salt?;
+    let (salttype, contents) = match i32::from(salt.salttype) {
+        HDB_PW_SALT if salt.contents.is_empty() => (MIT_SALTTYPE_V4, Vec::new()),
+        HDB_PW_SALT => {
+            let (components, realm) = default_salt(name);
+            if salt.contents == [realm, components].concat() {
+                return None;
+            }
+            (MIT_SALTTYPE_SPECIAL, salt.contents.clone())
+        }
+        HDB_AFS3_SALT => (MIT_SALTTYPE_AFS3, salt.contents.clone()),
+        _ => return Some(salt.clone()),
+    };
+    Some(Salt {
+        salttype: salttype.into(),
+        contents,
+    })
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    Some(
+        NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+            .ok()?
+            .and_utc(),
+    )
+}
+
+fn parse_optional_time(line: usize, time: &str) -> Result<Option<DateTime<Utc>>> {
+    match time {
+        "-" => Ok(None),
+        time => parse_time(time)
+            .map(Some)
+            .ok_or_else(|| parse_error(line, format!("invalid time {time:?}"))),
+    }
+}
+
+fn parse_delta(line: usize, delta: &str) -> Result<Option<Duration>> {
+    match delta {
+        "-" => Ok(None),
+        delta => delta
+            .parse()
+            .map(delta_to_dur)
+            .map_err(|_| parse_error(line, format!("invalid duration {delta:?}"))),
+    }
+}
+
+/// Split `contents` on spaces not escaped with a backslash
+///
+/// Escapes are kept, principal names are used in their unparsed form.
+fn split_fields(contents: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(contents);
+    std::iter::from_fn(move || {
+        let current = rest?;
+        let mut escaped = false;
+        for (i, c) in current.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                ' ' => {
+                    rest = Some(&current[i + 1..]);
+                    return Some(&current[..i]);
+                }
+                _ => {}
+            }
+        }
+        rest = None;
+        Some(current)
+    })
+}
+
+fn parse_event(line: usize, event: &str) -> Result<Option<Event>> {
+    if event == "-" {
+        return Ok(None);
+    }
+    let (time, principal) = event
+        .split_once(':')
+        .ok_or_else(|| parse_error(line, format!("invalid event {event:?}")))?;
+    Ok(Some(Event {
+        time: parse_time(time)
+            .ok_or_else(|| parse_error(line, format!("invalid time {time:?}")))?,
+        principal: Some(principal)
+            .filter(|principal| *principal != UNKNOWN_PRINCIPAL)
+            .map(String::from),
+    }))
+}
+
+/// Decode data written either quoted or as hexadecimal
+fn decode_data(data: &str) -> Option<Vec<u8>> {
+    match data
+        .strip_prefix('"')
+        .and_then(|data| data.strip_suffix('"'))
+    {
+        Some(data) => Some(data.as_bytes().to_vec()),
+        None => from_hex(data),
+    }
+}
+
+/// Encode data like Heimdal does, quoted if it only contains alphanumeric characters and dots,
+/// uppercase hexadecimal otherwise
+fn encode_data(data: &[u8]) -> String {
+    if data
+        .iter()
+        .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'.')
+    {
+        format!("\"{}\"", String::from_utf8_lossy(data))
+    } else {
+        to_hex(data).to_uppercase()
+    }
+}
+
+/// Minimal DER support for the `HDB-extension` values this module understands
+mod der {
+    pub(super) const UTF8_STRING: u8 = 0x0c;
+    pub(super) const GENERALIZED_TIME: u8 = 0x18;
+    const BOOLEAN: u8 = 0x01;
+    const SEQUENCE: u8 = 0x30;
+    /// Constructed context-specific tag, for explicit tagging
+    const CONTEXT: u8 = 0xa0;
+
+    /// Read a tag and its value from `data`, which must contain nothing else
+    pub(super) fn read(data: &[u8]) -> Option<(u8, &[u8])> {
+        match read_one(data)? {
+            (tag, value, []) => Some((tag, value)),
+            _ => None,
+        }
+    }
+
+    /// Read the first tag and value from `data`, along with the remaining data
+    fn read_one(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+        let (&tag, data) = data.split_first()?;
+        let (&length, mut data) = data.split_first()?;
+        let length = if length & 0x80 == 0 {
+            length as usize
+        } else {
+            let size = (length & 0x7f) as usize;
+            if size > std::mem::size_of::<usize>() || data.len() < size {
+                return None;
+            }
+            let (bytes, rest) = data.split_at(size);
+            data = rest;
+            bytes
+                .iter()
+                .fold(0, |length, byte| (length << 8) | *byte as usize)
+        };
+        if data.len() < length {
+            return None;
+        }
+        let (value, rest) = data.split_at(length);
+        Some((tag, value, rest))
+    }
+
+    pub(super) fn write(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut data = vec![tag];
+        if value.len() < 0x80 {
+            data.push(value.len() as u8);
+        } else {
+            let length = value.len().to_be_bytes();
+            let skip = length.iter().take_while(|byte| **byte == 0).count();
+            data.push(0x80 | (length.len() - skip) as u8);
+            data.extend_from_slice(&length[skip..]);
+        }
+        data.extend_from_slice(value);
+        data
+    }
+
+    /// Choice and DER-encoded value of an `HDB-extension`
+    pub(super) fn extension(data: &[u8]) -> Option<(u8, &[u8])> {
+        let (tag, mut fields) = read(data)?;
+        if tag != SEQUENCE {
+            return None;
+        }
+        loop {
+            let (tag, value, rest) = read_one(fields)?;
+            fields = rest;
+            if tag == CONTEXT | 1 {
+                let (choice, value) = read(value)?;
+                return Some((choice.checked_sub(CONTEXT)?, value));
+            }
+        }
+    }
+
+    /// Build a non-mandatory `HDB-extension`
+    pub(super) fn write_extension(choice: u8, value: &[u8]) -> Vec<u8> {
+        let mandatory = write(CONTEXT, &write(BOOLEAN, &[0]));
+        let data = write(CONTEXT | 1, &write(CONTEXT | choice, value));
+        write(SEQUENCE, &[mandatory, data].concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP: &str = include_str!("../../tests/fixtures/dump/heimdal.dump");
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let dump: Dump = DUMP.parse()?;
+        assert_eq!(dump.principals.len(), 3);
+        assert_eq!(dump.to_string(), DUMP);
+        Ok(())
+    }
+
+    #[test]
+    fn extensions() -> Result<()> {
+        let dump: Dump = DUMP.parse()?;
+        let alice = &dump.principals[2];
+        assert_eq!(alice.policy().as_deref(), Some("default"));
+        assert_eq!(
+            alice.last_password_change(),
+            DateTime::from_timestamp(1_705_000_000, 0)
+        );
+
+        let mut bob = alice.clone();
+        bob.set_policy(None);
+        assert_eq!(bob.policy(), None);
+        assert_eq!(bob.last_password_change(), alice.last_password_change());
+        bob.set_policy(Some("strict"));
+        assert_eq!(bob.policy().as_deref(), Some("strict"));
+        let parsed: Dump = format!("{bob}\n").parse()?;
+        assert_eq!(parsed.principals[0], bob);
+        Ok(())
+    }
+
+    #[test]
+    fn escaped_name_and_mkvnos() -> Result<()> {
+        let line = "svc\\ name@EXAMPLE.ORG 1:2:18:0102:-::17:0304:- 20231114221320:kadmin - - - - \
+                    - - 126 - -\n";
+        let dump: Dump = line.parse()?;
+        let principal = &dump.principals[0];
+        assert_eq!(principal.name, "svc\\ name@EXAMPLE.ORG");
+        assert_eq!(principal.keys[0].mkvno, Some(2));
+        assert_eq!(principal.keys[1].mkvno, None);
+        assert_eq!(principal.mkvno(), Some(2));
+        assert_eq!(dump.to_string(), line);
+        Ok(())
+    }
+
+    #[test]
+    fn attributes() {
+        let mut principal = DumpPrincipal::new("alice@EXAMPLE.ORG");
+        assert_eq!(flags_to_attributes(principal.flags, true), 0);
+        // REQUIRES_PRE_AUTH, DISALLOW_FORWARDABLE and OK_AS_DELEGATE
+        principal.flags = attributes_to_flags(0x0010_0082, true);
+        assert_eq!(principal.flags & HDB_FORWARDABLE, 0);
+        assert_ne!(principal.flags & HDB_REQUIRE_PREAUTH, 0);
+        assert_ne!(principal.flags & HDB_OK_AS_DELEGATE, 0);
+        assert_eq!(flags_to_attributes(principal.flags, true), 0x0010_0082);
+        assert_eq!(flags_to_attributes(principal.flags, false), 0x0001_0082);
+    }
+
+    #[test]
+    fn salts() {
+        let name = "host/www@EXAMPLE.ORG";
+        let mit = |salttype: i32, contents: &[u8]| Salt {
+            salttype: salttype.into(),
+            contents: contents.to_vec(),
+        };
+        let heimdal = salt_from_mit(name, Some(&mit(MIT_SALTTYPE_NOREALM, b""))).unwrap();
+        assert_eq!(i32::from(heimdal.salttype), HDB_PW_SALT);
+        assert_eq!(heimdal.contents, b"hostwww");
+        assert_eq!(
+            salt_to_mit(name, Some(&heimdal)),
+            Some(mit(MIT_SALTTYPE_SPECIAL, b"hostwww"))
+        );
+        let normal = salt_from_mit(name, Some(&mit(0, b"")));
+        assert_eq!(normal, None);
+        let default = mit(HDB_PW_SALT, b"EXAMPLE.ORGhostwww");
+        assert_eq!(salt_to_mit(name, Some(&default)), None);
+    }
+
+    #[cfg(all(mit_server, heimdal_server))]
+    #[test]
+    fn keys() {
+        let name = "host/www@EXAMPLE.ORG";
+        let key = |salttype: i32, salt: &[u8]| Key {
+            kvno: 2,
+            keysalt: KeySalt {
+                enctype: 18.into(),
+                salttype: salttype.into(),
+            },
+            contents: vec![0x42; 32].into(),
+            salt: salt.to_vec(),
+        };
+        let (mit, heimdal) = (KAdm5Variant::MitServer, KAdm5Variant::HeimdalServer);
+        assert_eq!(
+            translate_key(name, &key(0, b""), mit, heimdal),
+            key(HDB_PW_SALT, b"EXAMPLE.ORGhostwww")
+        );
+        assert_eq!(
+            translate_key(name, &key(HDB_PW_SALT, b"EXAMPLE.ORGhostwww"), heimdal, mit),
+            key(0, b"")
+        );
+        assert_eq!(translate_key(name, &key(0, b""), heimdal, mit), key(0, b""));
+        assert_eq!(
+            translate_key(name, &key(HDB_PW_SALT, b"pepper"), heimdal, mit),
+            key(MIT_SALTTYPE_SPECIAL, b"pepper")
+        );
+        assert_eq!(
+            translate_key(name, &key(MIT_SALTTYPE_ONLYREALM, b""), mit, heimdal),
+            key(HDB_PW_SALT, b"EXAMPLE.ORG")
+        );
+        assert_eq!(translate_key(name, &key(3, b""), mit, mit), key(3, b""));
+    }
+
+    #[test]
+    fn der() {
+        let extension = der::write_extension(EXTENSION_POLICY, &der::write(der::UTF8_STRING, b"x"));
+        assert_eq!(
+            extension,
+            [
+                0x30, 0x0c, 0xa0, 0x03, 0x01, 0x01, 0x00, 0xa1, 0x05, 0xac, 0x03, 0x0c, 0x01, b'x'
+            ]
+        );
+        let long = der::write(der::UTF8_STRING, &[b'a'; 300]);
+        assert_eq!(long[..4], [0x0c, 0x82, 0x01, 0x2c]);
+        assert_eq!(der::read(&long), Some((der::UTF8_STRING, &[b'a'; 300][..])));
+    }
+}
@@ -0,0 +1,964 @@
+//! MIT krb5 `kdb5_util dump` format
+//!
+//! This is version 7 of the format, written by `kdb5_util dump` since MIT krb5 1.11 and read back
+//! by `kdb5_util load`. Dumps made with `kdb5_util dump -i` for incremental propagation start with
+//! an [`IpropHeader`] instead of the usual header.
+//!
+//! ```no_run
+//! # use std::fs;
+//! # use crate::kadmin::dump::mit::Dump;
+//! let mut dump = Dump::from_file("realm.dump").unwrap();
+//! dump.principals
+//!     .retain(|princ| !princ.name.starts_with("test/"));
+//! for princ in &mut dump.principals {
+//!     if princ.policy().is_none() {
+//!         princ.set_policy(Some("default"));
+//!     }
+//! }
+//! fs::write("realm.dump", dump.to_text().unwrap()).unwrap();
+//! ```
+
+use std::{collections::HashMap, fmt, fs, path::Path, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use super::{KeyData, Salt, from_hex, parse_error, to_hex};
+#[cfg(any(mit_client, mit_server))]
+use crate::policy::Policy;
+use crate::{
+    conv::{delta_to_dur, ts_to_dt},
+    error::{Error, Result},
+    principal::Principal,
+    tl_data::{TlData, TlDataEntry},
+};
+
+/// First line of a dump
+const HEADER: &str = "kdb5_util load_dump version 7";
+/// Version of the `ipropx` header
+const IPROPX_VERSION: u32 = 1;
+/// Base length of a principal entry, `KRB5_KDB_V1_BASE_LENGTH`
+const BASE_LENGTH: u32 = 38;
+
+/// `KRB5_TL_LAST_PWD_CHANGE`
+const TL_LAST_PWD_CHANGE: i16 = 0x0001;
+/// `KRB5_TL_MOD_PRINC`
+const TL_MOD_PRINC: i16 = 0x0002;
+/// `KRB5_TL_KADM_DATA`
+const TL_KADM_DATA: i16 = 0x0003;
+/// `KRB5_TL_MKVNO`
+const TL_MKVNO: i16 = 0x0008;
+/// `KRB5_TL_STRING_ATTRS`
+const TL_STRING_ATTRS: i16 = 0x000b;
+
+/// `OSA_ADB_PRINC_VERSION_1`, without its low byte
+const OSA_ADB_PRINC_VERSION_MASK: u32 = 0x1234_5c00;
+/// `OSA_ADB_PRINC_VERSION_1`
+const OSA_ADB_PRINC_VERSION_1: u32 = 0x1234_5c01;
+
+/// Header of a dump made for incremental propagation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct IpropHeader {
+    /// Serial number of the last update included in the dump
+    pub last_serial: u32,
+    /// Time of the last update, seconds part
+    pub last_seconds: u32,
+    /// Time of the last update, microseconds part
+    pub last_useconds: u32,
+}
+
+/// An MIT krb5 database dump
+///
+/// It can be parsed with [`str::parse`] or [`Dump::from_file`], and written back with
+/// [`Dump::to_text`]. Its [`Display`][`fmt::Display`] implementation fails, and `to_string`
+/// panics, if a timestamp can't be represented as a `krb5_timestamp`, that is if it is before 1970
+/// or after 2106.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Dump {
+    /// Set for dumps made for incremental propagation
+    pub iprop: Option<IpropHeader>,
+    /// Principal records
+    pub principals: Vec<DumpPrincipal>,
+    /// Policy records
+    pub policies: Vec<DumpPolicy>,
+}
+
+impl Dump {
+    /// Construct a new empty [`Dump`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a [`Dump`] from the file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Format the dump, failing with [`Error::DateTimeConversion`] if a timestamp can't be
+    /// represented as a `krb5_timestamp`
+    pub fn to_text(&self) -> Result<String> {
+        for principal in &self.principals {
+            principal.check_timestamps()?;
+        }
+        Ok(self.to_string())
+    }
+}
+
+impl FromStr for Dump {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let mut dump = Self::new();
+
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            Some((line, header)) if header.starts_with("ipropx\t") => {
+                let mut fields = Fields::new(line, header);
+                fields.next("record type")?;
+                let version: u32 = fields.parse("ipropx version")?;
+                if version != IPROPX_VERSION {
+                    return Err(parse_error(
+                        line,
+                        format!("unsupported ipropx version {version}"),
+                    ));
+                }
+                dump.iprop = Some(IpropHeader {
+                    last_serial: fields.parse("last serial number")?,
+                    last_seconds: fields.parse("last update seconds")?,
+                    last_useconds: fields.parse("last update microseconds")?,
+                });
+                fields.end()?;
+            }
+            Some((line, _)) => return Err(parse_error(line, "unsupported dump format")),
+            None => return Err(parse_error(0, "empty dump")),
+        }
+
+        for (line, contents) in lines {
+            if contents.is_empty() {
+                continue;
+            }
+            match contents.split('\t').next() {
+                Some("princ") => dump.principals.push(DumpPrincipal::parse(line, contents)?),
+                Some("policy") => dump.policies.push(DumpPolicy::parse(line, contents)?),
+                _ => return Err(parse_error(line, "unknown record type")),
+            }
+        }
+        Ok(dump)
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.iprop {
+            Some(iprop) => writeln!(
+                f,
+                "ipropx\t{IPROPX_VERSION}\t{}\t{}\t{}",
+                iprop.last_serial, iprop.last_seconds, iprop.last_useconds
+            )?,
+            None => writeln!(f, "{HEADER}")?,
+        }
+        for principal in &self.principals {
+            writeln!(f, "{principal}")?;
+        }
+        for policy in &self.policies {
+            writeln!(f, "{policy}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Principal record of a [`Dump`]
+///
+/// Some principal attributes are stored by MIT krb5 in TL-data. They are available through
+/// methods like [`DumpPrincipal::policy`] and [`DumpPrincipal::set_policy`], which read and update
+/// [`DumpPrincipal::tl_data`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DumpPrincipal {
+    /// Principal name
+    pub name: String,
+    /// Principal attributes
+    pub attributes: i32,
+    /// Maximum ticket life
+    pub max_life: Option<Duration>,
+    /// Maximum renewable ticket life
+    pub max_renewable_life: Option<Duration>,
+    /// When the principal expires
+    pub expire_time: Option<DateTime<Utc>>,
+    /// When the password expires
+    pub password_expiration: Option<DateTime<Utc>>,
+    /// When the last successful authentication occurred
+    pub last_success: Option<DateTime<Utc>>,
+    /// When the last failed authentication occurred
+    pub last_failed: Option<DateTime<Utc>>,
+    /// Number of failed authentication attempts
+    pub fail_auth_count: u32,
+    /// TL-data
+    pub tl_data: TlData,
+    /// Keys of the principal
+    pub keys: Vec<KeyData>,
+    /// Extra data, unused by MIT krb5
+    pub e_data: Vec<u8>,
+}
+
+impl DumpPrincipal {
+    /// Construct a new [`DumpPrincipal`] without any key
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Construct a [`DumpPrincipal`] from a [`Principal`]
+    ///
+    /// kadm5 doesn't expose principal keys, so the record doesn't have any.
+    pub fn from_principal(principal: &Principal) -> Result<Self> {
+        let mut record = Self {
+            name: principal.name().to_owned(),
+            attributes: principal.attributes(),
+            max_life: principal.max_life(),
+            max_renewable_life: principal.max_renewable_life(),
+            expire_time: principal.expire_time(),
+            password_expiration: principal.password_expiration(),
+            last_success: principal.last_success(),
+            last_failed: principal.last_failed(),
+            fail_auth_count: principal.fail_auth_count(),
+            tl_data: principal.tl_data().clone(),
+            ..Default::default()
+        };
+        if let Some(last_password_change) = principal.last_password_change() {
+            record.set_last_password_change(last_password_change)?;
+        }
+        if let (Some(at), Some(by)) = (principal.modified_at(), principal.modified_by()) {
+            record.set_modified(at, by)?;
+        }
+        if principal.mkvno() != 0 {
+            record.set_mkvno(principal.mkvno())?;
+        }
+        if principal.policy().is_some()
+            || principal.aux_attributes() != 0
+            || record.tl_entry(TL_KADM_DATA).is_some()
+        {
+            let mut kadm_data = record.kadm_data().unwrap_or_default();
+            kadm_data.policy = principal.policy().map(String::from);
+            kadm_data.aux_attributes = i32::try_from(principal.aux_attributes())
+                .map_err(Error::AuxAttributesConversion)?;
+            record.set_tl_entry(TL_KADM_DATA, kadm_data.encode());
+        }
+        Ok(record)
+    }
+
+    /// Construct the [`Principal`] described by this record
+    pub fn to_principal(&self) -> Principal {
+        Principal::from_mit_dump(self)
+    }
+
+    /// Format the record, failing with [`Error::DateTimeConversion`] if a timestamp can't be
+    /// represented as a `krb5_timestamp`
+    pub fn to_text(&self) -> Result<String> {
+        self.check_timestamps()?;
+        Ok(self.to_string())
+    }
+
+    /// Check that the timestamps formatted by [`Display`][`fmt::Display`] fit in a
+    /// `krb5_timestamp`
+    fn check_timestamps(&self) -> Result<()> {
+        for at in [
+            self.expire_time,
+            self.password_expiration,
+            self.last_success,
+            self.last_failed,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            u32::try_from(at.timestamp()).map_err(Error::DateTimeConversion)?;
+        }
+        Ok(())
+    }
+
+    /// Highest key version number of the principal keys
+    pub fn kvno(&self) -> u32 {
+        self.keys
+            .iter()
+            .map(|key| key.kvno)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Master key version number the principal keys are encrypted with, if recorded
+    pub fn mkvno(&self) -> Option<u32> {
+        let data = self.tl_entry(TL_MKVNO)?;
+        Some(u16::from_le_bytes(data.get(..2)?.try_into().ok()?).into())
+    }
+
+    /// Set the master key version number the principal keys are encrypted with
+    ///
+    /// It is stored on 16 bits, and larger values are rejected
+    pub fn set_mkvno(&mut self, mkvno: u32) -> Result<()> {
+        let mkvno = u16::try_from(mkvno).map_err(Error::MkvnoConversion)?;
+        self.set_tl_entry(TL_MKVNO, mkvno.to_le_bytes().to_vec());
+        Ok(())
+    }
+
+    /// When the password was last changed
+    pub fn last_password_change(&self) -> Option<DateTime<Utc>> {
+        decode_timestamp(self.tl_entry(TL_LAST_PWD_CHANGE)?)
+    }
+
+    /// Set when the password was last changed
+    pub fn set_last_password_change(&mut self, at: DateTime<Utc>) -> Result<()> {
+        self.set_tl_entry(TL_LAST_PWD_CHANGE, encode_timestamp(at)?.to_vec());
+        Ok(())
+    }
+
+    /// When the principal was last modified
+    pub fn modified_at(&self) -> Option<DateTime<Utc>> {
+        decode_timestamp(self.tl_entry(TL_MOD_PRINC)?)
+    }
+
+    /// Last principal to modify this principal
+    pub fn modified_by(&self) -> Option<String> {
+        let data = self.tl_entry(TL_MOD_PRINC)?.get(4..)?;
+        let name = data.split(|byte| *byte == 0).next()?;
+        String::from_utf8(name.to_vec()).ok()
+    }
+
+    /// Set when and by whom the principal was last modified
+    pub fn set_modified(&mut self, at: DateTime<Utc>, by: &str) -> Result<()> {
+        let mut data = encode_timestamp(at)?.to_vec();
+        data.extend_from_slice(by.as_bytes());
+        data.push(0);
+        self.set_tl_entry(TL_MOD_PRINC, data);
+        Ok(())
+    }
+
+    /// Associated policy
+    pub fn policy(&self) -> Option<String> {
+        self.kadm_data()?.policy
+    }
+
+    /// Set the associated policy, or clear it with `None`
+    pub fn set_policy(&mut self, policy: Option<&str>) {
+        let mut kadm_data = self.kadm_data().unwrap_or_default();
+        kadm_data.policy = policy.map(String::from);
+        self.set_tl_entry(TL_KADM_DATA, kadm_data.encode());
+    }
+
+    /// Extra attributes
+    pub fn aux_attributes(&self) -> i64 {
+        self.kadm_data()
+            .map(|kadm_data| kadm_data.aux_attributes.into())
+            .unwrap_or_default()
+    }
+
+    /// String attributes of the principal
+    pub fn strings(&self) -> HashMap<String, String> {
+        let Some(data) = self.tl_entry(TL_STRING_ATTRS) else {
+            return HashMap::new();
+        };
+        let mut items = data
+            .split(|byte| *byte == 0)
+            .map(|item| String::from_utf8_lossy(item).into_owned());
+        let mut strings = HashMap::new();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            strings.insert(key, value);
+        }
+        strings
+    }
+
+    /// Replace the string attributes of the principal
+    pub fn set_strings(&mut self, strings: &HashMap<String, String>) {
+        if strings.is_empty() {
+            self.tl_data
+                .entries
+                .retain(|entry| entry.data_type != TL_STRING_ATTRS);
+            return;
+        }
+        let mut keys: Vec<&String> = strings.keys().collect();
+        keys.sort();
+        let mut data = Vec::new();
+        for key in keys {
+            for item in [key, &strings[key]] {
+                data.extend_from_slice(item.as_bytes());
+                data.push(0);
+            }
+        }
+        self.set_tl_entry(TL_STRING_ATTRS, data);
+    }
+
+    fn tl_entry(&self, data_type: i16) -> Option<&[u8]> {
+        self.tl_data
+            .entries
+            .iter()
+            .find(|entry| entry.data_type == data_type)
+            .map(|entry| entry.contents.as_slice())
+    }
+
+    /// Replace the TL-data entry of type `data_type`, or add it if there is none
+    fn set_tl_entry(&mut self, data_type: i16, contents: Vec<u8>) {
+        match self
+            .tl_data
+            .entries
+            .iter_mut()
+            .find(|entry| entry.data_type == data_type)
+        {
+            Some(entry) => entry.contents = contents,
+            None => self.tl_data.entries.push(TlDataEntry {
+                data_type,
+                contents,
+            }),
+        }
+    }
+
+    fn kadm_data(&self) -> Option<KAdmData> {
+        KAdmData::decode(self.tl_entry(TL_KADM_DATA)?)
+    }
+
+    fn parse(line: usize, contents: &str) -> Result<Self> {
+        let Some(contents) = contents.strip_suffix(';') else {
+            return Err(parse_error(line, "principal record doesn't end with `;`"));
+        };
+        let mut fields = Fields::new(line, contents);
+        fields.next("record type")?;
+        let _base_length: u32 = fields.parse("base length")?;
+        let name_length: usize = fields.parse("name length")?;
+        let n_tl_data: usize = fields.parse("TL-data count")?;
+        let n_key_data: usize = fields.parse("key data count")?;
+        let _e_length: usize = fields.parse("extra data length")?;
+        let name = fields.next("name")?;
+        if name.len() != name_length {
+            return Err(parse_error(line, "principal name length mismatch"));
+        }
+
+        let mut principal = Self::new(name);
+        principal.attributes = fields.parse("attributes")?;
+        principal.max_life = delta_to_dur(fields.parse("max life")?);
+        principal.max_renewable_life = delta_to_dur(fields.parse("max renewable life")?);
+        principal.expire_time = fields.timestamp("expiration")?;
+        principal.password_expiration = fields.timestamp("password expiration")?;
+        principal.last_success = fields.timestamp("last success")?;
+        principal.last_failed = fields.timestamp("last failed")?;
+        principal.fail_auth_count = fields.parse("failed authentication count")?;
+
+        for _ in 0..n_tl_data {
+            let data_type = fields.parse("TL-data type")?;
+            let contents = fields.data("TL-data")?;
+            principal.tl_data.entries.push(TlDataEntry {
+                data_type,
+                contents,
+            });
+        }
+
+        for _ in 0..n_key_data {
+            let version: u32 = fields.parse("key data version")?;
+            let kvno = fields.parse("key version number")?;
+            if !(1..=2).contains(&version) {
+                return Err(parse_error(
+                    line,
+                    format!("unsupported key data version {version}"),
+                ));
+            }
+            let enctype = fields.parse::<i32>("encryption type")?.into();
+            let key = fields.data("key")?;
+            let salt = if version == 2 {
+                Some(Salt {
+                    salttype: fields.parse::<i32>("salt type")?.into(),
+                    contents: fields.data("salt")?,
+                })
+            } else {
+                None
+            };
+            principal.keys.push(KeyData {
+                kvno,
+                mkvno: None,
+                enctype,
+                contents: key,
+                salt,
+            });
+        }
+
+        principal.e_data = fields.hex("extra data")?;
+        fields.end()?;
+        Ok(principal)
+    }
+}
+
+impl fmt::Display for DumpPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "princ\t{BASE_LENGTH}\t{}\t{}\t{}\t{}\t{}",
+            self.name.len(),
+            self.tl_data.entries.len(),
+            self.keys.len(),
+            self.e_data.len(),
+            self.name,
+        )?;
+        write!(
+            f,
+            "\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.attributes,
+            delta(self.max_life),
+            delta(self.max_renewable_life),
+            timestamp(self.expire_time)?,
+            timestamp(self.password_expiration)?,
+            timestamp(self.last_success)?,
+            timestamp(self.last_failed)?,
+            self.fail_auth_count,
+        )?;
+        for entry in &self.tl_data.entries {
+            write!(
+                f,
+                "\t{}\t{}\t{}",
+                entry.data_type,
+                entry.contents.len(),
+                data(&entry.contents)
+            )?;
+        }
+        for key in &self.keys {
+            let version = if key.salt.is_some() { 2 } else { 1 };
+            write!(
+                f,
+                "\t{version}\t{}\t{}\t{}\t{}",
+                key.kvno,
+                i32::from(key.enctype),
+                key.contents.len(),
+                data(&key.contents)
+            )?;
+            if let Some(salt) = &key.salt {
+                write!(
+                    f,
+                    "\t{}\t{}\t{}",
+                    i32::from(salt.salttype),
+                    salt.contents.len(),
+                    data(&salt.contents)
+                )?;
+            }
+        }
+        write!(f, "\t{};", data(&self.e_data))
+    }
+}
+
+/// Policy record of a [`Dump`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DumpPolicy {
+    /// Policy name
+    pub name: String,
+    /// Minimum lifetime of a password
+    pub password_min_life: Option<Duration>,
+    /// Maximum lifetime of a password
+    pub password_max_life: Option<Duration>,
+    /// Minimum length of a password
+    pub password_min_length: i64,
+    /// Minimum number of character classes required in a password
+    pub password_min_classes: i64,
+    /// Number of past keys kept for a principal
+    pub password_history_num: i64,
+    /// Reference count, unused by MIT krb5
+    pub policy_refcnt: i64,
+    /// Number of authentication failures before the principal is locked
+    pub password_max_fail: u32,
+    /// Allowable time between authentication failures
+    pub password_failcount_interval: Option<Duration>,
+    /// Duration for which the principal is locked after too many authentication failures
+    pub password_lockout_duration: Option<Duration>,
+    /// Policy attributes
+    pub attributes: i32,
+    /// Maximum ticket life
+    pub max_life: Option<Duration>,
+    /// Maximum renewable ticket life
+    pub max_renewable_life: Option<Duration>,
+    /// Allowed keysalts, in the `enctype:salttype` comma-separated form
+    pub allowed_keysalts: Option<String>,
+    /// TL-data
+    pub tl_data: TlData,
+}
+
+impl DumpPolicy {
+    /// Construct a new [`DumpPolicy`] without any restriction
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Construct a [`DumpPolicy`] from a [`Policy`]
+    ///
+    /// Allowed keysalts can only be converted to their string form with a kadm5 library, and are
+    /// left unset.
+    pub fn from_policy(policy: &Policy) -> Self {
+        Self {
+            name: policy.name().to_owned(),
+            password_min_life: policy.password_min_life(),
+            password_max_life: policy.password_max_life(),
+            password_min_length: policy.password_min_length(),
+            password_min_classes: policy.password_min_classes(),
+            password_history_num: policy.password_history_num(),
+            policy_refcnt: policy.policy_refcnt(),
+            password_max_fail: policy.password_max_fail(),
+            password_failcount_interval: policy.password_failcount_interval(),
+            password_lockout_duration: policy.password_lockout_duration(),
+            attributes: policy.attributes(),
+            max_life: policy.max_life(),
+            max_renewable_life: policy.max_renewable_life(),
+            allowed_keysalts: None,
+            tl_data: policy.tl_data().clone(),
+        }
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Construct the [`Policy`] described by this record
+    ///
+    /// Allowed keysalts can only be parsed with a kadm5 library, and are left unset.
+    pub fn to_policy(&self) -> Policy {
+        Policy::from_mit_dump(self)
+    }
+
+    fn parse(line: usize, contents: &str) -> Result<Self> {
+        let mut fields = Fields::new(line, contents);
+        fields.next("record type")?;
+        let mut policy = Self::new(fields.next("name")?);
+        policy.password_min_life = delta_to_dur(fields.parse("password min life")?);
+        policy.password_max_life = delta_to_dur(fields.parse("password max life")?);
+        policy.password_min_length = fields.parse("password min length")?;
+        policy.password_min_classes = fields.parse("password min classes")?;
+        policy.password_history_num = fields.parse("password history num")?;
+        policy.policy_refcnt = fields.parse("reference count")?;
+        policy.password_max_fail = fields.parse("password max fail")?;
+        policy.password_failcount_interval =
+            delta_to_dur(fields.parse("password failcount interval")?);
+        policy.password_lockout_duration = delta_to_dur(fields.parse("password lockout duration")?);
+        policy.attributes = fields.parse("attributes")?;
+        policy.max_life = delta_to_dur(fields.parse("max life")?);
+        policy.max_renewable_life = delta_to_dur(fields.parse("max renewable life")?);
+        policy.allowed_keysalts = match fields.next("allowed keysalts")? {
+            "-" => None,
+            keysalts => Some(keysalts.to_owned()),
+        };
+        let n_tl_data: usize = fields.parse("TL-data count")?;
+        for _ in 0..n_tl_data {
+            let data_type = fields.parse("TL-data type")?;
+            let contents = fields.data("TL-data")?;
+            policy.tl_data.entries.push(TlDataEntry {
+                data_type,
+                contents,
+            });
+        }
+        fields.end()?;
+        Ok(policy)
+    }
+}
+
+impl fmt::Display for DumpPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "policy\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.name,
+            delta(self.password_min_life),
+            delta(self.password_max_life),
+            self.password_min_length,
+            self.password_min_classes,
+            self.password_history_num,
+            self.policy_refcnt,
+            self.password_max_fail,
+            delta(self.password_failcount_interval),
+            delta(self.password_lockout_duration),
+            self.attributes,
+            delta(self.max_life),
+            delta(self.max_renewable_life),
+            self.allowed_keysalts.as_deref().unwrap_or("-"),
+            self.tl_data.entries.len(),
+        )?;
+        for entry in &self.tl_data.entries {
+            write!(
+                f,
+                "\t{}\t{}\t{}",
+                entry.data_type,
+                entry.contents.len(),
+                data(&entry.contents)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// kadm5 data of a principal, XDR-encoded in its `KRB5_TL_KADM_DATA` TL-data entry
+#[derive(Debug)]
+struct KAdmData {
+    version: u32,
+    policy: Option<String>,
+    aux_attributes: i32,
+    /// Password history and related fields, kept as-is
+    rest: Vec<u8>,
+}
+
+impl Default for KAdmData {
+    fn default() -> Self {
+        Self {
+            version: OSA_ADB_PRINC_VERSION_1,
+            policy: None,
+            aux_attributes: 0,
+            // old_key_next, admin_history_kvno and an empty old_keys array
+            rest: vec![0; 12],
+        }
+    }
+}
+
+impl KAdmData {
+    fn decode(data: &[u8]) -> Option<Self> {
+        let word = |offset: usize| -> Option<u32> {
+            Some(u32::from_be_bytes(
+                data.get(offset..offset + 4)?.try_into().ok()?,
+            ))
+        };
+        let version = word(0)?;
+        if version & !0xff != OSA_ADB_PRINC_VERSION_MASK {
+            return None;
+        }
+        // The policy name is a counted string including its trailing NUL, padded to 4 bytes
+        let policy_size = word(4)? as usize;
+        let (policy, offset) = if policy_size == 0 {
+            (None, 8)
+        } else {
+            let policy = data.get(8..8 + policy_size)?;
+            let policy = policy.strip_suffix(&[0]).unwrap_or(policy);
+            (
+                Some(String::from_utf8(policy.to_vec()).ok()?),
+                8 + policy_size.next_multiple_of(4),
+            )
+        };
+        let aux_attributes = word(offset)? as i32;
+        Some(Self {
+            version,
+            policy,
+            aux_attributes,
+            rest: data.get(offset + 4..)?.to_vec(),
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = self.version.to_be_bytes().to_vec();
+        match &self.policy {
+            Some(policy) => {
+                let size = policy.len() + 1;
+                data.extend_from_slice(&(size as u32).to_be_bytes());
+                data.extend_from_slice(policy.as_bytes());
+                data.resize(data.len() + size.next_multiple_of(4) - policy.len(), 0);
+            }
+            None => data.extend_from_slice(&0_u32.to_be_bytes()),
+        }
+        data.extend_from_slice(&self.aux_attributes.to_be_bytes());
+        data.extend_from_slice(&self.rest);
+        data
+    }
+}
+
+fn decode_timestamp(data: &[u8]) -> Option<DateTime<Utc>> {
+    let ts = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+    ts_to_dt(ts.into()).ok().flatten()
+}
+
+fn encode_timestamp(at: DateTime<Utc>) -> Result<[u8; 4]> {
+    let ts = u32::try_from(at.timestamp()).map_err(Error::DateTimeConversion)?;
+    Ok(ts.to_le_bytes())
+}
+
+/// Format a duration as a `krb5_deltat`
+fn delta(duration: Option<Duration>) -> u64 {
+    duration.map_or(0, |duration| duration.as_secs())
+}
+
+/// Format a timestamp as a `krb5_timestamp`, failing if it doesn't fit
+fn timestamp(at: Option<DateTime<Utc>>) -> std::result::Result<u32, fmt::Error> {
+    at.map_or(Ok(0), |at| {
+        u32::try_from(at.timestamp()).map_err(|_| fmt::Error)
+    })
+}
+
+/// Format data as hexadecimal, `-1` standing for no data
+fn data(contents: &[u8]) -> String {
+    if contents.is_empty() {
+        "-1".to_owned()
+    } else {
+        to_hex(contents)
+    }
+}
+
+/// Tab-separated fields of a record
+struct Fields<'a> {
+    line: usize,
+    fields: std::str::Split<'a, char>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(line: usize, contents: &'a str) -> Self {
+        Self {
+            line,
+            fields: contents.split('\t'),
+        }
+    }
+
+    fn next(&mut self, what: &str) -> Result<&'a str> {
+        self.fields
+            .next()
+            .ok_or_else(|| parse_error(self.line, format!("missing {what}")))
+    }
+
+    fn parse<T: FromStr>(&mut self, what: &str) -> Result<T> {
+        let field = self.next(what)?;
+        field
+            .parse()
+            .map_err(|_| parse_error(self.line, format!("invalid {what} {field:?}")))
+    }
+
+    fn timestamp(&mut self, what: &str) -> Result<Option<DateTime<Utc>>> {
+        let ts: u32 = self.parse(what)?;
+        ts_to_dt(ts.into()).map_err(|err| parse_error(self.line, err))
+    }
+
+    /// Hexadecimal field, `-1` standing for no data
+    fn hex(&mut self, what: &str) -> Result<Vec<u8>> {
+        match self.next(what)? {
+            "-1" | "" => Ok(Vec::new()),
+            field => {
+                from_hex(field).ok_or_else(|| parse_error(self.line, format!("invalid {what}")))
+            }
+        }
+    }
+
+    /// Length followed by hexadecimal contents
+    fn data(&mut self, what: &str) -> Result<Vec<u8>> {
+        let length: usize = self.parse(&format!("{what} length"))?;
+        let contents = self.hex(what)?;
+        if contents.len() != length {
+            return Err(parse_error(self.line, format!("{what} length mismatch")));
+        }
+        Ok(contents)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        match self.fields.next() {
+            None => Ok(()),
+            Some(_) => Err(parse_error(self.line, "unexpected trailing fields")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP: &str = include_str!("../../tests/fixtures/dump/mit.dump");
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let dump: Dump = DUMP.parse()?;
+        assert_eq!(dump.principals.len(), 3);
+        assert_eq!(dump.policies.len(), 1);
+        assert_eq!(dump.to_string(), DUMP);
+        Ok(())
+    }
+
+    #[test]
+    fn tl_data() -> Result<()> {
+        let dump: Dump = DUMP.parse()?;
+        let alice = &dump.principals[2];
+        assert_eq!(alice.name, "alice@EXAMPLE.ORG");
+        assert_eq!(alice.policy().as_deref(), Some("default"));
+        assert_eq!(
+            alice.modified_by().as_deref(),
+            Some("admin/admin@EXAMPLE.ORG")
+        );
+        assert_eq!(alice.strings()["session_enctypes"], "aes256-cts");
+        assert_eq!(alice.kvno(), 2);
+        assert_eq!(alice.mkvno(), Some(1));
+
+        let mut bob = alice.clone();
+        bob.set_policy(None);
+        assert_eq!(bob.policy(), None);
+        assert_eq!(bob.aux_attributes(), alice.aux_attributes());
+        bob.set_policy(Some("strict"));
+        assert_eq!(bob.policy().as_deref(), Some("strict"));
+        let parsed: Dump = format!("{HEADER}\n{bob}\n").parse()?;
+        assert_eq!(parsed.principals[0], bob);
+        Ok(())
+    }
+
+    #[test]
+    fn iprop() -> Result<()> {
+        let dump: Dump = "ipropx\t1\t42\t1700000000\t12\n".parse()?;
+        assert_eq!(
+            dump.iprop,
+            Some(IpropHeader {
+                last_serial: 42,
+                last_seconds: 1_700_000_000,
+                last_useconds: 12,
+            })
+        );
+        assert_eq!(dump.to_string(), "ipropx\t1\t42\t1700000000\t12\n");
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut record = DumpPrincipal::new("alice@EXAMPLE.ORG");
+        assert!(matches!(
+            record.set_mkvno(0x1_0000),
+            Err(Error::MkvnoConversion(_))
+        ));
+        let before_epoch = DateTime::from_timestamp(-1, 0).unwrap();
+        assert!(matches!(
+            record.set_last_password_change(before_epoch),
+            Err(Error::DateTimeConversion(_))
+        ));
+        record.expire_time = Some(before_epoch);
+        assert!(fmt::write(&mut String::new(), format_args!("{record}")).is_err());
+        assert!(matches!(
+            record.to_text(),
+            Err(Error::DateTimeConversion(_))
+        ));
+        let mut dump = Dump::new();
+        dump.principals.push(record);
+        assert!(matches!(dump.to_text(), Err(Error::DateTimeConversion(_))));
+    }
+
+    #[test]
+    fn policy_tl_data() -> Result<()> {
+        let mut policy = DumpPolicy::new("default");
+        policy.tl_data.entries.push(TlDataEntry {
+            data_type: 1,
+            contents: vec![],
+        });
+        let line = policy.to_string();
+        assert!(line.ends_with("\t1\t1\t0\t-1"), "{line}");
+        let parsed: Dump = format!("{HEADER}\n{line}\n").parse()?;
+        assert_eq!(parsed.policies[0], policy);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        for (contents, line) in [
+            ("", 0),
+            ("kdb5_util load_dump version 6\n", 1),
+            (
+                "kdb5_util load_dump version 7\nprinc\t38\t1\t0\t0\t0\ta\t0;\n",
+                2,
+            ),
+            ("kdb5_util load_dump version 7\n\nunknown\n", 3),
+        ] {
+            match contents.parse::<Dump>() {
+                Err(Error::DumpParse { line: l, .. }) => assert_eq!(l, line, "{contents}"),
+                other => panic!("unexpected result for {contents:?}: {other:?}"),
+            }
+        }
+    }
+}
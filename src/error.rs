@@ -1,6 +1,9 @@
 //! [`Error`] type for various errors this library can encounter
 
-use crate::{context::Context, sys::library_match};
+use crate::{
+    context::Context,
+    sys::{KAdm5Variant, library_match},
+};
 
 const KADM5_OK: i32 = 0;
 const KRB5_OK: i32 = 0;
@@ -31,6 +34,8 @@ pub enum Error {
         code: i64,
         /// kadm5 error message
         message: String,
+        /// Variant-independent kind of the error
+        kind: KAdminErrorKind,
     },
 
     /// Conversion to an encryption type failed
@@ -62,6 +67,9 @@ pub enum Error {
     /// Failed to receive the result from an operatior from the sync executor
     #[error("Failed to receive result from executor")]
     ThreadRecvError(#[from] std::sync::mpsc::RecvError),
+    /// An operation on the sync executor didn't complete within the configured timeout
+    #[error("Operation timed out")]
+    Timeout,
 
     /// Failed to convert a `krb5_timestamp` to a [`chrono::DateTime`]
     #[error("Failed to convert krb5 timestamp to chrono DateTime")]
@@ -72,6 +80,15 @@ pub enum Error {
     /// Failed to convert a [`Duration`][`std::time::Duration`] to a `krb5_deltat`
     #[error("Failed to convert Duration to a krb5 deltat")]
     DurationConversion(std::num::TryFromIntError),
+    /// Too many keysalts were given for them to be passed to kadm5
+    #[error("Too many keysalts to pass to kadm5")]
+    KeySaltsConversion(std::num::TryFromIntError),
+    /// A master key version number doesn't fit in a dump record
+    #[error("Failed to convert master key version number")]
+    MkvnoConversion(std::num::TryFromIntError),
+    /// Auxiliary attributes don't fit in a dump record
+    #[error("Failed to convert auxiliary attributes")]
+    AuxAttributesConversion(std::num::TryFromIntError),
 
     /// Failed to acquire [`crate::kadmin::KADMIN_INIT_LOCK`] or
     /// [`crate::context::CONTEXT_INIT_LOCK`]
@@ -84,6 +101,383 @@ pub enum Error {
     /// The library is not compatible with the current operation
     #[error("The library is not compatible with the current operation")]
     LibraryMismatch(&'static str),
+
+    /// The current kadm5 session lacks the privileges required for an operation
+    #[error("Insufficient privileges, missing {0:?}")]
+    InsufficientPrivileges(crate::privileges::Privileges),
+    /// A password was rejected by the password quality checks or the password policy
+    ///
+    /// Returned instead of [`Error::KAdmin`] by
+    /// [`KAdminImpl::principal_change_password`][`crate::kadmin::KAdminImpl::principal_change_password`]
+    /// and [`KAdminImpl::add_principal`][`crate::kadmin::KAdminImpl::add_principal`]
+    #[error("Password rejected: {0}")]
+    PasswordQuality(Box<PasswordQualityError>),
+
+    /// An operation of a [`Batch`][`crate::batch::Batch`] failed. The operations applied before it
+    /// were undone, except for the irreversible ones and the ones whose undoing failed
+    #[error("Batch operation {index} failed: {source}")]
+    BatchFailed {
+        /// Index of the operation that failed
+        index: usize,
+        /// Error returned by the operation
+        source: Box<Error>,
+        /// Index of the operations that couldn't be undone, with the error that occurred
+        rollback_errors: Vec<(usize, Error)>,
+        /// Index of the applied operations that cannot be undone
+        irreversible: Vec<usize>,
+    },
+
+    /// The keys of a principal were requested from a [`DryRun`][`crate::dry_run::DryRun`], but
+    /// they will only be known once its changes are applied
+    #[error("Keys of {0} aren't known until the dry run changes are applied")]
+    DryRunKeys(String),
+
+    #[cfg(feature = "reconcile")]
+    /// A [`DesiredState`][`crate::reconcile::DesiredState`] couldn't be parsed or is invalid
+    #[error("Invalid desired state: {0}")]
+    InvalidDesiredState(String),
+
+    #[cfg(feature = "replay")]
+    /// A recorded session couldn't be written or read back
+    #[error("Invalid recording: {0}")]
+    InvalidRecording(String),
+    #[cfg(feature = "replay")]
+    /// A call made on a [`Replayer`][`crate::replay::Replayer`] isn't the next recorded one
+    #[error("Replay diverged: expected {expected}, got {actual}")]
+    ReplayDivergence {
+        /// Next recorded call
+        expected: String,
+        /// Call that was made
+        actual: String,
+    },
+    #[cfg(feature = "replay")]
+    /// Error that occurred while recording a session, replayed from its description
+    #[error("Recorded error: {0}")]
+    Recorded(String),
+
+    #[cfg(feature = "migrate")]
+    /// A [`Migration`][`crate::migrate::Migration`] couldn't copy a principal or read its state
+    #[error("Migration failed: {0}")]
+    MigrationFailed(String),
+
+    /// Failed to get random data from the operating system
+    #[error("Failed to generate random data: {0}")]
+    RandomGeneration(getrandom::Error),
+
+    /// I/O error, for instance while reading a profile file
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Failed to parse a profile file
+    #[error("Failed to parse profile {}:{line}: {message}", .path.display())]
+    ProfileParse {
+        /// Path of the file being parsed
+        path: std::path::PathBuf,
+        /// Line at which the error occurred
+        line: usize,
+        /// Error description
+        message: String,
+    },
+    /// A profile relation has an invalid value
+    #[error("Invalid value {value:?} for profile relation {relation}")]
+    ProfileValue {
+        /// Name of the relation
+        relation: String,
+        /// Value that couldn't be parsed
+        value: String,
+    },
+    /// Failed to parse a database dump
+    #[error("Failed to parse dump at line {line}: {message}")]
+    DumpParse {
+        /// Line at which the error occurred
+        line: usize,
+        /// Error description
+        message: String,
+    },
+    /// A principal attribute name is unknown, or has no equivalent in the variant
+    #[error("Unknown principal attribute {0:?}")]
+    InvalidAttribute(String),
+    #[cfg(feature = "import")]
+    /// Failed to parse a CSV file
+    #[error("Failed to parse CSV at line {line}: {message}")]
+    CsvParse {
+        /// Line at which the error occurred
+        line: usize,
+        /// Error description
+        message: String,
+    },
+}
+
+impl Error {
+    /// Variant-independent kind of a kadm5 error, or `None` if this isn't a kadm5 error
+    pub fn kind(&self) -> Option<KAdminErrorKind> {
+        match self {
+            Self::KAdmin { kind, .. } => Some(*kind),
+            Self::PasswordQuality(details) => Some(details.rule.kind()),
+            _ => None,
+        }
+    }
+
+    /// Whether the principal or policy operated on doesn't exist
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self.kind(),
+            Some(KAdminErrorKind::UnknownPrincipal | KAdminErrorKind::UnknownPolicy)
+        )
+    }
+
+    /// Whether the principal or policy to create already exists
+    pub fn is_already_exists(&self) -> bool {
+        matches!(
+            self.kind(),
+            Some(KAdminErrorKind::DuplicatePrincipal | KAdminErrorKind::DuplicatePolicy)
+        )
+    }
+
+    /// kadm5 reports existing principals and policies with the same code: turn
+    /// [`KAdminErrorKind::DuplicatePrincipal`] into [`KAdminErrorKind::DuplicatePolicy`] for the
+    /// result of a policy operation
+    pub(crate) fn for_policy(mut self) -> Self {
+        if let Self::KAdmin { kind, .. } = &mut self {
+            if *kind == KAdminErrorKind::DuplicatePrincipal {
+                *kind = KAdminErrorKind::DuplicatePolicy;
+            }
+        }
+        self
+    }
+
+    /// Whether the operation was denied because of missing privileges, either by the server or
+    /// by a [`Privileges`][`crate::privileges::Privileges`] pre-check
+    pub fn is_insufficient_privilege(&self) -> bool {
+        matches!(self, Self::InsufficientPrivileges(_))
+            || self.kind() == Some(KAdminErrorKind::InsufficientPrivilege)
+    }
+
+    /// Whether the password was rejected by the password quality checks or the password policy
+    pub fn is_password_rejected(&self) -> bool {
+        matches!(
+            self.kind(),
+            Some(
+                KAdminErrorKind::PasswordTooShort
+                    | KAdminErrorKind::PasswordTooFewClasses
+                    | KAdminErrorKind::PasswordInDictionary
+                    | KAdminErrorKind::PasswordReuse
+                    | KAdminErrorKind::PasswordTooSoon
+                    | KAdminErrorKind::PasswordQuality
+            )
+        )
+    }
+
+    /// Whether communicating with the server failed, in which case retrying may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.kind(),
+            Some(KAdminErrorKind::RpcError | KAdminErrorKind::NoServer)
+        )
+    }
+}
+
+/// Variant-independent kind of a kadm5 error
+///
+/// kadm5 error codes differ between MIT krb5 and Heimdal, and each has errors the other doesn't.
+/// This groups them into kinds that can be matched on regardless of the variant in use.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum KAdminErrorKind {
+    /// Operation failed for unspecified reason
+    Failure,
+    /// Principal does not exist
+    UnknownPrincipal,
+    /// Policy does not exist
+    UnknownPolicy,
+    /// Principal already exists
+    DuplicatePrincipal,
+    /// Policy already exists
+    DuplicatePolicy,
+    /// Authentication failed, for instance because of an incorrect password
+    AuthFailure,
+    /// The operation requires a privilege that is not granted
+    InsufficientPrivilege,
+    /// Password is too short
+    PasswordTooShort,
+    /// Password does not contain enough character classes
+    PasswordTooFewClasses,
+    /// Password is in the password dictionary
+    PasswordInDictionary,
+    /// Password was used before
+    PasswordReuse,
+    /// Current password's minimum life has not expired
+    PasswordTooSoon,
+    /// Unspecified password quality failure
+    PasswordQuality,
+    /// Policy is in use
+    PolicyInUse,
+    /// Principal or its keys are protected
+    ProtectedPrincipal,
+    /// Illegal principal name
+    InvalidPrincipal,
+    /// Illegal policy name
+    InvalidPolicy,
+    /// Invalid argument passed to the operation
+    InvalidArgument,
+    /// Missing or invalid configuration
+    Configuration,
+    /// Communication failure with the server
+    RpcError,
+    /// Administration server couldn't be found
+    NoServer,
+    /// Database error
+    Database,
+    /// Operation not supported
+    Unsupported,
+    /// Any other error
+    Other,
+}
+
+/// Password quality rule that rejected a password
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum PasswordQualityRule {
+    /// Password is shorter than the policy minimum length
+    MinLength,
+    /// Password contains fewer character classes than required by the policy
+    MinClasses,
+    /// Password is in the password dictionary
+    Dictionary,
+    /// Password is in the principal's password history
+    History,
+    /// The minimum lifetime of the current password has not elapsed yet
+    MinLife,
+    /// Any other password quality check, for instance from a password quality plugin
+    Other,
+}
+
+impl PasswordQualityRule {
+    /// Rule corresponding to a [`KAdminErrorKind`], if it is a password quality one
+    pub fn from_kind(kind: KAdminErrorKind) -> Option<Self> {
+        match kind {
+            KAdminErrorKind::PasswordTooShort => Some(Self::MinLength),
+            KAdminErrorKind::PasswordTooFewClasses => Some(Self::MinClasses),
+            KAdminErrorKind::PasswordInDictionary => Some(Self::Dictionary),
+            KAdminErrorKind::PasswordReuse => Some(Self::History),
+            KAdminErrorKind::PasswordTooSoon => Some(Self::MinLife),
+            KAdminErrorKind::PasswordQuality => Some(Self::Other),
+            _ => None,
+        }
+    }
+
+    /// [`KAdminErrorKind`] corresponding to this rule
+    pub fn kind(self) -> KAdminErrorKind {
+        match self {
+            Self::MinLength => KAdminErrorKind::PasswordTooShort,
+            Self::MinClasses => KAdminErrorKind::PasswordTooFewClasses,
+            Self::Dictionary => KAdminErrorKind::PasswordInDictionary,
+            Self::History => KAdminErrorKind::PasswordReuse,
+            Self::MinLife => KAdminErrorKind::PasswordTooSoon,
+            Self::Other => KAdminErrorKind::PasswordQuality,
+        }
+    }
+}
+
+/// Details about a password rejected by the password quality checks
+///
+/// The policy values are only filled if the policy applying to the principal could be retrieved,
+/// which requires the `get` privilege and a library supporting policies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct PasswordQualityError {
+    /// Rule that rejected the password
+    pub rule: PasswordQualityRule,
+    /// kadm5 error code
+    pub code: i64,
+    /// kadm5 error message
+    pub message: String,
+    /// Name of the policy applying to the principal
+    pub policy: Option<String>,
+    /// Minimum length of a password
+    pub min_length: Option<i64>,
+    /// Minimum number of character classes required in a password
+    pub min_classes: Option<i64>,
+    /// Number of past keys kept for a principal
+    pub history_num: Option<i64>,
+    /// Minimum lifetime of a password
+    pub min_life: Option<std::time::Duration>,
+}
+
+impl PasswordQualityError {
+    /// Create a [`PasswordQualityError`] without policy values
+    pub(crate) fn new(rule: PasswordQualityRule, code: i64, message: String) -> Self {
+        Self {
+            rule,
+            code,
+            message,
+            policy: None,
+            min_length: None,
+            min_classes: None,
+            history_num: None,
+            min_life: None,
+        }
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Fill in the values of the `policy` applying to the principal
+    pub(crate) fn with_policy(mut self, policy: &crate::policy::Policy) -> Self {
+        self.policy = Some(policy.name().to_owned());
+        self.min_length = Some(policy.password_min_length());
+        self.min_classes = Some(policy.password_min_classes());
+        self.history_num = Some(policy.password_history_num());
+        self.min_life = policy.password_min_life();
+        self
+    }
+}
+
+impl std::fmt::Display for PasswordQualityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.rule, self) {
+            (
+                PasswordQualityRule::MinLength,
+                Self {
+                    min_length: Some(min),
+                    ..
+                },
+            ) => {
+                write!(f, "Password must be at least {min} characters long")
+            }
+            (
+                PasswordQualityRule::MinClasses,
+                Self {
+                    min_classes: Some(min),
+                    ..
+                },
+            ) => {
+                write!(f, "Password must contain at least {min} character classes")
+            }
+            (
+                PasswordQualityRule::History,
+                Self {
+                    history_num: Some(num),
+                    ..
+                },
+            ) => {
+                write!(f, "Password must not be one of the last {num} passwords")
+            }
+            (
+                PasswordQualityRule::MinLife,
+                Self {
+                    min_life: Some(min),
+                    ..
+                },
+            ) => write!(
+                f,
+                "Password cannot be changed within {} seconds of the previous change",
+                min.as_secs()
+            ),
+            _ => f.write_str(&self.message),
+        }?;
+        write!(f, " (code: {})", self.code)
+    }
 }
 
 impl<T> From<std::sync::mpsc::SendError<T>> for Error {
@@ -112,6 +506,7 @@ macro_rules! kadm5_error_enum {
         $libname:ident;
         $(#[$outer:meta])*
         $EnumName:ident { $($manual_fields:tt)* }
+        kinds { $($ManualVariant:ident => $ManualKind:ident),* $(,)? }
     ) => {
         $(#[$outer])*
         enum $EnumName {
@@ -219,11 +614,59 @@ macro_rules! kadm5_error_enum {
             $($manual_fields)*
         }
 
+        impl $EnumName {
+            fn kind(self) -> KAdminErrorKind {
+                match self {
+                    Self::Failure => KAdminErrorKind::Failure,
+                    Self::AuthGet
+                    | Self::AuthAdd
+                    | Self::AuthModify
+                    | Self::AuthDelete
+                    | Self::AuthInsufficient
+                    | Self::AuthList
+                    | Self::AuthChangepw => KAdminErrorKind::InsufficientPrivilege,
+                    Self::BadDb | Self::BadHistKey | Self::SecurePrincMissing => {
+                        KAdminErrorKind::Database
+                    }
+                    Self::Dup => KAdminErrorKind::DuplicatePrincipal,
+                    Self::RpcError => KAdminErrorKind::RpcError,
+                    Self::NoSrv | Self::BadServerName => KAdminErrorKind::NoServer,
+                    Self::UnkPrinc => KAdminErrorKind::UnknownPrincipal,
+                    Self::UnkPolicy => KAdminErrorKind::UnknownPolicy,
+                    Self::BadMask
+                    | Self::BadClass
+                    | Self::BadLength
+                    | Self::BadAuxAttr
+                    | Self::BadHistory
+                    | Self::BadMinPassLife
+                    | Self::BadTlType
+                    | Self::Setkey3EtypeMismatch => KAdminErrorKind::InvalidArgument,
+                    Self::BadPolicy => KAdminErrorKind::InvalidPolicy,
+                    Self::BadPrincipal => KAdminErrorKind::InvalidPrincipal,
+                    Self::PassQTooshort => KAdminErrorKind::PasswordTooShort,
+                    Self::PassQClass => KAdminErrorKind::PasswordTooFewClasses,
+                    Self::PassQDict => KAdminErrorKind::PasswordInDictionary,
+                    Self::PassReuse => KAdminErrorKind::PasswordReuse,
+                    Self::PassToosoon => KAdminErrorKind::PasswordTooSoon,
+                    Self::PolicyRef => KAdminErrorKind::PolicyInUse,
+                    Self::BadPassword => KAdminErrorKind::AuthFailure,
+                    Self::ProtectPrincipal => KAdminErrorKind::ProtectedPrincipal,
+                    Self::NoRenameSalt => KAdminErrorKind::Unsupported,
+                    Self::BadClientParams | Self::BadServerParams | Self::MissingConfParams => {
+                        KAdminErrorKind::Configuration
+                    }
+                    $(Self::$ManualVariant => KAdminErrorKind::$ManualKind,)*
+                    _ => KAdminErrorKind::Other,
+                }
+            }
+        }
+
         impl From<$EnumName> for Error {
             fn from(error: $EnumName) -> Self {
                 Error::KAdmin {
                     code: error as i64,
-                    message: error.to_string()
+                    message: error.to_string(),
+                    kind: error.kind(),
                 }
             }
         }
@@ -277,6 +720,21 @@ kadm5_error_enum!(
         #[error("Operation requires initial ticket")]
         AuthInitial = kadm5_error_enum!(mit_client, KADM5_AUTH_INITIAL),
     }
+    kinds {
+        PassQGeneric => PasswordQuality,
+        GssError => AuthFailure,
+        AuthSetkey => InsufficientPrivilege,
+        SetkeyDupEnctypes => InvalidArgument,
+        Setv4keyInvalEnctype => InvalidArgument,
+        MissingKrb5ConfParams => Configuration,
+        XdrFailure => RpcError,
+        CantResolve => NoServer,
+        BadKeysalts => InvalidArgument,
+        SetkeyBadKvno => InvalidArgument,
+        AuthExtract => InsufficientPrivilege,
+        ProtectKeys => ProtectedPrincipal,
+        AuthInitial => InsufficientPrivilege,
+    }
 );
 
 #[cfg(mit_server)]
@@ -313,6 +771,21 @@ kadm5_error_enum!(
         #[error("Operation requires initial ticket")]
         AuthInitial = kadm5_error_enum!(mit_server, KADM5_AUTH_INITIAL),
     }
+    kinds {
+        PassQGeneric => PasswordQuality,
+        GssError => AuthFailure,
+        AuthSetkey => InsufficientPrivilege,
+        SetkeyDupEnctypes => InvalidArgument,
+        Setv4keyInvalEnctype => InvalidArgument,
+        MissingKrb5ConfParams => Configuration,
+        XdrFailure => RpcError,
+        CantResolve => NoServer,
+        BadKeysalts => InvalidArgument,
+        SetkeyBadKvno => InvalidArgument,
+        AuthExtract => InsufficientPrivilege,
+        ProtectKeys => ProtectedPrincipal,
+        AuthInitial => InsufficientPrivilege,
+    }
 );
 
 #[cfg(heimdal_client)]
@@ -341,6 +814,17 @@ kadm5_error_enum!(
         #[error("Keep old keys option not supported")]
         KeepoldNosupp = kadm5_error_enum!(heimdal_client, KADM5_KEEPOLD_NOSUPP),
     }
+    kinds {
+        KsTupleNosupp => Unsupported,
+        DecryptUsageNosupp => Unsupported,
+        PolicyOpNosupp => Unsupported,
+        AuthGetKeys => InsufficientPrivilege,
+        AlreadyLocked => Database,
+        NotLocked => Database,
+        LogCorrupt => Database,
+        LogNeedsUpgrade => Database,
+        KeepoldNosupp => Unsupported,
+    }
 );
 
 #[cfg(heimdal_server)]
@@ -369,6 +853,17 @@ kadm5_error_enum!(
         #[error("Keep old keys option not supported")]
         KeepoldNosupp = kadm5_error_enum!(heimdal_server, KADM5_KEEPOLD_NOSUPP),
 }
+    kinds {
+        KsTupleNosupp => Unsupported,
+        DecryptUsageNosupp => Unsupported,
+        PolicyOpNosupp => Unsupported,
+        AuthGetKeys => InsufficientPrivilege,
+        AlreadyLocked => Database,
+        NotLocked => Database,
+        LogCorrupt => Database,
+        LogNeedsUpgrade => Database,
+        KeepoldNosupp => Unsupported,
+    }
 );
 
 /// Helper function to "raise" an error from a [`kadm5_ret_t`]
@@ -397,3 +892,47 @@ pub(crate) fn kadm5_ret_t_escape_hatch(context: &Context, code: i64) -> Result<(
         krb5_error_code_escape_hatch(context, code)
     }
 }
+
+/// Helper function to build the kadm5 error of `variant` matching `kind`, for errors this library
+/// detects before calling into kadm5
+///
+/// Only kinds with an equivalent common kadm5 error are supported, others result in
+/// [`KAdminErrorKind::Failure`]
+pub(crate) fn kadm5_error(variant: KAdm5Variant, kind: KAdminErrorKind) -> Error {
+    macro_rules! build {
+        ($EnumName:ident) => {
+            match kind {
+                KAdminErrorKind::UnknownPrincipal => $EnumName::UnkPrinc,
+                KAdminErrorKind::UnknownPolicy => $EnumName::UnkPolicy,
+                KAdminErrorKind::DuplicatePrincipal | KAdminErrorKind::DuplicatePolicy => {
+                    $EnumName::Dup
+                }
+                KAdminErrorKind::PolicyInUse => $EnumName::PolicyRef,
+                KAdminErrorKind::PasswordTooShort => $EnumName::PassQTooshort,
+                KAdminErrorKind::PasswordTooFewClasses => $EnumName::PassQClass,
+                KAdminErrorKind::PasswordInDictionary => $EnumName::PassQDict,
+                KAdminErrorKind::PasswordReuse => $EnumName::PassReuse,
+                KAdminErrorKind::PasswordTooSoon => $EnumName::PassToosoon,
+                KAdminErrorKind::InvalidPrincipal => $EnumName::BadPrincipal,
+                _ => $EnumName::Failure,
+            }
+            .into()
+        };
+    }
+
+    let error: Error = match variant {
+        #[cfg(mit_client)]
+        KAdm5Variant::MitClient => build!(KAdm5ErrorMitClient),
+        #[cfg(mit_server)]
+        KAdm5Variant::MitServer => build!(KAdm5ErrorMitServer),
+        #[cfg(heimdal_client)]
+        KAdm5Variant::HeimdalClient => build!(KAdm5ErrorHeimdalClient),
+        #[cfg(heimdal_server)]
+        KAdm5Variant::HeimdalServer => build!(KAdm5ErrorHeimdalServer),
+    };
+    if kind == KAdminErrorKind::DuplicatePolicy {
+        error.for_policy()
+    } else {
+        error
+    }
+}
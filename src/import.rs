@@ -0,0 +1,649 @@
+//! Bulk principal provisioning from CSV
+//!
+//! An [`Import`] reads principals from a CSV file and creates them, or updates those that already
+//! exist when [`Import::update`] is set:
+//!
+//! ```no_run
+//! # use crate::kadmin::{KAdmin, KAdm5Variant, import::Import};
+//! let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+//!     .with_ccache(None, None)
+//!     .unwrap();
+//! let report = Import::from_file("principals.csv")
+//!     .unwrap()
+//!     .update(true)
+//!     .run(&kadmin);
+//! for row in report.failed() {
+//!     eprintln!(
+//!         "line {}: {}: {}",
+//!         row.line,
+//!         row.name,
+//!         row.result.as_ref().unwrap_err()
+//!     );
+//! }
+//! ```
+//!
+//! The first line of the file names the columns, in any order. Only `name` is required:
+//!
+//! * `name`: the principal name
+//! * `password`: its password. Without one, new principals get a random key and existing ones keep
+//!   theirs. `randkey` gives the principal a new random key, including existing ones
+//! * `policy`: its password policy
+//! * `expire_time`: when it expires, as an RFC 3339 date and time, a `YYYY-MM-DD` date or `never`
+//! * `attributes`: kadmin-style flags, like `+requires_preauth -allow_tix`, see
+//!   [`apply_attribute_flags`]. They apply to the default attributes of new principals, and to the
+//!   current ones of existing principals
+//! * `strings`: string attributes, as `key=value` pairs separated by `;`. Only supported by MIT
+//!   krb5
+//!
+//! ```csv
+//! name,password,policy,expire_time,attributes,strings
+//! alice,correct horse battery staple,users,2030-01-01,+requires_preauth,
+//! HTTP/www.example.org,,services,never,-allow_tix,"session_enctypes=aes256-cts"
+//! ```
+//!
+//! Empty cells leave the field to its default for new principals, and unchanged for existing ones.
+//! Each row is applied on its own: a failure is reported in the [`ImportReport`] and the import
+//! carries on with the next row. A row that fails halfway is undone: principals it created are
+//! deleted, and the fields and string attributes it changed on existing principals are restored.
+//! Password changes come last, as they can't be undone.
+
+use std::{
+    fmt, fs,
+    iter::Peekable,
+    mem,
+    path::Path,
+    str::{Chars, FromStr},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use zeroize::Zeroizing;
+
+use crate::{
+    error::{Error, Result},
+    kadmin::KAdminImpl,
+    principal::{
+        Principal, PrincipalBuilder, PrincipalBuilderKey, PrincipalModifier, apply_attribute_flags,
+    },
+    sys::KAdm5Variant,
+};
+
+/// Columns an import file may contain
+const COLUMNS: [&str; 6] = [
+    "name",
+    "password",
+    "policy",
+    "expire_time",
+    "attributes",
+    "strings",
+];
+
+fn parse_error(line: usize, message: impl std::fmt::Display) -> Error {
+    Error::CsvParse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Split CSV `contents` into records, each with the line it starts at
+///
+/// Fields may be quoted, in which case they can contain commas, newlines and `""` for a quote.
+/// Empty lines are skipped.
+fn records(contents: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    fn next_is(chars: &mut Peekable<Chars<'_>>, c: char) -> bool {
+        chars.next_if_eq(&c).is_some()
+    }
+
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if next_is(&mut chars, '"') => field.push('"'),
+                '"' => quoted = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(mem::take(&mut field));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push((start, mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(parse_error(start, "unterminated quoted field"));
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+/// Parse an `expire_time` cell
+fn parse_expire_time(value: &str) -> Option<Option<DateTime<Utc>>> {
+    if value.eq_ignore_ascii_case("never") {
+        return Some(None);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(Some(datetime.with_timezone(&Utc)));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    Some(Some(date.and_hms_opt(0, 0, 0)?.and_utc()))
+}
+
+/// Parse a `strings` cell
+fn parse_strings(value: &str) -> Option<Vec<(String, String)>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            Some((key.trim().to_owned(), value.trim().to_owned()))
+        })
+        .collect()
+}
+
+/// A principal to create or update, read from a line of an import file
+#[derive(Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ImportRow {
+    /// Line of the file the row starts at
+    pub line: usize,
+    /// Principal name
+    pub name: String,
+    /// Password to set. A random key is used for new principals if unset
+    ///
+    /// It is zeroed on drop and left out of the `Debug` output.
+    pub password: Option<Zeroizing<String>>,
+    /// Whether to set a new random key, from a `randkey` password cell
+    pub randkey: bool,
+    /// Policy to set
+    pub policy: Option<String>,
+    /// Expiration time to set, `Some(None)` for none
+    pub expire_time: Option<Option<DateTime<Utc>>>,
+    /// Attribute flags to apply, see [`apply_attribute_flags`]
+    pub attributes: Option<String>,
+    /// String attributes to set
+    pub strings: Vec<(String, String)>,
+}
+
+impl fmt::Debug for ImportRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImportRow")
+            .field("line", &self.line)
+            .field("name", &self.name)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("randkey", &self.randkey)
+            .field("policy", &self.policy)
+            .field("expire_time", &self.expire_time)
+            .field("attributes", &self.attributes)
+            .field("strings", &self.strings)
+            .finish()
+    }
+}
+
+impl ImportRow {
+    /// Build the [`PrincipalBuilder`] creating this principal on a realm of `variant`
+    pub fn builder(&self, variant: KAdm5Variant) -> Result<PrincipalBuilder> {
+        let mut builder = PrincipalBuilder::new(&self.name);
+        if let Some(password) = &self.password {
+            builder = builder.key(&PrincipalBuilderKey::Password(String::clone(password)));
+        }
+        if let Some(policy) = &self.policy {
+            builder = builder.policy(Some(policy));
+        }
+        if let Some(expire_time) = self.expire_time {
+            builder = builder.expire_time(expire_time);
+        }
+        if let Some(flags) = &self.attributes {
+            builder = builder.attributes(apply_attribute_flags(0, flags, variant)?);
+        }
+        Ok(builder)
+    }
+
+    /// Build the [`PrincipalModifier`] updating the existing `principal` on a realm of `variant`
+    ///
+    /// The password isn't part of it, and must be changed separately.
+    pub fn modifier(
+        &self,
+        principal: &Principal,
+        variant: KAdm5Variant,
+    ) -> Result<PrincipalModifier> {
+        let mut modifier = PrincipalModifier::from_principal(principal);
+        if let Some(policy) = &self.policy {
+            modifier = modifier.policy(Some(policy));
+        }
+        if let Some(expire_time) = self.expire_time {
+            modifier = modifier.expire_time(expire_time);
+        }
+        if let Some(flags) = &self.attributes {
+            modifier = modifier.attributes(apply_attribute_flags(
+                principal.attributes(),
+                flags,
+                variant,
+            )?);
+        }
+        Ok(modifier)
+    }
+}
+
+/// What was done with an [`ImportRow`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImportAction {
+    /// The principal was created
+    Created,
+    /// The principal already existed and was updated
+    Updated,
+}
+
+/// Outcome of an [`ImportRow`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ImportOutcome {
+    /// Line of the file the row starts at
+    pub line: usize,
+    /// Principal name
+    pub name: String,
+    /// What was done, or why it failed
+    pub result: Result<ImportAction>,
+}
+
+/// Outcomes of an [`Import`], in the order of the file
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct ImportReport {
+    /// Outcome of each row
+    pub rows: Vec<ImportOutcome>,
+}
+
+impl ImportReport {
+    /// Rows that failed
+    pub fn failed(&self) -> impl Iterator<Item = &ImportOutcome> {
+        self.rows.iter().filter(|row| row.result.is_err())
+    }
+
+    /// Whether every row succeeded
+    pub fn is_success(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+/// Principals to provision from a CSV file
+///
+/// See the [module documentation][`crate::import`] for the file format.
+#[derive(Clone, Debug, Default)]
+pub struct Import {
+    rows: Vec<ImportRow>,
+    update: bool,
+}
+
+impl Import {
+    /// Read an import file from `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Rows read from the file
+    pub fn rows(&self) -> &[ImportRow] {
+        &self.rows
+    }
+
+    /// Update principals that already exist instead of failing to create them
+    ///
+    /// Defaults to `false`
+    pub fn update(mut self, update: bool) -> Self {
+        self.update = update;
+        self
+    }
+
+    /// Create or update every principal on `kadmin`
+    pub fn run<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> ImportReport {
+        ImportReport {
+            rows: self
+                .rows
+                .iter()
+                .map(|row| ImportOutcome {
+                    line: row.line,
+                    name: row.name.clone(),
+                    result: self.apply(kadmin, row),
+                })
+                .collect(),
+        }
+    }
+
+    fn apply<K: KAdminImpl + ?Sized>(&self, kadmin: &K, row: &ImportRow) -> Result<ImportAction> {
+        let variant = kadmin.variant();
+        if !row.strings.is_empty() && !variant.is_mit() {
+            return Err(Error::LibraryMismatch(
+                "String attributes are only available with MIT krb5",
+            ));
+        }
+
+        let existing = if self.update {
+            kadmin.get_principal(&row.name)?
+        } else {
+            None
+        };
+        let Some(principal) = existing else {
+            kadmin.add_principal(&row.builder(variant)?)?;
+            if let Err(err) = Self::set_strings(kadmin, row) {
+                // Best effort, the row is reported as failed either way
+                let _ = kadmin.delete_principal(&row.name);
+                return Err(err);
+            }
+            return Ok(ImportAction::Created);
+        };
+
+        #[cfg(any(mit_client, mit_server))]
+        let strings = if row.strings.is_empty() {
+            Default::default()
+        } else {
+            kadmin.principal_get_strings(&row.name)?
+        };
+        let modifier = row.modifier(&principal, variant)?;
+        kadmin.modify_principal(&modifier)?;
+        let result = Self::set_strings(kadmin, row).and_then(|()| {
+            if let Some(password) = &row.password {
+                kadmin.principal_change_password(
+                    &row.name,
+                    password,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                )
+            } else if row.randkey {
+                kadmin.principal_randkey(
+                    &row.name,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    None,
+                )
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = result {
+            // Best effort, the row is reported as failed either way
+            let _ = kadmin.modify_principal(&modifier.restoring(&principal));
+            #[cfg(any(mit_client, mit_server))]
+            for (key, _) in &row.strings {
+                let _ = kadmin.principal_set_string(
+                    &row.name,
+                    key,
+                    strings.get(key).map(String::as_str),
+                );
+            }
+            return Err(err);
+        }
+        Ok(ImportAction::Updated)
+    }
+
+    /// Set the string attributes of `row`
+    fn set_strings<K: KAdminImpl + ?Sized>(kadmin: &K, row: &ImportRow) -> Result<()> {
+        #[cfg(any(mit_client, mit_server))]
+        for (key, value) in &row.strings {
+            kadmin.principal_set_string(&row.name, key, Some(value.as_str()))?;
+        }
+        #[cfg(not(any(mit_client, mit_server)))]
+        let _ = (kadmin, row);
+        Ok(())
+    }
+}
+
+impl FromStr for Import {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut records = records(s)?.into_iter();
+        let Some((header_line, header)) = records.next() else {
+            return Err(parse_error(1, "missing header"));
+        };
+        let mut columns = Vec::with_capacity(header.len());
+        for column in &header {
+            let column = column.trim().to_ascii_lowercase();
+            if !COLUMNS.contains(&column.as_str()) {
+                return Err(parse_error(
+                    header_line,
+                    format!("unknown column {column:?}"),
+                ));
+            }
+            if columns.contains(&column) {
+                return Err(parse_error(
+                    header_line,
+                    format!("duplicate column {column:?}"),
+                ));
+            }
+            columns.push(column);
+        }
+        if !columns.iter().any(|column| column == "name") {
+            return Err(parse_error(header_line, "missing name column"));
+        }
+
+        let mut rows = Vec::new();
+        for (line, record) in records {
+            if record.len() != columns.len() {
+                return Err(parse_error(
+                    line,
+                    format!("expected {} fields, got {}", columns.len(), record.len()),
+                ));
+            }
+            let mut row = ImportRow {
+                line,
+                ..Default::default()
+            };
+            for (column, value) in columns.iter().zip(record) {
+                // Passwords are kept as they are, they may start or end with spaces
+                let value = if column == "password" {
+                    value
+                } else {
+                    value.trim().to_owned()
+                };
+                if value.is_empty() {
+                    continue;
+                }
+                match column.as_str() {
+                    "name" => row.name = value,
+                    "password" if value == "randkey" => row.randkey = true,
+                    "password" => row.password = Some(Zeroizing::new(value)),
+                    "policy" => row.policy = Some(value),
+                    "expire_time" => {
+                        row.expire_time = Some(parse_expire_time(&value).ok_or_else(|| {
+                            parse_error(line, format!("invalid expire_time {value:?}"))
+                        })?);
+                    }
+                    "attributes" => row.attributes = Some(value),
+                    "strings" => {
+                        row.strings = parse_strings(&value).ok_or_else(|| {
+                            parse_error(line, format!("invalid strings {value:?}"))
+                        })?;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            if row.name.is_empty() {
+                return Err(parse_error(line, "missing principal name"));
+            }
+            rows.push(row);
+        }
+        Ok(Self {
+            rows,
+            update: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() -> Result<()> {
+        let import: Import = "\
+Name,Password,expire_time,strings\r
+alice, two words ,2030-01-01,\r
+\r
+\"host/a.example.org\",,never,\"a=1; b=\"\"quoted\"\"\"
+\"multi
+line\",,,
+"
+        .parse()?;
+        let rows = import.rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].name, "alice");
+        assert_eq!(
+            rows[0].password.as_deref().map(String::as_str),
+            Some(" two words ")
+        );
+        assert!(!format!("{import:?}").contains("two words"));
+        assert_eq!(
+            rows[0].expire_time,
+            Some(Some(
+                NaiveDate::from_ymd_opt(2030, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            ))
+        );
+        assert_eq!(rows[1].line, 4);
+        assert_eq!(rows[1].name, "host/a.example.org");
+        assert_eq!(rows[1].password, None);
+        assert_eq!(rows[1].expire_time, Some(None));
+        assert_eq!(
+            rows[1].strings,
+            vec![
+                ("a".to_owned(), "1".to_owned()),
+                ("b".to_owned(), "\"quoted\"".to_owned())
+            ]
+        );
+        assert_eq!(rows[2].line, 5);
+        assert_eq!(rows[2].name, "multi\nline");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (contents, line) in [
+            ("", 1),
+            ("password\nfoo\n", 1),
+            ("name,color\nfoo,red\n", 1),
+            ("name,name\nfoo,foo\n", 1),
+            ("name,policy\nfoo\n", 2),
+            ("name,expire_time\nfoo,tomorrow\n", 2),
+            ("name,strings\nfoo,novalue\n", 2),
+            ("name\nfoo\n\"bar\n", 3),
+        ] {
+            match contents.parse::<Import>() {
+                Err(Error::CsvParse { line: actual, .. }) => {
+                    assert_eq!(actual, line, "{contents:?}")
+                }
+                other => panic!("{contents:?} parsed to {other:?}"),
+            }
+        }
+    }
+
+    #[cfg(mit_server)]
+    #[test]
+    fn run() -> Result<()> {
+        use crate::{MemoryKAdmin, Policy};
+
+        let kadmin = MemoryKAdmin::new(KAdm5Variant::MitServer, "EXAMPLE.ORG");
+        for policy in ["old", "new"] {
+            Policy::builder(policy).create(&kadmin)?;
+        }
+        PrincipalBuilder::new("alice")
+            .policy(Some("old"))
+            .create(&kadmin)?;
+        let contents = "\
+name,policy,attributes,strings
+alice,new,+requires_preauth,
+bob,,-allow_tix,a=1
+carol,,+not_an_attribute,
+";
+        let report = contents.parse::<Import>()?.run(&kadmin);
+        assert!(report.rows[0].result.is_err());
+        assert_eq!(
+            report.rows[1].result.as_ref().ok(),
+            Some(&ImportAction::Created)
+        );
+        assert!(matches!(
+            report.rows[2].result,
+            Err(Error::InvalidAttribute(_))
+        ));
+        assert!(!report.is_success());
+        let bob = kadmin.get_principal("bob")?.unwrap();
+        assert_eq!(bob.attributes() & 0x40, 0x40);
+        assert_eq!(
+            kadmin
+                .principal_get_strings("bob")?
+                .get("a")
+                .map(String::as_str),
+            Some("1")
+        );
+
+        let report = contents.parse::<Import>()?.update(true).run(&kadmin);
+        assert_eq!(
+            report.rows[0].result.as_ref().ok(),
+            Some(&ImportAction::Updated)
+        );
+        assert_eq!(
+            report.rows[1].result.as_ref().ok(),
+            Some(&ImportAction::Updated)
+        );
+        let alice = kadmin.get_principal("alice")?.unwrap();
+        assert_eq!(alice.policy(), Some("new"));
+        assert_eq!(alice.attributes() & 0x80, 0x80);
+
+        let report = "name,password\nalice,randkey\n"
+            .parse::<Import>()?
+            .update(true)
+            .run(&kadmin);
+        assert!(report.is_success());
+        assert_eq!(
+            kadmin.get_principal("alice")?.unwrap().kvno(),
+            alice.kvno() + 1
+        );
+
+        // The policy change is undone when the password is rejected by the new policy
+        Policy::builder("strict")
+            .password_min_length(12)
+            .create(&kadmin)?;
+        let report = "name,password,policy,strings\nbob,short,strict,a=2\n"
+            .parse::<Import>()?
+            .update(true)
+            .run(&kadmin);
+        assert_eq!(
+            report.rows[0].result.as_ref().unwrap_err().kind(),
+            Some(crate::KAdminErrorKind::PasswordTooShort)
+        );
+        assert_eq!(kadmin.get_principal("bob")?.unwrap().policy(), None);
+        assert_eq!(kadmin.principal_get_strings("bob")?["a"], "1");
+        Ok(())
+    }
+}
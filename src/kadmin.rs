@@ -5,25 +5,33 @@ use std::{
     ffi::{CString, c_char, c_void},
     mem::MaybeUninit,
     ptr::{self, null, null_mut},
-    sync::Mutex,
+    sync::{Arc, Mutex},
 };
 
 use libc::EINVAL;
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
+use zeroize::Zeroizing;
 
-#[cfg(any(mit_client, mit_server, heimdal_server))]
-use crate::policy::{Policy, PolicyBuilder, PolicyModifier};
 use crate::{
     context::Context,
-    conv::{c_string_to_string, parse_name, unparse_name},
+    conv::{bytes_from_raw, c_string_to_string, parse_name, unparse_name},
     db_args::DbArgs,
-    error::{Error, Result, kadm5_ret_t_escape_hatch, krb5_error_code_escape_hatch},
-    keysalt::KeySalts,
+    error::{
+        Error, PasswordQualityError, PasswordQualityRule, Result, kadm5_ret_t_escape_hatch,
+        krb5_error_code_escape_hatch,
+    },
+    keysalt::{Key, KeySalt, KeySalts},
     params::{Params, ParamsRaw},
     principal::{Principal, PrincipalBuilder, PrincipalBuilderKey, PrincipalModifier},
+    privileges::Privileges,
     sys::{self, KAdm5Variant, Library, library_match},
 };
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::{
+    error::{KAdminErrorKind, kadm5_error},
+    policy::{Policy, PolicyBuilder, PolicyModifier, PolicyUsage},
+};
 
 /// Lock acquired when creating or dropping a [`KAdmin`] instance
 pub static KADMIN_INIT_LOCK: Mutex<()> = Mutex::new(());
@@ -103,6 +111,10 @@ pub struct KAdmin {
 }
 
 /// Common methods for `KAdmin` implementations
+///
+/// This trait can be used as `dyn KAdminImpl`, and is implemented for `&T`, `Box<T>` and `Arc<T>`
+/// where `T` is an implementation, so wrappers such as [`DryRun`][`crate::dry_run::DryRun`] or
+/// [layers][`crate::layer`] can take any of them.
 pub trait KAdminImpl {
     /// Retrieve the kadm5 variant used
     fn variant(&self) -> KAdm5Variant;
@@ -205,6 +217,39 @@ pub trait KAdminImpl {
         #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
     ) -> Result<()>;
 
+    /// Retrieve the keys of a principal, in clear text
+    ///
+    /// * `kvno`: only return the keys with this key version number. Defaults to all of them.
+    ///
+    /// This requires the extract privilege with MIT, and the `get-keys` one with Heimdal. MIT
+    /// refuses to return the keys of principals with the `LOCKDOWN_KEYS` attribute. Heimdal
+    /// servers may return placeholder keys with empty contents to clients lacking privileges.
+    ///
+    /// Implementations that can't retrieve keys return [`Error::LibraryMismatch`]
+    fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+        let _ = (name, kvno);
+        Err(Error::LibraryMismatch(
+            "Key retrieval isn't supported by this implementation.",
+        ))
+    }
+
+    /// Set the keys of a principal, as returned by [`KAdminImpl::principal_get_keys`]
+    ///
+    /// * `keepold`: Keeps the existing keys in the database
+    ///
+    /// With MIT, the key version numbers of `keys` are kept. Heimdal only accepts keys sharing a
+    /// single key version number, so only the newest ones are set, and it assigns the key version
+    /// number itself. It also derives the salts from the salt types, dropping special salts.
+    ///
+    /// Implementations that can't set keys return [`Error::LibraryMismatch`]
+    #[doc(alias = "setkey")]
+    fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+        let _ = (name, keepold, keys);
+        Err(Error::LibraryMismatch(
+            "Setting keys isn't supported by this implementation.",
+        ))
+    }
+
     #[cfg(any(mit_client, mit_server))]
     /// Retrieve string attributes on a principal
     ///
@@ -268,6 +313,55 @@ pub trait KAdminImpl {
     #[doc(alias = "delpol")]
     fn delete_policy(&self, name: &str) -> Result<()>;
 
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Rename a policy
+    ///
+    /// kadm5 has no operation to rename a policy. Instead, a new policy is created with the same
+    /// values as the old one (see [`PolicyBuilder::from_policy`]), every principal referencing the
+    /// old policy is reassigned to the new one, and the old policy is deleted. If any of those
+    /// steps fail, the principals are reassigned back to the old policy and the new policy is
+    /// deleted, on a best-effort basis.
+    ///
+    /// ```no_run
+    /// # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant};
+    /// # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+    /// #     .with_ccache(None, None)
+    /// #     .unwrap();
+    /// kadmin.rename_policy("oldpol", "newpol").unwrap();
+    /// ```
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    #[doc(alias = "renpol")]
+    fn rename_policy(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let Some(policy) = self.get_policy(old_name)? else {
+            return Err(kadm5_error(self.variant(), KAdminErrorKind::UnknownPolicy));
+        };
+        self.add_policy(&PolicyBuilder::from_policy(&policy).name(new_name))?;
+
+        let mut reassigned = Vec::new();
+        let mut reassign = || -> Result<()> {
+            for name in self.list_principals(None)? {
+                let Some(principal) = self.get_principal(&name)? else {
+                    continue;
+                };
+                if principal.policy() == Some(old_name) {
+                    self.modify_principal(&principal.modifier().policy(Some(new_name)))?;
+                    reassigned.push(principal);
+                }
+            }
+            self.delete_policy(old_name)
+        };
+
+        if let Err(err) = reassign() {
+            for principal in &reassigned {
+                let _ = self.modify_principal(&principal.modifier().policy(Some(old_name)));
+            }
+            let _ = self.delete_policy(new_name);
+            return Err(err);
+        }
+        Ok(())
+    }
+
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     /// Retrieve a policy
     ///
@@ -323,15 +417,273 @@ pub trait KAdminImpl {
     #[doc(alias("listpols", "get_policies", "getpols"))]
     fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>>;
 
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Compute which principals reference each policy
+    ///
+    /// [`Policy::policy_refcnt`] isn't filled by every library, so this retrieves every principal
+    /// of the realm instead, which can be slow on large realms. The returned [`PolicyUsage`] can be
+    /// kept around and [refreshed][`PolicyUsage::refresh_if_older`] to avoid scanning too often.
+    ///
+    /// ```no_run
+    /// # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant};
+    /// # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+    /// #     .with_ccache(None, None)
+    /// #     .unwrap();
+    /// let usage = kadmin.policy_usage().unwrap();
+    /// for (policy, principals) in usage.usage() {
+    ///     println!("{policy}: {} principals", principals.len());
+    /// }
+    /// ```
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    fn policy_usage(&self) -> Result<PolicyUsage> {
+        let mut usage: HashMap<String, Vec<String>> = HashMap::new();
+        for name in self.list_principals(None)? {
+            let Some(principal) = self.get_principal(&name)? else {
+                continue;
+            };
+            if let Some(policy) = principal.policy() {
+                usage.entry(policy.to_owned()).or_default().push(name);
+            }
+        }
+        Ok(PolicyUsage::new(usage))
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// List the principals referencing a policy
+    ///
+    /// See [`Self::policy_usage`] about performance
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    fn principals_using_policy(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self.policy_usage()?.principals_using(name).to_vec())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Delete a policy, unless principals still reference it
+    ///
+    /// Returns an error of kind [`KAdminErrorKind::PolicyInUse`] if the policy is still in use.
+    /// See [`Self::policy_usage`] about performance
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    fn delete_policy_if_unused(&self, name: &str) -> Result<()> {
+        if self.policy_usage()?.is_in_use(name) {
+            return Err(kadm5_error(self.variant(), KAdminErrorKind::PolicyInUse));
+        }
+        self.delete_policy(name)
+    }
+
     /// Get current privileges
-    fn get_privileges(&self) -> Result<i64>;
+    fn get_privileges(&self) -> Result<Privileges>;
+
+    /// Check that the current session has all of the `required` privileges
+    ///
+    /// Returns [`Error::InsufficientPrivileges`] with the missing privileges otherwise
+    fn require_privileges(&self, required: Privileges) -> Result<()> {
+        self.get_privileges()?.require(required)
+    }
+}
+
+/// Forward every method of [`KAdminImpl`], including the provided ones, to `**self`
+macro_rules! forward_kadmin_impl {
+    ($($ty:ty),+) => {
+        $(
+            impl<T: KAdminImpl + ?Sized> KAdminImpl for $ty {
+                fn variant(&self) -> KAdm5Variant {
+                    (**self).variant()
+                }
+
+                fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
+                    (**self).add_principal(builder)
+                }
+
+                fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
+                    (**self).modify_principal(modifier)
+                }
+
+                fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
+                    (**self).rename_principal(old_name, new_name)
+                }
+
+                fn delete_principal(&self, name: &str) -> Result<()> {
+                    (**self).delete_principal(name)
+                }
+
+                fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
+                    (**self).get_principal(name)
+                }
+
+                fn principal_exists(&self, name: &str) -> Result<bool> {
+                    (**self).principal_exists(name)
+                }
+
+                fn principal_change_password(
+                    &self,
+                    name: &str,
+                    password: &str,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+                ) -> Result<()> {
+                    (**self).principal_change_password(
+                        name,
+                        password,
+                        #[cfg(any(mit_client, mit_server, heimdal_server))]
+                        keepold,
+                        #[cfg(any(mit_client, mit_server, heimdal_server))]
+                        keysalts,
+                    )
+                }
+
+                fn principal_randkey(
+                    &self,
+                    name: &str,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+                ) -> Result<()> {
+                    (**self).principal_randkey(
+                        name,
+                        #[cfg(any(mit_client, mit_server, heimdal_server))]
+                        keepold,
+                        #[cfg(any(mit_client, mit_server, heimdal_server))]
+                        keysalts,
+                    )
+                }
+
+                fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+                    (**self).principal_get_keys(name, kvno)
+                }
+
+                fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+                    (**self).principal_set_keys(name, keepold, keys)
+                }
+
+                #[cfg(any(mit_client, mit_server))]
+                fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
+                    (**self).principal_get_strings(name)
+                }
+
+                #[cfg(any(mit_client, mit_server))]
+                fn principal_set_string(
+                    &self,
+                    name: &str,
+                    key: &str,
+                    value: Option<&str>,
+                ) -> Result<()> {
+                    (**self).principal_set_string(name, key, value)
+                }
+
+                fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
+                    (**self).list_principals(query)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
+                    (**self).add_policy(builder)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
+                    (**self).modify_policy(modifier)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn delete_policy(&self, name: &str) -> Result<()> {
+                    (**self).delete_policy(name)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn rename_policy(&self, old_name: &str, new_name: &str) -> Result<()> {
+                    (**self).rename_policy(old_name, new_name)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+                    (**self).get_policy(name)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn policy_exists(&self, name: &str) -> Result<bool> {
+                    (**self).policy_exists(name)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
+                    (**self).list_policies(query)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn policy_usage(&self) -> Result<PolicyUsage> {
+                    (**self).policy_usage()
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn principals_using_policy(&self, name: &str) -> Result<Vec<String>> {
+                    (**self).principals_using_policy(name)
+                }
+
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                fn delete_policy_if_unused(&self, name: &str) -> Result<()> {
+                    (**self).delete_policy_if_unused(name)
+                }
+
+                fn get_privileges(&self) -> Result<Privileges> {
+                    (**self).get_privileges()
+                }
+
+                fn require_privileges(&self, required: Privileges) -> Result<()> {
+                    (**self).require_privileges(required)
+                }
+            }
+        )+
+    };
 }
 
+forward_kadmin_impl!(&T, Box<T>, Arc<T>);
+
 impl KAdmin {
     /// Construct a new [`KAdminBuilder`]
     pub fn builder(variant: KAdm5Variant) -> KAdminBuilder {
         KAdminBuilder::new(variant)
     }
+
+    /// Parse a keysalt list, like `aes256-cts-hmac-sha1-96:normal,aes128-cts-hmac-sha1-96`, with
+    /// the encryption and salt type names known to the Kerberos library
+    pub fn parse_keysalts(&self, keysalts: &str) -> Result<KeySalts> {
+        KeySalts::from_str(&self.context, keysalts)
+    }
+
+    /// Format a keysalt list with the encryption and salt type names known to the Kerberos library
+    pub fn format_keysalts(&self, keysalts: &KeySalts) -> Result<String> {
+        keysalts.to_string(&self.context)
+    }
+
+    /// Turn a password quality `error` into [`Error::PasswordQuality`]
+    ///
+    /// `policy_name` is only called for password quality errors, and the values of the returned
+    /// policy are added if it can be retrieved. Other errors are returned unchanged.
+    #[cfg_attr(
+        not(any(mit_client, mit_server, heimdal_server)),
+        allow(unused_variables)
+    )]
+    fn password_quality_error(
+        &self,
+        error: Error,
+        policy_name: impl FnOnce() -> Option<String>,
+    ) -> Error {
+        let Some(rule) = error.kind().and_then(PasswordQualityRule::from_kind) else {
+            return error;
+        };
+        let Error::KAdmin { code, message, .. } = error else {
+            return error;
+        };
+        #[allow(unused_mut)]
+        let mut details = PasswordQualityError::new(rule, code, message);
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        if let Some(policy) = policy_name().and_then(|name| self.get_policy(&name).ok().flatten()) {
+            details = details.with_policy(&policy);
+        }
+        Error::PasswordQuality(Box::new(details))
+    }
 }
 
 impl KAdminImpl for KAdmin {
@@ -480,7 +832,14 @@ impl KAdminImpl for KAdmin {
         } else {
             code
         };
-        kadm5_ret_t_escape_hatch(&self.context, code)?;
+        kadm5_ret_t_escape_hatch(&self.context, code).map_err(|err| {
+            self.password_quality_error(err, || match &builder.policy {
+                Some(policy) => policy.clone(),
+                // MIT assigns the `default` policy, if it exists, to principals created without one
+                None if self.variant().is_mit() => Some("default".to_owned()),
+                None => None,
+            })
+        })?;
 
         if old_style_randkey {
             library_match!(&self.context.library; |cont, lib| {
@@ -656,7 +1015,14 @@ impl KAdminImpl for KAdmin {
             }
         );
 
-        kadm5_ret_t_escape_hatch(&self.context, code)?;
+        kadm5_ret_t_escape_hatch(&self.context, code).map_err(|err| {
+            self.password_quality_error(err, || {
+                self.get_principal(name)
+                    .ok()
+                    .flatten()
+                    .and_then(|princ| princ.policy().map(String::from))
+            })
+        })?;
         Ok(())
     }
 
@@ -719,6 +1085,187 @@ impl KAdminImpl for KAdmin {
         Ok(())
     }
 
+    fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+        let princ = parse_name(&self.context, name)?;
+
+        library_match!(
+            &self.context.library;
+            mit_client, mit_server => |cont, lib| {
+                let mut count = 0;
+                let mut raw_keys = null_mut();
+
+                let code = unsafe {
+                    cont.kadm5_get_principal_keys(
+                        self.server_handle,
+                        princ.raw as lib!(krb5_principal),
+                        kvno.unwrap_or(0) as lib!(krb5_kvno),
+                        &mut raw_keys,
+                        &mut count,
+                    ).into()
+                };
+                kadm5_ret_t_escape_hatch(&self.context, code)?;
+
+                if raw_keys.is_null() {
+                    return Ok(Vec::new());
+                }
+
+                let keys = unsafe { std::slice::from_raw_parts(raw_keys, count as usize) }
+                    .iter()
+                    .map(|raw| Key {
+                        kvno: raw.kvno as u32,
+                        keysalt: KeySalt {
+                            enctype: (raw.key.enctype as i32).into(),
+                            salttype: (raw.salt.type_ as i32).into(),
+                        },
+                        contents: Zeroizing::new(bytes_from_raw(
+                            raw.key.contents as *const c_void,
+                            raw.key.length as usize,
+                        )),
+                        salt: bytes_from_raw(
+                            raw.salt.data.data as *const c_void,
+                            raw.salt.data.length as usize,
+                        ),
+                    })
+                    .collect();
+
+                unsafe {
+                    cont.kadm5_free_kadm5_key_data(
+                        self.context.context as lib!(krb5_context),
+                        count,
+                        raw_keys,
+                    );
+                }
+                Ok(keys)
+            },
+            heimdal_client, heimdal_server => |cont, lib| {
+                let mut princ_ent: lib!(_kadm5_principal_ent_t) = Default::default();
+                let princ_ptr = ptr::from_mut(&mut princ_ent);
+                let code = unsafe {
+                    cont.kadm5_get_principal(
+                        self.server_handle,
+                        princ.raw as lib!(krb5_principal),
+                        princ_ptr,
+                        ((lib!(KADM5_PRINCIPAL) | lib!(KADM5_KVNO) | lib!(KADM5_KEY_DATA)) as u32)
+                            .into(),
+                    ).into()
+                };
+                kadm5_ret_t_escape_hatch(&self.context, code)?;
+
+                let raw_keys = if princ_ent.key_data.is_null() {
+                    &[][..]
+                } else {
+                    unsafe {
+                        std::slice::from_raw_parts(
+                            princ_ent.key_data,
+                            princ_ent.n_key_data as usize,
+                        )
+                    }
+                };
+                let keys = raw_keys
+                    .iter()
+                    .filter(|raw| kvno.map_or(true, |kvno| raw.key_data_kvno as u32 == kvno))
+                    .map(|raw| {
+                        let salted = raw.key_data_ver > 1;
+                        Key {
+                            kvno: raw.key_data_kvno as u32,
+                            keysalt: KeySalt {
+                                enctype: (raw.key_data_type[0] as i32).into(),
+                                salttype: if salted {
+                                    (raw.key_data_type[1] as i32).into()
+                                } else {
+                                    Default::default()
+                                },
+                            },
+                            contents: Zeroizing::new(bytes_from_raw(
+                                raw.key_data_contents[0] as *const c_void,
+                                raw.key_data_length[0] as usize,
+                            )),
+                            salt: if salted {
+                                bytes_from_raw(
+                                    raw.key_data_contents[1] as *const c_void,
+                                    raw.key_data_length[1] as usize,
+                                )
+                            } else {
+                                Vec::new()
+                            },
+                        }
+                    })
+                    .collect();
+
+                unsafe {
+                    cont.kadm5_free_principal_ent(self.server_handle, princ_ptr);
+                }
+                Ok(keys)
+            }
+        )
+    }
+
+    fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+        let princ = parse_name(&self.context, name)?;
+
+        let code = library_match!(
+            &self.context.library;
+            mit_client, mit_server => |cont, lib| {
+                // The raw keys borrow the contents of `keys`, which outlive the call
+                let mut raw_keys: Vec<lib!(kadm5_key_data)> = keys
+                    .iter()
+                    .map(|key| {
+                        let mut raw: lib!(kadm5_key_data) = Default::default();
+                        raw.kvno = key.kvno as lib!(krb5_kvno);
+                        raw.key.enctype = Into::<i32>::into(key.keysalt.enctype) as _;
+                        raw.key.length = key.contents.len() as _;
+                        raw.key.contents = key.contents.as_ptr().cast_mut();
+                        raw.salt.type_ = Into::<i32>::into(key.keysalt.salttype) as _;
+                        raw.salt.data.length = key.salt.len() as _;
+                        raw.salt.data.data = key.salt.as_ptr().cast_mut().cast();
+                        raw
+                    })
+                    .collect();
+
+                unsafe {
+                    cont.kadm5_setkey_principal_4(
+                        self.server_handle,
+                        princ.raw as lib!(krb5_principal),
+                        keepold.into(),
+                        raw_keys.as_mut_ptr(),
+                        raw_keys.len() as i32,
+                    ).into()
+                }
+            },
+            heimdal_client, heimdal_server => |cont, lib| {
+                let newest = keys.iter().map(|key| key.kvno).max().unwrap_or_default();
+                let keys: Vec<&Key> = keys.iter().filter(|key| key.kvno == newest).collect();
+
+                let mut ks_tuple: Vec<lib!(krb5_key_salt_tuple)> =
+                    keys.iter().map(|key| key.keysalt.into()).collect();
+                let mut keyblocks: Vec<lib!(krb5_keyblock)> = keys
+                    .iter()
+                    .map(|key| {
+                        let mut raw: lib!(krb5_keyblock) = Default::default();
+                        raw.keytype = Into::<i32>::into(key.keysalt.enctype) as _;
+                        raw.keyvalue.length = key.contents.len() as _;
+                        raw.keyvalue.data = key.contents.as_ptr().cast_mut().cast();
+                        raw
+                    })
+                    .collect();
+
+                unsafe {
+                    cont.kadm5_setkey_principal_3(
+                        self.server_handle,
+                        princ.raw as lib!(krb5_principal),
+                        keepold.into(),
+                        ks_tuple.len() as i32,
+                        ks_tuple.as_mut_ptr(),
+                        keyblocks.as_mut_ptr(),
+                        keyblocks.len() as i32,
+                    ).into()
+                }
+            }
+        );
+        kadm5_ret_t_escape_hatch(&self.context, code)?;
+        Ok(())
+    }
+
     #[cfg(any(mit_client, mit_server))]
     fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
         library_match!(
@@ -858,7 +1405,7 @@ impl KAdminImpl for KAdmin {
                         mask,
                     ).into()
                 };
-                kadm5_ret_t_escape_hatch(&self.context, code)?;
+                kadm5_ret_t_escape_hatch(&self.context, code).map_err(Error::for_policy)?;
                 Ok(())
             }
         )
@@ -1006,13 +1553,14 @@ impl KAdminImpl for KAdmin {
         result
     }
 
-    fn get_privileges(&self) -> Result<i64> {
-        library_match!(&self.context.library; |cont, _lib| {
+    fn get_privileges(&self) -> Result<Privileges> {
+        let privs = library_match!(&self.context.library; |cont, _lib| {
             let mut privs = 0;
             let code = unsafe { cont.kadm5_get_privs(self.server_handle, &mut privs).into() };
             kadm5_ret_t_escape_hatch(&self.context, code)?;
-            Ok(privs.into())
-        })
+            privs.into()
+        });
+        Ok(Privileges::from_raw(self.context.library.variant(), privs))
     }
 }
 
@@ -1040,6 +1588,8 @@ pub struct KAdminBuilder {
     db_args: Option<DbArgs>,
     /// kadm5 API version
     api_version: KAdminApiVersion,
+    /// Privileges checked after initialization
+    required_privileges: Option<Privileges>,
 }
 
 impl KAdminBuilder {
@@ -1053,6 +1603,7 @@ impl KAdminBuilder {
             params: None,
             db_args: None,
             api_version: Default::default(),
+            required_privileges: None,
         }
     }
 
@@ -1086,6 +1637,24 @@ impl KAdminBuilder {
         self
     }
 
+    /// Check that the [`KAdmin`] instance has the `required` privileges once initialized, and
+    /// fail with [`Error::InsufficientPrivileges`] otherwise
+    pub fn require_privileges(mut self, required: Privileges) -> Self {
+        self.required_privileges = Some(required);
+        self
+    }
+
+    /// Check the required privileges, if any, on a freshly initialized [`KAdmin`] instance
+    ///
+    /// Must be called after [`KADMIN_INIT_LOCK`] is released, as dropping `kadmin` when the check
+    /// fails acquires it again.
+    fn check_privileges(kadmin: KAdmin, required: Option<Privileges>) -> Result<KAdmin> {
+        if let Some(required) = required {
+            kadmin.require_privileges(required)?;
+        }
+        Ok(kadmin)
+    }
+
     /// Construct a [`KAdmin`] object that isn't initialized yet from the builder inputs
     fn get_kadmin(self) -> Result<(KAdmin, Params, DbArgs, u32, CString, u32)> {
         if self.library.is_some() && self.context.is_some() {
@@ -1142,6 +1711,7 @@ impl KAdminBuilder {
     /// Construct a [`KAdmin`] object from this builder using a client name (usually a principal
     /// name) and a password
     pub fn with_password(self, client_name: &str, password: &str) -> Result<KAdmin> {
+        let required_privileges = self.required_privileges;
         let _guard = KADMIN_INIT_LOCK.lock().map_err(|_| Error::LockError)?;
 
         let (mut kadmin, params, db_args, api_version, service_name, struct_version) =
@@ -1185,7 +1755,7 @@ impl KAdminBuilder {
 
         kadm5_ret_t_escape_hatch(&kadmin.context, code)?;
 
-        Ok(kadmin)
+        Self::check_privileges(kadmin, required_privileges)
     }
 
     /// Construct a [`KAdmin`] object from this builder using an optional client name (usually a
@@ -1195,6 +1765,7 @@ impl KAdminBuilder {
     ///
     /// If no keytab is provided, the default keytab will be used
     pub fn with_keytab(self, client_name: Option<&str>, keytab: Option<&str>) -> Result<KAdmin> {
+        let required_privileges = self.required_privileges;
         let _guard = KADMIN_INIT_LOCK.lock().map_err(|_| Error::LockError)?;
 
         let (mut kadmin, params, db_args, api_version, service_name, struct_version) =
@@ -1274,7 +1845,7 @@ impl KAdminBuilder {
 
         kadm5_ret_t_escape_hatch(&kadmin.context, code)?;
 
-        Ok(kadmin)
+        Self::check_privileges(kadmin, required_privileges)
     }
 
     /// Construct a [`KAdmin`] object from this builder using an optional client name (usually a
@@ -1289,6 +1860,7 @@ impl KAdminBuilder {
         client_name: Option<&str>,
         ccache_name: Option<&str>,
     ) -> Result<KAdmin> {
+        let required_privileges = self.required_privileges;
         let _guard = KADMIN_INIT_LOCK.lock().map_err(|_| Error::LockError)?;
 
         let (mut kadmin, params, db_args, api_version, service_name, struct_version) =
@@ -1379,7 +1951,7 @@ impl KAdminBuilder {
 
         kadm5_ret_t_escape_hatch(&kadmin.context, code)?;
 
-        Ok(kadmin)
+        Self::check_privileges(kadmin, required_privileges)
     }
 
     /// Not implemented
@@ -1397,6 +1969,7 @@ impl KAdminBuilder {
     ///
     /// Only available on server-side libraries.
     pub fn with_local(self) -> Result<KAdmin> {
+        let required_privileges = self.required_privileges;
         let _guard = KADMIN_INIT_LOCK.lock().map_err(|_| Error::LockError)?;
 
         let (mut kadmin, params, db_args, api_version, service_name, struct_version) =
@@ -1451,6 +2024,6 @@ impl KAdminBuilder {
 
         kadm5_ret_t_escape_hatch(&kadmin.context, code)?;
 
-        Ok(kadmin)
+        Self::check_privileges(kadmin, required_privileges)
     }
 }
@@ -7,6 +7,7 @@ use std::{
 
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
+use zeroize::Zeroizing;
 
 use crate::{
     context::Context,
@@ -19,6 +20,7 @@ use crate::{
 #[allow(clippy::exhaustive_enums)]
 #[repr(transparent)]
 #[cfg_attr(feature = "python", pyclass(from_py_object))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct EncryptionType(i32);
 
 impl From<EncryptionType> for i32 {
@@ -104,6 +106,7 @@ impl EncryptionType {
 #[allow(clippy::exhaustive_enums)]
 #[repr(transparent)]
 #[cfg_attr(feature = "python", pyclass(from_py_object))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct SaltType(i32);
 
 impl From<SaltType> for i32 {
@@ -195,6 +198,7 @@ impl SaltType {
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[allow(clippy::exhaustive_structs)]
 #[cfg_attr(feature = "python", pyclass(from_py_object, get_all, set_all))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeySalt {
     /// Encryption type
     pub enctype: EncryptionType,
@@ -222,6 +226,35 @@ impl KeySalt {
     }
 }
 
+/// Kerberos key of a principal, in clear text
+///
+/// Returned by [`KAdminImpl::principal_get_keys`][`crate::KAdminImpl::principal_get_keys`] and
+/// accepted by [`KAdminImpl::principal_set_keys`][`crate::KAdminImpl::principal_set_keys`]. The
+/// key contents are zeroed on drop and left out of the `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Key {
+    /// Key version number
+    pub kvno: u32,
+    /// Encryption and salt types of the key
+    pub keysalt: KeySalt,
+    /// Key contents
+    pub contents: Zeroizing<Vec<u8>>,
+    /// Salt contents. Empty for salt types that derive the salt from the principal name
+    pub salt: Vec<u8>,
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key")
+            .field("kvno", &self.kvno)
+            .field("keysalt", &self.keysalt)
+            .field("contents", &"<redacted>")
+            .field("salt", &self.salt)
+            .finish()
+    }
+}
+
 #[cfg(mit_client)]
 impl From<KeySalt> for sys::mit_client::krb5_key_salt_tuple {
     fn from(ks: KeySalt) -> Self {
@@ -266,6 +299,7 @@ impl From<KeySalt> for sys::heimdal_server::krb5_key_salt_tuple {
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[allow(clippy::exhaustive_structs)]
 #[cfg_attr(feature = "python", pyclass(from_py_object, get_all, set_all))]
+#[cfg_attr(feature = "replay", derive(serde::Deserialize))]
 pub struct KeySalts {
     /// Keysalt list
     pub keysalts: HashSet<KeySalt>,
@@ -296,6 +330,23 @@ impl KeySalts {
     }
 }
 
+#[cfg(feature = "replay")]
+// Sorted so that equal lists always serialize the same way
+impl serde::Serialize for KeySalts {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut keysalts: Vec<&KeySalt> = self.keysalts.iter().collect();
+        keysalts.sort_by_key(|ks| (ks.enctype.0, ks.salttype.0));
+        let mut state = serializer.serialize_struct("KeySalts", 1)?;
+        state.serialize_field("keysalts", &keysalts)?;
+        state.end()
+    }
+}
+
 #[cfg(mit_client)]
 impl From<&KeySalts> for Vec<sys::mit_client::krb5_key_salt_tuple> {
     fn from(kss: &KeySalts) -> Self {
@@ -0,0 +1,808 @@
+//! Composable [`Layer`]s adding behaviour around any [`KAdminImpl`]
+//!
+//! A [`Middleware`] sees every operation done through the [`KAdminImpl`] it wraps, and decides how
+//! to run it. Any [`Middleware`] that is [`Clone`] is also a [`Layer`], and layers can be stacked
+//! with a [`LayerBuilder`]. [`Hooks`] are a simpler way to only observe operations.
+//!
+//! Ready-made layers are provided for [audit events][`AuditLayer`], [retries][`RetryLayer`] and
+//! [metrics][`MetricsLayer`]. With the `tracing` feature, `TracingLayer` opens a `tracing` span
+//! for every operation.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant, layer::{AuditLayer, LayerBuilder, MetricsLayer, RetryLayer}};
+//! # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+//! #     .with_ccache(None, None)
+//! #     .unwrap();
+//! let metrics = MetricsLayer::new();
+//! let kadmin = LayerBuilder::new()
+//!     .layer(AuditLayer::new(|event| println!("{event:?}")))
+//!     .layer(metrics.clone())
+//!     .layer(RetryLayer::new(3).backoff(Duration::from_millis(200)))
+//!     .build(kadmin);
+//! kadmin.delete_principal("alice").unwrap();
+//! println!("{:?}", metrics.snapshot().unwrap());
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::{Error, Result},
+    kadmin::KAdminImpl,
+    keysalt::Key,
+    principal::{Principal, PrincipalBuilder, PrincipalModifier},
+    privileges::Privileges,
+    sys::KAdm5Variant,
+};
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::{
+    keysalt::KeySalts,
+    policy::{Policy, PolicyBuilder, PolicyModifier, PolicyUsage},
+};
+
+/// Operation done through a [`KAdminImpl`], as seen by a [`Middleware`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Operation<'a> {
+    /// Name of the [`KAdminImpl`] method, for instance `add_principal`
+    pub name: &'static str,
+    /// Name of the principal or policy operated on, if any
+    pub target: Option<&'a str>,
+    /// Whether the operation changes the realm
+    pub is_write: bool,
+}
+
+impl<'a> Operation<'a> {
+    fn read(name: &'static str, target: Option<&'a str>) -> Self {
+        Self {
+            name,
+            target,
+            is_write: false,
+        }
+    }
+
+    fn write(name: &'static str, target: Option<&'a str>) -> Self {
+        Self {
+            name,
+            target,
+            is_write: true,
+        }
+    }
+}
+
+/// Behaviour wrapped around every operation of a [`KAdminImpl`]
+pub trait Middleware {
+    /// Handle `operation`. `next` runs it on the wrapped implementation, and may be called any
+    /// number of times
+    fn call<T>(&self, operation: &Operation<'_>, next: &mut dyn FnMut() -> Result<T>) -> Result<T>;
+}
+
+/// Wrap a [`KAdminImpl`] into another one
+pub trait Layer<K> {
+    /// Resulting [`KAdminImpl`]
+    type KAdmin: KAdminImpl;
+
+    /// Wrap `inner`
+    fn layer(&self, inner: K) -> Self::KAdmin;
+}
+
+impl<K: KAdminImpl, M: Middleware + Clone> Layer<K> for M {
+    type KAdmin = Layered<K, M>;
+
+    fn layer(&self, inner: K) -> Self::KAdmin {
+        Layered::new(inner, self.clone())
+    }
+}
+
+/// [`Layer`] that doesn't wrap anything
+#[derive(Clone, Copy, Debug, Default)]
+#[allow(clippy::exhaustive_structs)]
+pub struct Identity;
+
+impl<K: KAdminImpl> Layer<K> for Identity {
+    type KAdmin = K;
+
+    fn layer(&self, inner: K) -> Self::KAdmin {
+        inner
+    }
+}
+
+/// Two [`Layer`]s, `inner` being applied first
+#[derive(Clone, Debug)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<K, Inner: Layer<K>, Outer: Layer<Inner::KAdmin>> Layer<K> for Stack<Inner, Outer> {
+    type KAdmin = Outer::KAdmin;
+
+    fn layer(&self, inner: K) -> Self::KAdmin {
+        self.outer.layer(self.inner.layer(inner))
+    }
+}
+
+/// Stack [`Layer`]s
+///
+/// The first layer added is the outermost one: it sees operations first, and their results last.
+#[derive(Clone, Debug)]
+pub struct LayerBuilder<L> {
+    layer: L,
+}
+
+impl LayerBuilder<Identity> {
+    /// Construct a new [`LayerBuilder`] without any layer
+    pub fn new() -> Self {
+        Self { layer: Identity }
+    }
+}
+
+impl Default for LayerBuilder<Identity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> LayerBuilder<L> {
+    /// Add a layer, inside of the ones already added
+    pub fn layer<T>(self, layer: T) -> LayerBuilder<Stack<T, L>> {
+        LayerBuilder {
+            layer: Stack {
+                inner: layer,
+                outer: self.layer,
+            },
+        }
+    }
+
+    /// Wrap `kadmin` with the added layers
+    pub fn build<K>(&self, kadmin: K) -> L::KAdmin
+    where L: Layer<K> {
+        self.layer.layer(kadmin)
+    }
+}
+
+/// [`KAdminImpl`] wrapped with a [`Middleware`]
+#[derive(Clone, Debug)]
+pub struct Layered<K, M> {
+    inner: K,
+    middleware: M,
+}
+
+impl<K: KAdminImpl, M: Middleware> Layered<K, M> {
+    /// Wrap `inner` with `middleware`
+    pub fn new(inner: K, middleware: M) -> Self {
+        Self { inner, middleware }
+    }
+
+    /// Wrapped implementation
+    pub fn inner(&self) -> &K {
+        &self.inner
+    }
+
+    /// Middleware
+    pub fn middleware(&self) -> &M {
+        &self.middleware
+    }
+
+    /// Consume the [`Layered`] and return the wrapped implementation
+    pub fn into_inner(self) -> K {
+        self.inner
+    }
+
+    fn call<T>(&self, operation: Operation<'_>, mut next: impl FnMut() -> Result<T>) -> Result<T> {
+        self.middleware.call(&operation, &mut next)
+    }
+}
+
+impl<K: KAdminImpl, M: Middleware> KAdminImpl for Layered<K, M> {
+    fn variant(&self) -> KAdm5Variant {
+        self.inner.variant()
+    }
+
+    fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
+        self.call(
+            Operation::write("add_principal", Some(&builder.name)),
+            || self.inner.add_principal(builder),
+        )
+    }
+
+    fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
+        self.call(
+            Operation::write("modify_principal", Some(&modifier.name)),
+            || self.inner.modify_principal(modifier),
+        )
+    }
+
+    fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
+        self.call(Operation::write("rename_principal", Some(old_name)), || {
+            self.inner.rename_principal(old_name, new_name)
+        })
+    }
+
+    fn delete_principal(&self, name: &str) -> Result<()> {
+        self.call(Operation::write("delete_principal", Some(name)), || {
+            self.inner.delete_principal(name)
+        })
+    }
+
+    fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
+        self.call(Operation::read("get_principal", Some(name)), || {
+            self.inner.get_principal(name)
+        })
+    }
+
+    fn principal_exists(&self, name: &str) -> Result<bool> {
+        self.call(Operation::read("principal_exists", Some(name)), || {
+            self.inner.principal_exists(name)
+        })
+    }
+
+    fn principal_change_password(
+        &self,
+        name: &str,
+        password: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        self.call(
+            Operation::write("principal_change_password", Some(name)),
+            || {
+                self.inner.principal_change_password(
+                    name,
+                    password,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    keepold,
+                    #[cfg(any(mit_client, mit_server, heimdal_server))]
+                    keysalts,
+                )
+            },
+        )
+    }
+
+    fn principal_randkey(
+        &self,
+        name: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        self.call(Operation::write("principal_randkey", Some(name)), || {
+            self.inner.principal_randkey(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            )
+        })
+    }
+
+    fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+        self.call(Operation::read("principal_get_keys", Some(name)), || {
+            self.inner.principal_get_keys(name, kvno)
+        })
+    }
+
+    fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+        self.call(Operation::write("principal_set_keys", Some(name)), || {
+            self.inner.principal_set_keys(name, keepold, keys)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
+        self.call(Operation::read("principal_get_strings", Some(name)), || {
+            self.inner.principal_get_strings(name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_set_string(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        self.call(Operation::write("principal_set_string", Some(name)), || {
+            self.inner.principal_set_string(name, key, value)
+        })
+    }
+
+    fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
+        self.call(Operation::read("list_principals", None), || {
+            self.inner.list_principals(query)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
+        self.call(Operation::write("add_policy", Some(&builder.name)), || {
+            self.inner.add_policy(builder)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
+        self.call(
+            Operation::write("modify_policy", Some(&modifier.name)),
+            || self.inner.modify_policy(modifier),
+        )
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delete_policy(&self, name: &str) -> Result<()> {
+        self.call(Operation::write("delete_policy", Some(name)), || {
+            self.inner.delete_policy(name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn rename_policy(&self, old_name: &str, new_name: &str) -> Result<()> {
+        self.call(Operation::write("rename_policy", Some(old_name)), || {
+            self.inner.rename_policy(old_name, new_name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+        self.call(Operation::read("get_policy", Some(name)), || {
+            self.inner.get_policy(name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn policy_exists(&self, name: &str) -> Result<bool> {
+        self.call(Operation::read("policy_exists", Some(name)), || {
+            self.inner.policy_exists(name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
+        self.call(Operation::read("list_policies", None), || {
+            self.inner.list_policies(query)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn policy_usage(&self) -> Result<PolicyUsage> {
+        self.call(Operation::read("policy_usage", None), || {
+            self.inner.policy_usage()
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn principals_using_policy(&self, name: &str) -> Result<Vec<String>> {
+        self.call(
+            Operation::read("principals_using_policy", Some(name)),
+            || self.inner.principals_using_policy(name),
+        )
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delete_policy_if_unused(&self, name: &str) -> Result<()> {
+        self.call(
+            Operation::write("delete_policy_if_unused", Some(name)),
+            || self.inner.delete_policy_if_unused(name),
+        )
+    }
+
+    fn get_privileges(&self) -> Result<Privileges> {
+        self.call(Operation::read("get_privileges", None), || {
+            self.inner.get_privileges()
+        })
+    }
+
+    fn require_privileges(&self, required: Privileges) -> Result<()> {
+        self.call(Operation::read("require_privileges", None), || {
+            self.inner.require_privileges(required)
+        })
+    }
+}
+
+/// Callbacks run before and after every operation
+///
+/// Use them with a [`HooksLayer`]
+pub trait Hooks {
+    /// Called before `operation` runs
+    fn before(&self, _operation: &Operation<'_>) {}
+
+    /// Called after `operation` ran, with whether it succeeded
+    fn after(&self, _operation: &Operation<'_>, _result: std::result::Result<(), &Error>) {}
+}
+
+/// [`Middleware`] running [`Hooks`]
+#[derive(Debug)]
+pub struct HooksLayer<H> {
+    hooks: Arc<H>,
+}
+
+impl<H> HooksLayer<H> {
+    /// Construct a new [`HooksLayer`] running `hooks`
+    pub fn new(hooks: H) -> Self {
+        Self {
+            hooks: Arc::new(hooks),
+        }
+    }
+}
+
+impl<H> Clone for HooksLayer<H> {
+    fn clone(&self) -> Self {
+        Self {
+            hooks: Arc::clone(&self.hooks),
+        }
+    }
+}
+
+impl<H: Hooks> Middleware for HooksLayer<H> {
+    fn call<T>(&self, operation: &Operation<'_>, next: &mut dyn FnMut() -> Result<T>) -> Result<T> {
+        self.hooks.before(operation);
+        let result = next();
+        self.hooks.after(operation, result.as_ref().map(|_| ()));
+        result
+    }
+}
+
+/// Event emitted by an [`AuditLayer`]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct AuditEvent {
+    /// Name of the [`KAdminImpl`] method
+    pub operation: &'static str,
+    /// Name of the principal or policy operated on, if any
+    pub target: Option<String>,
+    /// When the operation started
+    pub timestamp: DateTime<Utc>,
+    /// How long the operation took
+    pub duration: Duration,
+    /// Error message if the operation failed
+    pub error: Option<String>,
+}
+
+/// [`Middleware`] emitting an [`AuditEvent`] for every operation changing the realm
+#[derive(Clone)]
+pub struct AuditLayer {
+    sink: Arc<dyn Fn(&AuditEvent) + Send + Sync>,
+    reads: bool,
+}
+
+impl AuditLayer {
+    /// Construct a new [`AuditLayer`] sending events to `sink`
+    pub fn new<F: Fn(&AuditEvent) + Send + Sync + 'static>(sink: F) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            reads: false,
+        }
+    }
+
+    /// Also emit events for operations that don't change the realm. Defaults to `false`
+    pub fn with_reads(mut self, reads: bool) -> Self {
+        self.reads = reads;
+        self
+    }
+}
+
+impl fmt::Debug for AuditLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLayer")
+            .field("reads", &self.reads)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Middleware for AuditLayer {
+    fn call<T>(&self, operation: &Operation<'_>, next: &mut dyn FnMut() -> Result<T>) -> Result<T> {
+        if !operation.is_write && !self.reads {
+            return next();
+        }
+        let timestamp = Utc::now();
+        let start = Instant::now();
+        let result = next();
+        (self.sink)(&AuditEvent {
+            operation: operation.name,
+            target: operation.target.map(String::from),
+            timestamp,
+            duration: start.elapsed(),
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+        result
+    }
+}
+
+/// [`Middleware`] retrying operations that failed with a [transient][`Error::is_transient`] error
+///
+/// Operations changing the realm are not retried by default, as they may have been applied even
+/// though an error was returned.
+#[derive(Clone)]
+pub struct RetryLayer {
+    max_attempts: u32,
+    backoff: Duration,
+    retry_writes: bool,
+    retry_if: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+}
+
+impl RetryLayer {
+    /// Construct a new [`RetryLayer`] trying operations at most `max_attempts` times
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff: Duration::from_millis(100),
+            retry_writes: false,
+            retry_if: Arc::new(Error::is_transient),
+        }
+    }
+
+    /// Set how long to wait before the first retry. The delay doubles after each attempt.
+    /// Defaults to 100 milliseconds
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Also retry operations changing the realm. Defaults to `false`
+    pub fn retry_writes(mut self, retry_writes: bool) -> Self {
+        self.retry_writes = retry_writes;
+        self
+    }
+
+    /// Set which errors are retried. Defaults to [`Error::is_transient`]
+    pub fn retry_if<F: Fn(&Error) -> bool + Send + Sync + 'static>(mut self, retry_if: F) -> Self {
+        self.retry_if = Arc::new(retry_if);
+        self
+    }
+}
+
+impl fmt::Debug for RetryLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryLayer")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("retry_writes", &self.retry_writes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Middleware for RetryLayer {
+    fn call<T>(&self, operation: &Operation<'_>, next: &mut dyn FnMut() -> Result<T>) -> Result<T> {
+        let retryable = !operation.is_write || self.retry_writes;
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match next() {
+                Err(err) if retryable && attempt < self.max_attempts && (self.retry_if)(&err) => {
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Metrics of an operation, as collected by a [`MetricsLayer`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OperationMetrics {
+    /// Number of calls
+    pub calls: u64,
+    /// Number of calls that failed
+    pub errors: u64,
+    /// Total time spent in calls
+    pub total_duration: Duration,
+    /// Longest call
+    pub max_duration: Duration,
+}
+
+/// [`Middleware`] collecting [`OperationMetrics`] for every operation
+///
+/// Clones share the same metrics, so a clone can be kept to read them while the layer is in use.
+#[derive(Clone, Debug, Default)]
+pub struct MetricsLayer {
+    metrics: Arc<Mutex<HashMap<&'static str, OperationMetrics>>>,
+}
+
+impl MetricsLayer {
+    /// Construct a new [`MetricsLayer`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Metrics collected so far, by operation name
+    pub fn snapshot(&self) -> Result<HashMap<&'static str, OperationMetrics>> {
+        Ok(self.metrics.lock().map_err(|_| Error::LockError)?.clone())
+    }
+
+    /// Reset the collected metrics
+    pub fn reset(&self) -> Result<()> {
+        self.metrics.lock().map_err(|_| Error::LockError)?.clear();
+        Ok(())
+    }
+}
+
+impl Middleware for MetricsLayer {
+    fn call<T>(&self, operation: &Operation<'_>, next: &mut dyn FnMut() -> Result<T>) -> Result<T> {
+        let start = Instant::now();
+        let result = next();
+        let duration = start.elapsed();
+        if let Ok(mut metrics) = self.metrics.lock() {
+            let metrics = metrics.entry(operation.name).or_default();
+            metrics.calls += 1;
+            metrics.errors += u64::from(result.is_err());
+            metrics.total_duration += duration;
+            metrics.max_duration = metrics.max_duration.max(duration);
+        }
+        result
+    }
+}
+
+#[cfg(feature = "tracing")]
+/// [`Layer`] opening a [`tracing`] span for every operation, and emitting metrics about it
+///
+/// Spans are named `kadmin` and have the `variant`, `operation` and `target` fields, plus an
+/// `outcome` field set to `ok` or `error` once the operation completed. Failed operations also get
+/// an `error.kind` field with their [`KAdminErrorKind`][`crate::error::KAdminErrorKind`], if any.
+/// With [`crate::sync::KAdmin`], the executor records in the `queue.wait` field the time, in
+/// seconds, the operation spent waiting to be handled. Passwords and keys are never recorded.
+///
+/// Metrics are emitted as events following the `tracing-opentelemetry` naming conventions:
+/// `histogram.kadmin.operation.duration`, in seconds, and
+/// `monotonic_counter.kadmin.operation.errors`.
+#[derive(Clone, Copy, Debug, Default)]
+#[allow(clippy::exhaustive_structs)]
+pub struct TracingLayer;
+
+#[cfg(feature = "tracing")]
+impl<K: KAdminImpl> Layer<K> for TracingLayer {
+    type KAdmin = Layered<K, TracingMiddleware>;
+
+    fn layer(&self, inner: K) -> Self::KAdmin {
+        let variant = inner.variant();
+        Layered::new(inner, TracingMiddleware { variant })
+    }
+}
+
+#[cfg(feature = "tracing")]
+/// [`Middleware`] of a [`TracingLayer`]
+#[derive(Clone, Copy, Debug)]
+pub struct TracingMiddleware {
+    variant: KAdm5Variant,
+}
+
+#[cfg(feature = "tracing")]
+impl Middleware for TracingMiddleware {
+    fn call<T>(&self, operation: &Operation<'_>, next: &mut dyn FnMut() -> Result<T>) -> Result<T> {
+        let span = tracing::info_span!(
+            "kadmin",
+            variant = ?self.variant,
+            operation = operation.name,
+            target = operation.target,
+            outcome = tracing::field::Empty,
+            error.kind = tracing::field::Empty,
+            queue.wait = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+
+        let start = Instant::now();
+        let result = next();
+        let duration = start.elapsed().as_secs_f64();
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        span.record("outcome", outcome);
+        tracing::info!(
+            histogram.kadmin.operation.duration = duration,
+            variant = ?self.variant,
+            operation = operation.name,
+            outcome,
+        );
+        if let Err(err) = &result {
+            if let Some(kind) = err.kind() {
+                span.record("error.kind", tracing::field::debug(kind));
+            }
+            tracing::info!(
+                monotonic_counter.kadmin.operation.errors = 1_u64,
+                variant = ?self.variant,
+                operation = operation.name,
+            );
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn transient_error() -> Error {
+        Error::KAdmin {
+            code: 0,
+            kind: crate::error::KAdminErrorKind::RpcError,
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn dyn_compatible() {
+        fn assert_dyn(_: Option<&dyn KAdminImpl>) {}
+        assert_dyn(None);
+    }
+
+    #[test]
+    fn retry() {
+        let layer = RetryLayer::new(3).backoff(Duration::ZERO);
+        let attempts = Cell::new(0);
+        let result: Result<()> = layer.call(&Operation::read("get_principal", None), &mut || {
+            attempts.set(attempts.get() + 1);
+            Err(transient_error())
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 3);
+
+        attempts.set(0);
+        let result: Result<()> =
+            layer.call(&Operation::write("delete_principal", None), &mut || {
+                attempts.set(attempts.get() + 1);
+                Err(transient_error())
+            });
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[test]
+    fn metrics() -> Result<()> {
+        let layer = MetricsLayer::new();
+        let operation = Operation::read("get_principal", Some("alice"));
+        layer.call(&operation, &mut || Ok(()))?;
+        assert!(
+            layer
+                .call(&operation, &mut || Err::<(), _>(transient_error()))
+                .is_err()
+        );
+        let metrics = layer.snapshot()?["get_principal"];
+        assert_eq!(metrics.calls, 2);
+        assert_eq!(metrics.errors, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn audit() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let layer = AuditLayer::new(move |event| sink.lock().unwrap().push(event.clone()));
+        layer
+            .call(
+                &Operation::read("get_principal", Some("alice")),
+                &mut || Ok(()),
+            )
+            .unwrap();
+        layer
+            .call(
+                &Operation::write("delete_principal", Some("alice")),
+                &mut || Ok(()),
+            )
+            .unwrap();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].operation, "delete_principal");
+        assert_eq!(events[0].target.as_deref(), Some("alice"));
+    }
+
+    #[cfg(all(feature = "tracing", mit_server))]
+    #[test]
+    fn tracing() {
+        let layer = TracingMiddleware {
+            variant: KAdm5Variant::MitServer,
+        };
+        let operation = Operation::write("delete_principal", Some("alice"));
+        assert!(layer.call(&operation, &mut || Ok(())).is_ok());
+        assert!(
+            layer
+                .call(&operation, &mut || Err::<(), _>(transient_error()))
+                .unwrap_err()
+                .is_transient()
+        );
+    }
+}
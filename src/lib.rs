@@ -12,6 +12,18 @@
 //! - `heimdal_client`
 //! - `heimdal_server`
 //!
+//! The optional `reconcile` feature enables the `reconcile` module, which plans and applies
+//! changes to bring a realm to a declarative desired state. The optional `replay` feature enables
+//! the `replay` module, which records kadm5 sessions and replays them without any Kerberos library.
+//! The optional `tracing` feature enables `layer::TracingLayer`, which opens a span and emits
+//! metrics for every operation.
+//! The optional `migrate` feature enables the `migrate` module and the `kadmin-migrate` binary,
+//! which copy principals, keys and policies from one realm to another.
+//! The optional `import` feature enables the `import` module and the `kadmin-import` binary, which
+//! create or update principals from a CSV file.
+//! The optional `cli` feature enables the `kadmin-rs` binary, a kadmin-compatible command-line
+//! interface that behaves the same against MIT and Heimdal realms.
+//!
 //! For remote operations:
 //!
 //! ```no_run
@@ -77,22 +89,28 @@
 mod conv;
 
 pub mod error;
-pub use error::Error;
+pub use error::{Error, KAdminErrorKind, PasswordQualityError, PasswordQualityRule};
 
 pub mod context;
 pub use context::Context;
 
+pub mod credentials;
+pub use credentials::Credentials;
+
 pub mod params;
 pub use params::Params;
 
 pub mod db_args;
 pub use db_args::DbArgs;
 
+pub mod profile;
+pub use profile::Profile;
+
 pub mod tl_data;
 pub use tl_data::{TlData, TlDataEntry};
 
 pub mod keysalt;
-pub use keysalt::{EncryptionType, KeySalt, KeySalts, SaltType};
+pub use keysalt::{EncryptionType, Key, KeySalt, KeySalts, SaltType};
 
 pub mod kadmin;
 pub use kadmin::{KAdmin, KAdminApiVersion, KAdminImpl};
@@ -102,11 +120,43 @@ pub mod sync;
 #[cfg(any(mit_client, mit_server, heimdal_server))]
 pub mod policy;
 #[cfg(any(mit_client, mit_server, heimdal_server))]
-pub use policy::Policy;
+pub use policy::{Policy, PolicyUsage};
 
 pub mod principal;
 pub use principal::Principal;
 
+pub mod privileges;
+pub use privileges::Privileges;
+
+pub mod password;
+pub use password::PasswordGenerator;
+
+pub mod batch;
+pub use batch::Batch;
+
+pub mod dry_run;
+pub use dry_run::DryRun;
+
+pub mod dump;
+
+pub mod layer;
+pub use layer::{Layer, LayerBuilder};
+
+pub mod memory;
+pub use memory::MemoryKAdmin;
+
+#[cfg(feature = "reconcile")]
+pub mod reconcile;
+
+#[cfg(feature = "replay")]
+pub mod replay;
+
+#[cfg(feature = "migrate")]
+pub mod migrate;
+
+#[cfg(feature = "import")]
+pub mod import;
+
 pub mod sys;
 pub use sys::KAdm5Variant;
 
@@ -0,0 +1,580 @@
+//! In-memory [`MemoryKAdmin`] implementation, for tests
+
+#[cfg(any(mit_client, mit_server))]
+use std::collections::HashMap;
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    conv::query_matches,
+    error::{Error, KAdminErrorKind, Result, kadm5_error},
+    kadmin::KAdminImpl,
+    keysalt::Key,
+    principal::{Principal, PrincipalBuilder, PrincipalModifier},
+    privileges::Privileges,
+    sys::KAdm5Variant,
+};
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::{
+    error::{PasswordQualityError, PasswordQualityRule},
+    keysalt::KeySalts,
+    policy::{Policy, PolicyBuilder, PolicyModifier},
+    principal::PrincipalBuilderKey,
+};
+
+/// Contents of the simulated realm
+#[derive(Debug, Default)]
+struct Database {
+    principals: BTreeMap<String, Principal>,
+    /// Keys set on each principal with [`KAdminImpl::principal_set_keys`]
+    keys: BTreeMap<String, Vec<Key>>,
+    #[cfg(any(mit_client, mit_server))]
+    strings: HashMap<String, HashMap<String, String>>,
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    policies: BTreeMap<String, Policy>,
+    /// Passwords set on each principal, most recent last
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    passwords: BTreeMap<String, Vec<String>>,
+}
+
+/// [`KAdminImpl`] keeping a simulated realm in memory
+///
+/// It doesn't need any Kerberos library or KDC, and is meant for testing code using a
+/// [`KAdminImpl`]. It mimics kadm5 where it matters to callers:
+///
+/// * principal names without a realm get the realm of the [`MemoryKAdmin`] appended
+/// * errors are [`Error::KAdmin`] with the codes of the chosen [`KAdm5Variant`], for instance for
+///   unknown or duplicate principals and policies
+/// * passwords are checked against the policy of the principal, including its history. Passwords
+///   are kept in memory in clear text to do so
+/// * changing the keys of a principal increments its key version number
+/// * only keys set with [`KAdminImpl::principal_set_keys`] are returned by
+///   [`KAdminImpl::principal_get_keys`], other key changes discard them
+/// * queries of [`KAdminImpl::list_principals`] have the local realm appended if they don't contain
+///   an `@`
+/// * with MIT variants, new principals get the `default` policy if it exists and no policy was set
+///
+/// ```
+/// # use kadmin::{KAdm5Variant, KAdminImpl, MemoryKAdmin, Principal};
+/// # #[cfg(mit_client)]
+/// # {
+/// let kadmin = MemoryKAdmin::new(KAdm5Variant::MitClient, "EXAMPLE.ORG");
+/// Principal::builder("alice").create(&kadmin).unwrap();
+/// assert!(kadmin.principal_exists("alice@EXAMPLE.ORG").unwrap());
+/// assert!(
+///     Principal::builder("alice")
+///         .create(&kadmin)
+///         .unwrap_err()
+///         .is_already_exists()
+/// );
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MemoryKAdmin {
+    variant: KAdm5Variant,
+    realm: String,
+    privileges: Privileges,
+    database: Mutex<Database>,
+}
+
+impl MemoryKAdmin {
+    /// Construct a new empty [`MemoryKAdmin`] behaving like `variant`, for `realm`
+    ///
+    /// All [privileges][`Privileges`] the variant reports are granted
+    pub fn new(variant: KAdm5Variant, realm: &str) -> Self {
+        let privileges = match variant {
+            #[cfg(any(heimdal_client, heimdal_server))]
+            KAdm5Variant::HeimdalClient | KAdm5Variant::HeimdalServer => Privileges::all(),
+            #[allow(unreachable_patterns)]
+            _ => Privileges::GET | Privileges::ADD | Privileges::MODIFY | Privileges::DELETE,
+        };
+        Self {
+            variant,
+            realm: realm.to_owned(),
+            privileges,
+            database: Mutex::default(),
+        }
+    }
+
+    /// Set the privileges returned by [`KAdminImpl::get_privileges`]
+    ///
+    /// Privileges are only reported, operations are not restricted by them
+    pub fn privileges(mut self, privileges: Privileges) -> Self {
+        self.privileges = privileges;
+        self
+    }
+
+    /// Realm of the simulated database
+    pub fn realm(&self) -> &str {
+        &self.realm
+    }
+
+    fn database(&self) -> Result<MutexGuard<'_, Database>> {
+        self.database.lock().map_err(|_| Error::LockError)
+    }
+
+    fn error(&self, kind: KAdminErrorKind) -> Error {
+        kadm5_error(self.variant, kind)
+    }
+
+    /// Append the realm to `name` if it doesn't have one
+    fn canonical(&self, name: &str) -> Result<String> {
+        if name.is_empty() || name.starts_with('@') {
+            return Err(self.error(KAdminErrorKind::InvalidPrincipal));
+        }
+        Ok(if name.contains('@') {
+            name.to_owned()
+        } else {
+            format!("{name}@{}", self.realm)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Check `password` against the policy of `principal`, like kadmind does
+    fn check_password(&self, db: &Database, principal: &Principal, password: &str) -> Result<()> {
+        let Some(policy) = principal.policy().and_then(|name| db.policies.get(name)) else {
+            return Ok(());
+        };
+        policy
+            .validate_password(password, Some(principal.name()), None)
+            .map_err(|err| match err {
+                Error::PasswordQuality(mut details) => {
+                    if let Error::KAdmin { code, .. } = self.error(details.rule.kind()) {
+                        details.code = code;
+                    }
+                    Error::PasswordQuality(details)
+                }
+                err => err,
+            })?;
+
+        let history_num = usize::try_from(policy.password_history_num()).unwrap_or_default();
+        if history_num > 1 {
+            let previous = db
+                .passwords
+                .get(principal.name())
+                .map_or(&[][..], Vec::as_slice);
+            let recent = &previous[previous.len().saturating_sub(history_num)..];
+            if recent.iter().any(|previous| previous == password) {
+                let (code, message) = match self.error(KAdminErrorKind::PasswordReuse) {
+                    Error::KAdmin { code, message, .. } => (code, message),
+                    err => (0, err.to_string()),
+                };
+                return Err(Error::PasswordQuality(Box::new(
+                    PasswordQualityError::new(PasswordQualityRule::History, code, message)
+                        .with_policy(policy),
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl KAdminImpl for MemoryKAdmin {
+    fn variant(&self) -> KAdm5Variant {
+        self.variant
+    }
+
+    fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
+        let name = self.canonical(&builder.name)?;
+        let mut db = self.database()?;
+        if db.principals.contains_key(&name) {
+            return Err(self.error(KAdminErrorKind::DuplicatePrincipal));
+        }
+        #[cfg_attr(not(any(mit_client, mit_server)), allow(unused_mut))]
+        let mut builder = builder.clone().name(&name);
+        #[cfg(any(mit_client, mit_server))]
+        if builder.policy.is_none()
+            && matches!(
+                self.variant,
+                KAdm5Variant::MitClient | KAdm5Variant::MitServer
+            )
+            && db.policies.contains_key("default")
+        {
+            builder = builder.policy(Some("default"));
+        }
+        let principal = Principal::from_builder(&builder);
+
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        {
+            if let Some(policy) = principal.policy() {
+                if !db.policies.contains_key(policy) {
+                    return Err(self.error(KAdminErrorKind::UnknownPolicy));
+                }
+            }
+            if let PrincipalBuilderKey::Password(password) = &builder.key {
+                self.check_password(&db, &principal, password)?;
+                db.passwords.insert(name.clone(), vec![password.clone()]);
+            }
+        }
+        #[cfg(any(mit_client, mit_server))]
+        db.strings.insert(name.clone(), HashMap::new());
+        db.principals.insert(name, principal);
+        Ok(())
+    }
+
+    fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
+        let name = self.canonical(&modifier.name)?;
+        let mut db = self.database()?;
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        if let Some(Some(policy)) = &modifier.policy {
+            if !db.policies.contains_key(policy) {
+                return Err(self.error(KAdminErrorKind::UnknownPolicy));
+            }
+        }
+        let Some(principal) = db.principals.get_mut(&name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        };
+        modifier.apply_to(principal);
+        Ok(())
+    }
+
+    fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let old_name = self.canonical(old_name)?;
+        let new_name = self.canonical(new_name)?;
+        let mut db = self.database()?;
+        if db.principals.contains_key(&new_name) {
+            return Err(self.error(KAdminErrorKind::DuplicatePrincipal));
+        }
+        let Some(principal) = db.principals.remove(&old_name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        };
+        db.principals
+            .insert(new_name.clone(), principal.renamed(&new_name));
+        if let Some(keys) = db.keys.remove(&old_name) {
+            db.keys.insert(new_name.clone(), keys);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(strings) = db.strings.remove(&old_name) {
+            db.strings.insert(new_name.clone(), strings);
+        }
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        if let Some(passwords) = db.passwords.remove(&old_name) {
+            db.passwords.insert(new_name, passwords);
+        }
+        Ok(())
+    }
+
+    fn delete_principal(&self, name: &str) -> Result<()> {
+        let name = self.canonical(name)?;
+        let mut db = self.database()?;
+        if db.principals.remove(&name).is_none() {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        }
+        db.keys.remove(&name);
+        #[cfg(any(mit_client, mit_server))]
+        db.strings.remove(&name);
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        db.passwords.remove(&name);
+        Ok(())
+    }
+
+    fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
+        let name = self.canonical(name)?;
+        Ok(self.database()?.principals.get(&name).cloned())
+    }
+
+    fn principal_change_password(
+        &self,
+        name: &str,
+        password: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        let name = self.canonical(name)?;
+        let mut db = self.database()?;
+        let Some(principal) = db.principals.get(&name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        };
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        {
+            self.check_password(&db, principal, password)?;
+            db.passwords
+                .entry(name.clone())
+                .or_default()
+                .push(password.to_owned());
+        }
+        // Heimdal client-side has no policies to check the password against
+        #[cfg(not(any(mit_client, mit_server, heimdal_server)))]
+        let _ = (principal, password);
+        if let Some(principal) = db.principals.get_mut(&name) {
+            principal.rotate_keys();
+        }
+        db.keys.remove(&name);
+        Ok(())
+    }
+
+    fn principal_randkey(
+        &self,
+        name: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] _keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        let name = self.canonical(name)?;
+        let mut db = self.database()?;
+        let Some(principal) = db.principals.get_mut(&name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        };
+        principal.rotate_keys();
+        db.keys.remove(&name);
+        Ok(())
+    }
+
+    fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+        let name = self.canonical(name)?;
+        let db = self.database()?;
+        if !db.principals.contains_key(&name) {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        }
+        Ok(db
+            .keys
+            .get(&name)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .filter(|key| kvno.map_or(true, |kvno| key.kvno == kvno))
+            .cloned()
+            .collect())
+    }
+
+    fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+        let name = self.canonical(name)?;
+        let mut db = self.database()?;
+        let Some(principal) = db.principals.get_mut(&name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        };
+        principal.set_keys(keys);
+        let stored = db.keys.entry(name.clone()).or_default();
+        if !keepold {
+            stored.clear();
+        }
+        stored.extend_from_slice(keys);
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
+        let name = self.canonical(name)?;
+        self.database()?
+            .strings
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| self.error(KAdminErrorKind::UnknownPrincipal))
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_set_string(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let name = self.canonical(name)?;
+        let mut db = self.database()?;
+        let Some(strings) = db.strings.get_mut(&name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPrincipal));
+        };
+        match value {
+            Some(value) => strings.insert(key.to_owned(), value.to_owned()),
+            None => strings.remove(key),
+        };
+        Ok(())
+    }
+
+    fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
+        let query = query.map(|query| {
+            if query.contains('@') {
+                query.to_owned()
+            } else {
+                format!("{query}@{}", self.realm)
+            }
+        });
+        Ok(self
+            .database()?
+            .principals
+            .keys()
+            .filter(|name| {
+                query
+                    .as_deref()
+                    .map_or(true, |query| query_matches(query, name))
+            })
+            .cloned()
+            .collect())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
+        if builder.name.is_empty() {
+            return Err(self.error(KAdminErrorKind::InvalidPolicy));
+        }
+        let mut db = self.database()?;
+        if db.policies.contains_key(&builder.name) {
+            return Err(self.error(KAdminErrorKind::DuplicatePolicy));
+        }
+        db.policies
+            .insert(builder.name.clone(), Policy::from_builder(builder));
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
+        let mut db = self.database()?;
+        let Some(policy) = db.policies.get_mut(&modifier.name) else {
+            return Err(self.error(KAdminErrorKind::UnknownPolicy));
+        };
+        modifier.apply_to(policy);
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delete_policy(&self, name: &str) -> Result<()> {
+        if self.database()?.policies.remove(name).is_none() {
+            return Err(self.error(KAdminErrorKind::UnknownPolicy));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+        Ok(self.database()?.policies.get(name).cloned())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
+        Ok(self
+            .database()?
+            .policies
+            .keys()
+            .filter(|name| query.map_or(true, |query| query_matches(query, name)))
+            .cloned()
+            .collect())
+    }
+
+    fn get_privileges(&self) -> Result<Privileges> {
+        Ok(self.privileges)
+    }
+}
+
+#[cfg(all(test, any(mit_server, heimdal_server)))]
+mod tests {
+    use super::*;
+
+    #[cfg(mit_server)]
+    const VARIANT: KAdm5Variant = KAdm5Variant::MitServer;
+    #[cfg(not(mit_server))]
+    const VARIANT: KAdm5Variant = KAdm5Variant::HeimdalServer;
+
+    fn kadmin() -> MemoryKAdmin {
+        MemoryKAdmin::new(VARIANT, "EXAMPLE.ORG")
+    }
+
+    #[test]
+    fn principals() -> Result<()> {
+        let kadmin = kadmin();
+        let princ = Principal::builder("alice").create(&kadmin)?;
+        assert_eq!(princ.name(), "alice@EXAMPLE.ORG");
+        assert_eq!(princ.kvno(), 1);
+
+        let err = Principal::builder("alice@EXAMPLE.ORG")
+            .create(&kadmin)
+            .unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::DuplicatePrincipal));
+        let err = kadmin.delete_principal("bob").unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::UnknownPrincipal));
+        assert!(matches!(err, Error::KAdmin { code, .. } if code != 0));
+
+        kadmin.principal_randkey("alice", None, None)?;
+        assert_eq!(kadmin.get_principal("alice")?.unwrap().kvno(), 2);
+
+        kadmin.rename_principal("alice", "bob")?;
+        assert!(!kadmin.principal_exists("alice")?);
+        assert!(kadmin.principal_exists("bob")?);
+        Ok(())
+    }
+
+    #[test]
+    fn list() -> Result<()> {
+        let kadmin = kadmin();
+        for name in ["alice", "host/a.example.org", "alice@OTHER.ORG"] {
+            Principal::builder(name).create(&kadmin)?;
+        }
+        assert_eq!(kadmin.list_principals(None)?.len(), 3);
+        assert_eq!(
+            kadmin.list_principals(Some("alice"))?,
+            vec!["alice@EXAMPLE.ORG"]
+        );
+        assert_eq!(
+            kadmin.list_principals(Some("host/*"))?,
+            vec!["host/a.example.org@EXAMPLE.ORG"]
+        );
+        assert_eq!(
+            kadmin.list_principals(Some("*@OTHER.ORG"))?,
+            vec!["alice@OTHER.ORG"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn policies() -> Result<()> {
+        let kadmin = kadmin();
+        let err = Principal::builder("alice")
+            .policy(Some("strict"))
+            .create(&kadmin)
+            .unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::UnknownPolicy));
+
+        Policy::builder("strict")
+            .password_min_length(12)
+            .password_history_num(2)
+            .create(&kadmin)?;
+        Policy::builder("default").create(&kadmin)?;
+        let err = Policy::builder("strict").create(&kadmin).unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::DuplicatePolicy));
+        assert!(err.is_already_exists());
+        // Only MIT gives new principals the default policy
+        assert_eq!(
+            Principal::builder("bob").create(&kadmin)?.policy(),
+            VARIANT.is_mit().then_some("default")
+        );
+
+        let err = Principal::builder("alice")
+            .policy(Some("strict"))
+            .key(&PrincipalBuilderKey::Password("short".to_owned()))
+            .create(&kadmin)
+            .unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::PasswordTooShort));
+
+        let password = "correct horse battery staple";
+        Principal::builder("alice")
+            .policy(Some("strict"))
+            .key(&PrincipalBuilderKey::Password(password.to_owned()))
+            .create(&kadmin)?;
+        let err = kadmin
+            .principal_change_password("alice", password, None, None)
+            .unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::PasswordReuse));
+        kadmin.principal_change_password("alice", "another long password", None, None)?;
+        assert_eq!(kadmin.get_principal("alice")?.unwrap().kvno(), 2);
+
+        // Random keys don't clear the password history
+        kadmin.principal_randkey("alice", None, None)?;
+        let err = kadmin
+            .principal_change_password("alice", password, None, None)
+            .unwrap_err();
+        assert_eq!(err.kind(), Some(KAdminErrorKind::PasswordReuse));
+        Ok(())
+    }
+
+    #[cfg(mit_server)]
+    #[test]
+    fn strings() -> Result<()> {
+        let kadmin = kadmin();
+        Principal::builder("alice").create(&kadmin)?;
+        kadmin.principal_set_string("alice", "key", Some("value"))?;
+        assert_eq!(kadmin.principal_get_strings("alice")?["key"], "value");
+        kadmin.principal_set_string("alice", "key", None)?;
+        assert!(kadmin.principal_get_strings("alice")?.is_empty());
+        assert!(
+            kadmin
+                .principal_get_strings("bob")
+                .unwrap_err()
+                .is_not_found()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,578 @@
+//! Copy principals and policies from one realm to another
+//!
+//! A [`Migration`] copies principals and policies from a source [`KAdminImpl`] to a destination
+//! one, which may be of another variant, for instance to move a realm from Heimdal to MIT krb5:
+//!
+//! ```no_run
+//! # use crate::kadmin::{KAdmin, KAdm5Variant, migrate::Migration};
+//! let source = KAdmin::builder(KAdm5Variant::HeimdalServer)
+//!     .with_local()
+//!     .unwrap();
+//! let destination = KAdmin::builder(KAdm5Variant::MitServer)
+//!     .with_local()
+//!     .unwrap();
+//! let report = Migration::new()
+//!     .exclude("host/*.old.example.org")
+//!     .state_file("migration.json")
+//!     .run(&source, &destination)
+//!     .unwrap();
+//! for name in &report.reissue {
+//!     println!("{name} needs new credentials");
+//! }
+//! ```
+//!
+//! Policies are copied first, then principals. All policies are copied, include and exclude
+//! patterns only apply to principals. Policies that already exist in the destination are left as
+//! they are. For each principal, its attributes, ticket lifetimes, expiration dates and
+//! policy are copied, along with its string attributes if both realms use MIT krb5. Attributes are
+//! translated between MIT and Heimdal values, and those without an equivalent are dropped.
+//!
+//! Keys are retrieved with [`KAdminImpl::principal_get_keys`] and set with
+//! [`KAdminImpl::principal_set_keys`], so that existing passwords and keytabs keep working. Their
+//! salt types are translated between MIT and Heimdal values. When
+//! that isn't possible, for instance for lack of privileges, the principal gets random keys instead
+//! and is listed in [`MigrationReport::reissue`]: its password or keytabs must be issued again.
+//!
+//! With a [state file][`Migration::state_file`], an interrupted migration can be run again and
+//! skips what was already copied. Principals and policies that failed to be copied are reported
+//! and retried on the next run. Remove the state file to copy everything anew.
+
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dump::heimdal::{translate_attributes, translate_key},
+    error::{Error, Result},
+    kadmin::KAdminImpl,
+    principal::{PrincipalBuilder, PrincipalModifier},
+};
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::{policy::PolicyBuilder, sys::KAdm5Variant};
+
+/// Principals excluded from a [`Migration`] unless [`Migration::internal`] is set
+///
+/// They are created along with a realm and hold keys specific to it.
+pub const INTERNAL_PRINCIPALS: &[&str] = &[
+    "K/M",
+    "krbtgt/*",
+    "kadmin/*",
+    "kiprop/*",
+    "changepw/*",
+    "WELLKNOWN/*",
+];
+
+fn failed(error: impl std::fmt::Display) -> Error {
+    Error::MigrationFailed(error.to_string())
+}
+
+/// Migration of principals and policies from a source [`KAdminImpl`] to a destination one
+///
+/// See the [module documentation][`crate::migrate`]
+#[derive(Clone, Debug)]
+pub struct Migration {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    internal: bool,
+    policies: bool,
+    keys: bool,
+    state_file: Option<PathBuf>,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            internal: false,
+            policies: true,
+            keys: true,
+            state_file: None,
+        }
+    }
+}
+
+impl Migration {
+    /// Construct a new [`Migration`] of all principals and policies
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only copy principals matching this shell-style glob. Can be called multiple times
+    ///
+    /// Principal names are matched both with and without their realm. Defaults to all principals
+    ///
+    /// Patterns only apply to principals: all policies are copied, since principals in scope may
+    /// reference any of them. Use [`Migration::policies`] to skip policies altogether.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_owned());
+        self
+    }
+
+    /// Don't copy principals matching this shell-style glob. Can be called multiple times
+    ///
+    /// Like [`Migration::include`], patterns don't apply to policies.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_owned());
+        self
+    }
+
+    /// Also copy the [`INTERNAL_PRINCIPALS`]. Defaults to `false`
+    pub fn internal(mut self, internal: bool) -> Self {
+        self.internal = internal;
+        self
+    }
+
+    /// Copy policies. Defaults to `true`
+    ///
+    /// Policies are only copied if both the source and the destination support them.
+    pub fn policies(mut self, policies: bool) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Copy keys. Defaults to `true`
+    ///
+    /// When `false`, all principals get random keys and are listed in
+    /// [`MigrationReport::reissue`].
+    pub fn keys(mut self, keys: bool) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Record progress in this JSON file, and skip what it lists as already copied
+    ///
+    /// The file is created if it doesn't exist, and updated after each principal and policy.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.state_file = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Copy principals and policies from `source` to `destination`
+    ///
+    /// Failures to copy a principal or policy are listed in [`MigrationReport::failed`] and don't
+    /// stop the migration. Errors listing principals or policies, or reading and writing the state
+    /// file, do.
+    pub fn run<S, D>(&self, source: &S, destination: &D) -> Result<MigrationReport>
+    where
+        S: KAdminImpl + ?Sized,
+        D: KAdminImpl + ?Sized,
+    {
+        let scope = Scope::new(self)?;
+        let mut state = match &self.state_file {
+            Some(path) => State::load(path)?,
+            None => State::default(),
+        };
+        let mut report = MigrationReport::default();
+
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        if self.policies && has_policies(source.variant()) && has_policies(destination.variant()) {
+            for name in source.list_policies(None)? {
+                if state.policies.contains(&name) {
+                    report.skipped += 1;
+                    continue;
+                }
+                match copy_policy(source, destination, &name) {
+                    Ok(()) => {
+                        report.policies.push(name.clone());
+                        state.policies.insert(name);
+                        self.save(&state)?;
+                    }
+                    Err(err) => report.failed.push((name, err)),
+                }
+            }
+        }
+
+        for name in source.list_principals(None)? {
+            if !scope.contains(&name) {
+                continue;
+            }
+            if state.principals.contains(&name) {
+                report.skipped += 1;
+                continue;
+            }
+            match self.copy_principal(source, destination, &name) {
+                Ok(Outcome::Gone) => {}
+                Ok(outcome) => {
+                    if outcome == Outcome::Reissue {
+                        state.reissue.insert(name.clone());
+                    }
+                    report.principals.push(name.clone());
+                    state.principals.insert(name);
+                    self.save(&state)?;
+                }
+                Err(err) => report.failed.push((name, err)),
+            }
+        }
+
+        report.reissue = state.reissue.into_iter().collect();
+        Ok(report)
+    }
+
+    fn save(&self, state: &State) -> Result<()> {
+        match &self.state_file {
+            Some(path) => state.save(path),
+            None => Ok(()),
+        }
+    }
+
+    fn copy_principal<S, D>(&self, source: &S, destination: &D, name: &str) -> Result<Outcome>
+    where
+        S: KAdminImpl + ?Sized,
+        D: KAdminImpl + ?Sized,
+    {
+        // Deleted since it was listed
+        let Some(principal) = source.get_principal(name)? else {
+            return Ok(Outcome::Gone);
+        };
+        let attributes = translate_attributes(
+            principal.attributes(),
+            source.variant(),
+            destination.variant(),
+        );
+
+        macro_rules! copy_fields {
+            ($doer:expr) => {
+                $doer
+                    .expire_time(principal.expire_time())
+                    .password_expiration(principal.password_expiration())
+                    .max_life(principal.max_life())
+                    .max_renewable_life(principal.max_renewable_life())
+                    .attributes(attributes)
+                    .policy(principal.policy())
+            };
+        }
+
+        // New principals get random keys when created
+        let existed = destination.principal_exists(name)?;
+        if existed {
+            destination
+                .modify_principal(&copy_fields!(PrincipalModifier::from_principal(&principal)))?;
+        } else {
+            destination.add_principal(&copy_fields!(
+                PrincipalBuilder::new(name).kvno(principal.kvno())
+            ))?;
+        }
+
+        #[cfg(any(mit_client, mit_server))]
+        if source.variant().is_mit() && destination.variant().is_mit() {
+            for (key, value) in source.principal_get_strings(name)? {
+                destination.principal_set_string(name, &key, Some(&value))?;
+            }
+        }
+
+        if self.keys {
+            match copy_keys(source, destination, name) {
+                Ok(()) => return Ok(Outcome::Copied),
+                Err(_err) => {
+                    #[cfg(feature = "log")]
+                    log::warn!("Couldn't copy the keys of {name}, using random ones: {_err}");
+                }
+            }
+        }
+        if existed {
+            destination.principal_randkey(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                None,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                None,
+            )?;
+        }
+        Ok(Outcome::Reissue)
+    }
+}
+
+fn copy_keys<S, D>(source: &S, destination: &D, name: &str) -> Result<()>
+where
+    S: KAdminImpl + ?Sized,
+    D: KAdminImpl + ?Sized,
+{
+    let keys = source.principal_get_keys(name, None)?;
+    // Heimdal returns empty keys to clients lacking the privilege to get them
+    if keys.is_empty() || keys.iter().any(|key| key.contents.is_empty()) {
+        return Err(failed(format!("no keys could be retrieved for {name}")));
+    }
+    // Salt types differ between MIT and Heimdal, keys must keep their salt to match the password
+    let keys: Vec<_> = keys
+        .iter()
+        .map(|key| translate_key(name, key, source.variant(), destination.variant()))
+        .collect();
+    destination.principal_set_keys(name, false, &keys)
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+fn has_policies(variant: KAdm5Variant) -> bool {
+    !(variant.is_heimdal() && variant.is_client())
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+fn copy_policy<S, D>(source: &S, destination: &D, name: &str) -> Result<()>
+where
+    S: KAdminImpl + ?Sized,
+    D: KAdminImpl + ?Sized,
+{
+    let Some(policy) = source.get_policy(name)? else {
+        return Ok(());
+    };
+    if destination.policy_exists(name)? {
+        return Ok(());
+    }
+    destination.add_policy(&PolicyBuilder::from_policy(&policy))
+}
+
+/// What happened to a principal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    /// Copied along with its keys
+    Copied,
+    /// Copied, with new random keys
+    Reissue,
+    /// Deleted from the source before it could be copied
+    Gone,
+}
+
+/// Result of [`Migration::run`]
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct MigrationReport {
+    /// Policies copied by this run
+    pub policies: Vec<String>,
+    /// Principals copied by this run
+    pub principals: Vec<String>,
+    /// Principals that got random keys instead of their own, by this run or a previous one
+    /// recorded in the state file. Their credentials must be issued again
+    pub reissue: Vec<String>,
+    /// Number of principals and policies skipped because a previous run copied them
+    pub skipped: usize,
+    /// Principals and policies that couldn't be copied, with the reason
+    pub failed: Vec<(String, Error)>,
+}
+
+impl MigrationReport {
+    /// Whether every principal and policy in scope was copied
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Progress of a [`Migration`], persisted in its state file
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+struct State {
+    policies: BTreeSet<String>,
+    principals: BTreeSet<String>,
+    reissue: BTreeSet<String>,
+}
+
+impl State {
+    fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|err| failed(format!("invalid state file {}: {err}", path.display()))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Write to a temporary file first, so that an interruption doesn't leave a truncated one
+    fn save(&self, path: &Path) -> Result<()> {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let contents = serde_json::to_string_pretty(self).map_err(failed)?;
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// Compiled include and exclude patterns of a [`Migration`]
+struct Scope {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Scope {
+    fn new(migration: &Migration) -> Result<Self> {
+        let compile = |patterns: Vec<&str>| {
+            patterns
+                .into_iter()
+                .map(|pattern| {
+                    Pattern::new(pattern)
+                        .map_err(|err| failed(format!("invalid pattern {pattern:?}: {err}")))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let internal: &[&str] = if migration.internal {
+            &[]
+        } else {
+            INTERNAL_PRINCIPALS
+        };
+        Ok(Self {
+            include: compile(migration.include.iter().map(String::as_str).collect())?,
+            exclude: compile(
+                migration
+                    .exclude
+                    .iter()
+                    .map(String::as_str)
+                    .chain(internal.iter().copied())
+                    .collect(),
+            )?,
+        })
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        let short_name = name
+            .rsplit_once('@')
+            .map_or(name, |(short_name, _)| short_name);
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches(name) || pattern.matches(short_name))
+        };
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+#[cfg(all(test, mit_server))]
+mod tests {
+    use super::*;
+    use crate::{EncryptionType, Key, KeySalt, MemoryKAdmin, Policy, Principal};
+
+    fn realms() -> Result<(MemoryKAdmin, MemoryKAdmin)> {
+        let source = MemoryKAdmin::new(KAdm5Variant::MitServer, "EXAMPLE.ORG");
+        Policy::builder("default")
+            .password_min_length(8)
+            .create(&source)?;
+        for name in ["K/M", "krbtgt/EXAMPLE.ORG", "bob", "host/a.example.org"] {
+            Principal::builder(name).create(&source)?;
+        }
+        Principal::builder("alice")
+            .policy(Some("default"))
+            .max_life(Some(std::time::Duration::from_secs(3600)))
+            .create(&source)?;
+        source.principal_set_keys(
+            "alice",
+            false,
+            &[Key {
+                kvno: 3,
+                keysalt: KeySalt {
+                    enctype: EncryptionType::from(18),
+                    salttype: Default::default(),
+                },
+                contents: vec![0x42; 32].into(),
+                salt: Vec::new(),
+            }],
+        )?;
+        source.principal_set_string("alice", "session_enctypes", Some("aes256-cts"))?;
+        let destination = MemoryKAdmin::new(KAdm5Variant::MitServer, "EXAMPLE.ORG");
+        Ok((source, destination))
+    }
+
+    #[test]
+    fn copy() -> Result<()> {
+        let (source, destination) = realms()?;
+        let report = Migration::new()
+            .exclude("host/*")
+            .run(&source, &destination)?;
+        assert!(report.is_complete());
+        assert_eq!(report.policies, vec!["default"]);
+        assert_eq!(
+            report.principals,
+            vec!["alice@EXAMPLE.ORG", "bob@EXAMPLE.ORG"]
+        );
+        // bob has no keys to retrieve
+        assert_eq!(report.reissue, vec!["bob@EXAMPLE.ORG"]);
+        assert!(!destination.principal_exists("K/M")?);
+        assert!(!destination.principal_exists("host/a.example.org")?);
+
+        let alice = destination.get_principal("alice")?.unwrap();
+        assert_eq!(alice.policy(), Some("default"));
+        assert_eq!(alice.max_life(), Some(std::time::Duration::from_secs(3600)));
+        assert_eq!(alice.kvno(), 3);
+        assert_eq!(
+            destination.principal_get_keys("alice", None)?,
+            source.principal_get_keys("alice", None)?
+        );
+        assert_eq!(
+            destination.principal_get_strings("alice")?["session_enctypes"],
+            "aes256-cts"
+        );
+        Ok(())
+    }
+
+    #[cfg(heimdal_server)]
+    #[test]
+    fn translate_salts() -> Result<()> {
+        let key = |salttype: i32, salt: &[u8]| Key {
+            kvno: 2,
+            keysalt: KeySalt {
+                enctype: EncryptionType::from(18),
+                salttype: salttype.into(),
+            },
+            contents: vec![0x42; 32].into(),
+            salt: salt.to_vec(),
+        };
+        // Heimdal's pw_salt with the default salt, then with a special one
+        let heimdal_keys = vec![key(3, b"EXAMPLE.ORGalice"), key(3, b"pepper")];
+        let mit_keys = vec![key(0, b""), key(4, b"pepper")];
+
+        let heimdal = MemoryKAdmin::new(KAdm5Variant::HeimdalServer, "EXAMPLE.ORG");
+        Principal::builder("alice").create(&heimdal)?;
+        heimdal.principal_set_keys("alice", false, &heimdal_keys)?;
+        let mit = MemoryKAdmin::new(KAdm5Variant::MitServer, "EXAMPLE.ORG");
+        let report = Migration::new().run(&heimdal, &mit)?;
+        assert!(report.is_complete());
+        assert!(report.reissue.is_empty());
+        assert_eq!(mit.principal_get_keys("alice", None)?, mit_keys);
+
+        let heimdal = MemoryKAdmin::new(KAdm5Variant::HeimdalServer, "EXAMPLE.ORG");
+        let report = Migration::new().run(&mit, &heimdal)?;
+        assert!(report.is_complete());
+        assert!(report.reissue.is_empty());
+        assert_eq!(heimdal.principal_get_keys("alice", None)?, heimdal_keys);
+        Ok(())
+    }
+
+    #[test]
+    fn resume() -> Result<()> {
+        let (source, destination) = realms()?;
+        let state_file = std::env::temp_dir().join(format!(
+            "kadmin-migrate-{}-{:?}.json",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = fs::remove_file(&state_file);
+        let migration = Migration::new().include("alice").state_file(&state_file);
+        assert_eq!(migration.run(&source, &destination)?.principals.len(), 1);
+
+        let report = migration
+            .clone()
+            .include("bob")
+            .run(&source, &destination)?;
+        assert_eq!(report.skipped, 2);
+        assert_eq!(report.principals, vec!["bob@EXAMPLE.ORG"]);
+        assert_eq!(report.reissue, vec!["bob@EXAMPLE.ORG"]);
+        fs::remove_file(&state_file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn scope() -> Result<()> {
+        let scope = Scope::new(&Migration::new().include("host/*").exclude("*@OTHER.ORG"))?;
+        assert!(scope.contains("host/a.example.org@EXAMPLE.ORG"));
+        assert!(!scope.contains("host/a.example.org@OTHER.ORG"));
+        assert!(!scope.contains("alice@EXAMPLE.ORG"));
+        assert!(!Scope::new(&Migration::new())?.contains("krbtgt/EXAMPLE.ORG@EXAMPLE.ORG"));
+        assert!(
+            Scope::new(&Migration::new().internal(true))?
+                .contains("krbtgt/EXAMPLE.ORG@EXAMPLE.ORG")
+        );
+        Ok(())
+    }
+}
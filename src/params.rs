@@ -3,22 +3,42 @@ use std::{
     ffi::{CString, c_void},
     ptr::null_mut,
 };
+#[cfg(any(mit_client, mit_server))]
+use std::{ptr::slice_from_raw_parts_mut, time::Duration};
 
+#[cfg(any(mit_client, mit_server))]
+use chrono::{DateTime, Utc};
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 
 use crate::{
     context::Context,
-    error::Result,
+    error::{Error, Result},
     sys::{cfg_match, library_match},
 };
+#[cfg(any(mit_client, mit_server))]
+use crate::{
+    conv::{dt_to_ts, dur_to_delta},
+    keysalt::{EncryptionType, KeySalts},
+};
 
 /// kadm5 config options
 ///
+/// Most options are only available on MIT variants, as Heimdal's `kadm5_config_params` only
+/// supports the realm, kadmind port, admin server, database name, ACL file and stash file, plus
+/// the read-only admin server and port which are Heimdal-specific. Setters for variant-specific
+/// options only exist when a variant supporting them is enabled, and are ignored by the other
+/// enabled variants.
+///
+/// The `kdc_ports` and `kdc_tcp_ports` options are not part of `kadm5_config_params` anymore.
+/// They are kept here so they can be overridden alongside the other realm parameters, but are
+/// never passed to kadm5; use [`Params::get_kdc_ports`] and [`Params::get_kdc_tcp_ports`] to
+/// configure the KDC.
+///
 /// ```
 /// let params = kadmin::Params::new().realm("EXAMPLE.ORG");
 /// ```
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "python", pyclass(from_py_object))]
 pub struct Params {
     #[cfg(mit_client)]
@@ -52,6 +72,70 @@ pub struct Params {
     dict_file: Option<String>,
     /// Location where the master key has been stored
     stash_file: Option<String>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Name of the master key in the database
+    mkey_name: Option<String>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Whether the master key is read from the keyboard instead of the stash file
+    mkey_from_kbd: bool,
+    #[cfg(any(mit_client, mit_server))]
+    /// Master key encryption type
+    enctype: Option<EncryptionType>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Default maximum ticket life for new principals
+    max_life: Option<Duration>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Default maximum renewable life for new principals
+    max_rlife: Option<Duration>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Default expiration date for new principals
+    expiration: Option<DateTime<Utc>>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Default attributes for new principals
+    flags: i32,
+    #[cfg(any(mit_client, mit_server))]
+    /// Default keysalts for new keys
+    keysalts: Option<KeySalts>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Master key version number
+    kvno: u32,
+    #[cfg(any(mit_client, mit_server))]
+    /// Whether incremental propagation is enabled
+    iprop_enabled: bool,
+    #[cfg(any(mit_client, mit_server))]
+    /// Maximum number of entries in the update log
+    iprop_ulogsize: u32,
+    #[cfg(any(mit_client, mit_server))]
+    /// How often replicas poll for updates
+    iprop_poll_time: Option<Duration>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Location of the update log
+    iprop_logfile: Option<String>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Port used for incremental propagation
+    iprop_port: i32,
+    #[cfg(any(mit_client, mit_server))]
+    /// Time after which a replica gives up on an incremental update and requests a full resync
+    iprop_resync_timeout: Option<Duration>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Addresses and ports kadmind listens on
+    kadmind_listen: Option<String>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Addresses and ports kpasswd listens on
+    kpasswd_listen: Option<String>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Addresses and ports the incremental propagation service listens on
+    iprop_listen: Option<String>,
+    /// Ports the KDC listens on for UDP requests
+    kdc_ports: Option<String>,
+    /// Ports the KDC listens on for TCP requests
+    kdc_tcp_ports: Option<String>,
+    #[cfg(any(heimdal_client, heimdal_server))]
+    /// Read-only admin server which kadmin should contact for read operations
+    readonly_admin_server: Option<String>,
+    #[cfg(any(heimdal_client, heimdal_server))]
+    /// Port of the read-only admin server
+    readonly_kadmind_port: i32,
 }
 
 macro_rules! set_mask {
@@ -109,7 +193,7 @@ impl Params {
     #[cfg(any(mit_client, mit_server))]
     /// Set the kpasswd port to connect to
     ///
-    /// No-op for non-MIT variants
+    /// Ignored by Heimdal variants
     pub fn kpasswd_port(mut self, port: i32) -> Self {
         self.kpasswd_port = port;
         set_mask!(self; mit_client, mit_server => KADM5_CONFIG_KPASSWD_PORT);
@@ -138,21 +222,255 @@ impl Params {
     }
 
     #[cfg(any(mit_client, mit_server))]
-    /// Set the location of the access control list file
+    /// Set the location of the dictionary file containing strings that are not allowed as
+    /// passwords
     ///
-    /// No-op for non-MIT variants
+    /// Ignored by Heimdal variants
     pub fn dict_file(mut self, dict_file: &str) -> Self {
         self.dict_file = Some(dict_file.to_owned());
         set_mask!(self; mit_client, mit_server => KADM5_CONFIG_DICT_FILE);
         self
     }
 
-    /// Set the location of the access control list file
+    /// Set the location where the master key has been stored
     pub fn stash_file(mut self, stash_file: &str) -> Self {
         self.stash_file = Some(stash_file.to_owned());
         set_mask!(self, KADM5_CONFIG_STASH_FILE);
         self
     }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the name of the master key in the database
+    ///
+    /// Ignored by Heimdal variants
+    pub fn mkey_name(mut self, mkey_name: &str) -> Self {
+        self.mkey_name = Some(mkey_name.to_owned());
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_MKEY_NAME);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set whether the master key should be read from the keyboard instead of the stash file
+    ///
+    /// Ignored by Heimdal variants
+    pub fn mkey_from_kbd(mut self, mkey_from_kbd: bool) -> Self {
+        self.mkey_from_kbd = mkey_from_kbd;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_MKEY_FROM_KBD);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the master key encryption type
+    ///
+    /// Ignored by Heimdal variants
+    pub fn enctype(mut self, enctype: EncryptionType) -> Self {
+        self.enctype = Some(enctype);
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_ENCTYPE);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the default maximum ticket life for new principals
+    ///
+    /// Ignored by Heimdal variants
+    pub fn max_life(mut self, max_life: Option<Duration>) -> Self {
+        self.max_life = max_life;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_MAX_LIFE);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the default maximum renewable life for new principals
+    ///
+    /// Ignored by Heimdal variants
+    pub fn max_rlife(mut self, max_rlife: Option<Duration>) -> Self {
+        self.max_rlife = max_rlife;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_MAX_RLIFE);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the default expiration date for new principals
+    ///
+    /// Ignored by Heimdal variants
+    pub fn expiration(mut self, expiration: Option<DateTime<Utc>>) -> Self {
+        self.expiration = expiration;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_EXPIRATION);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the default attributes for new principals
+    ///
+    /// Ignored by Heimdal variants
+    pub fn flags(mut self, flags: i32) -> Self {
+        self.flags = flags;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_FLAGS);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the default keysalts used when creating new keys
+    ///
+    /// Ignored by Heimdal variants
+    pub fn keysalts(mut self, keysalts: &KeySalts) -> Self {
+        self.keysalts = Some(keysalts.clone());
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_ENCTYPES);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the master key version number
+    ///
+    /// Ignored by Heimdal variants
+    pub fn kvno(mut self, kvno: u32) -> Self {
+        self.kvno = kvno;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_KVNO);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set whether incremental propagation is enabled
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_enabled(mut self, iprop_enabled: bool) -> Self {
+        self.iprop_enabled = iprop_enabled;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_IPROP_ENABLED);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the maximum number of entries in the update log
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_ulogsize(mut self, iprop_ulogsize: u32) -> Self {
+        self.iprop_ulogsize = iprop_ulogsize;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_ULOG_SIZE);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set how often replicas poll for updates
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_poll_time(mut self, iprop_poll_time: Duration) -> Self {
+        self.iprop_poll_time = Some(iprop_poll_time);
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_POLL_TIME);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the location of the update log
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_logfile(mut self, iprop_logfile: &str) -> Self {
+        self.iprop_logfile = Some(iprop_logfile.to_owned());
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_IPROP_LOGFILE);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the port used for incremental propagation
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_port(mut self, port: i32) -> Self {
+        self.iprop_port = port;
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_IPROP_PORT);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the time after which a replica gives up on an incremental update and requests a full
+    /// resync
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_resync_timeout(mut self, iprop_resync_timeout: Duration) -> Self {
+        self.iprop_resync_timeout = Some(iprop_resync_timeout);
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_IPROP_RESYNC_TIMEOUT);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the addresses and ports kadmind listens on
+    ///
+    /// Ignored by Heimdal variants
+    pub fn kadmind_listen(mut self, kadmind_listen: &str) -> Self {
+        self.kadmind_listen = Some(kadmind_listen.to_owned());
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_KADMIND_LISTEN);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the addresses and ports kpasswd listens on
+    ///
+    /// Ignored by Heimdal variants
+    pub fn kpasswd_listen(mut self, kpasswd_listen: &str) -> Self {
+        self.kpasswd_listen = Some(kpasswd_listen.to_owned());
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_KPASSWD_LISTEN);
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Set the addresses and ports the incremental propagation service listens on
+    ///
+    /// Ignored by Heimdal variants
+    pub fn iprop_listen(mut self, iprop_listen: &str) -> Self {
+        self.iprop_listen = Some(iprop_listen.to_owned());
+        set_mask!(self; mit_client, mit_server => KADM5_CONFIG_IPROP_LISTEN);
+        self
+    }
+
+    /// Set the ports the KDC listens on for UDP requests
+    ///
+    /// Not passed to kadm5, see [`Params::get_kdc_ports`]
+    pub fn kdc_ports(mut self, kdc_ports: &str) -> Self {
+        self.kdc_ports = Some(kdc_ports.to_owned());
+        self
+    }
+
+    /// Set the ports the KDC listens on for TCP requests
+    ///
+    /// Not passed to kadm5, see [`Params::get_kdc_tcp_ports`]
+    pub fn kdc_tcp_ports(mut self, kdc_tcp_ports: &str) -> Self {
+        self.kdc_tcp_ports = Some(kdc_tcp_ports.to_owned());
+        self
+    }
+
+    /// Ports the KDC listens on for UDP requests
+    ///
+    /// kadm5 doesn't use this value anymore. It is meant for callers that configure the KDC of
+    /// the realm, for instance to generate its `kdc.conf`.
+    pub fn get_kdc_ports(&self) -> Option<&str> {
+        self.kdc_ports.as_deref()
+    }
+
+    /// Ports the KDC listens on for TCP requests
+    ///
+    /// kadm5 doesn't use this value anymore. It is meant for callers that configure the KDC of
+    /// the realm, for instance to generate its `kdc.conf`.
+    pub fn get_kdc_tcp_ports(&self) -> Option<&str> {
+        self.kdc_tcp_ports.as_deref()
+    }
+
+    #[cfg(any(heimdal_client, heimdal_server))]
+    /// Set the read-only admin server which kadmin should contact for read operations
+    ///
+    /// Ignored by MIT variants
+    pub fn readonly_admin_server(mut self, readonly_admin_server: &str) -> Self {
+        self.readonly_admin_server = Some(readonly_admin_server.to_owned());
+        set_mask!(self; heimdal_client, heimdal_server => KADM5_CONFIG_READONLY_ADMIN_SERVER);
+        self
+    }
+
+    #[cfg(any(heimdal_client, heimdal_server))]
+    /// Set the port of the read-only admin server
+    ///
+    /// Ignored by MIT variants
+    pub fn readonly_kadmind_port(mut self, port: i32) -> Self {
+        self.readonly_kadmind_port = port;
+        set_mask!(self; heimdal_client, heimdal_server => KADM5_CONFIG_READONLY_KADMIN_PORT);
+        self
+    }
 }
 
 pub(crate) struct ParamsRaw<'a> {
@@ -166,6 +484,21 @@ pub(crate) struct ParamsRaw<'a> {
     #[cfg(any(mit_client, mit_server))]
     dict_file: Option<CString>,
     stash_file: Option<CString>,
+    #[cfg(any(mit_client, mit_server))]
+    mkey_name: Option<CString>,
+    #[cfg(any(mit_client, mit_server))]
+    iprop_logfile: Option<CString>,
+    #[cfg(any(mit_client, mit_server))]
+    kadmind_listen: Option<CString>,
+    #[cfg(any(mit_client, mit_server))]
+    kpasswd_listen: Option<CString>,
+    #[cfg(any(mit_client, mit_server))]
+    iprop_listen: Option<CString>,
+    #[cfg(any(heimdal_client, heimdal_server))]
+    readonly_admin_server: Option<CString>,
+    /// Length of the keysalts array owned by `raw`
+    #[cfg(any(mit_client, mit_server))]
+    keysalts_len: usize,
 }
 
 impl<'a> ParamsRaw<'a> {
@@ -202,6 +535,42 @@ impl<'a> ParamsRaw<'a> {
             .as_ref()
             .map(|s| CString::new(s.as_str()))
             .transpose()?;
+        #[cfg(any(mit_client, mit_server))]
+        let mkey_name = params
+            .mkey_name
+            .as_ref()
+            .map(|s| CString::new(s.as_str()))
+            .transpose()?;
+        #[cfg(any(mit_client, mit_server))]
+        let iprop_logfile = params
+            .iprop_logfile
+            .as_ref()
+            .map(|s| CString::new(s.as_str()))
+            .transpose()?;
+        #[cfg(any(mit_client, mit_server))]
+        let kadmind_listen = params
+            .kadmind_listen
+            .as_ref()
+            .map(|s| CString::new(s.as_str()))
+            .transpose()?;
+        #[cfg(any(mit_client, mit_server))]
+        let kpasswd_listen = params
+            .kpasswd_listen
+            .as_ref()
+            .map(|s| CString::new(s.as_str()))
+            .transpose()?;
+        #[cfg(any(mit_client, mit_server))]
+        let iprop_listen = params
+            .iprop_listen
+            .as_ref()
+            .map(|s| CString::new(s.as_str()))
+            .transpose()?;
+        #[cfg(any(heimdal_client, heimdal_server))]
+        let readonly_admin_server = params
+            .readonly_admin_server
+            .as_ref()
+            .map(|s| CString::new(s.as_str()))
+            .transpose()?;
 
         let mut guard = Self {
             raw: null_mut(),
@@ -213,6 +582,20 @@ impl<'a> ParamsRaw<'a> {
             #[cfg(any(mit_client, mit_server))]
             dict_file,
             stash_file,
+            #[cfg(any(mit_client, mit_server))]
+            mkey_name,
+            #[cfg(any(mit_client, mit_server))]
+            iprop_logfile,
+            #[cfg(any(mit_client, mit_server))]
+            kadmind_listen,
+            #[cfg(any(mit_client, mit_server))]
+            kpasswd_listen,
+            #[cfg(any(mit_client, mit_server))]
+            iprop_listen,
+            #[cfg(any(heimdal_client, heimdal_server))]
+            readonly_admin_server,
+            #[cfg(any(mit_client, mit_server))]
+            keysalts_len: 0,
         };
 
         let mask = library_match!(
@@ -261,6 +644,52 @@ impl<'a> ParamsRaw<'a> {
                 } else {
                     null_mut()
                 };
+                raw.mkey_name = if let Some(mkey_name) = &guard.mkey_name {
+                    mkey_name.as_ptr().cast_mut()
+                } else {
+                    null_mut()
+                };
+                raw.mkey_from_kbd = params.mkey_from_kbd.into();
+                raw.enctype = params.enctype.map(Into::into).unwrap_or_default();
+                raw.max_life = dur_to_delta(params.max_life)?;
+                raw.max_rlife = dur_to_delta(params.max_rlife)?;
+                raw.expiration = dt_to_ts(params.expiration)?;
+                raw.flags = params.flags;
+                raw.kvno = params.kvno;
+                raw.iprop_enabled = params.iprop_enabled.into();
+                raw.iprop_ulogsize = params.iprop_ulogsize;
+                raw.iprop_poll_time = dur_to_delta(params.iprop_poll_time)?;
+                raw.iprop_logfile = if let Some(iprop_logfile) = &guard.iprop_logfile {
+                    iprop_logfile.as_ptr().cast_mut()
+                } else {
+                    null_mut()
+                };
+                raw.iprop_port = params.iprop_port;
+                raw.iprop_resync_timeout = dur_to_delta(params.iprop_resync_timeout)?;
+                raw.kadmind_listen = if let Some(kadmind_listen) = &guard.kadmind_listen {
+                    kadmind_listen.as_ptr().cast_mut()
+                } else {
+                    null_mut()
+                };
+                raw.kpasswd_listen = if let Some(kpasswd_listen) = &guard.kpasswd_listen {
+                    kpasswd_listen.as_ptr().cast_mut()
+                } else {
+                    null_mut()
+                };
+                raw.iprop_listen = if let Some(iprop_listen) = &guard.iprop_listen {
+                    iprop_listen.as_ptr().cast_mut()
+                } else {
+                    null_mut()
+                };
+                if let Some(keysalts) = &params.keysalts {
+                    let keysalts: Vec<lib!(krb5_key_salt_tuple)> = keysalts.into();
+                    raw.num_keysalts = keysalts
+                        .len()
+                        .try_into()
+                        .map_err(Error::KeySaltsConversion)?;
+                    guard.keysalts_len = keysalts.len();
+                    raw.keysalts = Box::into_raw(keysalts.into_boxed_slice()).cast();
+                }
 
                 let raw = Box::new(raw);
                 guard.raw = Box::into_raw(raw) as *const c_void;
@@ -295,6 +724,13 @@ impl<'a> ParamsRaw<'a> {
                 } else {
                     null_mut()
                 };
+                raw.readonly_admin_server =
+                    if let Some(readonly_admin_server) = &guard.readonly_admin_server {
+                        readonly_admin_server.as_ptr().cast_mut()
+                    } else {
+                        null_mut()
+                    };
+                raw.readonly_kadmind_port = params.readonly_kadmind_port;
 
                 let raw = Box::new(raw);
                 guard.raw = Box::into_raw(raw) as *const c_void;
@@ -310,10 +746,23 @@ impl Drop for ParamsRaw<'_> {
         if self.raw.is_null() {
             return;
         }
-        library_match!(&self.context.library; |_cont, lib| {
-            let raw: Box<lib!(kadm5_config_params)> = unsafe { Box::from_raw(self.raw as *mut lib!(kadm5_config_params)) };
-            drop(raw);
-        });
+        library_match!(
+            &self.context.library;
+            mit_client, mit_server => |_cont, lib| {
+                let raw: Box<lib!(kadm5_config_params)> = unsafe { Box::from_raw(self.raw as *mut lib!(kadm5_config_params)) };
+                if !raw.keysalts.is_null() {
+                    let keysalts: Box<[lib!(krb5_key_salt_tuple)]> = unsafe {
+                        Box::from_raw(slice_from_raw_parts_mut(raw.keysalts, self.keysalts_len))
+                    };
+                    drop(keysalts);
+                }
+                drop(raw);
+            },
+            heimdal_client, heimdal_server => |_cont, lib| {
+                let raw: Box<lib!(kadm5_config_params)> = unsafe { Box::from_raw(self.raw as *mut lib!(kadm5_config_params)) };
+                drop(raw);
+            }
+        );
     }
 }
 
@@ -398,4 +847,36 @@ mod tests {
             .kadmind_port(750);
         assert_eq!(params.mask_heimdal_server, 0xd);
     }
+
+    #[cfg(heimdal_client)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn build_readonly_heimdal_client() {
+        let params = Params::new()
+            .readonly_admin_server("kadmin-ro.example.org")
+            .readonly_kadmind_port(7749)
+            .kdc_ports("88");
+        assert_eq!(params.mask_heimdal_client, 0x180);
+        assert_eq!(params.get_kdc_ports(), Some("88"));
+    }
+    #[cfg(mit_server)]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn build_iprop_mit_server() {
+        let params = Params::new()
+            .mkey_name("K/M")
+            .iprop_enabled(true)
+            .iprop_ulogsize(1000)
+            .iprop_port(754);
+        assert_eq!(params.mask_mit_server, 0x13000004);
+    }
+
+    #[cfg(all(mit_client, heimdal_client))]
+    #[test_log::test]
+    #[serial_test::serial]
+    fn build_mit_only_heimdal_client() {
+        let params = Params::new().realm("EXAMPLE.ORG").mkey_name("K/M").kvno(2);
+        assert_eq!(params.mask_mit_client, 0x20000005);
+        assert_eq!(params.mask_heimdal_client, 0x1);
+    }
 }
@@ -0,0 +1,225 @@
+//! Random [`PasswordGenerator`] compliant with password policies
+
+use crate::{
+    error::{Error, Result},
+    kadmin::KAdminImpl,
+};
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::{keysalt::KeySalts, policy::Policy};
+
+/// Default length of generated passwords
+const DEFAULT_LENGTH: usize = 24;
+
+/// Character classes, in the order they are required. They match the classes counted by kadmind
+/// for [`Policy::password_min_classes`]. Quotes and backslashes are left out of punctuation so
+/// that generated passwords are easy to paste in shells and configuration files.
+const CLASSES: [&[u8]; 5] = [
+    b"abcdefghijklmnopqrstuvwxyz",
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    b"0123456789",
+    b"!#$%&()*+,-./:;<=>?@[]^_{|}~",
+    b" ",
+];
+
+/// Generate random passwords that satisfy a password policy
+///
+/// Passwords contain characters from the first `min_classes` of lower case, upper case, numbers,
+/// punctuation and whitespace, with at least one character of each. Whitespace is only used when
+/// all five classes are required.
+///
+/// ```no_run
+/// # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant, password::PasswordGenerator};
+/// # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+/// #     .with_ccache(None, None)
+/// #     .unwrap();
+/// let policy = kadmin.get_policy("default").unwrap().unwrap();
+/// let generator = PasswordGenerator::from_policy(&policy);
+/// for (princ, password) in generator.reset_passwords(&kadmin, &["alice", "bob"]) {
+///     println!("{princ}: {}", password.unwrap());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PasswordGenerator {
+    length: usize,
+    min_classes: usize,
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    keysalts: Option<KeySalts>,
+}
+
+impl Default for PasswordGenerator {
+    fn default() -> Self {
+        Self {
+            length: DEFAULT_LENGTH,
+            min_classes: 4,
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            keysalts: None,
+        }
+    }
+}
+
+impl PasswordGenerator {
+    /// Construct a new [`PasswordGenerator`] generating 24 characters long passwords with 4
+    /// character classes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Construct a new [`PasswordGenerator`] whose passwords satisfy `policy`
+    ///
+    /// The defaults of [`Self::new`] are kept if they are stricter than the policy. With MIT, the
+    /// [allowed keysalts][`Policy::allowed_keysalts`] of the policy are used by
+    /// [`Self::reset_passwords`].
+    pub fn from_policy(policy: &Policy) -> Self {
+        let default = Self::default();
+        Self {
+            length: default
+                .length
+                .max(usize::try_from(policy.password_min_length()).unwrap_or_default()),
+            min_classes: default
+                .min_classes
+                .max(usize::try_from(policy.password_min_classes()).unwrap_or_default())
+                .min(CLASSES.len()),
+            #[cfg(any(mit_client, mit_server))]
+            keysalts: policy.allowed_keysalts().cloned(),
+            #[cfg(not(any(mit_client, mit_server)))]
+            keysalts: None,
+        }
+    }
+
+    /// Set the length of generated passwords
+    ///
+    /// Raised to the number of required character classes if lower
+    pub fn length(mut self, length: usize) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// Set the number of character classes generated passwords must contain, between 1 and 5
+    pub fn min_classes(mut self, min_classes: usize) -> Self {
+        self.min_classes = min_classes.clamp(1, CLASSES.len());
+        self
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Use the specified keysalt list for setting the keys of principals in
+    /// [`Self::reset_passwords`]
+    ///
+    /// Only available on MIT and Heimdal server libraries, and silently ignored otherwise
+    pub fn keysalts(mut self, keysalts: &KeySalts) -> Self {
+        self.keysalts = Some(keysalts.clone());
+        self
+    }
+
+    /// Generate a random password
+    pub fn generate(&self) -> Result<String> {
+        let classes = &CLASSES[..self.min_classes];
+        // Whitespace only fills its required slot
+        let alphabet: Vec<u8> = classes
+            .iter()
+            .take(4)
+            .flat_map(|class| class.iter().copied())
+            .collect();
+
+        let mut password: Vec<u8> = Vec::with_capacity(self.length.max(classes.len()));
+        for class in classes {
+            password.push(class[random_index(class.len())?]);
+        }
+        while password.len() < self.length {
+            password.push(alphabet[random_index(alphabet.len())?]);
+        }
+        // Fisher-Yates shuffle, so the required characters aren't always first
+        for i in (1..password.len()).rev() {
+            password.swap(i, random_index(i + 1)?);
+        }
+
+        Ok(password.into_iter().map(char::from).collect())
+    }
+
+    /// Reset the passwords of `principals` to newly generated ones
+    ///
+    /// Returns each principal name alongside its new password, or the error that occurred while
+    /// changing it. Principals for which an error is returned keep their previous password.
+    pub fn reset_passwords<K: KAdminImpl + ?Sized, S: AsRef<str>>(
+        &self,
+        kadmin: &K,
+        principals: &[S],
+    ) -> Vec<(String, Result<String>)> {
+        principals
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+                let result = self.generate().and_then(|password| {
+                    kadmin.principal_change_password(
+                        name,
+                        &password,
+                        #[cfg(any(mit_client, mit_server, heimdal_server))]
+                        None,
+                        #[cfg(any(mit_client, mit_server, heimdal_server))]
+                        self.keysalts.as_ref(),
+                    )?;
+                    Ok(password)
+                });
+                (name.to_owned(), result)
+            })
+            .collect()
+    }
+}
+
+/// Uniformly pick an index lower than `len`
+fn random_index(len: usize) -> Result<usize> {
+    let len = len as u32;
+    // Reject values from the incomplete last range to avoid modulo bias
+    let zone = u32::MAX - (u32::MAX % len);
+    loop {
+        let value = getrandom::u32().map_err(Error::RandomGeneration)?;
+        if value < zone {
+            return Ok((value % len) as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(password: &str) -> usize {
+        CLASSES
+            .iter()
+            .filter(|class| password.bytes().any(|c| class.contains(&c)))
+            .count()
+    }
+
+    #[test]
+    fn generate() -> Result<()> {
+        let password = PasswordGenerator::new().generate()?;
+        assert_eq!(password.len(), DEFAULT_LENGTH);
+        assert_eq!(classes(&password), 4);
+        assert_ne!(password, PasswordGenerator::new().generate()?);
+        Ok(())
+    }
+
+    #[test]
+    fn generate_min_classes() -> Result<()> {
+        for min_classes in 1..=5 {
+            let generator = PasswordGenerator::new().length(5).min_classes(min_classes);
+            for _ in 0..100 {
+                let password = generator.generate()?;
+                assert!(classes(&password) >= min_classes);
+                assert_eq!(classes(&password) == 5, min_classes == 5);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn generate_short_length() -> Result<()> {
+        let password = PasswordGenerator::new()
+            .length(2)
+            .min_classes(3)
+            .generate()?;
+        assert_eq!(password.len(), 3);
+        assert_eq!(classes(&password), 3);
+        Ok(())
+    }
+}
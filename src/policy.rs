@@ -1,20 +1,26 @@
 //! kadm5 policy
 use std::{
+    collections::HashMap,
     ffi::{CString, c_long, c_void},
+    fs,
+    path::Path,
     ptr::null_mut,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use getset::{CopyGetters, Getters};
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 
+#[cfg(any(mit_client, mit_server))]
+use crate::dump::mit::DumpPolicy;
 use crate::{
     KeySalts,
     context::Context,
     conv::{c_string_to_string, delta_to_dur, dur_to_delta},
-    error::Result,
+    error::{Error, PasswordQualityError, PasswordQualityRule, Result},
     kadmin::KAdminImpl,
+    privileges::Privileges,
     sys::{cfg_match, library_match},
     tl_data::{TlData, TlDataRaw},
 };
@@ -25,6 +31,7 @@ use crate::{
 #[derive(Clone, Debug, Default, Getters, CopyGetters)]
 #[getset(get_copy = "pub")]
 #[cfg_attr(feature = "python", pyclass(from_py_object, get_all))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct Policy {
     /// The policy name
     #[getset(skip)]
@@ -41,7 +48,8 @@ pub struct Policy {
     /// Number of past keys kept for a principal. May not be filled if used with other database
     /// modules such as the MIT krb5 LDAP KDC database module
     password_history_num: i64,
-    /// How many principals use this policy. Not filled for at least MIT krb5
+    /// How many principals use this policy. Not filled for at least MIT krb5, see
+    /// [`KAdminImpl::policy_usage`] instead
     policy_refcnt: i64,
     #[cfg(any(mit_client, mit_server))]
     /// Number of authentication failures before the principal is locked. Authentication failures
@@ -178,6 +186,38 @@ impl Policy {
         &self.tl_data
     }
 
+    /// Construct the [`Policy`] kadm5 would create from `builder`, without contacting any server
+    pub(crate) fn from_builder(builder: &PolicyBuilder) -> Self {
+        let mut policy = Self {
+            name: builder.name.clone(),
+            ..Default::default()
+        };
+        builder.apply_to(&mut policy);
+        policy
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Construct a [`Policy`] from an MIT dump record
+    pub(crate) fn from_mit_dump(record: &DumpPolicy) -> Self {
+        Self {
+            name: record.name.clone(),
+            password_min_life: record.password_min_life,
+            password_max_life: record.password_max_life,
+            password_min_length: record.password_min_length,
+            password_min_classes: record.password_min_classes,
+            password_history_num: record.password_history_num,
+            policy_refcnt: record.policy_refcnt,
+            password_max_fail: record.password_max_fail,
+            password_failcount_interval: record.password_failcount_interval,
+            password_lockout_duration: record.password_lockout_duration,
+            attributes: record.attributes,
+            max_life: record.max_life,
+            max_renewable_life: record.max_renewable_life,
+            allowed_keysalts: None,
+            tl_data: record.tl_data.clone(),
+        }
+    }
+
     /// Construct a new [`PolicyBuilder`] for a policy with `name`
     ///
     /// ```no_run
@@ -223,9 +263,136 @@ impl Policy {
     ///
     /// The [`Policy`] object is not consumed by this method, but after deletion, it shouldn't be
     /// used for modifying, as the policy may not exist anymore
-    pub fn delete<K: KAdminImpl>(&self, kadmin: &K) -> Result<()> {
+    pub fn delete<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<()> {
         kadmin.delete_policy(&self.name)
     }
+
+    /// Check a password against this policy without contacting the server
+    ///
+    /// The checks follow the ones done by MIT kadmind, in the same order:
+    ///
+    /// * the password must not be empty, and must be at least
+    ///   [`password_min_length`][`Self::password_min_length`] bytes long
+    /// * it must contain at least [`password_min_classes`][`Self::password_min_classes`] character
+    ///   classes
+    /// * it must not be in `dict_file`, if provided. See
+    ///   [`Params::dict_file`][`crate::params::Params::dict_file`] for the format
+    /// * it must not match the realm or any component of `principal_name`, if provided
+    ///
+    /// Comparisons with the dictionary and the principal name are case-insensitive. Password
+    /// history and minimum life can only be checked by the server.
+    ///
+    /// Returns [`Error::PasswordQuality`] with a `code` of `0` if the password is rejected
+    ///
+    /// ```no_run
+    /// # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant};
+    /// # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+    /// #     .with_ccache(None, None)
+    /// #     .unwrap();
+    /// let policy = kadmin.get_policy("default").unwrap().unwrap();
+    /// assert!(
+    ///     policy
+    ///         .validate_password("user", Some("user@EXAMPLE.ORG"), None)
+    ///         .is_err()
+    /// );
+    /// ```
+    pub fn validate_password(
+        &self,
+        password: &str,
+        principal_name: Option<&str>,
+        dict_file: Option<&Path>,
+    ) -> Result<()> {
+        let reject = |rule, message: &str| {
+            Err(Error::PasswordQuality(Box::new(
+                PasswordQualityError::new(rule, 0, message.to_owned()).with_policy(self),
+            )))
+        };
+
+        if password.is_empty() {
+            return reject(
+                PasswordQualityRule::MinLength,
+                "Empty passwords are not allowed",
+            );
+        }
+        if (password.len() as i64) < self.password_min_length {
+            return reject(PasswordQualityRule::MinLength, "Password is too short");
+        }
+        if password_classes(password) < self.password_min_classes {
+            return reject(
+                PasswordQualityRule::MinClasses,
+                "Password does not contain enough character classes",
+            );
+        }
+        if let Some(dict_file) = dict_file {
+            let dict = fs::read_to_string(dict_file)?;
+            if dict
+                .lines()
+                .map(str::trim)
+                .any(|word| !word.is_empty() && word.eq_ignore_ascii_case(password))
+            {
+                return reject(
+                    PasswordQualityRule::Dictionary,
+                    "Password is in the password dictionary",
+                );
+            }
+        }
+        if let Some(principal_name) = principal_name {
+            if principal_name_components(principal_name)
+                .iter()
+                .any(|component| component.eq_ignore_ascii_case(password))
+            {
+                return reject(
+                    PasswordQualityRule::Dictionary,
+                    "Password may not match principal name",
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Count the character classes in `password`: lower case, upper case, numbers, punctuation, and
+/// whitespace/unprintable characters
+fn password_classes(password: &str) -> i64 {
+    let (mut lower, mut upper, mut digit, mut punct, mut other) = (0, 0, 0, 0, 0);
+    for c in password.chars() {
+        if c.is_ascii_digit() {
+            digit = 1;
+        } else if c.is_ascii_lowercase() {
+            lower = 1;
+        } else if c.is_ascii_uppercase() {
+            upper = 1;
+        } else if c.is_ascii_punctuation() {
+            punct = 1;
+        } else {
+            other = 1;
+        }
+    }
+    lower + upper + digit + punct + other
+}
+
+/// Split a principal name into its components and realm, handling backslash escapes
+fn principal_name_components(name: &str) -> Vec<String> {
+    let mut components = vec![String::new()];
+    let mut in_realm = false;
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    components.last_mut().unwrap().push(escaped);
+                }
+            }
+            '/' if !in_realm => components.push(String::new()),
+            '@' if !in_realm => {
+                in_realm = true;
+                components.push(String::new());
+            }
+            c => components.last_mut().unwrap().push(c),
+        }
+    }
+    components.retain(|component| !component.is_empty());
+    components
 }
 
 macro_rules! policy_doer_struct {
@@ -265,6 +432,7 @@ macro_rules! policy_doer_struct {
             pub(crate) allowed_keysalts: Option<Option<KeySalts>>,
             #[cfg(any(mit_client, mit_server))]
             pub(crate) tl_data: Option<TlData>,
+            pub(crate) check_privileges: bool,
             $($manual_fields)*
         }
     }
@@ -449,6 +617,62 @@ macro_rules! policy_doer_impl {
             self
         }
 
+        /// Check that the session has the privileges needed by this operation before running it,
+        /// and fail with [`Error::InsufficientPrivileges`] otherwise. Defaults to `false`
+        ///
+        /// This retrieves the privileges with
+        /// [`KAdminImpl::get_privileges`][`crate::kadmin::KAdminImpl::get_privileges`] every time
+        pub fn check_privileges(mut self, check_privileges: bool) -> Self {
+            self.check_privileges = check_privileges;
+            self
+        }
+
+        /// Set the fields set on this builder on `policy`, like kadm5 would
+        pub(crate) fn apply_to(&self, policy: &mut Policy) {
+            if let Some(password_min_life) = self.password_min_life {
+                policy.password_min_life = password_min_life;
+            }
+            if let Some(password_max_life) = self.password_max_life {
+                policy.password_max_life = password_max_life;
+            }
+            if let Some(password_min_length) = self.password_min_length {
+                policy.password_min_length = password_min_length as i64;
+            }
+            if let Some(password_min_classes) = self.password_min_classes {
+                policy.password_min_classes = password_min_classes as i64;
+            }
+            if let Some(password_history_num) = self.password_history_num {
+                policy.password_history_num = password_history_num as i64;
+            }
+            #[cfg(any(mit_client, mit_server))]
+            {
+                if let Some(password_max_fail) = self.password_max_fail {
+                    policy.password_max_fail = password_max_fail;
+                }
+                if let Some(password_failcount_interval) = self.password_failcount_interval {
+                    policy.password_failcount_interval = password_failcount_interval;
+                }
+                if let Some(password_lockout_duration) = self.password_lockout_duration {
+                    policy.password_lockout_duration = password_lockout_duration;
+                }
+                if let Some(attributes) = self.attributes {
+                    policy.attributes = attributes;
+                }
+                if let Some(max_life) = self.max_life {
+                    policy.max_life = max_life;
+                }
+                if let Some(max_renewable_life) = self.max_renewable_life {
+                    policy.max_renewable_life = max_renewable_life;
+                }
+                if let Some(allowed_keysalts) = &self.allowed_keysalts {
+                    policy.allowed_keysalts = allowed_keysalts.clone();
+                }
+                if let Some(tl_data) = &self.tl_data {
+                    policy.tl_data.merge(tl_data);
+                }
+            }
+        }
+
         /// Create a [`_kadm5_policy_ent_t`] from this builder
         pub(crate) fn make_entry<'a>(&self, context: &'a Context) -> Result<(PolicyEntryRaw<'a>, i64)> {
             let mask = library_match!(
@@ -576,6 +800,49 @@ impl PolicyBuilder {
         }
     }
 
+    /// Construct a new [`PolicyBuilder`] with the same values as `policy`
+    ///
+    /// MIT-specific fields are only copied if they differ from their defaults, so that the
+    /// resulting builder can be used with any [API version][`crate::kadmin::KAdminApiVersion`]
+    /// the source policy could be retrieved with.
+    pub fn from_policy(policy: &Policy) -> Self {
+        #[allow(unused_mut)]
+        let mut builder = Self::new(policy.name())
+            .password_min_life(policy.password_min_life)
+            .password_max_life(policy.password_max_life)
+            .password_min_length(policy.password_min_length as c_long)
+            .password_min_classes(policy.password_min_classes as c_long)
+            .password_history_num(policy.password_history_num as c_long);
+        #[cfg(any(mit_client, mit_server))]
+        {
+            if policy.password_max_fail != 0 {
+                builder = builder.password_max_fail(policy.password_max_fail);
+            }
+            if policy.password_failcount_interval.is_some() {
+                builder = builder.password_failcount_interval(policy.password_failcount_interval);
+            }
+            if policy.password_lockout_duration.is_some() {
+                builder = builder.password_lockout_duration(policy.password_lockout_duration);
+            }
+            if policy.attributes != 0 {
+                builder = builder.attributes(policy.attributes);
+            }
+            if policy.max_life.is_some() {
+                builder = builder.max_life(policy.max_life);
+            }
+            if policy.max_renewable_life.is_some() {
+                builder = builder.max_renewable_life(policy.max_renewable_life);
+            }
+            if policy.allowed_keysalts.is_some() {
+                builder = builder.allowed_keysalts(policy.allowed_keysalts.clone());
+            }
+            if !policy.tl_data.entries.is_empty() {
+                builder = builder.tl_data(policy.tl_data.clone());
+            }
+        }
+        builder
+    }
+
     /// Set the name of the policy
     pub fn name(mut self, name: &str) -> Self {
         self.name = name.to_owned();
@@ -583,7 +850,12 @@ impl PolicyBuilder {
     }
 
     /// Create the policy
-    pub fn create<K: KAdminImpl>(&self, kadmin: &K) -> Result<Policy> {
+    ///
+    /// Requires [`Privileges::ADD`] if [privileges are checked][`Self::check_privileges`]
+    pub fn create<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<Policy> {
+        if self.check_privileges {
+            kadmin.require_privileges(Privileges::ADD)?;
+        }
         kadmin.add_policy(self)?;
         Ok(kadmin.get_policy(&self.name)?.unwrap())
     }
@@ -618,15 +890,132 @@ impl PolicyModifier {
         }
     }
 
+    /// Construct a [`PolicyModifier`] restoring the fields set on this one to their values in
+    /// `snapshot`
+    pub(crate) fn restoring(&self, snapshot: &Policy) -> Self {
+        let mut modifier = Self::from_policy(snapshot);
+        if self.password_min_life.is_some() {
+            modifier = modifier.password_min_life(snapshot.password_min_life);
+        }
+        if self.password_max_life.is_some() {
+            modifier = modifier.password_max_life(snapshot.password_max_life);
+        }
+        if self.password_min_length.is_some() {
+            modifier = modifier.password_min_length(snapshot.password_min_length as c_long);
+        }
+        if self.password_min_classes.is_some() {
+            modifier = modifier.password_min_classes(snapshot.password_min_classes as c_long);
+        }
+        if self.password_history_num.is_some() {
+            modifier = modifier.password_history_num(snapshot.password_history_num as c_long);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        {
+            if self.password_max_fail.is_some() {
+                modifier = modifier.password_max_fail(snapshot.password_max_fail);
+            }
+            if self.password_failcount_interval.is_some() {
+                modifier =
+                    modifier.password_failcount_interval(snapshot.password_failcount_interval);
+            }
+            if self.password_lockout_duration.is_some() {
+                modifier = modifier.password_lockout_duration(snapshot.password_lockout_duration);
+            }
+            if self.attributes.is_some() {
+                modifier = modifier.attributes(snapshot.attributes);
+            }
+            if self.max_life.is_some() {
+                modifier = modifier.max_life(snapshot.max_life);
+            }
+            if self.max_renewable_life.is_some() {
+                modifier = modifier.max_renewable_life(snapshot.max_renewable_life);
+            }
+            if self.allowed_keysalts.is_some() {
+                modifier = modifier.allowed_keysalts(snapshot.allowed_keysalts.clone());
+            }
+            if self.tl_data.is_some() {
+                modifier = modifier.tl_data(snapshot.tl_data.clone());
+            }
+        }
+        modifier
+    }
+
     /// Modify the policy
     ///
     /// A new up-to-date instance of [`Policy`] is returned, but the old one is still available
-    pub fn modify<K: KAdminImpl>(&self, kadmin: &K) -> Result<Policy> {
+    ///
+    /// Requires [`Privileges::MODIFY`] if [privileges are checked][`Self::check_privileges`]
+    pub fn modify<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<Policy> {
+        if self.check_privileges {
+            kadmin.require_privileges(Privileges::MODIFY)?;
+        }
         kadmin.modify_policy(self)?;
         Ok(kadmin.get_policy(&self.name)?.unwrap())
     }
 }
 
+/// Principals referencing each policy, as computed by
+/// [`KAdminImpl::policy_usage`][`crate::kadmin::KAdminImpl::policy_usage`]
+///
+/// Computing it requires retrieving every principal of the realm, so it can be kept as a cache and
+/// refreshed when needed
+#[derive(Clone, Debug)]
+pub struct PolicyUsage {
+    usage: HashMap<String, Vec<String>>,
+    computed_at: Instant,
+}
+
+impl PolicyUsage {
+    pub(crate) fn new(usage: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            usage,
+            computed_at: Instant::now(),
+        }
+    }
+
+    /// Names of the principals referencing each policy. Policies that aren't referenced by any
+    /// principal are not included
+    pub fn usage(&self) -> &HashMap<String, Vec<String>> {
+        &self.usage
+    }
+
+    /// Names of the principals referencing `policy`
+    pub fn principals_using(&self, policy: &str) -> &[String] {
+        self.usage.get(policy).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether any principal references `policy`
+    pub fn is_in_use(&self, policy: &str) -> bool {
+        !self.principals_using(policy).is_empty()
+    }
+
+    /// Time elapsed since the usage was computed
+    pub fn age(&self) -> Duration {
+        self.computed_at.elapsed()
+    }
+
+    /// Compute the usage again
+    pub fn refresh<K: KAdminImpl + ?Sized>(&mut self, kadmin: &K) -> Result<()> {
+        *self = kadmin.policy_usage()?;
+        Ok(())
+    }
+
+    /// Compute the usage again if it is older than `max_age`
+    ///
+    /// Returns whether it was refreshed
+    pub fn refresh_if_older<K: KAdminImpl + ?Sized>(
+        &mut self,
+        kadmin: &K,
+        max_age: Duration,
+    ) -> Result<bool> {
+        if self.age() < max_age {
+            return Ok(false);
+        }
+        self.refresh(kadmin)?;
+        Ok(true)
+    }
+}
+
 pub(crate) struct PolicyEntryRaw<'a> {
     pub(crate) raw: *const c_void,
     context: &'a Context,
@@ -647,3 +1036,95 @@ impl Drop for PolicyEntryRaw<'_> {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn policy(min_length: i64, min_classes: i64) -> Policy {
+        Policy {
+            name: "test".to_owned(),
+            password_min_length: min_length,
+            password_min_classes: min_classes,
+            ..Default::default()
+        }
+    }
+
+    fn rejected_rule(result: Result<()>) -> Option<PasswordQualityRule> {
+        match result {
+            Err(Error::PasswordQuality(details)) => Some(details.rule),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn validate_password_length() {
+        let policy = policy(8, 1);
+        assert_eq!(
+            rejected_rule(policy.validate_password("", None, None)),
+            Some(PasswordQualityRule::MinLength)
+        );
+        let Err(Error::PasswordQuality(details)) = policy.validate_password("short", None, None)
+        else {
+            panic!("expected a password quality error");
+        };
+        assert_eq!(details.rule, PasswordQualityRule::MinLength);
+        assert_eq!(details.policy.as_deref(), Some("test"));
+        assert_eq!(details.min_length, Some(8));
+        assert!(policy.validate_password("longenough", None, None).is_ok());
+    }
+
+    #[test]
+    fn validate_password_classes() {
+        let policy = policy(0, 3);
+        assert_eq!(password_classes("abc"), 1);
+        assert_eq!(password_classes("aB1! é"), 5);
+        assert_eq!(
+            rejected_rule(policy.validate_password("abcDEF", None, None)),
+            Some(PasswordQualityRule::MinClasses)
+        );
+        assert!(policy.validate_password("abcDEF1", None, None).is_ok());
+    }
+
+    #[test]
+    fn validate_password_principal_name() {
+        let policy = policy(0, 0);
+        assert_eq!(
+            principal_name_components("host/a\\/b@EXAMPLE.ORG"),
+            vec!["host", "a/b", "EXAMPLE.ORG"]
+        );
+        for password in ["Alice", "admin", "example.org"] {
+            assert_eq!(
+                rejected_rule(policy.validate_password(
+                    password,
+                    Some("alice/admin@EXAMPLE.ORG"),
+                    None
+                )),
+                Some(PasswordQualityRule::Dictionary)
+            );
+        }
+        assert!(
+            policy
+                .validate_password("alice-admin", Some("alice/admin@EXAMPLE.ORG"), None)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn validate_password_dict_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("kadmin-rs-dict-{}", std::process::id()));
+        let mut file = fs::File::create(&path)?;
+        writeln!(file, "password\nletmein\n")?;
+        drop(file);
+
+        let policy = policy(0, 0);
+        let result = policy.validate_password("LetMeIn", None, Some(&path));
+        let ok = policy.validate_password("hunter2", None, Some(&path));
+        fs::remove_file(&path)?;
+        assert_eq!(rejected_rule(result), Some(PasswordQualityRule::Dictionary));
+        assert!(ok.is_ok());
+        Ok(())
+    }
+}
@@ -16,17 +16,108 @@ use crate::{
     context::Context,
     conv::{c_string_to_string, delta_to_dur, dt_to_ts, dur_to_delta, ts_to_dt, unparse_name},
     db_args::DbArgs,
-    error::{Result, krb5_error_code_escape_hatch},
+    dump::{heimdal, mit},
+    error::{Error, Result, krb5_error_code_escape_hatch},
     kadmin::KAdminImpl,
-    keysalt::KeySalts,
+    keysalt::{Key, KeySalts},
+    privileges::Privileges,
     sys::{self, KAdm5Variant, cfg_match, library_match},
     tl_data::{TlData, TlDataEntry, TlDataRaw},
 };
 
+/// Principal attribute names, as used by kadmin
+///
+/// Each item is the name, whether the attribute is set when the name is disabled, then the MIT and
+/// Heimdal attribute values, 0 when there is none.
+const ATTRIBUTE_NAMES: [(&str, bool, i32, i32); 18] = [
+    ("allow_postdated", true, 0x0000_0001, 0x0000_0001),
+    ("allow_forwardable", true, 0x0000_0002, 0x0000_0002),
+    ("allow_tgs_req", true, 0x0000_0004, 0x0000_0004),
+    ("allow_renewable", true, 0x0000_0008, 0x0000_0008),
+    ("allow_proxiable", true, 0x0000_0010, 0x0000_0010),
+    ("allow_dup_skey", true, 0x0000_0020, 0x0000_0020),
+    ("allow_tix", true, 0x0000_0040, 0x0000_0040),
+    ("requires_preauth", false, 0x0000_0080, 0x0000_0080),
+    ("requires_hwauth", false, 0x0000_0100, 0x0000_0100),
+    ("needchange", false, 0x0000_0200, 0x0000_0200),
+    ("allow_svr", true, 0x0000_1000, 0x0000_1000),
+    ("password_changing_service", false, 0x0000_2000, 0x0000_2000),
+    ("support_desmd5", false, 0x0000_4000, 0x0000_4000),
+    ("ok_as_delegate", false, 0x0010_0000, 0x0001_0000),
+    ("ok_to_auth_as_delegate", false, 0x0020_0000, 0x0002_0000),
+    ("no_auth_data_required", false, 0x0040_0000, 0x0100_0000),
+    ("lockdown_keys", false, 0x0080_0000, 0),
+    ("allow_digest", false, 0, 0x0008_0000),
+];
+
+/// Apply kadmin-style attribute flags to `attributes`, with the values used by `variant`
+///
+/// Flags are separated by commas or whitespace, and are prefixed by `+` (the default) to enable
+/// them or by `-` to disable them, for instance `+requires_preauth,-allow_tix`. Names are those
+/// of MIT kadmin, and are case-insensitive with `-` and `_` used interchangeably.
+///
+/// ```
+/// # use kadmin::{KAdm5Variant, principal::apply_attribute_flags};
+/// # #[cfg(mit_client)]
+/// assert_eq!(
+///     apply_attribute_flags(0, "+requires_preauth -allow_tix", KAdm5Variant::MitClient).unwrap(),
+///     0x80 | 0x40
+/// );
+/// ```
+pub fn apply_attribute_flags(attributes: i32, flags: &str, variant: KAdm5Variant) -> Result<i32> {
+    let mut attributes = attributes;
+    for flag in flags
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|flag| !flag.is_empty())
+    {
+        let (enable, name) = if let Some(name) = flag.strip_prefix('+') {
+            (true, name)
+        } else if let Some(name) = flag.strip_prefix('-') {
+            (false, name)
+        } else {
+            (true, flag)
+        };
+        let name = name.to_ascii_lowercase().replace('-', "_");
+        let (_, inverted, mit, heimdal) = ATTRIBUTE_NAMES
+            .iter()
+            .find(|(known, ..)| *known == name)
+            .ok_or_else(|| Error::InvalidAttribute(flag.to_owned()))?;
+        let mask = if variant.is_mit() { *mit } else { *heimdal };
+        if mask == 0 {
+            return Err(Error::InvalidAttribute(flag.to_owned()));
+        }
+        if enable != *inverted {
+            attributes |= mask;
+        } else {
+            attributes &= !mask;
+        }
+    }
+    Ok(attributes)
+}
+
+/// Describe `attributes` as kadmin-style flags, the reverse of [`apply_attribute_flags`]
+///
+/// Only the flags differing from the defaults are listed, like `["+requires_preauth",
+/// "-allow_tix"]`, so applying them to 0 gives `attributes` back. Bits without a name for
+/// `variant` are left out.
+pub fn attribute_flags(attributes: i32, variant: KAdm5Variant) -> Vec<String> {
+    ATTRIBUTE_NAMES
+        .iter()
+        .filter_map(|(name, inverted, mit, heimdal)| {
+            let mask = if variant.is_mit() { *mit } else { *heimdal };
+            if mask == 0 || attributes & mask == 0 {
+                return None;
+            }
+            Some(format!("{}{name}", if *inverted { '-' } else { '+' }))
+        })
+        .collect()
+}
+
 /// A kadm5 principal
 #[derive(Clone, Debug, Default, Getters, CopyGetters)]
 #[getset(get_copy = "pub")]
 #[cfg_attr(feature = "python", pyclass(from_py_object, get_all))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct Principal {
     /// The principal name
     #[getset(skip)]
@@ -120,6 +211,84 @@ impl Principal {
         &self.tl_data
     }
 
+    /// Construct the [`Principal`] kadm5 would create from `builder`, without contacting any server
+    pub(crate) fn from_builder(builder: &PrincipalBuilder) -> Self {
+        let mut principal = Self {
+            name: builder.name.clone(),
+            kvno: builder.kvno.unwrap_or(1),
+            last_password_change: Some(Utc::now()),
+            ..Default::default()
+        };
+        builder.apply_to(&mut principal);
+        principal
+    }
+
+    /// Construct a [`Principal`] from an MIT dump record
+    pub(crate) fn from_mit_dump(record: &mit::DumpPrincipal) -> Self {
+        Self {
+            name: record.name.clone(),
+            expire_time: record.expire_time,
+            last_password_change: record.last_password_change(),
+            password_expiration: record.password_expiration,
+            max_life: record.max_life,
+            modified_by: record.modified_by(),
+            modified_at: record.modified_at(),
+            attributes: record.attributes,
+            kvno: record.kvno(),
+            mkvno: record.mkvno().unwrap_or_default(),
+            policy: record.policy(),
+            aux_attributes: record.aux_attributes() as c_long,
+            max_renewable_life: record.max_renewable_life,
+            last_success: record.last_success,
+            last_failed: record.last_failed,
+            fail_auth_count: record.fail_auth_count,
+            tl_data: record.tl_data.clone(),
+        }
+    }
+
+    /// Construct a [`Principal`] from a Heimdal dump record
+    pub(crate) fn from_heimdal_dump(record: &heimdal::DumpPrincipal) -> Self {
+        let modified = record.modified.as_ref().unwrap_or(&record.created);
+        Self {
+            name: record.name.clone(),
+            expire_time: record.valid_end,
+            last_password_change: record.last_password_change(),
+            password_expiration: record.password_end,
+            max_life: record.max_life,
+            modified_by: modified.principal.clone(),
+            modified_at: Some(modified.time),
+            attributes: record.heimdal_attributes(),
+            kvno: record.kvno,
+            mkvno: record.mkvno().unwrap_or_default(),
+            policy: record.policy(),
+            max_renewable_life: record.max_renewable_life,
+            ..Default::default()
+        }
+    }
+
+    /// Copy of this principal with a new name
+    pub(crate) fn renamed(&self, name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..self.clone()
+        }
+    }
+
+    /// Record a key change, like a password change or a new random key would
+    pub(crate) fn rotate_keys(&mut self) {
+        self.kvno += 1;
+        self.last_password_change = Some(Utc::now());
+        self.modified_at = self.last_password_change;
+    }
+
+    /// Record keys being set, taking the newest key version number among them
+    pub(crate) fn set_keys(&mut self, keys: &[Key]) {
+        self.rotate_keys();
+        if let Some(kvno) = keys.iter().map(|key| key.kvno).max() {
+            self.kvno = kvno;
+        }
+    }
+
     /// Construct a new [`PrincipalBuilder`] for a principal with `name`
     ///
     /// ```no_run
@@ -159,7 +328,7 @@ impl Principal {
     ///
     /// The [`Principal`] object is not consumed by this method, but after deletion, it shouldn't be
     /// used for modifying, as the principal may not exist anymore
-    pub fn delete<K: KAdminImpl>(&self, kadmin: &K) -> Result<()> {
+    pub fn delete<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<()> {
         kadmin.delete_principal(&self.name)
     }
 
@@ -172,7 +341,7 @@ impl Principal {
     ///   Heimdal client, this option is silently ignored.
     ///
     /// Note that principal data will have changed after this, so you may need to refresh it
-    pub fn change_password<K: KAdminImpl>(
+    pub fn change_password<K: KAdminImpl + ?Sized>(
         &self,
         kadmin: &K,
         password: &str,
@@ -198,7 +367,7 @@ impl Principal {
     ///   Heimdal client, this option is silently ignored.
     ///
     /// Note that principal data will have changed after this, so you may need to refresh it
-    pub fn randkey<K: KAdminImpl>(
+    pub fn randkey<K: KAdminImpl + ?Sized>(
         &self,
         kadmin: &K,
         #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
@@ -218,7 +387,7 @@ impl Principal {
     /// successfully authenticate
     ///
     /// Note that principal data will have changed after this, so you may need to refresh it
-    pub fn unlock<K: KAdminImpl>(&self, kadmin: &K) -> Result<()> {
+    pub fn unlock<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<()> {
         let tl_entry_data_type = match kadmin.variant() {
             #[cfg(mit_client)]
             KAdm5Variant::MitClient => Some(sys::mit_client::KRB5_TL_LAST_ADMIN_UNLOCK as i16),
@@ -246,7 +415,10 @@ impl Principal {
     /// Retrieve string attributes on this principal
     ///
     /// Only available for MIT variants
-    pub fn get_strings<K: KAdminImpl>(&self, kadmin: &K) -> Result<HashMap<String, String>> {
+    pub fn get_strings<K: KAdminImpl + ?Sized>(
+        &self,
+        kadmin: &K,
+    ) -> Result<HashMap<String, String>> {
         kadmin.principal_get_strings(&self.name)
     }
 
@@ -256,7 +428,7 @@ impl Principal {
     /// Set `value` to None to remove the string
     ///
     /// Only available for MIT variants
-    pub fn set_string<K: KAdminImpl>(
+    pub fn set_string<K: KAdminImpl + ?Sized>(
         &self,
         kadmin: &K,
         key: &str,
@@ -291,7 +463,9 @@ macro_rules! principal_doer_struct {
             pub(crate) max_renewable_life: Option<Option<Duration>>,
             pub(crate) fail_auth_count: Option<u32>,
             pub(crate) tl_data: Option<TlData>,
+            pub(crate) kvno: Option<u32>,
             pub(crate) db_args: Option<DbArgs>,
+            pub(crate) check_privileges: bool,
             $($manual_fields)*
         }
     }
@@ -433,6 +607,13 @@ macro_rules! principal_doer_impl {
             self
         }
 
+        /// Set the key version number
+        pub fn kvno(mut self, kvno: u32) -> Self {
+            self.kvno = Some(kvno);
+            set_mask!(self, KADM5_KVNO);
+            self
+        }
+
         /// Add new TL-data
         pub fn tl_data(mut self, tl_data: TlData) -> Self {
             self.tl_data = Some(tl_data);
@@ -448,6 +629,51 @@ macro_rules! principal_doer_impl {
             self
         }
 
+        /// Check that the session has the privileges needed by this operation before running it,
+        /// and fail with [`Error::InsufficientPrivileges`] otherwise. Defaults to `false`
+        ///
+        /// This retrieves the privileges with
+        /// [`KAdminImpl::get_privileges`][`crate::kadmin::KAdminImpl::get_privileges`] every time
+        pub fn check_privileges(mut self, check_privileges: bool) -> Self {
+            self.check_privileges = check_privileges;
+            self
+        }
+
+        /// Set the fields set on this builder on `principal`, like kadm5 would
+        pub(crate) fn apply_to(&self, principal: &mut Principal) {
+            if let Some(expire_time) = self.expire_time {
+                principal.expire_time = expire_time;
+            }
+            if let Some(password_expiration) = self.password_expiration {
+                principal.password_expiration = password_expiration;
+            }
+            if let Some(max_life) = self.max_life {
+                principal.max_life = max_life;
+            }
+            if let Some(attributes) = self.attributes {
+                principal.attributes = attributes;
+            }
+            if let Some(policy) = &self.policy {
+                principal.policy = policy.clone();
+            }
+            if let Some(aux_attributes) = self.aux_attributes {
+                principal.aux_attributes = aux_attributes;
+            }
+            if let Some(max_renewable_life) = self.max_renewable_life {
+                principal.max_renewable_life = max_renewable_life;
+            }
+            if let Some(fail_auth_count) = self.fail_auth_count {
+                principal.fail_auth_count = fail_auth_count;
+            }
+            if let Some(tl_data) = &self.tl_data {
+                principal.tl_data.merge(tl_data);
+            }
+            if let Some(kvno) = self.kvno {
+                principal.kvno = kvno;
+            }
+            principal.modified_at = Some(Utc::now());
+        }
+
         /// Create a `_kadm5_principal_ent_t` from this builder
         pub(crate) fn make_entry<'a>(&self, context: &'a Context) -> Result<(PrincipalEntryRaw<'a>, i64)> {
             let mask = library_match!(
@@ -491,6 +717,9 @@ macro_rules! principal_doer_impl {
                 if let Some(max_renewable_life) = self.max_renewable_life {
                     entry.max_renewable_life = dur_to_delta(max_renewable_life)?.into();
                 }
+                if let Some(kvno) = self.kvno {
+                    entry.kvno = kvno as lib!(krb5_kvno);
+                }
 
                 let tl_data = if let Some(db_args) = &self.db_args {
                     let mut tl_data: TlData = db_args.into();
@@ -556,7 +785,6 @@ principal_doer_struct!(
     /// ```
     #[derive(Clone, Debug, Default)]
     PrincipalBuilder {
-        pub(crate) kvno: Option<u32>,
         pub(crate) key: PrincipalBuilderKey,
         #[cfg(any(mit_client, mit_server, heimdal_server))]
         pub(crate) keysalts: Option<KeySalts>,
@@ -580,13 +808,6 @@ impl PrincipalBuilder {
         self
     }
 
-    /// Set the initial key version number
-    pub fn kvno(mut self, kvno: u32) -> Self {
-        self.kvno = Some(kvno);
-        set_mask!(self, KADM5_KVNO);
-        self
-    }
-
     /// How the principal key should be set
     ///
     /// See [`PrincipalBuilderKey`] for the default value
@@ -610,9 +831,6 @@ impl PrincipalBuilder {
                 Box::from_raw(raw as *mut lib!(_kadm5_principal_ent_t))
             };
 
-            if let Some(kvno) = self.kvno {
-                entry.kvno = kvno as lib!(krb5_kvno);
-            }
             if self.key == PrincipalBuilderKey::OldStyleRandKey {
                 entry.attributes |= lib!(KRB5_KDB_DISALLOW_ALL_TIX) as lib!(krb5_flags);
             }
@@ -622,7 +840,12 @@ impl PrincipalBuilder {
     }
 
     /// Create the principal
-    pub fn create<K: KAdminImpl>(&self, kadmin: &K) -> Result<Principal> {
+    ///
+    /// Requires [`Privileges::ADD`] if [privileges are checked][`Self::check_privileges`]
+    pub fn create<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<Principal> {
+        if self.check_privileges {
+            kadmin.require_privileges(Privileges::ADD)?;
+        }
         kadmin.add_principal(self)?;
         Ok(kadmin.get_principal(&self.name)?.unwrap())
     }
@@ -657,6 +880,43 @@ impl PrincipalModifier {
         }
     }
 
+    /// Construct a [`PrincipalModifier`] restoring the fields set on this one to their values in
+    /// `snapshot`
+    pub(crate) fn restoring(&self, snapshot: &Principal) -> Self {
+        let mut modifier = Self::from_principal(snapshot);
+        if self.expire_time.is_some() {
+            modifier = modifier.expire_time(snapshot.expire_time);
+        }
+        if self.password_expiration.is_some() {
+            modifier = modifier.password_expiration(snapshot.password_expiration);
+        }
+        if self.max_life.is_some() {
+            modifier = modifier.max_life(snapshot.max_life);
+        }
+        if self.attributes.is_some() {
+            modifier = modifier.attributes(snapshot.attributes);
+        }
+        if self.policy.is_some() {
+            modifier = modifier.policy(snapshot.policy());
+        }
+        if self.aux_attributes.is_some() {
+            modifier = modifier.aux_attributes(snapshot.aux_attributes);
+        }
+        if self.max_renewable_life.is_some() {
+            modifier = modifier.max_renewable_life(snapshot.max_renewable_life);
+        }
+        if self.fail_auth_count.is_some() {
+            modifier = modifier.fail_auth_count(snapshot.fail_auth_count);
+        }
+        if self.tl_data.is_some() {
+            modifier = modifier.tl_data(snapshot.tl_data.clone());
+        }
+        if self.kvno.is_some() {
+            modifier = modifier.kvno(snapshot.kvno);
+        }
+        modifier
+    }
+
     fn make_entry_extra(&self, _context: &Context, raw: *mut c_void) -> *const c_void {
         raw.cast_const()
     }
@@ -664,7 +924,12 @@ impl PrincipalModifier {
     /// Modify the principal
     ///
     /// A new up-to-date instance of [`Principal`] is returned, but the old one is still available
-    pub fn modify<K: KAdminImpl>(&self, kadmin: &K) -> Result<Principal> {
+    ///
+    /// Requires [`Privileges::MODIFY`] if [privileges are checked][`Self::check_privileges`]
+    pub fn modify<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<Principal> {
+        if self.check_privileges {
+            kadmin.require_privileges(Privileges::MODIFY)?;
+        }
         kadmin.modify_principal(self)?;
         Ok(kadmin.get_principal(&self.name)?.unwrap())
     }
@@ -692,6 +957,24 @@ pub enum PrincipalBuilderKey {
     OldStyleRandKey,
 }
 
+#[cfg(feature = "replay")]
+// Passwords are never serialized, only the fact that one was provided
+impl serde::Serialize for PrincipalBuilderKey {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let variant = match self {
+            Self::Password(_) => "Password",
+            Self::NoKey => "NoKey",
+            Self::RandKey => "RandKey",
+            Self::ServerRandKey => "ServerRandKey",
+            Self::OldStyleRandKey => "OldStyleRandKey",
+        };
+        serializer.serialize_str(variant)
+    }
+}
+
 pub(crate) struct PrincipalEntryRaw<'a> {
     pub(crate) raw: *const c_void,
     context: &'a Context,
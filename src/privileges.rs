@@ -0,0 +1,223 @@
+//! kadm5 [`Privileges`]
+
+use bitflags::bitflags;
+
+use crate::{
+    error::{Error, Result},
+    sys::{self, KAdm5Variant},
+};
+
+bitflags! {
+    /// Privileges of the current kadm5 session, as returned by
+    /// [`KAdminImpl::get_privileges`][`crate::kadmin::KAdminImpl::get_privileges`]
+    ///
+    /// MIT krb5 only reports [`Self::GET`], [`Self::ADD`], [`Self::MODIFY`] and
+    /// [`Self::DELETE`]. The other privileges are only reported by Heimdal, which grants
+    /// [`Self::SETKEY`] along with [`Self::CHANGEPW`].
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Privileges: u32 {
+        /// Get principals and policies
+        const GET = 1 << 0;
+        /// Add principals and policies
+        const ADD = 1 << 1;
+        /// Modify principals and policies
+        const MODIFY = 1 << 2;
+        /// Delete principals and policies
+        const DELETE = 1 << 3;
+        /// List principals and policies. Heimdal only
+        const LIST = 1 << 4;
+        /// Change passwords of principals. Heimdal only
+        const CHANGEPW = 1 << 5;
+        /// Get the keys of principals. Heimdal only
+        const GET_KEYS = 1 << 6;
+        /// Set the keys of principals. Heimdal only
+        const SETKEY = 1 << 7;
+    }
+}
+
+#[cfg(any(mit_client, mit_server))]
+macro_rules! mit_mapping {
+    ($lib:ident) => {
+        vec![
+            (sys::$lib::KADM5_PRIV_GET, Privileges::GET),
+            (sys::$lib::KADM5_PRIV_ADD, Privileges::ADD),
+            (sys::$lib::KADM5_PRIV_MODIFY, Privileges::MODIFY),
+            (sys::$lib::KADM5_PRIV_DELETE, Privileges::DELETE),
+        ]
+    };
+}
+
+#[cfg(any(heimdal_client, heimdal_server))]
+macro_rules! heimdal_mapping {
+    ($lib:ident) => {
+        vec![
+            (sys::$lib::KADM5_PRIV_GET, Privileges::GET),
+            (sys::$lib::KADM5_PRIV_ADD, Privileges::ADD),
+            (sys::$lib::KADM5_PRIV_MODIFY, Privileges::MODIFY),
+            (sys::$lib::KADM5_PRIV_DELETE, Privileges::DELETE),
+            (sys::$lib::KADM5_PRIV_LIST, Privileges::LIST),
+            (
+                sys::$lib::KADM5_PRIV_CPW,
+                Privileges::CHANGEPW.union(Privileges::SETKEY),
+            ),
+            (sys::$lib::KADM5_PRIV_GET_KEYS, Privileges::GET_KEYS),
+        ]
+    };
+}
+
+impl Privileges {
+    /// Convert the privileges returned by `kadm5_get_privs` for `variant`
+    pub(crate) fn from_raw(variant: KAdm5Variant, raw: i64) -> Self {
+        let mapping = match variant {
+            #[cfg(mit_client)]
+            KAdm5Variant::MitClient => mit_mapping!(mit_client),
+            #[cfg(mit_server)]
+            KAdm5Variant::MitServer => mit_mapping!(mit_server),
+            #[cfg(heimdal_client)]
+            KAdm5Variant::HeimdalClient => heimdal_mapping!(heimdal_client),
+            #[cfg(heimdal_server)]
+            KAdm5Variant::HeimdalServer => heimdal_mapping!(heimdal_server),
+        };
+        mapping
+            .into_iter()
+            .filter(|(bit, _)| raw & i64::from(*bit) != 0)
+            .fold(Self::empty(), |privs, (_, privilege)| privs | privilege)
+    }
+
+    /// Check that all of the `required` privileges are granted
+    ///
+    /// Returns [`Error::InsufficientPrivileges`] with the missing privileges otherwise
+    pub fn require(self, required: Self) -> Result<()> {
+        let missing = required.difference(self);
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InsufficientPrivileges(missing))
+        }
+    }
+
+    /// Whether principals and policies can be retrieved
+    pub fn can_get(self) -> bool {
+        self.contains(Self::GET)
+    }
+
+    /// Whether principals and policies can be added
+    pub fn can_add(self) -> bool {
+        self.contains(Self::ADD)
+    }
+
+    /// Whether principals and policies can be modified
+    pub fn can_modify(self) -> bool {
+        self.contains(Self::MODIFY)
+    }
+
+    /// Whether principals and policies can be deleted
+    pub fn can_delete(self) -> bool {
+        self.contains(Self::DELETE)
+    }
+
+    /// Whether principals and policies can be listed. Always `false` on MIT krb5
+    pub fn can_list(self) -> bool {
+        self.contains(Self::LIST)
+    }
+
+    /// Whether passwords of principals can be changed. Always `false` on MIT krb5
+    pub fn can_change_password(self) -> bool {
+        self.contains(Self::CHANGEPW)
+    }
+
+    /// Whether the keys of principals can be set. Always `false` on MIT krb5
+    pub fn can_set_keys(self) -> bool {
+        self.contains(Self::SETKEY)
+    }
+
+    /// Whether the keys of principals can be retrieved. Always `false` on MIT krb5
+    pub fn can_get_keys(self) -> bool {
+        self.contains(Self::GET_KEYS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(mit_client)]
+    #[test]
+    fn from_raw_mit() {
+        let privs = Privileges::from_raw(KAdm5Variant::MitClient, 0x05);
+        assert_eq!(privs, Privileges::GET | Privileges::MODIFY);
+        assert!(privs.can_modify());
+        assert!(!privs.can_delete());
+    }
+
+    #[cfg(mit_server)]
+    #[test]
+    fn from_raw_mit_all() {
+        // Local MIT kadm5 reports every privilege
+        let privs = Privileges::from_raw(KAdm5Variant::MitServer, !0);
+        assert_eq!(
+            privs,
+            Privileges::GET | Privileges::ADD | Privileges::MODIFY | Privileges::DELETE
+        );
+    }
+
+    #[cfg(heimdal_client)]
+    #[test]
+    fn from_raw_heimdal() {
+        let privs = Privileges::from_raw(KAdm5Variant::HeimdalClient, 0x31);
+        assert_eq!(
+            privs,
+            Privileges::GET | Privileges::LIST | Privileges::CHANGEPW | Privileges::SETKEY
+        );
+        assert!(privs.can_list());
+        assert!(privs.can_change_password());
+        assert!(privs.can_set_keys());
+        assert!(!privs.can_get_keys());
+    }
+
+    #[cfg(mit_server)]
+    #[test]
+    fn check_privileges() -> Result<()> {
+        use crate::{KAdminImpl, MemoryKAdmin, Policy, Principal};
+
+        let kadmin =
+            MemoryKAdmin::new(KAdm5Variant::MitServer, "EXAMPLE.ORG").privileges(Privileges::GET);
+        let err = Principal::builder("alice")
+            .check_privileges(true)
+            .create(&kadmin)
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::InsufficientPrivileges(missing) if missing == Privileges::ADD)
+        );
+        assert!(!kadmin.principal_exists("alice")?);
+
+        let princ = Principal::builder("alice").create(&kadmin)?;
+        let err = princ
+            .modifier()
+            .attributes(0x80)
+            .check_privileges(true)
+            .modify(&kadmin)
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::InsufficientPrivileges(missing) if missing == Privileges::MODIFY)
+        );
+        assert!(
+            Policy::builder("strict")
+                .check_privileges(true)
+                .create(&kadmin)
+                .unwrap_err()
+                .is_insufficient_privilege()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn require() {
+        let privs = Privileges::GET | Privileges::ADD;
+        assert!(privs.require(Privileges::GET).is_ok());
+        assert!(matches!(
+            privs.require(Privileges::ADD | Privileges::DELETE),
+            Err(Error::InsufficientPrivileges(missing)) if missing == Privileges::DELETE
+        ));
+    }
+}
@@ -0,0 +1,995 @@
+//! Parse krb5 profile files (`krb5.conf`, `kdc.conf`) to build [`Params`] and [`DbArgs`]
+//!
+//! This is a pure Rust parser for the profile format shared by MIT krb5 and Heimdal. It supports
+//! sections, subsections, quoted values, comments, and the `include` and `includedir` directives.
+//! Dynamic profile modules (the `module` directive) are not supported.
+//!
+//! ```no_run
+//! let profile = kadmin::Profile::from_file("/etc/krb5kdc/kdc.conf").unwrap();
+//! let params = profile.params("EXAMPLE.ORG").unwrap();
+//! let db_args = profile.db_args("EXAMPLE.ORG").unwrap();
+//! ```
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+#[cfg(any(mit_client, mit_server))]
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+// Principal flags have the same values in both MIT variants
+#[cfg(mit_client)]
+use crate::sys::mit_client as mit;
+#[cfg(all(mit_server, not(mit_client)))]
+use crate::sys::mit_server as mit;
+use crate::{
+    db_args::DbArgs,
+    error::{Error, Result},
+    params::Params,
+};
+
+/// Maximum depth of nested `include` and `includedir` directives
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Realm relations `[kdcdefaults]` provides defaults for
+const KDCDEFAULTS_RELATIONS: &[&str] = &[
+    "host_based_services",
+    "kdc_listen",
+    "kdc_ports",
+    "kdc_tcp_listen",
+    "kdc_tcp_ports",
+    "no_host_referral",
+    "restrict_anonymous_to_tgt",
+    "spake_preauth_kdc_challenge",
+];
+
+/// Node of a profile tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    /// Value of a relation
+    Value(String),
+    /// Subsection, containing relations
+    Section(Vec<(String, Node)>),
+}
+
+/// Parsed krb5 profile
+///
+/// When multiple files are loaded, or the same section appears multiple times, relations are
+/// kept in order of appearance. Lookups return the first matching value, like libkrb5 does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    sections: Vec<(String, Node)>,
+}
+
+impl Profile {
+    /// Load a profile from a file, following its includes
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_files(&[path])
+    }
+
+    /// Load a profile from multiple files, following their includes
+    ///
+    /// Values from earlier files take precedence over those from later files
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut profile = Self::default();
+        for path in paths {
+            profile.load_file(path.as_ref(), 0)?;
+        }
+        Ok(profile)
+    }
+
+    fn load_file(&mut self, path: &Path, depth: usize) -> Result<()> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(Error::ProfileParse {
+                path: path.to_path_buf(),
+                line: 0,
+                message: "too many nested includes".to_owned(),
+            });
+        }
+        let contents = fs::read_to_string(path)?;
+        self.parse(&contents, path, depth)
+    }
+
+    fn load_dir(&mut self, path: &Path, depth: usize) -> Result<()> {
+        let mut files = vec![];
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if valid_include_name(name) && entry.file_type()?.is_file() {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+        for file in files {
+            self.load_file(&file, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Parse `contents` and append its sections to this profile
+    fn parse(&mut self, contents: &str, path: &Path, depth: usize) -> Result<()> {
+        let err = |line: usize, message: &str| Error::ProfileParse {
+            path: path.to_path_buf(),
+            line,
+            message: message.to_owned(),
+        };
+        let resolve = |target: &str| {
+            let target = PathBuf::from(target);
+            match path.parent() {
+                Some(parent) if target.is_relative() => parent.join(target),
+                _ => target,
+            }
+        };
+
+        // Current top-level section
+        let mut section: Option<(String, Vec<(String, Node)>)> = None;
+        // Open subsections, innermost last
+        let mut stack: Vec<(String, Vec<(String, Node)>)> = vec![];
+        // Relation waiting for its opening brace on the next line
+        let mut pending: Option<(String, usize)> = None;
+
+        for (index, raw_line) in contents.lines().enumerate() {
+            let line_number = index + 1;
+
+            if stack.is_empty() && pending.is_none() {
+                if directive(raw_line, "module").is_some() {
+                    return Err(err(line_number, "profile modules are not supported"));
+                }
+                let include = directive(raw_line, "includedir")
+                    .map(|target| (target, true))
+                    .or_else(|| directive(raw_line, "include").map(|target| (target, false)));
+                if let Some((target, is_dir)) = include {
+                    // Keep included sections in order, and resume the current section afterwards
+                    if let Some((name, relations)) = section.take() {
+                        self.sections.push((name.clone(), Node::Section(relations)));
+                        section = Some((name, vec![]));
+                    }
+                    if is_dir {
+                        self.load_dir(&resolve(target), depth)?;
+                    } else {
+                        self.load_file(&resolve(target), depth + 1)?;
+                    }
+                    continue;
+                }
+            }
+
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some((tag, _)) = pending.take() {
+                if line.trim_end_matches('*').trim() != "{" {
+                    return Err(err(line_number, "expected '{'"));
+                }
+                stack.push((tag, vec![]));
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('[') {
+                if !stack.is_empty() {
+                    return Err(err(line_number, "unclosed subsection"));
+                }
+                let Some((name, rest)) = rest.split_once(']') else {
+                    return Err(err(line_number, "missing ']' in section header"));
+                };
+                if !matches!(rest.trim(), "" | "*") {
+                    return Err(err(line_number, "unexpected text after section header"));
+                }
+                if let Some((name, relations)) = section.take() {
+                    self.sections.push((name, Node::Section(relations)));
+                }
+                section = Some((name.trim().to_owned(), vec![]));
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('}') {
+                if !matches!(rest.trim(), "" | "*") {
+                    return Err(err(line_number, "unexpected text after '}'"));
+                }
+                let Some((tag, relations)) = stack.pop() else {
+                    return Err(err(line_number, "unmatched '}'"));
+                };
+                let parent = match stack.last_mut() {
+                    Some((_, relations)) => relations,
+                    None => match &mut section {
+                        Some((_, relations)) => relations,
+                        None => return Err(err(line_number, "unmatched '}'")),
+                    },
+                };
+                parent.push((tag, Node::Section(relations)));
+                continue;
+            }
+
+            let Some((tag, value)) = line.split_once('=') else {
+                return Err(err(line_number, "missing '=' in relation"));
+            };
+            let tag = tag.trim();
+            if tag.is_empty() || tag.contains(char::is_whitespace) {
+                return Err(err(line_number, "invalid relation name"));
+            }
+            let value = value.trim();
+
+            let relations = match stack.last_mut() {
+                Some((_, relations)) => relations,
+                None => match &mut section {
+                    Some((_, relations)) => relations,
+                    None => return Err(err(line_number, "relation outside of a section")),
+                },
+            };
+            if value.is_empty() {
+                pending = Some((tag.to_owned(), line_number));
+            } else if value.trim_end_matches('*').trim() == "{" {
+                stack.push((tag.to_owned(), vec![]));
+            } else if let Some(quoted) = value.strip_prefix('"') {
+                let value = unquote(quoted)
+                    .ok_or_else(|| err(line_number, "unexpected text after quoted value"))?;
+                relations.push((tag.to_owned(), Node::Value(value)));
+            } else {
+                relations.push((tag.to_owned(), Node::Value(value.to_owned())));
+            }
+        }
+
+        if let Some((_, line_number)) = pending {
+            return Err(err(line_number, "expected '{'"));
+        }
+        if !stack.is_empty() {
+            return Err(err(contents.lines().count(), "unclosed subsection"));
+        }
+        if let Some((name, relations)) = section {
+            self.sections.push((name, Node::Section(relations)));
+        }
+        Ok(())
+    }
+
+    /// Get all the nodes matching `path`
+    fn nodes(&self, path: &[&str]) -> Vec<&Node> {
+        let mut current: Vec<&[(String, Node)]> = vec![&self.sections];
+        let mut found = vec![];
+        for (i, name) in path.iter().enumerate() {
+            found = current
+                .iter()
+                .flat_map(|relations| relations.iter())
+                .filter(|(tag, _)| tag == name)
+                .map(|(_, node)| node)
+                .collect();
+            if i + 1 < path.len() {
+                current = found
+                    .iter()
+                    .filter_map(|node| match node {
+                        Node::Section(relations) => Some(relations.as_slice()),
+                        Node::Value(_) => None,
+                    })
+                    .collect();
+            }
+        }
+        found
+    }
+
+    /// Get all the values of the relation at `path`, in order of appearance
+    ///
+    /// ```
+    /// # use kadmin::Profile;
+    /// let profile: Profile = "[realms]\nEXAMPLE.ORG = {\nkdc = kdc1\nkdc = kdc2\n}"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     profile.values(&["realms", "EXAMPLE.ORG", "kdc"]),
+    ///     vec!["kdc1", "kdc2"]
+    /// );
+    /// ```
+    pub fn values(&self, path: &[&str]) -> Vec<&str> {
+        self.nodes(path)
+            .into_iter()
+            .filter_map(|node| match node {
+                Node::Value(value) => Some(value.as_str()),
+                Node::Section(_) => None,
+            })
+            .collect()
+    }
+
+    /// Get the first value of the relation at `path`
+    pub fn value(&self, path: &[&str]) -> Option<&str> {
+        self.values(path).into_iter().next()
+    }
+
+    /// Get the names of the relations in the (sub)sections at `path`, in order of appearance and
+    /// without duplicates
+    pub fn names(&self, path: &[&str]) -> Vec<&str> {
+        let mut names: Vec<&str> = vec![];
+        for node in self.nodes(path) {
+            if let Node::Section(relations) = node {
+                for (tag, _) in relations {
+                    if !names.contains(&tag.as_str()) {
+                        names.push(tag);
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    /// Get the default realm, from `[libdefaults]`
+    pub fn default_realm(&self) -> Option<&str> {
+        self.value(&["libdefaults", "default_realm"])
+    }
+
+    /// Get the realms defined in `[realms]`
+    pub fn realms(&self) -> Vec<&str> {
+        self.names(&["realms"])
+    }
+
+    /// Get a relation for `realm` from `[realms]`, falling back to `[kdcdefaults]` for the
+    /// relations it provides defaults for
+    pub fn realm_value(&self, realm: &str, tag: &str) -> Option<&str> {
+        self.value(&["realms", realm, tag]).or_else(|| {
+            KDCDEFAULTS_RELATIONS
+                .contains(&tag)
+                .then(|| self.value(&["kdcdefaults", tag]))
+                .flatten()
+        })
+    }
+
+    /// Get a relation for `realm` from its database module in `[dbmodules]`, falling back to
+    /// `[dbdefaults]`
+    ///
+    /// The database module is the `database_module` of the realm, or the realm name itself
+    pub fn dbmodule_value(&self, realm: &str, tag: &str) -> Option<&str> {
+        let module = self.realm_value(realm, "database_module").unwrap_or(realm);
+        self.value(&["dbmodules", module, tag])
+            .or_else(|| self.value(&["dbdefaults", tag]))
+    }
+
+    /// Get a relation from the Heimdal `[kdc] database` entry for `realm`
+    ///
+    /// If no entry is specific to `realm`, the entry without a `realm` relation is used
+    fn heimdal_database_value(&self, realm: &str, tag: &str) -> Option<&str> {
+        let databases: Vec<&[(String, Node)]> = self
+            .nodes(&["kdc", "database"])
+            .into_iter()
+            .filter_map(|node| match node {
+                Node::Section(relations) => Some(relations.as_slice()),
+                Node::Value(_) => None,
+            })
+            .collect();
+        let database = databases
+            .iter()
+            .find(|relations| relation(relations, "realm") == Some(realm))
+            .or_else(|| {
+                databases
+                    .iter()
+                    .find(|relations| relation(relations, "realm").is_none())
+            })?;
+        relation(database, tag)
+    }
+
+    /// Name of the database for `realm`
+    fn database_name(&self, realm: &str) -> Option<&str> {
+        self.dbmodule_value(realm, "database_name")
+            .or_else(|| self.value(&["realms", realm, "database_name"]))
+            .or_else(|| self.heimdal_database_value(realm, "dbname"))
+    }
+
+    /// Build [`Params`] for `realm`
+    ///
+    /// Both the MIT `kdc.conf` realm stanzas and the Heimdal `[kdc] database` entries are
+    /// supported. The master key type and supported enctypes are not read, as they require a
+    /// library context to be parsed.
+    pub fn params(&self, realm: &str) -> Result<Params> {
+        let mut params = Params::new().realm(realm);
+
+        if let Some(admin_server) = self.realm_value(realm, "admin_server") {
+            match admin_server.rsplit_once(':') {
+                Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                    params = params
+                        .admin_server(host)
+                        .kadmind_port(parse_int("admin_server", port)?);
+                }
+                _ => params = params.admin_server(admin_server),
+            }
+        }
+        if let Some(port) = self.realm_value(realm, "kadmind_port") {
+            params = params.kadmind_port(parse_int("kadmind_port", port)?);
+        }
+        if let Some(dbname) = self.database_name(realm) {
+            params = params.dbname(dbname);
+        }
+        if let Some(acl_file) = self
+            .realm_value(realm, "acl_file")
+            .or_else(|| self.heimdal_database_value(realm, "acl_file"))
+        {
+            params = params.acl_file(acl_file);
+        }
+        if let Some(stash_file) = self
+            .realm_value(realm, "key_stash_file")
+            .or_else(|| self.heimdal_database_value(realm, "mkey_file"))
+        {
+            params = params.stash_file(stash_file);
+        }
+        if let Some(ports) = self
+            .realm_value(realm, "kdc_ports")
+            .or_else(|| self.value(&["kdc", "ports"]))
+        {
+            params = params.kdc_ports(ports);
+        }
+        if let Some(ports) = self.realm_value(realm, "kdc_tcp_ports") {
+            params = params.kdc_tcp_ports(ports);
+        }
+
+        #[cfg(any(heimdal_client, heimdal_server))]
+        if let Some(admin_server) = self.realm_value(realm, "readonly_admin_server") {
+            match admin_server.rsplit_once(':') {
+                Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                    params = params
+                        .readonly_admin_server(host)
+                        .readonly_kadmind_port(parse_int("readonly_admin_server", port)?);
+                }
+                _ => params = params.readonly_admin_server(admin_server),
+            }
+        }
+
+        #[cfg(any(mit_client, mit_server))]
+        {
+            if let Some(port) = self.realm_value(realm, "kpasswd_port") {
+                params = params.kpasswd_port(parse_int("kpasswd_port", port)?);
+            }
+            if let Some(dict_file) = self.realm_value(realm, "dict_file") {
+                params = params.dict_file(dict_file);
+            }
+            if let Some(mkey_name) = self.realm_value(realm, "master_key_name") {
+                params = params.mkey_name(mkey_name);
+            }
+            if let Some(max_life) = self.realm_value(realm, "max_life") {
+                params = params.max_life(Some(duration_value("max_life", max_life)?));
+            }
+            if let Some(max_rlife) = self.realm_value(realm, "max_renewable_life") {
+                params = params.max_rlife(Some(duration_value("max_renewable_life", max_rlife)?));
+            }
+            if let Some(expiration) = self.realm_value(realm, "default_principal_expiration") {
+                params = params.expiration(Some(parse_timestamp(
+                    "default_principal_expiration",
+                    expiration,
+                )?));
+            }
+            if let Some(flags) = self.realm_value(realm, "default_principal_flags") {
+                params = params.flags(parse_flags("default_principal_flags", flags)?);
+            }
+            if let Some(enabled) = self.realm_value(realm, "iprop_enable") {
+                params = params.iprop_enabled(parse_bool("iprop_enable", enabled)?);
+            }
+            if let Some(port) = self.realm_value(realm, "iprop_port") {
+                params = params.iprop_port(parse_int("iprop_port", port)?);
+            }
+            if let Some(logfile) = self.realm_value(realm, "iprop_logfile") {
+                params = params.iprop_logfile(logfile);
+            }
+            if let Some(ulogsize) = self
+                .realm_value(realm, "iprop_ulogsize")
+                .or_else(|| self.realm_value(realm, "iprop_master_ulogsize"))
+            {
+                params = params.iprop_ulogsize(parse_int("iprop_ulogsize", ulogsize)?);
+            }
+            if let Some(poll) = self
+                .realm_value(realm, "iprop_replica_poll")
+                .or_else(|| self.realm_value(realm, "iprop_slave_poll"))
+            {
+                params = params.iprop_poll_time(duration_value("iprop_replica_poll", poll)?);
+            }
+            if let Some(timeout) = self.realm_value(realm, "iprop_resync_timeout") {
+                params =
+                    params.iprop_resync_timeout(duration_value("iprop_resync_timeout", timeout)?);
+            }
+            if let Some(listen) = self.realm_value(realm, "kadmind_listen") {
+                params = params.kadmind_listen(listen);
+            }
+            if let Some(listen) = self.realm_value(realm, "kpasswd_listen") {
+                params = params.kpasswd_listen(listen);
+            }
+            if let Some(listen) = self.realm_value(realm, "iprop_listen") {
+                params = params.iprop_listen(listen);
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Build [`DbArgs`] for `realm` from its database module
+    ///
+    /// The database name and the LDAP settings used by kadmind are forwarded
+    pub fn db_args(&self, realm: &str) -> Result<DbArgs> {
+        let mut builder = DbArgs::builder();
+        if let Some(dbname) = self.database_name(realm) {
+            builder = builder.arg("dbname", Some(dbname));
+        }
+        if let Some(servers) = self.dbmodule_value(realm, "ldap_servers") {
+            for server in servers.split_whitespace() {
+                builder = builder.arg("host", Some(server));
+            }
+        }
+        for (relation, arg) in [
+            ("ldap_kadmind_dn", "binddn"),
+            ("ldap_conns_per_server", "nconns"),
+            ("ldap_kadmind_sasl_mech", "sasl_mech"),
+            ("ldap_kadmind_sasl_authcid", "sasl_authcid"),
+            ("ldap_kadmind_sasl_authzid", "sasl_authzid"),
+            ("ldap_kadmind_sasl_realm", "sasl_realm"),
+        ] {
+            if let Some(value) = self.dbmodule_value(realm, relation) {
+                builder = builder.arg(arg, Some(value));
+            }
+        }
+        builder.build()
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    /// Parse a profile from a string. Relative includes are resolved from the current directory
+    fn from_str(s: &str) -> Result<Self> {
+        let mut profile = Self::default();
+        profile.parse(s, Path::new(""), 0)?;
+        Ok(profile)
+    }
+}
+
+/// Get the value of the relation `name` directly in `relations`
+fn relation<'a>(relations: &'a [(String, Node)], name: &str) -> Option<&'a str> {
+    relations.iter().find_map(|(tag, node)| match node {
+        Node::Value(value) if tag == name => Some(value.as_str()),
+        _ => None,
+    })
+}
+
+/// Get the argument of `directive` if `line` is such a directive
+///
+/// Directives must start at the beginning of the line
+fn directive<'a>(line: &'a str, directive: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(directive)?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+/// Whether a file in an `includedir` directory should be loaded
+///
+/// Same rules as libkrb5: names made only of alphanumeric characters, dashes and underscores, or
+/// ending in `.conf`, and not starting with a dot
+fn valid_include_name(name: &str) -> bool {
+    if name.starts_with('.') {
+        return false;
+    }
+    name.ends_with(".conf")
+        || name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parse a quoted value, with the opening quote already stripped
+///
+/// Returns `None` if anything but whitespace follows the closing quote
+fn unquote(quoted: &str) -> Option<String> {
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('b') => value.push('\u{8}'),
+                Some(c) => value.push(c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    chars.as_str().trim().is_empty().then_some(value)
+}
+
+fn invalid_value(relation: &str, value: &str) -> Error {
+    Error::ProfileValue {
+        relation: relation.to_owned(),
+        value: value.to_owned(),
+    }
+}
+
+fn parse_int<T: FromStr>(relation: &str, value: &str) -> Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid_value(relation, value))
+}
+
+#[cfg(any(mit_client, mit_server))]
+fn parse_bool(relation: &str, value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" | "true" | "t" | "1" | "on" => Ok(true),
+        "n" | "no" | "false" | "nil" | "0" | "off" => Ok(false),
+        _ => Err(invalid_value(relation, value)),
+    }
+}
+
+/// Parse a duration in one of the formats accepted by `krb5_string_to_deltat`: a number of
+/// seconds, `[Nd][Nh][Nm][Ns]`, or `[Nd ]h:m[:s]`
+///
+/// Units may also be spelled out (`2 days 4 hours`), and `w` counts weeks. Returns `None` if
+/// `value` isn't a valid duration or doesn't fit in a `u64` number of seconds.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    // Add `number` times `unit` seconds to `secs`, failing on overflow
+    let add = |secs: u64, number: u64, unit: u64| {
+        number
+            .checked_mul(unit)
+            .and_then(|number| secs.checked_add(number))
+    };
+
+    let mut secs: u64 = 0;
+    let mut rest = value;
+    let mut seen_unit = false;
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(digits_end);
+        if tail.starts_with(':') {
+            // `h:m[:s]` ends the duration
+            let parts = rest
+                .split(':')
+                .map(|part| part.parse::<u64>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let (h, m, s) = match parts.as_slice() {
+                [h, m] => (*h, *m, 0),
+                [h, m, s] => (*h, *m, *s),
+                _ => return None,
+            };
+            secs = add(secs, h, 60 * 60)?;
+            secs = add(secs, m, 60)?;
+            secs = add(secs, s, 1)?;
+            return Some(Duration::from_secs(secs));
+        }
+        let number: u64 = number.parse().ok()?;
+        let tail = tail.trim_start();
+        let unit_end = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let unit = match tail[..unit_end].to_ascii_lowercase().as_str() {
+            "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+            "d" | "day" | "days" => 24 * 60 * 60,
+            "h" | "hour" | "hours" => 60 * 60,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            _ => return None,
+        };
+        secs = add(secs, number, unit)?;
+        seen_unit = true;
+        rest = tail[unit_end..].trim_start();
+    }
+    seen_unit.then(|| Duration::from_secs(secs))
+}
+
+/// [`parse_duration`], failing with a [`Error::ProfileValue`] for `relation`
+#[cfg(any(mit_client, mit_server))]
+fn duration_value(relation: &str, value: &str) -> Result<Duration> {
+    parse_duration(value).ok_or_else(|| invalid_value(relation, value))
+}
+
+/// Parse an absolute time, either as `YYYYMMDDhhmmss`, `YYYY-MM-DD[Thh:mm:ss]` or RFC 3339
+#[cfg(any(mit_client, mit_server))]
+fn parse_timestamp(relation: &str, value: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    for format in ["%Y%m%d%H%M%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(dt.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+        .ok_or_else(|| invalid_value(relation, value))
+}
+
+/// Parse principal flags, as used by `default_principal_flags`
+///
+/// Flags are separated by commas or whitespace, and can be prefixed by `+` (the default) to
+/// enable them or by `-` to disable them
+#[cfg(any(mit_client, mit_server))]
+fn parse_flags(relation: &str, value: &str) -> Result<i32> {
+    let mut flags: i32 = 0;
+    for flag in value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|flag| !flag.is_empty())
+    {
+        let (enable, name) = if let Some(name) = flag.strip_prefix('+') {
+            (true, name)
+        } else if let Some(name) = flag.strip_prefix('-') {
+            (false, name)
+        } else {
+            (true, flag)
+        };
+        let name = name.to_ascii_lowercase().replace('_', "-");
+        // Flags that disallow something when set are inverted
+        let (mask, inverted) = flag_mask(&name).ok_or_else(|| invalid_value(relation, value))?;
+        if enable != inverted {
+            flags |= mask;
+        } else {
+            flags &= !mask;
+        }
+    }
+    Ok(flags)
+}
+
+/// Get the attribute mask for a flag name, and whether setting the flag clears the mask
+#[cfg(any(mit_client, mit_server))]
+fn flag_mask(name: &str) -> Option<(i32, bool)> {
+    Some(match name {
+        "allow-tickets" => (mit::KRB5_KDB_DISALLOW_ALL_TIX as i32, true),
+        "dup-skey" => (mit::KRB5_KDB_DISALLOW_DUP_SKEY as i32, true),
+        "forwardable" => (mit::KRB5_KDB_DISALLOW_FORWARDABLE as i32, true),
+        "postdateable" => (mit::KRB5_KDB_DISALLOW_POSTDATED as i32, true),
+        "proxiable" => (mit::KRB5_KDB_DISALLOW_PROXIABLE as i32, true),
+        "renewable" => (mit::KRB5_KDB_DISALLOW_RENEWABLE as i32, true),
+        "service" => (mit::KRB5_KDB_DISALLOW_SVR as i32, true),
+        "tgt-based" => (mit::KRB5_KDB_DISALLOW_TGT_BASED as i32, true),
+        "hwauth" => (mit::KRB5_KDB_REQUIRES_HW_AUTH as i32, false),
+        "preauth" => (mit::KRB5_KDB_REQUIRES_PRE_AUTH as i32, false),
+        "pwchange" => (mit::KRB5_KDB_REQUIRES_PWCHANGE as i32, false),
+        "pwservice" => (mit::KRB5_KDB_PWCHANGE_SERVICE as i32, false),
+        "ok-as-delegate" => (mit::KRB5_KDB_OK_AS_DELEGATE as i32, false),
+        "ok-to-auth-as-delegate" => (mit::KRB5_KDB_OK_TO_AUTH_AS_DELEGATE as i32, false),
+        "no-auth-data-required" => (mit::KRB5_KDB_NO_AUTH_DATA_REQUIRED as i32, false),
+        "lockdown-keys" => (mit::KRB5_KDB_LOCKDOWN_KEYS as i32, false),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tl_data::TlData;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/profile")
+            .join(name)
+    }
+
+    #[test]
+    fn parse_mit_kdc_conf() -> Result<()> {
+        let profile = Profile::from_file(fixture("kdc.conf"))?;
+        assert_eq!(profile.realms(), vec!["EXAMPLE.ORG", "TEST.EXAMPLE.ORG"]);
+        assert_eq!(
+            profile.value(&["realms", "EXAMPLE.ORG", "acl_file"]),
+            Some("/var/lib/krb5kdc/kadm5.acl")
+        );
+        assert_eq!(
+            profile.values(&["realms", "EXAMPLE.ORG", "supported_enctypes"]),
+            vec!["aes256-cts-hmac-sha1-96:normal aes128-cts-hmac-sha1-96:normal"]
+        );
+        assert_eq!(profile.value(&["realms", "EXAMPLE.ORG", "missing"]), None);
+        assert_eq!(
+            profile.realm_value("TEST.EXAMPLE.ORG", "kdc_ports"),
+            Some("88")
+        );
+        assert_eq!(
+            profile.dbmodule_value("EXAMPLE.ORG", "database_name"),
+            Some("/var/lib/krb5kdc/principal")
+        );
+        assert_eq!(
+            profile.dbmodule_value("TEST.EXAMPLE.ORG", "ldap_kadmind_dn"),
+            Some("cn=kadmind,dc=example,dc=org")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn kdcdefaults() -> Result<()> {
+        let profile: Profile = concat!(
+            "[kdcdefaults]\n kdc_ports = 88\n acl_file = /tmp/acl\n",
+            "[realms]\n EXAMPLE.ORG = {\n }\n",
+        )
+        .parse()?;
+        assert_eq!(profile.realm_value("EXAMPLE.ORG", "kdc_ports"), Some("88"));
+        // Not a relation [kdcdefaults] provides defaults for
+        assert_eq!(profile.realm_value("EXAMPLE.ORG", "acl_file"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_includes() -> Result<()> {
+        let profile = Profile::from_file(fixture("krb5.conf"))?;
+        assert_eq!(profile.default_realm(), Some("EXAMPLE.ORG"));
+        // From `include`
+        assert_eq!(
+            profile.value(&["realms", "EXAMPLE.ORG", "admin_server"]),
+            Some("kdc.example.org:7749")
+        );
+        // From `includedir`, in order, skipping files with invalid names
+        assert_eq!(
+            profile.values(&["realms", "EXAMPLE.ORG", "kdc"]),
+            vec!["kdc1.example.org", "kdc2.example.org"]
+        );
+        assert_eq!(profile.value(&["libdefaults", "ignored"]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_quoted() -> Result<()> {
+        let profile: Profile = "[section]\n  tag = \"a \\\"quoted\\\"\\tvalue\"  \n".parse()?;
+        assert_eq!(
+            profile.value(&["section", "tag"]),
+            Some("a \"quoted\"\tvalue")
+        );
+        assert!(matches!(
+            "[section]\n  tag = \"value\" trailing\n".parse::<Profile>(),
+            Err(Error::ProfileParse { line: 2, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_brace_on_next_line() -> Result<()> {
+        let profile: Profile =
+            "[realms]\nEXAMPLE.ORG =\n{\n kdc = kdc.example.org\n}*\n".parse()?;
+        assert_eq!(
+            profile.value(&["realms", "EXAMPLE.ORG", "kdc"]),
+            Some("kdc.example.org")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (contents, line) in [
+            ("tag = value\n", 1),
+            ("[section\n", 1),
+            ("[section]\ntag value\n", 2),
+            ("[section]\ntag = {\n", 2),
+            ("[section]\n}\n", 2),
+            ("[section]\nmodule /usr/lib/module.so:residual\n", 2),
+        ] {
+            match contents.parse::<Profile>() {
+                Err(Error::ProfileParse { line: l, .. }) => assert_eq!(l, line, "{contents}"),
+                other => panic!("unexpected result for {contents:?}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn heimdal_database() -> Result<()> {
+        let profile = Profile::from_file(fixture("heimdal-kdc.conf"))?;
+        assert_eq!(
+            profile.database_name("EXAMPLE.ORG"),
+            Some("/var/heimdal/example")
+        );
+        assert_eq!(
+            profile.heimdal_database_value("EXAMPLE.ORG", "mkey_file"),
+            Some("/var/heimdal/example.mkey")
+        );
+        // Falls back to the entry without a realm
+        assert_eq!(
+            profile.database_name("OTHER.ORG"),
+            Some("/var/heimdal/heimdal")
+        );
+        assert_eq!(
+            profile.heimdal_database_value("OTHER.ORG", "acl_file"),
+            Some("/var/heimdal/kadmind.acl")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("2 days 4 hours"), parse_duration("2d4h"));
+        assert_eq!(parse_duration("1w"), Some(Duration::from_secs(7 * 86400)));
+        assert_eq!(
+            parse_duration("1 day 02:00:00"),
+            Some(Duration::from_secs(86400 + 7200))
+        );
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("1:2:3:4"), None);
+        assert_eq!(parse_duration("99999999999999999999w"), None);
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    #[test]
+    fn parse_values() -> Result<()> {
+        assert_eq!(duration_value("r", "3600")?, Duration::from_secs(3600));
+        assert_eq!(duration_value("r", "10h")?, Duration::from_secs(36000));
+        assert_eq!(
+            duration_value("r", "7d 0h 30m 5s")?,
+            Duration::from_secs(7 * 86400 + 1805)
+        );
+        assert_eq!(duration_value("r", "1:30")?, Duration::from_secs(5400));
+        assert_eq!(
+            duration_value("r", "1d 2:00:01")?,
+            Duration::from_secs(86400 + 7201)
+        );
+        assert!(duration_value("r", "soon").is_err());
+        assert!(duration_value("r", "1000000000000000000d").is_err());
+        assert!(duration_value("r", "18446744073709551615s 1s").is_err());
+        assert!(duration_value("r", "5124095576030432:00").is_err());
+        assert!(parse_bool("r", "Yes")?);
+        assert!(!parse_bool("r", "off")?);
+        assert_eq!(
+            parse_timestamp("r", "20300101000000")?,
+            parse_timestamp("r", "2030-01-01")?
+        );
+        assert_eq!(parse_flags("r", "+preauth,-forwardable")?, 0x82);
+        assert_eq!(parse_flags("r", "preauth -preauth allow_tickets")?, 0);
+        assert!(parse_flags("r", "+bogus").is_err());
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    #[test]
+    fn build_params_mit() -> Result<()> {
+        let profile = Profile::from_files(&[fixture("krb5.conf"), fixture("kdc.conf")])?;
+        let params = profile.params("EXAMPLE.ORG")?;
+        let expected = Params::new()
+            .realm("EXAMPLE.ORG")
+            .admin_server("kdc.example.org")
+            .kadmind_port(7749)
+            .dbname("/var/lib/krb5kdc/principal")
+            .acl_file("/var/lib/krb5kdc/kadm5.acl")
+            .stash_file("/var/lib/krb5kdc/stash")
+            .kdc_ports("88")
+            .kdc_tcp_ports("88")
+            .kpasswd_port(7464)
+            .dict_file("/var/lib/krb5kdc/dict")
+            .mkey_name("K/M")
+            .max_life(Some(Duration::from_secs(10 * 3600)))
+            .max_rlife(Some(Duration::from_secs(7 * 86400)))
+            .flags(0x80)
+            .iprop_enabled(true)
+            .iprop_port(7754)
+            .iprop_logfile("/var/lib/krb5kdc/principal.ulog")
+            .iprop_ulogsize(2000)
+            .iprop_poll_time(Duration::from_secs(120));
+        assert_eq!(params, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn build_params_heimdal() -> Result<()> {
+        let profile = Profile::from_file(fixture("heimdal-kdc.conf"))?;
+        let params = profile.params("EXAMPLE.ORG")?;
+        let expected = Params::new()
+            .realm("EXAMPLE.ORG")
+            .dbname("/var/heimdal/example")
+            .acl_file("/var/heimdal/example.acl")
+            .stash_file("/var/heimdal/example.mkey")
+            .kdc_ports("88 750");
+        #[cfg(any(heimdal_client, heimdal_server))]
+        let expected = expected
+            .readonly_admin_server("kadmin-ro.example.org")
+            .readonly_kadmind_port(7749);
+        assert_eq!(params, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn build_db_args() -> Result<()> {
+        let profile = Profile::from_file(fixture("kdc.conf"))?;
+        let db_args = profile.db_args("EXAMPLE.ORG")?;
+        let expected = DbArgs::builder()
+            .arg("dbname", Some("/var/lib/krb5kdc/principal"))
+            .build()?;
+        assert_eq!(TlData::from(&db_args), TlData::from(&expected));
+        Ok(())
+    }
+}
@@ -3,8 +3,11 @@
 use std::{
     collections::{HashMap, HashSet},
     ffi::c_int,
+    path::PathBuf,
+    time::Duration,
 };
 
+use chrono::{DateTime, Utc};
 use pyo3::{
     prelude::*,
     types::{PyDict, PyString, PyTuple},
@@ -59,6 +62,28 @@ impl Params {
         acl_file=None,
         dict_file=None,
         stash_file=None,
+        mkey_name=None,
+        mkey_from_kbd=None,
+        enctype=None,
+        max_life=None,
+        max_rlife=None,
+        expiration=None,
+        flags=None,
+        keysalts=None,
+        kvno=None,
+        iprop_enabled=None,
+        iprop_ulogsize=None,
+        iprop_poll_time=None,
+        iprop_logfile=None,
+        iprop_port=None,
+        iprop_resync_timeout=None,
+        kadmind_listen=None,
+        kpasswd_listen=None,
+        iprop_listen=None,
+        kdc_ports=None,
+        kdc_tcp_ports=None,
+        readonly_admin_server=None,
+        readonly_kadmind_port=None,
     ))]
     #[allow(clippy::too_many_arguments)]
     fn py_new(
@@ -70,6 +95,28 @@ impl Params {
         acl_file: Option<&str>,
         dict_file: Option<&str>,
         stash_file: Option<&str>,
+        mkey_name: Option<&str>,
+        mkey_from_kbd: Option<bool>,
+        enctype: Option<EncryptionType>,
+        max_life: Option<Duration>,
+        max_rlife: Option<Duration>,
+        expiration: Option<DateTime<Utc>>,
+        flags: Option<i32>,
+        keysalts: Option<KeySalts>,
+        kvno: Option<u32>,
+        iprop_enabled: Option<bool>,
+        iprop_ulogsize: Option<u32>,
+        iprop_poll_time: Option<Duration>,
+        iprop_logfile: Option<&str>,
+        iprop_port: Option<c_int>,
+        iprop_resync_timeout: Option<Duration>,
+        kadmind_listen: Option<&str>,
+        kpasswd_listen: Option<&str>,
+        iprop_listen: Option<&str>,
+        kdc_ports: Option<&str>,
+        kdc_tcp_ports: Option<&str>,
+        readonly_admin_server: Option<&str>,
+        readonly_kadmind_port: Option<c_int>,
     ) -> Self {
         let mut params = Params::new();
         if let Some(realm) = realm {
@@ -98,6 +145,92 @@ impl Params {
         if let Some(stash_file) = stash_file {
             params = params.stash_file(stash_file);
         }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(mkey_name) = mkey_name {
+            params = params.mkey_name(mkey_name);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(mkey_from_kbd) = mkey_from_kbd {
+            params = params.mkey_from_kbd(mkey_from_kbd);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(enctype) = enctype {
+            params = params.enctype(enctype);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(max_life) = max_life {
+            params = params.max_life(Some(max_life));
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(max_rlife) = max_rlife {
+            params = params.max_rlife(Some(max_rlife));
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(expiration) = expiration {
+            params = params.expiration(Some(expiration));
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(flags) = flags {
+            params = params.flags(flags);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(keysalts) = keysalts {
+            params = params.keysalts(&keysalts);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(kvno) = kvno {
+            params = params.kvno(kvno);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_enabled) = iprop_enabled {
+            params = params.iprop_enabled(iprop_enabled);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_ulogsize) = iprop_ulogsize {
+            params = params.iprop_ulogsize(iprop_ulogsize);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_poll_time) = iprop_poll_time {
+            params = params.iprop_poll_time(iprop_poll_time);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_logfile) = iprop_logfile {
+            params = params.iprop_logfile(iprop_logfile);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_port) = iprop_port {
+            params = params.iprop_port(iprop_port);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_resync_timeout) = iprop_resync_timeout {
+            params = params.iprop_resync_timeout(iprop_resync_timeout);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(kadmind_listen) = kadmind_listen {
+            params = params.kadmind_listen(kadmind_listen);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(kpasswd_listen) = kpasswd_listen {
+            params = params.kpasswd_listen(kpasswd_listen);
+        }
+        #[cfg(any(mit_client, mit_server))]
+        if let Some(iprop_listen) = iprop_listen {
+            params = params.iprop_listen(iprop_listen);
+        }
+        if let Some(kdc_ports) = kdc_ports {
+            params = params.kdc_ports(kdc_ports);
+        }
+        if let Some(kdc_tcp_ports) = kdc_tcp_ports {
+            params = params.kdc_tcp_ports(kdc_tcp_ports);
+        }
+        #[cfg(any(heimdal_client, heimdal_server))]
+        if let Some(readonly_admin_server) = readonly_admin_server {
+            params = params.readonly_admin_server(readonly_admin_server);
+        }
+        #[cfg(any(heimdal_client, heimdal_server))]
+        if let Some(readonly_kadmind_port) = readonly_kadmind_port {
+            params = params.readonly_kadmind_port(readonly_kadmind_port);
+        }
         params
     }
 }
@@ -201,6 +334,7 @@ impl KAdmin {
         db_args: Option<DbArgs>,
         api_version: Option<KAdminApiVersion>,
         library_path: Option<&str>,
+        timeout: Option<Duration>,
     ) -> KAdminBuilder {
         let mut builder = KAdminBuilder::new(variant);
         if let Some(params) = params {
@@ -215,6 +349,9 @@ impl KAdmin {
         if let Some(library_path) = library_path {
             builder = builder.library_path(library_path);
         }
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
         builder
     }
 }
@@ -411,6 +548,30 @@ impl KAdmin {
         self.delete_policy(name)
     }
 
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    #[pyo3(name = "rename_policy")]
+    fn py_rename_policy(&self, old_name: &str, new_name: &str) -> Result<()> {
+        self.rename_policy(old_name, new_name)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    #[pyo3(name = "delete_policy_if_unused")]
+    fn py_delete_policy_if_unused(&self, name: &str) -> Result<()> {
+        self.delete_policy_if_unused(name)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    #[pyo3(name = "policy_usage")]
+    fn py_policy_usage(&self) -> Result<HashMap<String, Vec<String>>> {
+        Ok(self.policy_usage()?.usage().clone())
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    #[pyo3(name = "principals_using_policy")]
+    fn py_principals_using_policy(&self, name: &str) -> Result<Vec<String>> {
+        self.principals_using_policy(name)
+    }
+
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     #[pyo3(name = "get_policy")]
     fn py_get_policy(&self, name: &str) -> Result<Option<Policy>> {
@@ -431,11 +592,16 @@ impl KAdmin {
 
     #[pyo3(name = "get_privileges")]
     fn py_get_privileges(&self) -> Result<i64> {
-        self.get_privileges()
+        Ok(self.get_privileges()?.bits().into())
+    }
+
+    #[pyo3(name = "with_timeout", signature = (timeout))]
+    fn py_with_timeout(&self, timeout: Option<Duration>) -> Self {
+        self.with_timeout(timeout)
     }
 
     #[staticmethod]
-    #[pyo3(name = "with_password", signature = (variant, client_name, password, params=None, db_args=None, api_version=None, library_path=None))]
+    #[pyo3(name = "with_password", signature = (variant, client_name, password, params=None, db_args=None, api_version=None, library_path=None, timeout=None))]
     fn py_with_password(
         variant: KAdm5Variant,
         client_name: &str,
@@ -444,13 +610,14 @@ impl KAdmin {
         db_args: Option<DbArgs>,
         api_version: Option<KAdminApiVersion>,
         library_path: Option<&str>,
+        timeout: Option<Duration>,
     ) -> Result<Self> {
-        Self::py_get_builder(variant, params, db_args, api_version, library_path)
+        Self::py_get_builder(variant, params, db_args, api_version, library_path, timeout)
             .with_password(client_name, password)
     }
 
     #[staticmethod]
-    #[pyo3(name = "with_keytab", signature = (variant, client_name=None, keytab=None, params=None, db_args=None, api_version=None, library_path=None))]
+    #[pyo3(name = "with_keytab", signature = (variant, client_name=None, keytab=None, params=None, db_args=None, api_version=None, library_path=None, timeout=None))]
     fn py_with_keytab(
         variant: KAdm5Variant,
         client_name: Option<&str>,
@@ -459,13 +626,14 @@ impl KAdmin {
         db_args: Option<DbArgs>,
         api_version: Option<KAdminApiVersion>,
         library_path: Option<&str>,
+        timeout: Option<Duration>,
     ) -> Result<Self> {
-        Self::py_get_builder(variant, params, db_args, api_version, library_path)
+        Self::py_get_builder(variant, params, db_args, api_version, library_path, timeout)
             .with_keytab(client_name, keytab)
     }
 
     #[staticmethod]
-    #[pyo3(name = "with_ccache", signature = (variant, client_name=None, ccache_name=None, params=None, db_args=None, api_version=None, library_path=None))]
+    #[pyo3(name = "with_ccache", signature = (variant, client_name=None, ccache_name=None, params=None, db_args=None, api_version=None, library_path=None, timeout=None))]
     fn py_with_ccache(
         variant: KAdm5Variant,
         client_name: Option<&str>,
@@ -474,13 +642,14 @@ impl KAdmin {
         db_args: Option<DbArgs>,
         api_version: Option<KAdminApiVersion>,
         library_path: Option<&str>,
+        timeout: Option<Duration>,
     ) -> Result<Self> {
-        Self::py_get_builder(variant, params, db_args, api_version, library_path)
+        Self::py_get_builder(variant, params, db_args, api_version, library_path, timeout)
             .with_ccache(client_name, ccache_name)
     }
 
     #[staticmethod]
-    #[pyo3(name = "with_anonymous", signature = (variant, client_name, params=None, db_args=None, api_version=None, library_path=None))]
+    #[pyo3(name = "with_anonymous", signature = (variant, client_name, params=None, db_args=None, api_version=None, library_path=None, timeout=None))]
     fn py_with_anonymous(
         variant: KAdm5Variant,
         client_name: &str,
@@ -488,22 +657,25 @@ impl KAdmin {
         db_args: Option<DbArgs>,
         api_version: Option<KAdminApiVersion>,
         library_path: Option<&str>,
+        timeout: Option<Duration>,
     ) -> Result<Self> {
-        Self::py_get_builder(variant, params, db_args, api_version, library_path)
+        Self::py_get_builder(variant, params, db_args, api_version, library_path, timeout)
             .with_anonymous(client_name)
     }
 
     #[cfg(any(mit_server, heimdal_server))]
     #[staticmethod]
-    #[pyo3(name = "with_local", signature = (variant, params=None, db_args=None, api_version=None, library_path=None))]
+    #[pyo3(name = "with_local", signature = (variant, params=None, db_args=None, api_version=None, library_path=None, timeout=None))]
     fn py_with_local(
         variant: KAdm5Variant,
         params: Option<Params>,
         db_args: Option<DbArgs>,
         api_version: Option<KAdminApiVersion>,
         library_path: Option<&str>,
+        timeout: Option<Duration>,
     ) -> Result<Self> {
-        Self::py_get_builder(variant, params, db_args, api_version, library_path).with_local()
+        Self::py_get_builder(variant, params, db_args, api_version, library_path, timeout)
+            .with_local()
     }
 }
 
@@ -544,6 +716,9 @@ impl Principal {
             if let Some(db_args) = kwargs.get_item("db_args")? {
                 modifier = modifier.db_args(db_args.extract()?);
             }
+            if let Some(kvno) = kwargs.get_item("kvno")? {
+                modifier = modifier.kvno(kvno.extract()?);
+            }
             Ok(modifier.modify(kadmin)?)
         } else {
             Ok(self.clone())
@@ -698,6 +873,16 @@ impl Policy {
     fn py_delete(&self, kadmin: &KAdmin) -> Result<()> {
         self.delete(kadmin)
     }
+
+    #[pyo3(name = "validate_password", signature = (password, principal_name=None, dict_file=None))]
+    fn py_validate_password(
+        &self,
+        password: &str,
+        principal_name: Option<&str>,
+        dict_file: Option<PathBuf>,
+    ) -> Result<()> {
+        self.validate_password(password, principal_name, dict_file.as_deref())
+    }
 }
 
 /// python-kadmin-rs exceptions
@@ -733,6 +918,7 @@ mod exceptions {
         m.add("StringConversion", m.py().get_type::<StringConversion>())?;
         m.add("ThreadSendError", m.py().get_type::<ThreadSendError>())?;
         m.add("ThreadRecvError", m.py().get_type::<ThreadRecvError>())?;
+        m.add("Timeout", m.py().get_type::<Timeout>())?;
         m.add(
             "TimestampConversion",
             m.py().get_type::<TimestampConversion>(),
@@ -745,9 +931,49 @@ mod exceptions {
             "DurationConversion",
             m.py().get_type::<DurationConversion>(),
         )?;
+        m.add(
+            "KeySaltsConversion",
+            m.py().get_type::<KeySaltsConversion>(),
+        )?;
+        m.add("MkvnoConversion", m.py().get_type::<MkvnoConversion>())?;
+        m.add(
+            "AuxAttributesConversion",
+            m.py().get_type::<AuxAttributesConversion>(),
+        )?;
         m.add("LockError", m.py().get_type::<LockError>())?;
         m.add("LibraryLoadError", m.py().get_type::<LibraryLoadError>())?;
         m.add("LibraryMismatch", m.py().get_type::<LibraryMismatch>())?;
+        m.add(
+            "InsufficientPrivileges",
+            m.py().get_type::<InsufficientPrivileges>(),
+        )?;
+        m.add(
+            "PasswordQualityError",
+            m.py().get_type::<PasswordQualityError>(),
+        )?;
+        m.add("BatchFailed", m.py().get_type::<BatchFailed>())?;
+        m.add("DryRunKeys", m.py().get_type::<DryRunKeys>())?;
+        #[cfg(feature = "reconcile")]
+        m.add(
+            "InvalidDesiredState",
+            m.py().get_type::<InvalidDesiredState>(),
+        )?;
+        #[cfg(feature = "replay")]
+        m.add("InvalidRecording", m.py().get_type::<InvalidRecording>())?;
+        #[cfg(feature = "replay")]
+        m.add("ReplayDivergence", m.py().get_type::<ReplayDivergence>())?;
+        #[cfg(feature = "replay")]
+        m.add("Recorded", m.py().get_type::<Recorded>())?;
+        #[cfg(feature = "migrate")]
+        m.add("MigrationFailed", m.py().get_type::<MigrationFailed>())?;
+        m.add("RandomGeneration", m.py().get_type::<RandomGeneration>())?;
+        m.add("IoError", m.py().get_type::<IoError>())?;
+        m.add("ProfileParse", m.py().get_type::<ProfileParse>())?;
+        m.add("ProfileValue", m.py().get_type::<ProfileValue>())?;
+        m.add("DumpParse", m.py().get_type::<DumpParse>())?;
+        m.add("InvalidAttribute", m.py().get_type::<InvalidAttribute>())?;
+        #[cfg(feature = "import")]
+        m.add("CsvParse", m.py().get_type::<CsvParse>())?;
         parent.add_submodule(&m)?;
         Ok(())
     }
@@ -828,6 +1054,12 @@ mod exceptions {
         PyKAdminException,
         "Failed to receive the result from an operatior from the sync executor"
     );
+    create_exception!(
+        exceptions,
+        Timeout,
+        PyKAdminException,
+        "An operation on the sync executor didn't complete within the configured timeout"
+    );
     create_exception!(
         exceptions,
         TimestampConversion,
@@ -846,6 +1078,24 @@ mod exceptions {
         PyKAdminException,
         "Failed to convert a `Duration` to a `krb5_deltat`"
     );
+    create_exception!(
+        exceptions,
+        KeySaltsConversion,
+        PyKAdminException,
+        "Too many keysalts to pass to kadm5"
+    );
+    create_exception!(
+        exceptions,
+        MkvnoConversion,
+        PyKAdminException,
+        "A master key version number doesn't fit in a dump record"
+    );
+    create_exception!(
+        exceptions,
+        AuxAttributesConversion,
+        PyKAdminException,
+        "Auxiliary attributes don't fit in a dump record"
+    );
     create_exception!(
         exceptions,
         LockError,
@@ -864,6 +1114,108 @@ mod exceptions {
         PyKAdminException,
         "The library is not compatible with the current operation"
     );
+    create_exception!(
+        exceptions,
+        InsufficientPrivileges,
+        PyKAdminException,
+        "The current kadm5 session lacks the privileges required for an operation"
+    );
+    create_exception!(
+        exceptions,
+        PasswordQualityError,
+        KAdminException,
+        indoc! {"
+            A password was rejected by the password quality checks or the password policy
+
+            :ivar code: kadm5 error code
+            :ivar origin_message: kadm5 error message
+            "}
+    );
+    create_exception!(
+        exceptions,
+        BatchFailed,
+        PyKAdminException,
+        "An operation of a batch failed"
+    );
+    create_exception!(
+        exceptions,
+        DryRunKeys,
+        PyKAdminException,
+        "The keys of a principal aren't known until the dry run changes are applied"
+    );
+    #[cfg(feature = "reconcile")]
+    create_exception!(
+        exceptions,
+        InvalidDesiredState,
+        PyKAdminException,
+        "A desired state couldn't be parsed or is invalid"
+    );
+    #[cfg(feature = "replay")]
+    create_exception!(
+        exceptions,
+        InvalidRecording,
+        PyKAdminException,
+        "A recorded session couldn't be written or read back"
+    );
+    #[cfg(feature = "replay")]
+    create_exception!(
+        exceptions,
+        ReplayDivergence,
+        PyKAdminException,
+        "A call made while replaying a session isn't the next recorded one"
+    );
+    #[cfg(feature = "replay")]
+    create_exception!(
+        exceptions,
+        Recorded,
+        PyKAdminException,
+        "Error that occurred while recording a session"
+    );
+    #[cfg(feature = "migrate")]
+    create_exception!(
+        exceptions,
+        MigrationFailed,
+        PyKAdminException,
+        "A migration couldn't copy a principal or read its state"
+    );
+    create_exception!(
+        exceptions,
+        RandomGeneration,
+        PyKAdminException,
+        "Failed to get random data from the operating system"
+    );
+    create_exception!(exceptions, IoError, PyKAdminException, "I/O error");
+    create_exception!(
+        exceptions,
+        ProfileParse,
+        PyKAdminException,
+        "Failed to parse a profile file"
+    );
+    create_exception!(
+        exceptions,
+        ProfileValue,
+        PyKAdminException,
+        "A profile relation has an invalid value"
+    );
+    create_exception!(
+        exceptions,
+        DumpParse,
+        PyKAdminException,
+        "Failed to parse a database dump"
+    );
+    create_exception!(
+        exceptions,
+        InvalidAttribute,
+        PyKAdminException,
+        "A principal attribute name is unknown"
+    );
+    #[cfg(feature = "import")]
+    create_exception!(
+        exceptions,
+        CsvParse,
+        PyKAdminException,
+        "Failed to parse a CSV file"
+    );
 
     impl From<Error> for PyErr {
         fn from(error: Error) -> Self {
@@ -872,7 +1224,7 @@ mod exceptions {
                     KerberosException::new_err(error.to_string()),
                     Some((*code as i64, message)),
                 ),
-                Error::KAdmin { code, message } => (
+                Error::KAdmin { code, message, .. } => (
                     KAdminException::new_err(error.to_string()),
                     Some((*code, message)),
                 ),
@@ -892,6 +1244,7 @@ mod exceptions {
                 Error::StringConversion(_) => (StringConversion::new_err(error.to_string()), None),
                 Error::ThreadSendError => (ThreadSendError::new_err(error.to_string()), None),
                 Error::ThreadRecvError(_) => (ThreadRecvError::new_err(error.to_string()), None),
+                Error::Timeout => (Timeout::new_err(error.to_string()), None),
                 Error::TimestampConversion => {
                     (TimestampConversion::new_err(error.to_string()), None)
                 }
@@ -901,9 +1254,47 @@ mod exceptions {
                 Error::DurationConversion(_) => {
                     (DurationConversion::new_err(error.to_string()), None)
                 }
+                Error::KeySaltsConversion(_) => {
+                    (KeySaltsConversion::new_err(error.to_string()), None)
+                }
+                Error::MkvnoConversion(_) => (MkvnoConversion::new_err(error.to_string()), None),
+                Error::AuxAttributesConversion(_) => {
+                    (AuxAttributesConversion::new_err(error.to_string()), None)
+                }
                 Error::LockError => (LockError::new_err(error.to_string()), None),
                 Error::LibraryLoadError(_) => (LibraryLoadError::new_err(error.to_string()), None),
                 Error::LibraryMismatch(_) => (LibraryMismatch::new_err(error.to_string()), None),
+                Error::InsufficientPrivileges(_) => {
+                    (InsufficientPrivileges::new_err(error.to_string()), None)
+                }
+                Error::PasswordQuality(details) => (
+                    PasswordQualityError::new_err(error.to_string()),
+                    Some((details.code, &details.message)),
+                ),
+                Error::BatchFailed { .. } => (BatchFailed::new_err(error.to_string()), None),
+                Error::DryRunKeys(_) => (DryRunKeys::new_err(error.to_string()), None),
+                #[cfg(feature = "reconcile")]
+                Error::InvalidDesiredState(_) => {
+                    (InvalidDesiredState::new_err(error.to_string()), None)
+                }
+                #[cfg(feature = "replay")]
+                Error::InvalidRecording(_) => (InvalidRecording::new_err(error.to_string()), None),
+                #[cfg(feature = "replay")]
+                Error::ReplayDivergence { .. } => {
+                    (ReplayDivergence::new_err(error.to_string()), None)
+                }
+                #[cfg(feature = "replay")]
+                Error::Recorded(_) => (Recorded::new_err(error.to_string()), None),
+                #[cfg(feature = "migrate")]
+                Error::MigrationFailed(_) => (MigrationFailed::new_err(error.to_string()), None),
+                Error::RandomGeneration(_) => (RandomGeneration::new_err(error.to_string()), None),
+                Error::Io(_) => (IoError::new_err(error.to_string()), None),
+                Error::ProfileParse { .. } => (ProfileParse::new_err(error.to_string()), None),
+                Error::ProfileValue { .. } => (ProfileValue::new_err(error.to_string()), None),
+                Error::DumpParse { .. } => (DumpParse::new_err(error.to_string()), None),
+                Error::InvalidAttribute(_) => (InvalidAttribute::new_err(error.to_string()), None),
+                #[cfg(feature = "import")]
+                Error::CsvParse { .. } => (CsvParse::new_err(error.to_string()), None),
             };
 
             Python::attach(|py| {
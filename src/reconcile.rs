@@ -0,0 +1,828 @@
+//! Declarative reconciliation of a realm against a [`DesiredState`]
+//!
+//! A [`DesiredState`] describes the principals and policies a realm should contain. It can be
+//! loaded from TOML or JSON with [`DesiredState::from_file`], or from any other format supported by
+//! `serde`, such as YAML. [`DesiredState::plan`] compares it with the realm and returns a [`Plan`]
+//! that can be reviewed before being [applied][`Plan::apply`].
+//!
+//! ```toml
+//! managed = ["svc/*", "team-*"]
+//! prune = true
+//!
+//! [policies.team-default]
+//! password_min_length = 12
+//! password_min_classes = 3
+//!
+//! [principals."svc/backup"]
+//! policy = "team-default"
+//! max_life = 36000
+//! expire_time = "2030-01-01T00:00:00Z"
+//!
+//! [principals."svc/backup".strings]
+//! session_enctypes = "aes256-cts"
+//! ```
+
+#[cfg(any(mit_client, mit_server))]
+use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::policy::{Policy, PolicyBuilder, PolicyModifier};
+use crate::{
+    batch::Batch,
+    error::{Error, Result},
+    kadmin::KAdminImpl,
+    principal::{Principal, PrincipalBuilder, PrincipalModifier},
+};
+#[cfg(any(mit_client, mit_server))]
+use crate::{context::Context, keysalt::KeySalts, sys::Library};
+
+/// Desired state of a realm
+///
+/// Only the principals and policies whose names are in scope are compared with the realm. A name is
+/// in scope if it matches any of the [`managed`][`Self::managed`] patterns, or if there are none,
+/// and doesn't match any of the [`unmanaged`][`Self::unmanaged`] patterns. Patterns are shell-style
+/// globs. Principal names are matched both with and without their realm.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct DesiredState {
+    /// Patterns of the names managed by this state. Defaults to all names
+    pub managed: Vec<String>,
+    /// Patterns of names excluded from [`Self::managed`]
+    pub unmanaged: Vec<String>,
+    /// Delete managed principals and policies that are not part of this state
+    ///
+    /// Requires [`Self::managed`] to be set, to avoid deleting principals such as `krbtgt` by
+    /// mistake
+    pub prune: bool,
+    /// Desired principals, by name
+    pub principals: BTreeMap<String, DesiredPrincipal>,
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Desired policies, by name
+    ///
+    /// Only available for MIT and Heimdal server-side libraries.
+    pub policies: BTreeMap<String, DesiredPolicy>,
+}
+
+/// Desired state of a principal
+///
+/// Fields left unset are not compared with the realm. New principals get a random key.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct DesiredPrincipal {
+    /// Principal attributes
+    pub attributes: Option<i32>,
+    /// Associated policy. An empty string means no policy
+    pub policy: Option<String>,
+    /// When the principal expires, as an RFC 3339 date, or `never`
+    pub expire_time: Option<String>,
+    /// Maximum ticket life in seconds. `0` means the realm default
+    pub max_life: Option<u64>,
+    /// Maximum renewable ticket life in seconds. `0` means the realm default
+    pub max_renewable_life: Option<u64>,
+    #[cfg(any(mit_client, mit_server))]
+    /// String attributes. Strings that are not listed are removed
+    ///
+    /// Only available for MIT variants
+    pub strings: Option<BTreeMap<String, String>>,
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+/// Desired state of a policy
+///
+/// Fields left unset are not compared with the realm.
+///
+/// Only available for MIT and Heimdal server-side libraries.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct DesiredPolicy {
+    /// Minimum lifetime of a password in seconds
+    pub password_min_life: Option<u64>,
+    /// Maximum lifetime of a password in seconds
+    pub password_max_life: Option<u64>,
+    /// Minimum length of a password
+    pub password_min_length: Option<i64>,
+    /// Minimum number of character classes required in a password
+    pub password_min_classes: Option<i64>,
+    /// Number of past keys kept for a principal
+    pub password_history_num: Option<i64>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Number of authentication failures before the principal is locked. `0` disables lockout
+    ///
+    /// Only available for MIT variants
+    pub password_max_fail: Option<u32>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Time in seconds after which the authentication failure count is reset. `0` means never
+    ///
+    /// Only available for MIT variants
+    pub password_failcount_interval: Option<u64>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Duration in seconds of a lockout. `0` means until the principal is unlocked by an
+    /// administrator
+    ///
+    /// Only available for MIT variants
+    pub password_lockout_duration: Option<u64>,
+    #[cfg(any(mit_client, mit_server))]
+    /// Allowed keysalts, such as `aes256-cts:normal,aes128-cts:normal`. An empty string allows all
+    /// keysalts
+    ///
+    /// Only available for MIT variants
+    pub allowed_keysalts: Option<String>,
+}
+
+/// Change of a single field, as shown in a [`Plan`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FieldChange {
+    /// Name of the field
+    pub field: &'static str,
+    /// Current value, `None` if the principal or policy doesn't exist yet
+    pub from: Option<String>,
+    /// Desired value
+    pub to: String,
+}
+
+impl FieldChange {
+    fn new(field: &'static str, from: Option<String>, to: String) -> Self {
+        Self { field, from, to }
+    }
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.from {
+            Some(from) => write!(f, "{}: {from} -> {}", self.field, self.to),
+            None => write!(f, "{} = {}", self.field, self.to),
+        }
+    }
+}
+
+/// Action of a [`Plan`]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum PlanAction {
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Create a policy
+    CreatePolicy(PolicyBuilder, Vec<FieldChange>),
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Modify a policy
+    ModifyPolicy(PolicyModifier, Vec<FieldChange>),
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    /// Delete a policy
+    DeletePolicy(String),
+    /// Create a principal
+    CreatePrincipal(PrincipalBuilder, Vec<FieldChange>),
+    /// Modify a principal
+    ModifyPrincipal(PrincipalModifier, Vec<FieldChange>),
+    #[cfg(any(mit_client, mit_server))]
+    /// Set or remove a string attribute of a principal
+    SetString {
+        /// Name of the principal
+        name: String,
+        /// Key of the string attribute
+        key: String,
+        /// New value, `None` to remove it
+        value: Option<String>,
+    },
+    /// Delete a principal
+    DeletePrincipal(String),
+}
+
+impl fmt::Display for PlanAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sign, kind, name, changes) = match self {
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::CreatePolicy(builder, changes) => {
+                ('+', "policy", &builder.name, changes.as_slice())
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::ModifyPolicy(modifier, changes) => {
+                ('~', "policy", &modifier.name, changes.as_slice())
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            Self::DeletePolicy(name) => ('-', "policy", name, [].as_slice()),
+            Self::CreatePrincipal(builder, changes) => {
+                ('+', "principal", &builder.name, changes.as_slice())
+            }
+            Self::ModifyPrincipal(modifier, changes) => {
+                ('~', "principal", &modifier.name, changes.as_slice())
+            }
+            #[cfg(any(mit_client, mit_server))]
+            Self::SetString { name, key, value } => {
+                return match value {
+                    Some(value) => write!(f, "~ principal {name}: string {key} = {value}"),
+                    None => write!(f, "~ principal {name}: remove string {key}"),
+                };
+            }
+            Self::DeletePrincipal(name) => ('-', "principal", name, [].as_slice()),
+        };
+        write!(f, "{sign} {kind} {name}")?;
+        for (i, change) in changes.iter().enumerate() {
+            write!(f, "{}{change}", if i == 0 { ": " } else { ", " })?;
+        }
+        Ok(())
+    }
+}
+
+/// Actions needed to bring a realm to a [`DesiredState`], as computed by [`DesiredState::plan`]
+///
+/// Policies are created and modified first, then principals, and deletions come last. Nothing is
+/// changed until [`Self::apply`] is called.
+#[derive(Clone, Debug, Default)]
+pub struct Plan {
+    actions: Vec<PlanAction>,
+}
+
+impl Plan {
+    /// Planned actions, in the order they will be applied
+    pub fn actions(&self) -> &[PlanAction] {
+        &self.actions
+    }
+
+    /// Whether the realm already matches the desired state
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Build the [`Batch`] applying this plan
+    pub fn to_batch(&self) -> Batch {
+        self.actions
+            .iter()
+            .fold(Batch::new(), |batch, action| match action {
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                PlanAction::CreatePolicy(builder, _) => batch.add_policy(builder.clone()),
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                PlanAction::ModifyPolicy(modifier, _) => batch.modify_policy(modifier.clone()),
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                PlanAction::DeletePolicy(name) => batch.delete_policy(name),
+                PlanAction::CreatePrincipal(builder, _) => batch.add_principal(builder.clone()),
+                PlanAction::ModifyPrincipal(modifier, _) => {
+                    batch.modify_principal(modifier.clone())
+                }
+                #[cfg(any(mit_client, mit_server))]
+                PlanAction::SetString { name, key, value } => {
+                    batch.set_string(name, key, value.as_deref())
+                }
+                PlanAction::DeletePrincipal(name) => batch.delete_principal(name),
+            })
+    }
+
+    /// Apply the plan
+    ///
+    /// Actions are applied as a [`Batch`], so a failure undoes the actions applied before it,
+    /// except for principal deletions
+    pub fn apply<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<()> {
+        self.to_batch().apply(kadmin)
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for action in &self.actions {
+            writeln!(f, "{action}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compiled [`DesiredState::managed`] and [`DesiredState::unmanaged`] patterns
+struct Scope {
+    managed: Vec<Pattern>,
+    unmanaged: Vec<Pattern>,
+}
+
+impl Scope {
+    fn new(state: &DesiredState) -> Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Pattern::new(pattern).map_err(|err| {
+                        Error::InvalidDesiredState(format!("invalid pattern {pattern:?}: {err}"))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Self {
+            managed: compile(&state.managed)?,
+            unmanaged: compile(&state.unmanaged)?,
+        })
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        let short_name = name
+            .rsplit_once('@')
+            .map_or(name, |(short_name, _)| short_name);
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches(name) || pattern.matches(short_name))
+        };
+        (self.managed.is_empty() || matches(&self.managed)) && !matches(&self.unmanaged)
+    }
+
+    fn check(&self, kind: &str, name: &str) -> Result<()> {
+        if self.contains(name) {
+            Ok(())
+        } else {
+            Err(Error::InvalidDesiredState(format!(
+                "{kind} {name} is outside of the managed names"
+            )))
+        }
+    }
+}
+
+fn secs_to_dur(secs: u64) -> Option<Duration> {
+    (secs != 0).then(|| Duration::from_secs(secs))
+}
+
+fn fmt_dur(dur: Option<Duration>) -> String {
+    dur.map_or_else(|| "0".to_owned(), |dur| dur.as_secs().to_string())
+}
+
+fn parse_expire_time(expire_time: &str) -> Result<Option<DateTime<Utc>>> {
+    if expire_time == "never" {
+        return Ok(None);
+    }
+    DateTime::parse_from_rfc3339(expire_time)
+        .map(|dt| Some(dt.with_timezone(&Utc)))
+        .map_err(|err| {
+            Error::InvalidDesiredState(format!("invalid expire_time {expire_time:?}: {err}"))
+        })
+}
+
+fn fmt_expire_time(expire_time: Option<DateTime<Utc>>) -> String {
+    expire_time.map_or_else(|| "never".to_owned(), |dt| dt.to_rfc3339())
+}
+
+impl DesiredState {
+    /// Parse a [`DesiredState`] from TOML
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        toml::from_str(toml).map_err(|err| Error::InvalidDesiredState(err.to_string()))
+    }
+
+    /// Parse a [`DesiredState`] from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|err| Error::InvalidDesiredState(err.to_string()))
+    }
+
+    /// Read a [`DesiredState`] from a `.toml` or `.json` file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(Error::InvalidDesiredState(format!(
+                "unsupported file format for {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Compare this state with the realm and compute the [`Plan`] to apply
+    pub fn plan<K: KAdminImpl + ?Sized>(&self, kadmin: &K) -> Result<Plan> {
+        let scope = Scope::new(self)?;
+        if self.prune && self.managed.is_empty() {
+            return Err(Error::InvalidDesiredState(
+                "prune requires managed patterns".to_owned(),
+            ));
+        }
+
+        let mut actions = Vec::new();
+        let mut deletions = Vec::new();
+
+        #[cfg(any(mit_client, mit_server))]
+        if !kadmin.variant().is_mit() {
+            if let Some(name) = self
+                .policies
+                .iter()
+                .find_map(|(name, desired)| desired.has_mit_fields().then_some(name))
+            {
+                return Err(Error::InvalidDesiredState(format!(
+                    "policy {name} sets fields only supported by MIT variants"
+                )));
+            }
+        }
+        // Keysalts are converted with a krb5 context of the same variant
+        #[cfg(any(mit_client, mit_server))]
+        let context = self
+            .policies
+            .values()
+            .any(|desired| desired.allowed_keysalts.is_some())
+            .then(|| Library::from_variant(kadmin.variant()).and_then(Context::new))
+            .transpose()?;
+
+        #[cfg(any(mit_client, mit_server, heimdal_server))]
+        for (name, desired) in &self.policies {
+            scope.check("policy", name)?;
+            actions.push(match kadmin.get_policy(name)? {
+                Some(current) => {
+                    #[cfg_attr(not(any(mit_client, mit_server)), allow(unused_mut))]
+                    let (mut modifier, mut changes) = desired.diff(&current);
+                    #[cfg(any(mit_client, mit_server))]
+                    if let Some((allowed_keysalts, change)) =
+                        desired.allowed_keysalts_change(context.as_ref(), Some(&current))?
+                    {
+                        modifier = modifier.allowed_keysalts(allowed_keysalts);
+                        changes.push(change);
+                    }
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    PlanAction::ModifyPolicy(modifier, changes)
+                }
+                None => {
+                    #[cfg_attr(not(any(mit_client, mit_server)), allow(unused_mut))]
+                    let (mut builder, mut changes) = desired.builder(name);
+                    #[cfg(any(mit_client, mit_server))]
+                    if let Some((allowed_keysalts, change)) =
+                        desired.allowed_keysalts_change(context.as_ref(), None)?
+                    {
+                        builder = builder.allowed_keysalts(allowed_keysalts);
+                        changes.push(change);
+                    }
+                    PlanAction::CreatePolicy(builder, changes)
+                }
+            });
+        }
+
+        let mut existing = HashSet::new();
+        for (name, desired) in &self.principals {
+            scope.check("principal", name)?;
+            match kadmin.get_principal(name)? {
+                Some(current) => {
+                    existing.insert(current.name().to_owned());
+                    let (modifier, changes) = desired.diff(&current)?;
+                    if !changes.is_empty() {
+                        actions.push(PlanAction::ModifyPrincipal(modifier, changes));
+                    }
+                    #[cfg(any(mit_client, mit_server))]
+                    if let Some(strings) = &desired.strings {
+                        let current_strings = kadmin.principal_get_strings(name)?;
+                        actions.extend(strings_actions(name, &current_strings, strings));
+                    }
+                }
+                None => {
+                    let (builder, changes) = desired.builder(name)?;
+                    actions.push(PlanAction::CreatePrincipal(builder, changes));
+                    #[cfg(any(mit_client, mit_server))]
+                    if let Some(strings) = &desired.strings {
+                        actions.extend(strings_actions(name, &HashMap::new(), strings));
+                    }
+                }
+            }
+        }
+
+        if self.prune {
+            for name in kadmin.list_principals(None)? {
+                if scope.contains(&name) && !existing.contains(&name) {
+                    deletions.push(PlanAction::DeletePrincipal(name));
+                }
+            }
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            for name in kadmin.list_policies(None)? {
+                if scope.contains(&name) && !self.policies.contains_key(&name) {
+                    deletions.push(PlanAction::DeletePolicy(name));
+                }
+            }
+        }
+
+        actions.extend(deletions);
+        Ok(Plan { actions })
+    }
+}
+
+impl DesiredPrincipal {
+    fn builder(&self, name: &str) -> Result<(PrincipalBuilder, Vec<FieldChange>)> {
+        let mut builder = PrincipalBuilder::new(name);
+        let mut changes = Vec::new();
+        if let Some(attributes) = self.attributes {
+            builder = builder.attributes(attributes);
+            changes.push(FieldChange::new("attributes", None, attributes.to_string()));
+        }
+        if let Some(policy) = self.policy.as_deref().filter(|policy| !policy.is_empty()) {
+            builder = builder.policy(Some(policy));
+            changes.push(FieldChange::new("policy", None, policy.to_owned()));
+        }
+        if let Some(expire_time) = &self.expire_time {
+            let expire_time = parse_expire_time(expire_time)?;
+            builder = builder.expire_time(expire_time);
+            changes.push(FieldChange::new(
+                "expire_time",
+                None,
+                fmt_expire_time(expire_time),
+            ));
+        }
+        if let Some(max_life) = self.max_life {
+            builder = builder.max_life(secs_to_dur(max_life));
+            changes.push(FieldChange::new("max_life", None, max_life.to_string()));
+        }
+        if let Some(max_renewable_life) = self.max_renewable_life {
+            builder = builder.max_renewable_life(secs_to_dur(max_renewable_life));
+            changes.push(FieldChange::new(
+                "max_renewable_life",
+                None,
+                max_renewable_life.to_string(),
+            ));
+        }
+        Ok((builder, changes))
+    }
+
+    fn diff(&self, current: &Principal) -> Result<(PrincipalModifier, Vec<FieldChange>)> {
+        let mut modifier = current.modifier();
+        let mut changes = Vec::new();
+        if let Some(attributes) = self.attributes {
+            if attributes != current.attributes() {
+                modifier = modifier.attributes(attributes);
+                changes.push(FieldChange::new(
+                    "attributes",
+                    Some(current.attributes().to_string()),
+                    attributes.to_string(),
+                ));
+            }
+        }
+        if let Some(policy) = &self.policy {
+            let policy = Some(policy.as_str()).filter(|policy| !policy.is_empty());
+            if policy != current.policy() {
+                modifier = modifier.policy(policy);
+                changes.push(FieldChange::new(
+                    "policy",
+                    Some(current.policy().unwrap_or("none").to_owned()),
+                    policy.unwrap_or("none").to_owned(),
+                ));
+            }
+        }
+        if let Some(expire_time) = &self.expire_time {
+            let expire_time = parse_expire_time(expire_time)?;
+            if expire_time != current.expire_time() {
+                modifier = modifier.expire_time(expire_time);
+                changes.push(FieldChange::new(
+                    "expire_time",
+                    Some(fmt_expire_time(current.expire_time())),
+                    fmt_expire_time(expire_time),
+                ));
+            }
+        }
+        if let Some(max_life) = self.max_life.map(secs_to_dur) {
+            if max_life != current.max_life() {
+                modifier = modifier.max_life(max_life);
+                changes.push(FieldChange::new(
+                    "max_life",
+                    Some(fmt_dur(current.max_life())),
+                    fmt_dur(max_life),
+                ));
+            }
+        }
+        if let Some(max_renewable_life) = self.max_renewable_life.map(secs_to_dur) {
+            if max_renewable_life != current.max_renewable_life() {
+                modifier = modifier.max_renewable_life(max_renewable_life);
+                changes.push(FieldChange::new(
+                    "max_renewable_life",
+                    Some(fmt_dur(current.max_renewable_life())),
+                    fmt_dur(max_renewable_life),
+                ));
+            }
+        }
+        Ok((modifier, changes))
+    }
+}
+
+#[cfg(any(mit_client, mit_server))]
+fn strings_actions(
+    name: &str,
+    current: &HashMap<String, String>,
+    desired: &BTreeMap<String, String>,
+) -> Vec<PlanAction> {
+    let mut actions: Vec<PlanAction> = desired
+        .iter()
+        .filter(|(key, value)| current.get(*key) != Some(*value))
+        .map(|(key, value)| PlanAction::SetString {
+            name: name.to_owned(),
+            key: key.clone(),
+            value: Some(value.clone()),
+        })
+        .collect();
+    let mut removed: Vec<&String> = current
+        .keys()
+        .filter(|key| !desired.contains_key(*key))
+        .collect();
+    removed.sort();
+    actions.extend(removed.into_iter().map(|key| PlanAction::SetString {
+        name: name.to_owned(),
+        key: key.clone(),
+        value: None,
+    }));
+    actions
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+macro_rules! policy_fields {
+    ($self:ident, $target:ident, $changes:ident, $current:expr) => {
+        policy_fields!(@dur $self, $target, $changes, $current, password_min_life);
+        policy_fields!(@dur $self, $target, $changes, $current, password_max_life);
+        policy_fields!(@int $self, $target, $changes, $current, password_min_length);
+        policy_fields!(@int $self, $target, $changes, $current, password_min_classes);
+        policy_fields!(@int $self, $target, $changes, $current, password_history_num);
+        #[cfg(any(mit_client, mit_server))]
+        {
+            if let Some(value) = $self.password_max_fail {
+                let current: Option<u32> = $current.map(Policy::password_max_fail);
+                if current != Some(value) {
+                    $target = $target.password_max_fail(value);
+                    $changes.push(FieldChange::new(
+                        "password_max_fail",
+                        current.map(|current| current.to_string()),
+                        value.to_string(),
+                    ));
+                }
+            }
+            policy_fields!(@dur $self, $target, $changes, $current, password_failcount_interval);
+            policy_fields!(@dur $self, $target, $changes, $current, password_lockout_duration);
+        }
+    };
+    (@dur $self:ident, $target:ident, $changes:ident, $current:expr, $field:ident) => {
+        if let Some(value) = $self.$field.map(secs_to_dur) {
+            let current: Option<Option<Duration>> = $current.map(Policy::$field);
+            if current != Some(value) {
+                $target = $target.$field(value);
+                $changes.push(FieldChange::new(
+                    stringify!($field),
+                    current.map(fmt_dur),
+                    fmt_dur(value),
+                ));
+            }
+        }
+    };
+    (@int $self:ident, $target:ident, $changes:ident, $current:expr, $field:ident) => {
+        if let Some(value) = $self.$field {
+            let current: Option<i64> = $current.map(Policy::$field);
+            if current != Some(value) {
+                $target = $target.$field(value as std::ffi::c_long);
+                $changes.push(FieldChange::new(
+                    stringify!($field),
+                    current.map(|current| current.to_string()),
+                    value.to_string(),
+                ));
+            }
+        }
+    };
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+impl DesiredPolicy {
+    fn builder(&self, name: &str) -> (PolicyBuilder, Vec<FieldChange>) {
+        let mut builder = PolicyBuilder::new(name);
+        let mut changes = Vec::new();
+        policy_fields!(self, builder, changes, None::<&Policy>);
+        (builder, changes)
+    }
+
+    fn diff(&self, current: &Policy) -> (PolicyModifier, Vec<FieldChange>) {
+        let mut modifier = current.modifier();
+        let mut changes = Vec::new();
+        policy_fields!(self, modifier, changes, Some(current));
+        (modifier, changes)
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Whether fields only supported by MIT variants are set
+    fn has_mit_fields(&self) -> bool {
+        self.password_max_fail.is_some()
+            || self.password_failcount_interval.is_some()
+            || self.password_lockout_duration.is_some()
+            || self.allowed_keysalts.is_some()
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    /// Desired allowed keysalts and the corresponding change, if they differ from `current`
+    ///
+    /// Keysalts are parsed and formatted with `context`, which is required if they are set.
+    fn allowed_keysalts_change(
+        &self,
+        context: Option<&Context>,
+        current: Option<&Policy>,
+    ) -> Result<Option<(Option<KeySalts>, FieldChange)>> {
+        let (Some(allowed_keysalts), Some(context)) = (&self.allowed_keysalts, context) else {
+            return Ok(None);
+        };
+        let allowed_keysalts = Some(allowed_keysalts.as_str())
+            .filter(|allowed_keysalts| !allowed_keysalts.is_empty())
+            .map(|allowed_keysalts| KeySalts::from_str(context, allowed_keysalts))
+            .transpose()?;
+        let current = current.map(|current| current.allowed_keysalts().cloned());
+        if current.as_ref() == Some(&allowed_keysalts) {
+            return Ok(None);
+        }
+        let fmt = |keysalts: &Option<KeySalts>| {
+            keysalts.as_ref().map_or_else(
+                || Ok("none".to_owned()),
+                |keysalts| keysalts.to_string(context),
+            )
+        };
+        let change = FieldChange::new(
+            "allowed_keysalts",
+            current.as_ref().map(fmt).transpose()?,
+            fmt(&allowed_keysalts)?,
+        );
+        Ok(Some((allowed_keysalts, change)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_toml() -> Result<()> {
+        let state = DesiredState::from_toml(
+            r#"
+            managed = ["svc/*"]
+            prune = true
+
+            [principals."svc/backup"]
+            policy = "default"
+            max_life = 36000
+            expire_time = "never"
+            "#,
+        )?;
+        assert!(state.prune);
+        let principal = &state.principals["svc/backup"];
+        assert_eq!(principal.policy.as_deref(), Some("default"));
+        assert_eq!(principal.max_life, Some(36000));
+        assert_eq!(principal.attributes, None);
+        assert!(DesiredState::from_toml("unknown = 1").is_err());
+        Ok(())
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    #[test]
+    fn parse_mit_policy() -> Result<()> {
+        let state = DesiredState::from_toml(
+            r#"
+            [policies.locked]
+            password_max_fail = 3
+            password_failcount_interval = 0
+            allowed_keysalts = "aes256-cts:normal"
+            "#,
+        )?;
+        let policy = &state.policies["locked"];
+        assert!(policy.has_mit_fields());
+        assert_eq!(policy.password_max_fail, Some(3));
+        assert_eq!(
+            policy.password_failcount_interval.map(secs_to_dur),
+            Some(None)
+        );
+        assert_eq!(policy.password_lockout_duration, None);
+        assert!(!DesiredPolicy::default().has_mit_fields());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_json() -> Result<()> {
+        let state = DesiredState::from_json(r#"{"principals": {"alice": {"attributes": 0}}}"#)?;
+        assert_eq!(state.principals["alice"].attributes, Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn scope() -> Result<()> {
+        let state = DesiredState {
+            managed: vec!["svc/*".to_owned(), "team-*".to_owned()],
+            unmanaged: vec!["svc/kadmin*".to_owned()],
+            ..Default::default()
+        };
+        let scope = Scope::new(&state)?;
+        assert!(scope.contains("svc/backup@EXAMPLE.ORG"));
+        assert!(scope.contains("svc/backup"));
+        assert!(scope.contains("team-default"));
+        assert!(!scope.contains("svc/kadmin@EXAMPLE.ORG"));
+        assert!(!scope.contains("krbtgt/EXAMPLE.ORG@EXAMPLE.ORG"));
+        assert!(Scope::new(&DesiredState::default())?.contains("krbtgt/EXAMPLE.ORG@EXAMPLE.ORG"));
+        Ok(())
+    }
+
+    #[test]
+    fn expire_time() -> Result<()> {
+        assert_eq!(parse_expire_time("never")?, None);
+        let expire_time = parse_expire_time("2030-01-01T00:00:00Z")?;
+        assert_eq!(
+            fmt_expire_time(expire_time),
+            "2030-01-01T00:00:00+00:00".to_owned()
+        );
+        assert!(parse_expire_time("tomorrow").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,849 @@
+//! Record kadm5 sessions and replay them without any Kerberos library
+//!
+//! A [`Recorder`] wraps a [`KAdminImpl`] and writes every call made through it, along with its
+//! result, to a file. A [`Replayer`] reads that file back and answers the same calls with the
+//! recorded results, without loading any kadm5 library. A call that isn't the next recorded one
+//! fails with [`Error::ReplayDivergence`].
+//!
+//! Recordings are JSON lines. The first line holds the [`KAdm5Variant`] of the recorded session,
+//! so that the [`Replayer`] reports the same one, and each following line a call with its
+//! arguments and result. Arguments don't depend on the variants the crate was built with, so a
+//! recording can be replayed by a build supporting other ones. Passwords are never recorded: new
+//! principals only record whether one was provided, and password changes only the principal name
+//! and key options, so that replaying with another password doesn't diverge. Keys aren't recorded
+//! either: [`KAdminImpl::principal_get_keys`] and [`KAdminImpl::principal_set_keys`] fail with
+//! [`Error::LibraryMismatch`] on both a [`Recorder`] and a [`Replayer`].
+//!
+//! ```no_run
+//! # use crate::kadmin::{KAdmin, KAdminImpl, KAdm5Variant, replay::{Recorder, Replayer}};
+//! # let kadmin = KAdmin::builder(KAdm5Variant::MitClient)
+//! #     .with_ccache(None, None)
+//! #     .unwrap();
+//! let recorder = Recorder::new(kadmin, "session.jsonl").unwrap();
+//! assert!(!recorder.principal_exists("alice").unwrap());
+//! drop(recorder);
+//!
+//! let replayer = Replayer::open("session.jsonl").unwrap();
+//! assert!(!replayer.principal_exists("alice").unwrap());
+//! assert_eq!(replayer.remaining().unwrap(), 0);
+//! ```
+
+#[cfg(any(mit_client, mit_server))]
+use std::collections::HashMap;
+use std::{
+    collections::VecDeque,
+    ffi::c_long,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+use crate::policy::{Policy, PolicyBuilder, PolicyModifier};
+use crate::{
+    error::{Error, KAdminErrorKind, PasswordQualityError, Result},
+    kadmin::KAdminImpl,
+    keysalt::KeySalts,
+    principal::{Principal, PrincipalBuilder, PrincipalBuilderKey, PrincipalModifier},
+    privileges::Privileges,
+    sys::KAdm5Variant,
+    tl_data::TlData,
+};
+
+/// Version of the recording format, bumped on incompatible changes
+const FORMAT_VERSION: u32 = 3;
+
+fn invalid(error: impl fmt::Display) -> Error {
+    Error::InvalidRecording(error.to_string())
+}
+
+/// First line of a recording
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    variant: KAdm5Variant,
+}
+
+/// A [`KAdminImpl`] method call and its arguments
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Call {
+    method: String,
+    args: Value,
+}
+
+impl Call {
+    fn new(method: &str, args: impl Serialize) -> Result<Self> {
+        Ok(Self {
+            method: method.to_owned(),
+            args: serde_json::to_value(args).map_err(invalid)?,
+        })
+    }
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.method, self.args)
+    }
+}
+
+/// Recorded arguments of [`KAdminImpl::add_principal`] and [`KAdminImpl::modify_principal`]
+///
+/// Only fields that are set are recorded, without the variant-specific masks of the builders.
+#[derive(Serialize)]
+struct PrincipalArgs<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expire_time: Option<Option<DateTime<Utc>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_expiration: Option<Option<DateTime<Utc>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_life: Option<Option<Duration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    policy: Option<&'a Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aux_attributes: Option<c_long>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_renewable_life: Option<Option<Duration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fail_auth_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tl_data: Option<&'a TlData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kvno: Option<u32>,
+    /// Only whether a password was provided, see [`PrincipalBuilderKey`]'s serialization
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<&'a PrincipalBuilderKey>,
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keysalts: Option<&'a KeySalts>,
+}
+
+/// Fields shared by [`PrincipalBuilder`] and [`PrincipalModifier`]
+macro_rules! principal_args {
+    ($doer:expr) => {
+        PrincipalArgs {
+            name: &$doer.name,
+            expire_time: $doer.expire_time,
+            password_expiration: $doer.password_expiration,
+            max_life: $doer.max_life,
+            attributes: $doer.attributes,
+            policy: $doer.policy.as_ref(),
+            aux_attributes: $doer.aux_attributes,
+            max_renewable_life: $doer.max_renewable_life,
+            fail_auth_count: $doer.fail_auth_count,
+            tl_data: $doer.tl_data.as_ref(),
+            kvno: $doer.kvno,
+            key: None,
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            keysalts: None,
+        }
+    };
+}
+
+impl<'a> From<&'a PrincipalBuilder> for PrincipalArgs<'a> {
+    fn from(builder: &'a PrincipalBuilder) -> Self {
+        Self {
+            key: Some(&builder.key),
+            #[cfg(any(mit_client, mit_server, heimdal_server))]
+            keysalts: builder.keysalts.as_ref(),
+            ..principal_args!(builder)
+        }
+    }
+}
+
+impl<'a> From<&'a PrincipalModifier> for PrincipalArgs<'a> {
+    fn from(modifier: &'a PrincipalModifier) -> Self {
+        principal_args!(modifier)
+    }
+}
+
+/// Recorded arguments of [`KAdminImpl::add_policy`] and [`KAdminImpl::modify_policy`]
+///
+/// Only fields that are set are recorded, without the variant-specific masks of the builders.
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+#[derive(Serialize)]
+struct PolicyArgs<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_min_life: Option<Option<Duration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_max_life: Option<Option<Duration>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_min_length: Option<c_long>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_min_classes: Option<c_long>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_history_num: Option<c_long>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_max_fail: Option<u32>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_failcount_interval: Option<Option<Duration>>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_lockout_duration: Option<Option<Duration>>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<i32>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_life: Option<Option<Duration>>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_renewable_life: Option<Option<Duration>>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_keysalts: Option<&'a Option<KeySalts>>,
+    #[cfg(any(mit_client, mit_server))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tl_data: Option<&'a TlData>,
+}
+
+/// Fields of [`PolicyBuilder`] and [`PolicyModifier`]
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+macro_rules! policy_args {
+    ($doer:expr) => {
+        PolicyArgs {
+            name: &$doer.name,
+            password_min_life: $doer.password_min_life,
+            password_max_life: $doer.password_max_life,
+            password_min_length: $doer.password_min_length,
+            password_min_classes: $doer.password_min_classes,
+            password_history_num: $doer.password_history_num,
+            #[cfg(any(mit_client, mit_server))]
+            password_max_fail: $doer.password_max_fail,
+            #[cfg(any(mit_client, mit_server))]
+            password_failcount_interval: $doer.password_failcount_interval,
+            #[cfg(any(mit_client, mit_server))]
+            password_lockout_duration: $doer.password_lockout_duration,
+            #[cfg(any(mit_client, mit_server))]
+            attributes: $doer.attributes,
+            #[cfg(any(mit_client, mit_server))]
+            max_life: $doer.max_life,
+            #[cfg(any(mit_client, mit_server))]
+            max_renewable_life: $doer.max_renewable_life,
+            #[cfg(any(mit_client, mit_server))]
+            allowed_keysalts: $doer.allowed_keysalts.as_ref(),
+            #[cfg(any(mit_client, mit_server))]
+            tl_data: $doer.tl_data.as_ref(),
+        }
+    };
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+impl<'a> From<&'a PolicyBuilder> for PolicyArgs<'a> {
+    fn from(builder: &'a PolicyBuilder) -> Self {
+        policy_args!(builder)
+    }
+}
+
+#[cfg(any(mit_client, mit_server, heimdal_server))]
+impl<'a> From<&'a PolicyModifier> for PolicyArgs<'a> {
+    fn from(modifier: &'a PolicyModifier) -> Self {
+        policy_args!(modifier)
+    }
+}
+
+/// Recorded result of a call
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Ok(Value),
+    Err(RecordedError),
+}
+
+/// Serializable form of an [`Error`]
+///
+/// Errors callers are expected to match on are kept as-is, others only by their description
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedError {
+    Kerberos {
+        code: i64,
+        message: String,
+    },
+    KAdmin {
+        code: i64,
+        message: String,
+        kind: KAdminErrorKind,
+    },
+    PasswordQuality(Box<PasswordQualityError>),
+    InsufficientPrivileges(u32),
+    Other(String),
+}
+
+impl From<&Error> for RecordedError {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Kerberos { code, message } => Self::Kerberos {
+                code: *code,
+                message: message.clone(),
+            },
+            Error::KAdmin {
+                code,
+                message,
+                kind,
+            } => Self::KAdmin {
+                code: *code,
+                message: message.clone(),
+                kind: *kind,
+            },
+            Error::PasswordQuality(details) => Self::PasswordQuality(details.clone()),
+            Error::InsufficientPrivileges(missing) => Self::InsufficientPrivileges(missing.bits()),
+            error => Self::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for Error {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::Kerberos { code, message } => Self::Kerberos { code, message },
+            RecordedError::KAdmin {
+                code,
+                message,
+                kind,
+            } => Self::KAdmin {
+                code,
+                message,
+                kind,
+            },
+            RecordedError::PasswordQuality(details) => Self::PasswordQuality(details),
+            RecordedError::InsufficientPrivileges(missing) => {
+                Self::InsufficientPrivileges(Privileges::from_bits_retain(missing))
+            }
+            RecordedError::Other(message) => Self::Recorded(message),
+        }
+    }
+}
+
+/// A line of a recording after the header
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    call: Call,
+    result: Outcome,
+}
+
+/// [`KAdminImpl`] recording every call made through it to be replayed by a [`Replayer`]
+///
+/// Each call is written as soon as it returns. Failing to write it is returned as an error, even
+/// if the call itself succeeded.
+pub struct Recorder<K: KAdminImpl> {
+    inner: K,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl<K: KAdminImpl> Recorder<K> {
+    /// Record calls made on `inner` to the file at `path`, replacing it if it exists
+    pub fn new<P: AsRef<Path>>(inner: K, path: P) -> Result<Self> {
+        let file = File::create(path)?;
+        Self::from_writer(inner, BufWriter::new(file))
+    }
+
+    /// Record calls made on `inner` to `writer`
+    pub fn from_writer<W: Write + Send + 'static>(inner: K, mut writer: W) -> Result<Self> {
+        let header = Header {
+            version: FORMAT_VERSION,
+            variant: inner.variant(),
+        };
+        serde_json::to_writer(&mut writer, &header).map_err(invalid)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(Self {
+            inner,
+            writer: Mutex::new(Box::new(writer)),
+        })
+    }
+
+    /// Wrapped [`KAdminImpl`]
+    pub fn inner(&self) -> &K {
+        &self.inner
+    }
+
+    /// Stop recording and return the wrapped [`KAdminImpl`]
+    pub fn into_inner(self) -> K {
+        self.inner
+    }
+
+    fn record<T: Serialize>(&self, call: Call, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let result = f();
+        let outcome = match &result {
+            Ok(value) => Outcome::Ok(serde_json::to_value(value).map_err(invalid)?),
+            Err(error) => Outcome::Err(error.into()),
+        };
+        let entry = Entry {
+            call,
+            result: outcome,
+        };
+
+        let mut writer = self.writer.lock().map_err(|_| Error::LockError)?;
+        serde_json::to_writer(&mut *writer, &entry).map_err(invalid)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        result
+    }
+}
+
+/// [`KAdminImpl`] answering calls from a session recorded by a [`Recorder`]
+///
+/// Calls must be made in the same order and with the same arguments as when recording. Calls
+/// past the end of the recording fail with [`Error::ReplayDivergence`] as well.
+#[derive(Debug)]
+pub struct Replayer {
+    variant: KAdm5Variant,
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+impl Replayer {
+    /// Replay the recording in the file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Replay the recording read from `reader`
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(invalid)?,
+            None => return Err(invalid("empty recording")),
+        };
+        if header.version != FORMAT_VERSION {
+            return Err(invalid(format!(
+                "unsupported format version {}",
+                header.version
+            )));
+        }
+
+        let mut entries = VecDeque::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push_back(serde_json::from_str(&line).map_err(invalid)?);
+        }
+        Ok(Self {
+            variant: header.variant,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Number of recorded calls that haven't been replayed yet
+    pub fn remaining(&self) -> Result<usize> {
+        Ok(self.entries.lock().map_err(|_| Error::LockError)?.len())
+    }
+
+    fn replay<T: DeserializeOwned>(&self, call: Call) -> Result<T> {
+        let mut entries = self.entries.lock().map_err(|_| Error::LockError)?;
+        match entries.front() {
+            Some(entry) if entry.call == call => {}
+            Some(entry) => {
+                return Err(Error::ReplayDivergence {
+                    expected: entry.call.to_string(),
+                    actual: call.to_string(),
+                });
+            }
+            None => {
+                return Err(Error::ReplayDivergence {
+                    expected: "end of recording".to_owned(),
+                    actual: call.to_string(),
+                });
+            }
+        }
+        let entry = entries.pop_front().expect("front entry was just checked");
+        match entry.result {
+            Outcome::Ok(value) => serde_json::from_value(value).map_err(invalid),
+            Outcome::Err(error) => Err(error.into()),
+        }
+    }
+}
+
+impl<K: KAdminImpl> KAdminImpl for Recorder<K> {
+    fn variant(&self) -> KAdm5Variant {
+        self.inner.variant()
+    }
+
+    fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
+        self.record(
+            Call::new("add_principal", PrincipalArgs::from(builder))?,
+            || self.inner.add_principal(builder),
+        )
+    }
+
+    fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
+        self.record(
+            Call::new("modify_principal", PrincipalArgs::from(modifier))?,
+            || self.inner.modify_principal(modifier),
+        )
+    }
+
+    fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let call = Call::new(
+            "rename_principal",
+            json!({ "old_name": old_name, "new_name": new_name }),
+        )?;
+        self.record(call, || self.inner.rename_principal(old_name, new_name))
+    }
+
+    fn delete_principal(&self, name: &str) -> Result<()> {
+        self.record(
+            Call::new("delete_principal", json!({ "name": name }))?,
+            || self.inner.delete_principal(name),
+        )
+    }
+
+    fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
+        self.record(Call::new("get_principal", json!({ "name": name }))?, || {
+            self.inner.get_principal(name)
+        })
+    }
+
+    fn principal_change_password(
+        &self,
+        name: &str,
+        password: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        let call = Call::new(
+            "principal_change_password",
+            key_change_args(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            ),
+        )?;
+        self.record(call, || {
+            self.inner.principal_change_password(
+                name,
+                password,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            )
+        })
+    }
+
+    fn principal_randkey(
+        &self,
+        name: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        let call = Call::new(
+            "principal_randkey",
+            key_change_args(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            ),
+        )?;
+        self.record(call, || {
+            self.inner.principal_randkey(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            )
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
+        self.record(
+            Call::new("principal_get_strings", json!({ "name": name }))?,
+            || self.inner.principal_get_strings(name),
+        )
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_set_string(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let call = Call::new(
+            "principal_set_string",
+            json!({ "name": name, "key": key, "value": value }),
+        )?;
+        self.record(call, || self.inner.principal_set_string(name, key, value))
+    }
+
+    fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
+        self.record(
+            Call::new("list_principals", json!({ "query": query }))?,
+            || self.inner.list_principals(query),
+        )
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
+        self.record(Call::new("add_policy", PolicyArgs::from(builder))?, || {
+            self.inner.add_policy(builder)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
+        self.record(
+            Call::new("modify_policy", PolicyArgs::from(modifier))?,
+            || self.inner.modify_policy(modifier),
+        )
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delete_policy(&self, name: &str) -> Result<()> {
+        self.record(Call::new("delete_policy", json!({ "name": name }))?, || {
+            self.inner.delete_policy(name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+        self.record(Call::new("get_policy", json!({ "name": name }))?, || {
+            self.inner.get_policy(name)
+        })
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
+        self.record(
+            Call::new("list_policies", json!({ "query": query }))?,
+            || self.inner.list_policies(query),
+        )
+    }
+
+    fn get_privileges(&self) -> Result<Privileges> {
+        self.record(Call::new("get_privileges", Value::Null)?, || {
+            self.inner
+                .get_privileges()
+                .map(|privileges| privileges.bits())
+        })
+        .map(Privileges::from_bits_retain)
+    }
+}
+
+impl KAdminImpl for Replayer {
+    fn variant(&self) -> KAdm5Variant {
+        self.variant
+    }
+
+    fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
+        self.replay(Call::new("add_principal", PrincipalArgs::from(builder))?)
+    }
+
+    fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
+        self.replay(Call::new(
+            "modify_principal",
+            PrincipalArgs::from(modifier),
+        )?)
+    }
+
+    fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
+        self.replay(Call::new(
+            "rename_principal",
+            json!({ "old_name": old_name, "new_name": new_name }),
+        )?)
+    }
+
+    fn delete_principal(&self, name: &str) -> Result<()> {
+        self.replay(Call::new("delete_principal", json!({ "name": name }))?)
+    }
+
+    fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
+        self.replay(Call::new("get_principal", json!({ "name": name }))?)
+    }
+
+    fn principal_change_password(
+        &self,
+        name: &str,
+        _password: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        self.replay(Call::new(
+            "principal_change_password",
+            key_change_args(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            ),
+        )?)
+    }
+
+    fn principal_randkey(
+        &self,
+        name: &str,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+        #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+    ) -> Result<()> {
+        self.replay(Call::new(
+            "principal_randkey",
+            key_change_args(
+                name,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keepold,
+                #[cfg(any(mit_client, mit_server, heimdal_server))]
+                keysalts,
+            ),
+        )?)
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
+        self.replay(Call::new("principal_get_strings", json!({ "name": name }))?)
+    }
+
+    #[cfg(any(mit_client, mit_server))]
+    fn principal_set_string(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        self.replay(Call::new(
+            "principal_set_string",
+            json!({ "name": name, "key": key, "value": value }),
+        )?)
+    }
+
+    fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
+        self.replay(Call::new("list_principals", json!({ "query": query }))?)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
+        self.replay(Call::new("add_policy", PolicyArgs::from(builder))?)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
+        self.replay(Call::new("modify_policy", PolicyArgs::from(modifier))?)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn delete_policy(&self, name: &str) -> Result<()> {
+        self.replay(Call::new("delete_policy", json!({ "name": name }))?)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
+        self.replay(Call::new("get_policy", json!({ "name": name }))?)
+    }
+
+    #[cfg(any(mit_client, mit_server, heimdal_server))]
+    fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
+        self.replay(Call::new("list_policies", json!({ "query": query }))?)
+    }
+
+    fn get_privileges(&self) -> Result<Privileges> {
+        self.replay(Call::new("get_privileges", Value::Null)?)
+            .map(Privileges::from_bits_retain)
+    }
+}
+
+/// Arguments of a password change or randkey, without the password
+///
+/// Key options are recorded as `null` when the crate is built without support for them.
+fn key_change_args(
+    name: &str,
+    #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
+    #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
+) -> Value {
+    #[cfg(not(any(mit_client, mit_server, heimdal_server)))]
+    let (keepold, keysalts): (Option<bool>, Option<&KeySalts>) = (None, None);
+    json!({ "name": name, "keepold": keepold, "keysalts": keysalts })
+}
+
+#[cfg(all(test, mit_server))]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+    use crate::memory::MemoryKAdmin;
+
+    #[test]
+    fn record_replay() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("kadmin-replay-{}.jsonl", process::id()));
+        let recorder = Recorder::new(
+            MemoryKAdmin::new(KAdm5Variant::MitServer, "EXAMPLE.ORG"),
+            &path,
+        )?;
+        Principal::builder("alice").create(&recorder)?;
+        assert!(
+            Principal::builder("alice")
+                .create(&recorder)
+                .unwrap_err()
+                .is_already_exists()
+        );
+        recorder.principal_change_password("alice", "hunter2hunter2", None, None)?;
+        drop(recorder);
+
+        let recording = fs::read_to_string(&path)?;
+        assert!(!recording.contains("hunter2"));
+        let replayer = Replayer::open(&path)?;
+        fs::remove_file(&path)?;
+        assert_eq!(replayer.variant(), KAdm5Variant::MitServer);
+
+        let alice = Principal::builder("alice").create(&replayer)?;
+        assert_eq!(alice.name(), "alice@EXAMPLE.ORG");
+        assert!(
+            Principal::builder("alice")
+                .create(&replayer)
+                .unwrap_err()
+                .is_already_exists()
+        );
+        assert!(matches!(
+            replayer.delete_principal("alice"),
+            Err(Error::ReplayDivergence { .. })
+        ));
+        assert!(matches!(
+            replayer.principal_change_password("bob", "hunter2hunter2", None, None),
+            Err(Error::ReplayDivergence { .. })
+        ));
+        // Passwords aren't recorded, any one replays the change
+        replayer.principal_change_password("alice", "another password", None, None)?;
+        assert_eq!(replayer.remaining()?, 0);
+        assert!(matches!(
+            replayer.get_privileges(),
+            Err(Error::ReplayDivergence { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn variant_independent_args() -> Result<()> {
+        let builder = Principal::builder("alice")
+            .max_life(Some(Duration::from_secs(3600)))
+            .key(&PrincipalBuilderKey::Password("hunter2".to_owned()));
+        let args = serde_json::to_value(PrincipalArgs::from(&builder)).map_err(invalid)?;
+        assert_eq!(
+            args,
+            json!({
+                "name": "alice",
+                "max_life": { "secs": 3600, "nanos": 0 },
+                "key": "Password",
+            })
+        );
+        let args = serde_json::to_value(PolicyArgs::from(
+            &Policy::builder("strict").password_min_length(12),
+        ))
+        .map_err(invalid)?;
+        assert_eq!(args, json!({ "name": "strict", "password_min_length": 12 }));
+        assert_eq!(
+            key_change_args("alice", None, None),
+            json!({ "name": "alice", "keepold": null, "keysalts": null })
+        );
+        Ok(())
+    }
+}
@@ -6,29 +6,35 @@
 //!
 //! The APIs between this wrapper and the underlying [`crate::kadmin::KAdmin`] are the same, and
 //! wrapped and the [`KAdminImpl`] trait.
+#[cfg(feature = "tracing")]
+use std::time::Instant;
 use std::{
     collections::HashMap,
     ffi::{OsStr, OsString},
     panic::resume_unwind,
     sync::{
-        Arc,
-        mpsc::{Sender, channel},
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvError, RecvTimeoutError, Sender, channel},
     },
     thread::{JoinHandle, spawn},
+    time::Duration,
 };
 
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
+use zeroize::Zeroizing;
 
 #[cfg(any(mit_client, mit_server, heimdal_server))]
 use crate::policy::{Policy, PolicyBuilder, PolicyModifier};
 use crate::{
     db_args::DbArgs,
-    error::Result,
+    error::{Error, Result},
     kadmin::{KAdminApiVersion, KAdminImpl},
-    keysalt::KeySalts,
+    keysalt::{Key, KeySalts},
     params::Params,
     principal::{Principal, PrincipalBuilder, PrincipalModifier},
+    privileges::Privileges,
     sys::{KAdm5Variant, Library},
 };
 
@@ -59,6 +65,10 @@ enum KAdminOperation {
         #[cfg(any(mit_client, mit_server, heimdal_server))] Option<KeySalts>,
         Sender<Result<()>>,
     ),
+    /// See [`KAdminImpl::principal_get_keys`]
+    PrincipalGetKeys(String, Option<u32>, Sender<Result<Vec<Key>>>),
+    /// See [`KAdminImpl::principal_set_keys`]
+    PrincipalSetKeys(String, bool, Vec<Key>, Sender<Result<()>>),
     #[cfg(any(mit_client, mit_server))]
     /// See [`KAdminImpl::principal_get_strings`]
     PrincipalGetStrings(String, Sender<Result<HashMap<String, String>>>),
@@ -83,7 +93,7 @@ enum KAdminOperation {
     /// See [`KAdminImpl::list_policies`]
     ListPolicies(Option<String>, Sender<Result<Vec<String>>>),
     /// See [`KAdminImpl::get_privileges`]
-    GetPrivileges(Sender<Result<i64>>),
+    GetPrivileges(Sender<Result<Privileges>>),
     /// Stop the kadmin thread
     Exit,
 }
@@ -128,6 +138,12 @@ impl KAdminOperation {
             Self::PrincipalRandkey(name, sender) => {
                 let _ = sender.send(kadmin.principal_randkey(name));
             }
+            Self::PrincipalGetKeys(name, kvno, sender) => {
+                let _ = sender.send(kadmin.principal_get_keys(name, *kvno));
+            }
+            Self::PrincipalSetKeys(name, keepold, keys, sender) => {
+                let _ = sender.send(kadmin.principal_set_keys(name, *keepold, keys));
+            }
             #[cfg(any(mit_client, mit_server))]
             Self::PrincipalGetStrings(name, sender) => {
                 let _ = sender.send(kadmin.principal_get_strings(name));
@@ -166,17 +182,128 @@ impl KAdminOperation {
     }
 }
 
-/// Inner attributes to be wrapped in an [`Arc`]
+/// [`KAdminOperation`] sent to an executor
+struct Queued {
+    op: KAdminOperation,
+    /// When the operation was sent, to measure how long it waited to be handled
+    #[cfg(feature = "tracing")]
+    sent_at: Instant,
+    /// Span of the caller, in which the operation is handled and its wait recorded
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl Queued {
+    fn new(op: KAdminOperation) -> Self {
+        Self {
+            op,
+            #[cfg(feature = "tracing")]
+            sent_at: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        }
+    }
+
+    fn handle(&self, kadmin: &crate::kadmin::KAdmin) {
+        #[cfg(feature = "tracing")]
+        let _entered = {
+            self.span
+                .record("queue.wait", self.sent_at.elapsed().as_secs_f64());
+            self.span.enter()
+        };
+        self.op.handle(kadmin);
+    }
+}
+
+/// Function used to build the underlying [`crate::kadmin::KAdmin`] instance, kept around so the
+/// executor can be rebuilt after a timeout
+type KAdminBuildFn =
+    dyn Fn(crate::kadmin::KAdminBuilder) -> Result<crate::kadmin::KAdmin> + Send + Sync;
+
+/// Executor thread owning a [`crate::kadmin::KAdmin`] instance
 #[derive(Debug)]
-struct InnerKAdmin {
-    op_sender: Sender<KAdminOperation>,
+struct Worker {
+    op_sender: Sender<Queued>,
     join_handle: Option<JoinHandle<()>>,
+    /// Set when an operation timed out. The thread exits as soon as it regains control, without
+    /// handling any queued operation
+    poisoned: Arc<AtomicBool>,
 }
 
-impl Drop for InnerKAdmin {
+impl Worker {
+    /// Spawn a new executor thread and wait for the [`crate::kadmin::KAdmin`] instance to be
+    /// initialized, for at most `timeout` if set
+    fn spawn(
+        builder: KAdminBuilder,
+        kadmin_build: Arc<KAdminBuildFn>,
+        timeout: Option<Duration>,
+    ) -> Result<Self> {
+        let (op_sender, op_receiver) = channel();
+        let (start_sender, start_receiver) = channel();
+        let poisoned = Arc::new(AtomicBool::new(false));
+
+        let thread_poisoned = poisoned.clone();
+        let join_handle = spawn(move || {
+            let builder = match builder.get_builder() {
+                Ok(builder) => builder,
+                Err(e) => {
+                    let _ = start_sender.send(Err(e));
+                    return;
+                }
+            };
+            let kadmin = match kadmin_build(builder) {
+                Ok(kadmin) => {
+                    let _ = start_sender.send(Ok(()));
+                    kadmin
+                }
+                Err(e) => {
+                    let _ = start_sender.send(Err(e));
+                    return;
+                }
+            };
+            while let Ok(queued) = op_receiver.recv() {
+                if thread_poisoned.load(Ordering::Acquire) {
+                    break;
+                }
+                match queued.op {
+                    KAdminOperation::Exit => break,
+                    _ => queued.handle(&kadmin),
+                };
+            }
+        });
+
+        let mut worker = Self {
+            op_sender,
+            join_handle: Some(join_handle),
+            poisoned,
+        };
+        match recv_with_timeout(&start_receiver, timeout) {
+            Ok(Ok(())) => Ok(worker),
+            Ok(Err(e)) => {
+                if let Some(join_handle) = worker.join_handle.take() {
+                    if let Err(e) = join_handle.join() {
+                        resume_unwind(e);
+                    }
+                }
+                Err(e)
+            }
+            Err(e) => {
+                worker.poisoned.store(true, Ordering::Release);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for Worker {
     fn drop(&mut self) {
+        // A poisoned thread may still be stuck in a library call, so it is left to exit on its
+        // own instead of being waited upon
+        if self.poisoned.load(Ordering::Acquire) {
+            return;
+        }
         // Thread might have already exited, so we don't care about the result of this
-        let _ = self.op_sender.send(KAdminOperation::Exit);
+        let _ = self.op_sender.send(Queued::new(KAdminOperation::Exit));
         if let Some(join_handle) = self.join_handle.take() {
             if let Err(e) = join_handle.join() {
                 resume_unwind(e);
@@ -185,13 +312,84 @@ impl Drop for InnerKAdmin {
     }
 }
 
+/// Inner attributes to be wrapped in an [`Arc`]
+struct InnerKAdmin {
+    builder: KAdminBuilder,
+    kadmin_build: Arc<KAdminBuildFn>,
+    worker: Mutex<Option<Worker>>,
+}
+
+impl InnerKAdmin {
+    /// Send an operation to the current executor, spawning a new one if the previous one was
+    /// poisoned. `timeout` is the one of the calling handle, and bounds how long the new
+    /// executor may take to start
+    ///
+    /// Returns the poisoned flag of the executor the operation was sent to
+    fn send(&self, op: KAdminOperation, timeout: Option<Duration>) -> Result<Arc<AtomicBool>> {
+        let mut worker = self.worker.lock().unwrap_or_else(PoisonError::into_inner);
+        let worker = match &mut *worker {
+            Some(worker) => worker,
+            None => worker.insert(Worker::spawn(
+                self.builder.clone(),
+                self.kadmin_build.clone(),
+                timeout,
+            )?),
+        };
+        worker.op_sender.send(Queued::new(op))?;
+        Ok(worker.poisoned.clone())
+    }
+
+    /// Poison the executor identified by `poisoned`, if it is still the current one. It will be
+    /// replaced on the next operation.
+    fn poison(&self, poisoned: &Arc<AtomicBool>) {
+        let mut worker = self.worker.lock().unwrap_or_else(PoisonError::into_inner);
+        if worker
+            .as_ref()
+            .is_some_and(|w| Arc::ptr_eq(&w.poisoned, poisoned))
+        {
+            poisoned.store(true, Ordering::Release);
+            worker.take();
+        }
+    }
+}
+
+impl std::fmt::Debug for InnerKAdmin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InnerKAdmin")
+            .field("builder", &self.builder)
+            .field("worker", &self.worker)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wait on `receiver`, for at most `timeout` if set
+fn recv_with_timeout<T>(receiver: &Receiver<T>, timeout: Option<Duration>) -> Result<T> {
+    let result = match timeout {
+        Some(timeout) => receiver.recv_timeout(timeout),
+        None => receiver.recv().map_err(RecvTimeoutError::from),
+    };
+    match result {
+        Ok(value) => Ok(value),
+        Err(RecvTimeoutError::Timeout) => Err(Error::Timeout),
+        Err(RecvTimeoutError::Disconnected) => Err(RecvError.into()),
+    }
+}
+
 /// Thread-safe interface to kadm5
 ///
 /// This is a thread-safe wrapper over [`crate::kadmin::KAdmin`].
+///
+/// Operations wait for at most the timeout set with [`KAdminBuilder::timeout`], which can be
+/// overridden with [`KAdmin::with_timeout`]. When an operation times out, [`Error::Timeout`] is
+/// returned and the underlying executor is discarded: the operation may or may not have been
+/// applied, but no further operation will be run on that executor. Operations already queued on
+/// it fail with [`Error::Timeout`] as well. A new one is transparently created on the next
+/// operation.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "python", pyclass(from_py_object))]
 pub struct KAdmin {
     variant: KAdm5Variant,
+    timeout: Option<Duration>,
     inner: Arc<InnerKAdmin>,
 }
 
@@ -200,6 +398,34 @@ impl KAdmin {
     pub fn builder(variant: KAdm5Variant) -> KAdminBuilder {
         KAdminBuilder::new(variant)
     }
+
+    /// Get a handle to the same executor that uses `timeout` for its operations instead of the
+    /// one set on the builder. `None` disables the timeout.
+    pub fn with_timeout(&self, timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            ..self.clone()
+        }
+    }
+
+    /// Send an operation to the executor and wait for its result
+    fn send_op<T, F>(&self, op: F) -> Result<T>
+    where F: FnOnce(Sender<Result<T>>) -> KAdminOperation {
+        let (sender, receiver) = channel();
+        let poisoned = self.inner.send(op(sender), self.timeout)?;
+        match recv_with_timeout(&receiver, self.timeout) {
+            Ok(result) => result,
+            Err(Error::Timeout) => {
+                self.inner.poison(&poisoned);
+                Err(Error::Timeout)
+            }
+            // The executor was poisoned by another operation timing out, and dropped this one
+            Err(Error::ThreadRecvError(_)) if poisoned.load(Ordering::Acquire) => {
+                Err(Error::Timeout)
+            }
+            Err(e) => Err(e),
+        }
+    }
 }
 
 impl KAdminImpl for KAdmin {
@@ -208,45 +434,25 @@ impl KAdminImpl for KAdmin {
     }
 
     fn add_principal(&self, builder: &PrincipalBuilder) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::AddPrincipal(builder.clone(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::AddPrincipal(builder.clone(), sender))
     }
 
     fn modify_principal(&self, modifier: &PrincipalModifier) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::ModifyPrincipal(modifier.clone(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::ModifyPrincipal(modifier.clone(), sender))
     }
 
     fn rename_principal(&self, old_name: &str, new_name: &str) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner.op_sender.send(KAdminOperation::RenamePrincipal(
-            old_name.to_owned(),
-            new_name.to_owned(),
-            sender,
-        ))?;
-        receiver.recv()?
+        self.send_op(|sender| {
+            KAdminOperation::RenamePrincipal(old_name.to_owned(), new_name.to_owned(), sender)
+        })
     }
 
     fn delete_principal(&self, name: &str) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::DeletePrincipal(name.to_owned(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::DeletePrincipal(name.to_owned(), sender))
     }
 
     fn get_principal(&self, name: &str) -> Result<Option<Principal>> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::GetPrincipal(name.to_owned(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::GetPrincipal(name.to_owned(), sender))
     }
 
     fn principal_change_password(
@@ -256,10 +462,8 @@ impl KAdminImpl for KAdmin {
         #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
         #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
     ) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::PrincipalChangePassword(
+        self.send_op(|sender| {
+            KAdminOperation::PrincipalChangePassword(
                 name.to_owned(),
                 password.to_owned(),
                 #[cfg(any(mit_client, mit_server, heimdal_server))]
@@ -267,8 +471,8 @@ impl KAdminImpl for KAdmin {
                 #[cfg(any(mit_client, mit_server, heimdal_server))]
                 keysalts.cloned(),
                 sender,
-            ))?;
-        receiver.recv()?
+            )
+        })
     }
 
     fn principal_randkey(
@@ -277,118 +481,89 @@ impl KAdminImpl for KAdmin {
         #[cfg(any(mit_client, mit_server, heimdal_server))] keepold: Option<bool>,
         #[cfg(any(mit_client, mit_server, heimdal_server))] keysalts: Option<&KeySalts>,
     ) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::PrincipalRandkey(
+        self.send_op(|sender| {
+            KAdminOperation::PrincipalRandkey(
                 name.to_owned(),
                 #[cfg(any(mit_client, mit_server, heimdal_server))]
                 keepold,
                 #[cfg(any(mit_client, mit_server, heimdal_server))]
                 keysalts.cloned(),
                 sender,
-            ))?;
-        receiver.recv()?
+            )
+        })
+    }
+
+    fn principal_get_keys(&self, name: &str, kvno: Option<u32>) -> Result<Vec<Key>> {
+        self.send_op(|sender| KAdminOperation::PrincipalGetKeys(name.to_owned(), kvno, sender))
+    }
+
+    fn principal_set_keys(&self, name: &str, keepold: bool, keys: &[Key]) -> Result<()> {
+        self.send_op(|sender| {
+            KAdminOperation::PrincipalSetKeys(name.to_owned(), keepold, keys.to_vec(), sender)
+        })
     }
 
     #[cfg(any(mit_client, mit_server))]
     fn principal_get_strings(&self, name: &str) -> Result<HashMap<String, String>> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::PrincipalGetStrings(
-                name.to_owned(),
-                sender,
-            ))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::PrincipalGetStrings(name.to_owned(), sender))
     }
 
     #[cfg(any(mit_client, mit_server))]
     fn principal_set_string(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::PrincipalSetString(
+        self.send_op(|sender| {
+            KAdminOperation::PrincipalSetString(
                 name.to_owned(),
                 key.to_owned(),
                 value.map(String::from),
                 sender,
-            ))?;
-        receiver.recv()?
+            )
+        })
     }
 
     fn list_principals(&self, query: Option<&str>) -> Result<Vec<String>> {
-        let (sender, receiver) = channel();
-        self.inner.op_sender.send(KAdminOperation::ListPrincipals(
-            query.map(String::from),
-            sender,
-        ))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::ListPrincipals(query.map(String::from), sender))
     }
 
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     fn add_policy(&self, builder: &PolicyBuilder) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::AddPolicy(builder.clone(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::AddPolicy(builder.clone(), sender))
     }
 
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     fn modify_policy(&self, modifier: &PolicyModifier) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::ModifyPolicy(modifier.clone(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::ModifyPolicy(modifier.clone(), sender))
     }
 
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     fn delete_policy(&self, name: &str) -> Result<()> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::DeletePolicy(name.to_owned(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::DeletePolicy(name.to_owned(), sender))
     }
 
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     fn get_policy(&self, name: &str) -> Result<Option<Policy>> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::GetPolicy(name.to_owned(), sender))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::GetPolicy(name.to_owned(), sender))
     }
 
     #[cfg(any(mit_client, mit_server, heimdal_server))]
     fn list_policies(&self, query: Option<&str>) -> Result<Vec<String>> {
-        let (sender, receiver) = channel();
-        self.inner.op_sender.send(KAdminOperation::ListPolicies(
-            query.map(String::from),
-            sender,
-        ))?;
-        receiver.recv()?
+        self.send_op(|sender| KAdminOperation::ListPolicies(query.map(String::from), sender))
     }
 
-    fn get_privileges(&self) -> Result<i64> {
-        let (sender, receiver) = channel();
-        self.inner
-            .op_sender
-            .send(KAdminOperation::GetPrivileges(sender))?;
-        receiver.recv()?
+    fn get_privileges(&self) -> Result<Privileges> {
+        self.send_op(KAdminOperation::GetPrivileges)
     }
 }
 
 /// [`KAdmin`] builder
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct KAdminBuilder {
     variant: KAdm5Variant,
     library_path: Option<OsString>,
     params: Option<Params>,
     db_args: Option<DbArgs>,
     api_version: KAdminApiVersion,
+    timeout: Option<Duration>,
+    required_privileges: Option<Privileges>,
 }
 
 impl KAdminBuilder {
@@ -400,6 +575,8 @@ impl KAdminBuilder {
             params: None,
             db_args: None,
             api_version: Default::default(),
+            timeout: None,
+            required_privileges: None,
         }
     }
 
@@ -429,6 +606,22 @@ impl KAdminBuilder {
         self
     }
 
+    /// Set the default timeout for operations on the built [`KAdmin`] instance, including its
+    /// initialization. By default, operations wait indefinitely.
+    ///
+    /// It can be overridden with [`KAdmin::with_timeout`]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Check that the [`KAdmin`] instance has the `required` privileges once initialized, and
+    /// fail with [`Error::InsufficientPrivileges`] otherwise
+    pub fn require_privileges(mut self, required: Privileges) -> Self {
+        self.required_privileges = Some(required);
+        self
+    }
+
     /// Construct a [`crate::kadmin::KAdminBuilder`] object that isn't initialized yet from the
     /// builder inputs
     fn get_builder(self) -> Result<crate::kadmin::KAdminBuilder> {
@@ -444,63 +637,44 @@ impl KAdminBuilder {
             builder = builder.library(library);
         }
         builder = builder.api_version(self.api_version);
+        if let Some(required_privileges) = self.required_privileges {
+            builder = builder.require_privileges(required_privileges);
+        }
         Ok(builder)
     }
 
     /// Build a [`crate::kadmin::KAdmin`] instance with a custom function
+    ///
+    /// The function is kept to rebuild the executor after an operation timed out.
     fn build<F>(self, kadmin_build: F) -> Result<KAdmin>
-    where F: FnOnce(crate::kadmin::KAdminBuilder) -> Result<crate::kadmin::KAdmin> + Send + 'static
-    {
-        let variant = self.variant;
-        let (op_sender, op_receiver) = channel();
-        let (start_sender, start_receiver) = channel();
-
-        let join_handle = spawn(move || {
-            let builder = match self.get_builder() {
-                Ok(builder) => builder,
-                Err(e) => {
-                    let _ = start_sender.send(Err(e));
-                    return;
-                }
-            };
-            let kadmin = match kadmin_build(builder) {
-                Ok(kadmin) => {
-                    let _ = start_sender.send(Ok(()));
-                    kadmin
-                }
-                Err(e) => {
-                    let _ = start_sender.send(Err(e));
-                    return;
-                }
-            };
-            while let Ok(op) = op_receiver.recv() {
-                match op {
-                    KAdminOperation::Exit => break,
-                    _ => op.handle(&kadmin),
-                };
-            }
-        });
-
-        match start_receiver.recv()? {
-            Ok(_) => Ok(KAdmin {
-                variant,
-                inner: Arc::new(InnerKAdmin {
-                    op_sender,
-                    join_handle: Some(join_handle),
-                }),
+    where F: Fn(crate::kadmin::KAdminBuilder) -> Result<crate::kadmin::KAdmin>
+            + Send
+            + Sync
+            + 'static {
+        let kadmin_build: Arc<KAdminBuildFn> = Arc::new(kadmin_build);
+        let worker = Worker::spawn(self.clone(), kadmin_build.clone(), self.timeout)?;
+
+        Ok(KAdmin {
+            variant: self.variant,
+            timeout: self.timeout,
+            inner: Arc::new(InnerKAdmin {
+                builder: self,
+                kadmin_build,
+                worker: Mutex::new(Some(worker)),
             }),
-            Err(e) => match join_handle.join() {
-                Ok(_) => Err(e),
-                Err(e) => resume_unwind(e),
-            },
-        }
+        })
     }
 
     /// Construct a [`KAdmin`] object from this builder using a client name (usually a principal
     /// name) and a password
+    ///
+    /// The password is kept in memory for as long as the [`KAdmin`] instance or any of its clones
+    /// is alive, to rebuild the executor after an operation timed out. It is zeroed once they are
+    /// all dropped. To avoid keeping it around, obtain
+    /// [`Credentials`][`crate::credentials::Credentials`] and use [`Self::with_ccache`] instead.
     pub fn with_password(self, client_name: &str, password: &str) -> Result<KAdmin> {
         let client_name = client_name.to_owned();
-        let password = password.to_owned();
+        let password = Zeroizing::new(password.to_owned());
 
         self.build(move |builder| builder.with_password(&client_name, &password))
     }
@@ -19,6 +19,7 @@ use crate::error::Result;
 #[allow(clippy::exhaustive_enums)]
 #[repr(u32)]
 #[cfg_attr(feature = "python", pyclass(from_py_object, eq, eq_int))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub enum KAdm5Variant {
     #[cfg(mit_client)]
     /// MIT krb5 client-side
@@ -82,6 +83,27 @@ impl KAdm5Variant {
     }
 }
 
+impl std::str::FromStr for KAdm5Variant {
+    type Err = crate::error::Error;
+
+    /// Parse a variant from its feature name, for instance `mit_client`
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            #[cfg(mit_client)]
+            "mit_client" => Ok(Self::MitClient),
+            #[cfg(mit_server)]
+            "mit_server" => Ok(Self::MitServer),
+            #[cfg(heimdal_client)]
+            "heimdal_client" => Ok(Self::HeimdalClient),
+            #[cfg(heimdal_server)]
+            "heimdal_server" => Ok(Self::HeimdalServer),
+            _ => Err(crate::error::Error::LibraryMismatch(
+                "Unknown or unsupported kadm5 variant.",
+            )),
+        }
+    }
+}
+
 /// Bindings to a kadm5 library
 #[allow(clippy::exhaustive_enums)]
 pub enum Library {
@@ -12,8 +12,9 @@ use crate::{context::Context, sys::library_match};
 
 /// A single TL-data entry
 #[allow(clippy::exhaustive_structs)]
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "python", pyclass(from_py_object, get_all, set_all))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct TlDataEntry {
     /// TL-data type
     pub data_type: i16,
@@ -22,9 +23,10 @@ pub struct TlDataEntry {
 }
 
 /// TL-data entries
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
 #[allow(clippy::exhaustive_structs)]
 #[cfg_attr(feature = "python", pyclass(from_py_object, get_all, set_all))]
+#[cfg_attr(feature = "replay", derive(serde::Serialize, serde::Deserialize))]
 pub struct TlData {
     /// TL-data entries
     pub entries: Vec<TlDataEntry>,
@@ -78,6 +80,15 @@ impl TlData {
 
         Self { entries }
     }
+
+    /// Add the entries of `other`, replacing existing entries of the same type like kadm5 does
+    pub(crate) fn merge(&mut self, other: &Self) {
+        for entry in &other.entries {
+            self.entries
+                .retain(|existing| existing.data_type != entry.data_type);
+            self.entries.push(entry.clone());
+        }
+    }
 }
 
 pub(crate) struct TlDataRaw<'a> {
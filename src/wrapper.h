@@ -1,5 +1,9 @@
 #include <kadm5/admin.h>
 
+#if defined(KADMIN_RS_MIT_CLIENT) || defined(KADMIN_RS_MIT_SERVER)
+#include <profile.h>
+#endif
+
 #if defined(KADMIN_RS_HEIMDAL_CLIENT) || defined(KADMIN_RS_HEIMDAL_SERVER)
 #include <kadm5/kadm5_err.h>
 #endif
@@ -0,0 +1,87 @@
+//! Test batches
+mod k5test;
+mod util;
+
+macro_rules! gen_tests {
+    ($libname:ident, $variant:ident) => {
+        #[cfg($libname)]
+        mod $libname {
+            use anyhow::Result;
+            use kadmin::{Batch, Error, KAdm5Variant, KAdminImpl, Policy, Principal};
+            use serial_test::serial;
+
+            use super::{
+                super::{k5test::K5Test, util::random_string},
+                *,
+            };
+
+            #[test]
+            #[serial]
+            fn apply() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let polname = random_string(16);
+                let princname = random_string(16);
+                let batch = Batch::new()
+                    .add_policy(Policy::builder(&polname).password_min_length(12))
+                    .add_principal(Principal::builder(&princname).policy(Some(&polname)))
+                    .randkey(&princname);
+                assert_eq!(batch.irreversible().len(), 1);
+                batch.apply(&kadmin)?;
+                let princ = kadmin.get_principal(&princname)?.unwrap();
+                assert_eq!(princ.policy(), Some(polname.as_str()));
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn rollback() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let polname = random_string(16);
+                let princname = random_string(16);
+                let user = kadmin.get_principal(&realm.user_princ()?)?.unwrap();
+                let batch = Batch::new()
+                    .add_policy(Policy::builder(&polname))
+                    .add_principal(Principal::builder(&princname).policy(Some(&polname)))
+                    .modify_principal(user.modifier().policy(Some(&polname)))
+                    .add_principal(Principal::builder(&princname));
+
+                let Err(Error::BatchFailed {
+                    index,
+                    rollback_errors,
+                    irreversible,
+                    ..
+                }) = batch.apply(&kadmin)
+                else {
+                    panic!("expected the batch to fail");
+                };
+                assert_eq!(index, 3);
+                assert!(rollback_errors.is_empty());
+                assert!(irreversible.is_empty());
+
+                assert!(!kadmin.principal_exists(&princname)?);
+                assert!(!kadmin.policy_exists(&polname)?);
+                let restored = kadmin.get_principal(&realm.user_princ()?)?.unwrap();
+                assert_eq!(restored.policy(), user.policy());
+                Ok(())
+            }
+        }
+    };
+}
+
+mod direct {
+    use kadmin::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
+
+mod sync {
+    use kadmin::sync::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
@@ -0,0 +1,134 @@
+//! Test dry runs
+mod k5test;
+mod util;
+
+macro_rules! gen_tests {
+    ($libname:ident, $variant:ident) => {
+        #[cfg($libname)]
+        mod $libname {
+            use anyhow::Result;
+            use kadmin::{
+                DryRun, EncryptionType, Error, KAdm5Variant, KAdminImpl, Key, KeySalt, Policy,
+                Principal,
+            };
+            use serial_test::serial;
+
+            use super::{
+                super::{k5test::K5Test, util::random_string},
+                *,
+            };
+
+            #[test]
+            #[serial]
+            fn overlay() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let dry_run = DryRun::new(kadmin);
+                let polname = random_string(16);
+                let princname = random_string(16);
+
+                Policy::builder(&polname).create(&dry_run)?;
+                let princ = Principal::builder(&princname)
+                    .policy(Some(&polname))
+                    .create(&dry_run)?;
+                assert_eq!(princ.policy(), Some(polname.as_str()));
+                let qualified = format!("{princname}@{}", realm.realm_name()?);
+                assert!(dry_run.list_principals(None)?.contains(&qualified));
+                assert_eq!(
+                    dry_run
+                        .get_principal(&qualified)?
+                        .map(|princ| princ.name().to_owned()),
+                    Some(qualified)
+                );
+                dry_run.principal_set_string(&princname, "key", Some("value"))?;
+                assert_eq!(
+                    dry_run.principal_get_strings(&princname)?.get("key"),
+                    Some(&"value".to_owned())
+                );
+                dry_run.delete_principal(&realm.user_princ()?)?;
+                assert!(!dry_run.principal_exists(&realm.user_princ()?)?);
+                assert!(Principal::builder(&princname).create(&dry_run).is_err());
+
+                assert_eq!(dry_run.changes().len(), 4);
+                let kadmin = dry_run.into_inner();
+                assert!(!kadmin.policy_exists(&polname)?);
+                assert!(!kadmin.principal_exists(&princname)?);
+                assert!(kadmin.principal_exists(&realm.user_princ()?)?);
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn batch() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let dry_run = DryRun::new(kadmin);
+                let princname = random_string(16);
+                let newname = random_string(16);
+
+                Principal::builder(&princname).create(&dry_run)?;
+                dry_run.rename_principal(&princname, &newname)?;
+                assert!(!dry_run.principal_exists(&princname)?);
+                assert!(dry_run.principal_exists(&newname)?);
+
+                let batch = dry_run.batch();
+                let kadmin = dry_run.into_inner();
+                batch.apply(&kadmin)?;
+                assert!(kadmin.principal_exists(&newname)?);
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn keys() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let dry_run = DryRun::new(kadmin);
+                let princname = random_string(16);
+
+                Principal::builder(&princname).create(&dry_run)?;
+                assert!(matches!(
+                    dry_run.principal_get_keys(&princname, None),
+                    Err(Error::DryRunKeys(_))
+                ));
+
+                let key = Key {
+                    kvno: 2,
+                    keysalt: KeySalt {
+                        enctype: EncryptionType::from(18),
+                        salttype: Default::default(),
+                    },
+                    contents: vec![0x42; 32].into(),
+                    salt: vec![],
+                };
+                dry_run.principal_set_keys(&princname, false, std::slice::from_ref(&key))?;
+                assert_eq!(dry_run.principal_get_keys(&princname, None)?, vec![key]);
+                assert!(dry_run.principal_get_keys(&princname, Some(1))?.is_empty());
+
+                dry_run.principal_randkey(&princname, None, None)?;
+                assert!(matches!(
+                    dry_run.principal_get_keys(&princname, None),
+                    Err(Error::DryRunKeys(_))
+                ));
+                Ok(())
+            }
+        }
+    };
+}
+
+mod direct {
+    use kadmin::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
+
+mod sync {
+    use kadmin::sync::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
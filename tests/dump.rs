@@ -0,0 +1,133 @@
+//! Test reading and writing database dumps
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use chrono::DateTime;
+use kadmin::dump::{
+    heimdal,
+    mit::{Dump, DumpPrincipal},
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/dump")
+        .join(name)
+}
+
+#[test]
+fn mit_principals() -> Result<()> {
+    let dump = Dump::from_file(fixture("mit.dump"))?;
+    assert_eq!(dump.iprop, None);
+
+    let alice = dump
+        .principals
+        .iter()
+        .find(|princ| princ.name == "alice@EXAMPLE.ORG")
+        .unwrap()
+        .to_principal();
+    assert_eq!(alice.policy(), Some("default"));
+    assert_eq!(alice.modified_by(), Some("admin/admin@EXAMPLE.ORG"));
+    assert_eq!(alice.kvno(), 2);
+    assert_eq!(alice.mkvno(), 1);
+    assert_eq!(alice.max_life(), Some(Duration::from_secs(36000)));
+    assert_eq!(alice.max_renewable_life(), None);
+    assert_eq!(
+        alice.expire_time(),
+        DateTime::from_timestamp(1_900_000_000, 0)
+    );
+    assert_eq!(
+        alice.last_password_change(),
+        DateTime::from_timestamp(1_705_000_000, 0)
+    );
+
+    let record = DumpPrincipal::from_principal(&alice)?;
+    assert_eq!(record.policy().as_deref(), Some("default"));
+    assert_eq!(record.to_principal().modified_at(), alice.modified_at());
+    Ok(())
+}
+
+#[cfg(any(mit_client, mit_server))]
+#[test]
+fn mit_policies() -> Result<()> {
+    use kadmin::dump::mit::DumpPolicy;
+
+    let dump = Dump::from_file(fixture("mit.dump"))?;
+    let policy = dump.policies[0].to_policy();
+    assert_eq!(policy.name(), "default");
+    assert_eq!(policy.password_min_length(), 8);
+    assert_eq!(policy.password_max_fail(), 5);
+    assert_eq!(
+        policy.password_lockout_duration(),
+        Some(Duration::from_secs(600))
+    );
+    assert_eq!(DumpPolicy::from_policy(&policy), dump.policies[0]);
+    Ok(())
+}
+
+#[test]
+fn heimdal_principals() -> Result<()> {
+    let dump = heimdal::Dump::from_file(fixture("heimdal.dump"))?;
+    let alice = dump.principals[2].to_principal();
+    assert_eq!(alice.name(), "alice@EXAMPLE.ORG");
+    assert_eq!(alice.policy(), Some("default"));
+    // DISALLOW_FORWARDABLE, REQUIRES_PRE_AUTH and Heimdal's OK_AS_DELEGATE
+    assert_eq!(alice.attributes(), 0x0001_0082);
+    assert_eq!(alice.kvno(), 2);
+    assert_eq!(
+        alice.expire_time(),
+        DateTime::from_timestamp(1_900_000_000, 0)
+    );
+    assert_eq!(
+        alice.last_password_change(),
+        DateTime::from_timestamp(1_705_000_000, 0)
+    );
+    assert_eq!(
+        heimdal::DumpPrincipal::from_principal(&alice).flags,
+        dump.principals[2].flags
+    );
+    Ok(())
+}
+
+#[test]
+fn heimdal_to_mit() -> Result<()> {
+    let heimdal = heimdal::Dump::from_file(fixture("heimdal.dump"))?;
+    let mit = Dump::try_from(&heimdal)?;
+    let alice = &mit.principals[2];
+    // DISALLOW_FORWARDABLE, REQUIRES_PRE_AUTH and MIT's OK_AS_DELEGATE
+    assert_eq!(alice.attributes, 0x0010_0082);
+    assert_eq!(alice.policy().as_deref(), Some("default"));
+    assert_eq!(alice.mkvno(), Some(1));
+    assert_eq!(alice.kvno(), 2);
+    // The default salt is implied in MIT dumps, other salts are special ones
+    assert_eq!(alice.keys[0].salt, None);
+    let salt = alice.keys[1].salt.as_ref().unwrap();
+    assert_eq!(i32::from(salt.salttype), 4);
+    assert_eq!(salt.contents, b"hostwww");
+
+    let back = heimdal::Dump::from(&mit);
+    assert_eq!(back.principals[2].flags, heimdal.principals[2].flags);
+    assert_eq!(back.principals[2].policy().as_deref(), Some("default"));
+    assert_eq!(back.principals[2].keys[1], heimdal.principals[2].keys[1]);
+    Ok(())
+}
+
+#[test]
+fn mit_to_heimdal() -> Result<()> {
+    let mit = Dump::from_file(fixture("mit.dump"))?;
+    let heimdal = heimdal::Dump::from(&mit);
+    let alice = &heimdal.principals[2];
+    assert_eq!(alice.kvno, 2);
+    // Only the keys of the current kvno are kept
+    assert_eq!(alice.keys.len(), 2);
+    assert_eq!(alice.policy().as_deref(), Some("default"));
+    assert_eq!(
+        alice
+            .modified
+            .as_ref()
+            .and_then(|event| event.principal.as_deref()),
+        Some("admin/admin@EXAMPLE.ORG")
+    );
+    let parsed: heimdal::Dump = heimdal.to_string().parse()?;
+    assert_eq!(parsed, heimdal);
+    Ok(())
+}
@@ -5,8 +5,10 @@ macro_rules! gen_tests_remote {
     ($libname: ident, $variant:ident) => {
         #[cfg($libname)]
         mod $libname {
+            use std::time::Duration;
+
             use anyhow::Result;
-            use kadmin::{KAdm5Variant, KAdminImpl};
+            use kadmin::{Credentials, Error, KAdm5Variant, KAdminImpl, Privileges};
             use serial_test::serial;
 
             use super::{super::k5test::K5Test, *};
@@ -21,6 +23,21 @@ macro_rules! gen_tests_remote {
                 Ok(())
             }
 
+            #[test]
+            #[serial]
+            fn require_privileges() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .require_privileges(Privileges::ADD | Privileges::DELETE)
+                    .with_password(&realm.user_princ()?, &realm.password("user")?);
+                assert!(matches!(kadmin, Err(Error::InsufficientPrivileges(_))));
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .require_privileges(Privileges::ADD | Privileges::DELETE)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                kadmin.list_principals(None)?;
+                Ok(())
+            }
+
             #[test]
             #[serial]
             fn with_keytab() -> Result<()> {
@@ -42,6 +59,68 @@ macro_rules! gen_tests_remote {
                 kadmin.list_principals(None)?;
                 Ok(())
             }
+
+            #[test]
+            #[serial]
+            fn with_credentials() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let creds = Credentials::builder(KAdm5Variant::$variant)
+                    .lifetime(Duration::from_secs(3600))
+                    .renewable_lifetime(Duration::from_secs(7200))
+                    .service("kadmin/admin")
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                assert!(creds.ccache_name().starts_with("MEMORY:"));
+                assert!(creds.end_time().is_some());
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_ccache(Some(creds.client_name()), Some(creds.ccache_name()))?;
+                kadmin.list_principals(None)?;
+                assert!(creds.needs_renewal(Duration::from_secs(7200)));
+                assert!(!creds.needs_renewal(Duration::from_secs(60)));
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn renew_expired() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let mut creds = Credentials::builder(KAdm5Variant::$variant)
+                    .lifetime(Duration::from_secs(2))
+                    .renewable_lifetime(Duration::from_secs(3600))
+                    .service("kadmin/admin")
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                std::thread::sleep(Duration::from_secs(3));
+                // Expired but still renewable, so new credentials are obtained with the password
+                assert!(
+                    creds
+                        .renew_till()
+                        .is_some_and(|renew_till| renew_till > chrono::Utc::now())
+                );
+                creds.renew()?;
+                assert!(!creds.needs_renewal(Duration::ZERO));
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_ccache(Some(creds.client_name()), Some(creds.ccache_name()))?;
+                kadmin.list_principals(None)?;
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn renew_with_password() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let password = realm.password("admin")?;
+                let mut creds = Credentials::builder(KAdm5Variant::$variant)
+                    .lifetime(Duration::from_secs(3600))
+                    .service("kadmin/admin")
+                    .with_password(&realm.admin_princ()?, &password)?;
+                assert!(!format!("{creds:?}").contains(&password));
+                // Not renewable, so new credentials are obtained with the password
+                creds.renew()?;
+                assert!(creds.end_time().is_some());
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_ccache(Some(creds.client_name()), Some(creds.ccache_name()))?;
+                kadmin.list_principals(None)?;
+                Ok(())
+            }
         }
     };
 }
@@ -82,6 +161,95 @@ macro_rules! gen_tests_local {
     };
 }
 
+macro_rules! gen_tests_timeout {
+    (remote $libname: ident, $variant:ident) => {
+        gen_tests_timeout!(@tests $libname, $variant, |realm: &K5Test| -> Result<KAdmin> {
+            Ok(KAdmin::builder(KAdm5Variant::$variant)
+                .timeout(Duration::from_secs(30))
+                .with_password(&realm.admin_princ()?, &realm.password("admin")?)?)
+        });
+    };
+
+    (local $libname: ident, $variant:ident) => {
+        gen_tests_timeout!(@tests $libname, $variant, |realm: &K5Test| -> Result<KAdmin> {
+            let db_args = DbArgs::builder()
+                .arg("dbname", Some(&format!("{}/db", realm.tmpdir()?)))
+                .build()?;
+            let mut params = Params::new()
+                .dbname(&format!("{}/db", realm.tmpdir()?))
+                .acl_file(&format!("{}/acl", realm.tmpdir()?))
+                .stash_file(&format!("{}/stash", realm.tmpdir()?));
+            #[cfg(any(mit_client, mit_server))]
+            {
+                params = params.dict_file(&format!("{}/dict", realm.tmpdir()?));
+            }
+            Ok(KAdmin::builder(KAdm5Variant::$variant)
+                .timeout(Duration::from_secs(30))
+                .db_args(db_args)
+                .params(params)
+                .with_local()?)
+        });
+    };
+
+    (@tests $libname: ident, $variant:ident, $connect:expr) => {
+        #[cfg($libname)]
+        mod $libname {
+            use std::{thread, time::Duration};
+
+            use anyhow::Result;
+            #[allow(unused_imports)]
+            use kadmin::{DbArgs, Error, KAdm5Variant, KAdminImpl, Params};
+            use serial_test::serial;
+
+            use super::*;
+            use crate::k5test::K5Test;
+
+            #[test]
+            #[serial]
+            fn timeout() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = ($connect)(&realm)?;
+                assert!(matches!(
+                    kadmin
+                        .with_timeout(Some(Duration::from_nanos(1)))
+                        .list_principals(None),
+                    Err(Error::Timeout)
+                ));
+                // The executor is rebuilt after a timeout
+                kadmin.list_principals(None)?;
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn timeout_queued() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = ($connect)(&realm)?;
+                // Operations queued behind the one that timed out are dropped with the executor,
+                // and must time out as well instead of failing with another error
+                let handles: Vec<_> = (0..8)
+                    .map(|i| {
+                        let kadmin = if i == 0 {
+                            kadmin.with_timeout(Some(Duration::from_nanos(1)))
+                        } else {
+                            kadmin.clone()
+                        };
+                        thread::spawn(move || kadmin.list_principals(None))
+                    })
+                    .collect();
+                for handle in handles {
+                    match handle.join().unwrap() {
+                        Ok(_) | Err(Error::Timeout) => (),
+                        Err(err) => panic!("unexpected error: {err:?}"),
+                    }
+                }
+                kadmin.list_principals(None)?;
+                Ok(())
+            }
+        }
+    };
+}
+
 mod direct {
     use kadmin::KAdmin;
 
@@ -98,4 +266,13 @@ mod sync {
     gen_tests_remote!(heimdal_client, HeimdalClient);
     gen_tests_local!(mit_server, MitServer);
     gen_tests_local!(heimdal_server, HeimdalServer);
+
+    mod timeout {
+        use super::KAdmin;
+
+        gen_tests_timeout!(remote mit_client, MitClient);
+        gen_tests_timeout!(remote heimdal_client, HeimdalClient);
+        gen_tests_timeout!(local mit_server, MitServer);
+        gen_tests_timeout!(local heimdal_server, HeimdalServer);
+    }
 }
@@ -0,0 +1,167 @@
+//! Test layers
+mod k5test;
+mod util;
+
+macro_rules! gen_tests {
+    ($libname:ident, $variant:ident) => {
+        #[cfg($libname)]
+        mod $libname {
+            use std::sync::{Arc, Mutex};
+
+            use anyhow::Result;
+            use kadmin::{
+                KAdm5Variant, KAdminImpl, LayerBuilder, Principal,
+                layer::{AuditLayer, MetricsLayer, RetryLayer},
+            };
+            use serial_test::serial;
+
+            use super::{
+                super::{k5test::K5Test, util::random_string},
+                *,
+            };
+
+            #[test]
+            #[serial]
+            fn layers() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let events = Arc::new(Mutex::new(Vec::new()));
+                let sink = Arc::clone(&events);
+                let metrics = MetricsLayer::new();
+                let kadmin: Box<dyn KAdminImpl> = Box::new(
+                    LayerBuilder::new()
+                        .layer(AuditLayer::new(move |event| {
+                            sink.lock().unwrap().push(event.clone())
+                        }))
+                        .layer(metrics.clone())
+                        .layer(RetryLayer::new(3))
+                        .build(kadmin),
+                );
+
+                let princname = random_string(16);
+                let princ = Principal::builder(&princname).create(&kadmin)?;
+                princ.delete(&kadmin)?;
+                assert!(!kadmin.principal_exists(&princname)?);
+
+                let events = events.lock().unwrap();
+                assert_eq!(
+                    events
+                        .iter()
+                        .map(|event| event.operation)
+                        .collect::<Vec<_>>(),
+                    vec!["add_principal", "delete_principal"]
+                );
+                assert!(events.iter().all(|event| event.error.is_none()));
+                let metrics = metrics.snapshot()?;
+                assert_eq!(metrics["get_principal"].calls, 1);
+                assert_eq!(metrics["principal_exists"].calls, 1);
+                Ok(())
+            }
+
+            #[cfg(feature = "tracing")]
+            #[test]
+            #[serial]
+            fn tracing() -> Result<()> {
+                use kadmin::layer::TracingLayer;
+
+                use super::super::util::capture::Capture;
+
+                let capture = Capture::default();
+                let _subscriber = tracing::subscriber::set_default(capture.subscriber());
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let kadmin = LayerBuilder::new().layer(TracingLayer).build(kadmin);
+
+                let princname = random_string(16);
+                kadmin.add_principal(&Principal::builder(&princname))?;
+                kadmin.delete_principal(&princname)?;
+                assert!(kadmin.delete_principal(&princname).is_err());
+
+                let spans: Vec<_> = capture
+                    .spans()
+                    .into_iter()
+                    .filter(|(_, fields)| fields.get("target") == Some(&princname))
+                    .collect();
+                assert_eq!(spans.len(), 3);
+                for (name, fields) in &spans {
+                    assert_eq!(name, "kadmin");
+                    assert_eq!(fields["variant"], stringify!($variant));
+                }
+                assert_eq!(spans[0].1["operation"], "add_principal");
+                assert_eq!(spans[0].1["outcome"], "ok");
+                assert!(!spans[0].1.contains_key("error.kind"));
+                assert_eq!(spans[2].1["operation"], "delete_principal");
+                assert_eq!(spans[2].1["outcome"], "error");
+                assert_eq!(spans[2].1["error.kind"], "UnknownPrincipal");
+
+                let events: Vec<_> = capture
+                    .events()
+                    .into_iter()
+                    .filter(|event| {
+                        event
+                            .span
+                            .as_ref()
+                            .is_some_and(|(_, fields)| fields.get("target") == Some(&princname))
+                    })
+                    .collect();
+                let durations: Vec<_> = events
+                    .iter()
+                    .filter_map(|event| event.fields.get("histogram.kadmin.operation.duration"))
+                    .collect();
+                assert_eq!(durations.len(), 3);
+                assert!(
+                    durations
+                        .iter()
+                        .all(|duration| duration.parse::<f64>().is_ok())
+                );
+                let errors: Vec<_> = events
+                    .iter()
+                    .filter(|event| {
+                        event
+                            .fields
+                            .contains_key("monotonic_counter.kadmin.operation.errors")
+                    })
+                    .collect();
+                assert_eq!(errors.len(), 1);
+                assert_eq!(
+                    errors[0].fields["monotonic_counter.kadmin.operation.errors"],
+                    "1"
+                );
+                assert_eq!(errors[0].fields["operation"], "delete_principal");
+
+                // The sync executor records how long operations waited in the caller span
+                let waits: Vec<_> = spans
+                    .iter()
+                    .filter_map(|(_, fields)| fields.get("queue.wait"))
+                    .collect();
+                assert_eq!(waits.len(), if QUEUED { 3 } else { 0 });
+                assert!(waits.iter().all(|wait| wait.parse::<f64>().is_ok()));
+                Ok(())
+            }
+        }
+    };
+}
+
+mod direct {
+    use kadmin::KAdmin;
+
+    /// Whether operations go through the sync executor queue
+    #[allow(dead_code)]
+    const QUEUED: bool = false;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
+
+mod sync {
+    use kadmin::sync::KAdmin;
+
+    /// Whether operations go through the sync executor queue
+    #[allow(dead_code)]
+    const QUEUED: bool = true;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
@@ -7,7 +7,9 @@ macro_rules! gen_tests {
         #[cfg($libname)]
         mod $libname {
             use anyhow::Result;
-            use kadmin::{KAdm5Variant, KAdminImpl, Policy};
+            use kadmin::{
+                KAdm5Variant, KAdminApiVersion, KAdminErrorKind, KAdminImpl, Policy, Principal,
+            };
             use serial_test::serial;
 
             use super::{
@@ -80,6 +82,70 @@ macro_rules! gen_tests {
                 assert_eq!(policy.password_min_length(), 42);
                 Ok(())
             }
+
+            #[test]
+            #[serial]
+            fn rename_policy() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .api_version(KAdminApiVersion::Version4)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let old_polname = random_string(16);
+                let new_polname = random_string(16);
+                Policy::builder(&old_polname)
+                    .password_min_length(12)
+                    .password_max_fail(3)
+                    .create(&kadmin)?;
+                let princname = random_string(16);
+                Principal::builder(&princname)
+                    .policy(Some(&old_polname))
+                    .create(&kadmin)?;
+
+                kadmin.rename_policy(&old_polname, &new_polname)?;
+                assert!(!kadmin.policy_exists(&old_polname)?);
+                let policy = kadmin.get_policy(&new_polname)?.unwrap();
+                assert_eq!(policy.password_min_length(), 12);
+                assert_eq!(policy.password_max_fail(), 3);
+                let princ = kadmin.get_principal(&princname)?.unwrap();
+                assert_eq!(princ.policy(), Some(new_polname.as_str()));
+
+                let err = kadmin
+                    .rename_policy(&old_polname, &random_string(16))
+                    .unwrap_err();
+                assert_eq!(err.kind(), Some(KAdminErrorKind::UnknownPolicy));
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn policy_usage() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let used_polname = random_string(16);
+                let unused_polname = random_string(16);
+                Policy::builder(&used_polname).create(&kadmin)?;
+                Policy::builder(&unused_polname).create(&kadmin)?;
+                let princname = random_string(16);
+                let princ = Principal::builder(&princname)
+                    .policy(Some(&used_polname))
+                    .create(&kadmin)?;
+
+                let usage = kadmin.policy_usage()?;
+                assert_eq!(usage.principals_using(&used_polname), [princ.name()]);
+                assert!(!usage.is_in_use(&unused_polname));
+                assert_eq!(
+                    kadmin.principals_using_policy(&used_polname)?,
+                    vec![princ.name().to_owned()]
+                );
+
+                let err = kadmin.delete_policy_if_unused(&used_polname).unwrap_err();
+                assert_eq!(err.kind(), Some(KAdminErrorKind::PolicyInUse));
+                assert!(kadmin.policy_exists(&used_polname)?);
+                kadmin.delete_policy_if_unused(&unused_polname)?;
+                assert!(!kadmin.policy_exists(&unused_polname)?);
+                Ok(())
+            }
         }
     };
 }
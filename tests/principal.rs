@@ -7,7 +7,10 @@ macro_rules! gen_tests {
         #[cfg($libname)]
         mod $libname {
             use anyhow::Result;
-            use kadmin::{KAdm5Variant, KAdminImpl, Principal, sys};
+            use kadmin::{
+                Error, KAdm5Variant, KAdminErrorKind, KAdminImpl, PasswordGenerator,
+                PasswordQualityRule, Policy, Principal, principal::PrincipalBuilderKey, sys,
+            };
             use serial_test::serial;
 
             use super::{
@@ -100,6 +103,105 @@ macro_rules! gen_tests {
                 Ok(())
             }
 
+            #[test]
+            #[serial]
+            fn error_kinds() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let err = kadmin
+                    .delete_principal(&format!("nonexistent@{}", &realm.realm_name()?))
+                    .unwrap_err();
+                assert_eq!(err.kind(), Some(KAdminErrorKind::UnknownPrincipal));
+                assert!(err.is_not_found());
+                let err = Principal::builder(&realm.user_princ()?)
+                    .create(&kadmin)
+                    .unwrap_err();
+                assert_eq!(err.kind(), Some(KAdminErrorKind::DuplicatePrincipal));
+                assert!(err.is_already_exists());
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn password_quality_error() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let polname = random_string(16);
+                Policy::builder(&polname)
+                    .password_min_length(12)
+                    .create(&kadmin)?;
+                let princname = random_string(16);
+                let princ = Principal::builder(&princname)
+                    .policy(Some(&polname))
+                    .key(&PrincipalBuilderKey::Password(random_string(16)))
+                    .create(&kadmin)?;
+                let err = princ
+                    .change_password(&kadmin, "short", None, None)
+                    .unwrap_err();
+                assert!(err.is_password_rejected());
+                assert_eq!(err.kind(), Some(KAdminErrorKind::PasswordTooShort));
+                let Error::PasswordQuality(details) = err else {
+                    panic!("expected a password quality error");
+                };
+                assert_eq!(details.rule, PasswordQualityRule::MinLength);
+                assert_eq!(details.policy.as_deref(), Some(polname.as_str()));
+                assert_eq!(details.min_length, Some(12));
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn password_quality_error_default_policy() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                Policy::builder("default")
+                    .password_min_length(12)
+                    .create(&kadmin)?;
+                let err = Principal::builder(&random_string(16))
+                    .key(&PrincipalBuilderKey::Password("short".to_owned()))
+                    .create(&kadmin)
+                    .unwrap_err();
+                let Error::PasswordQuality(details) = err else {
+                    panic!("expected a password quality error");
+                };
+                assert_eq!(details.policy.as_deref(), Some("default"));
+                assert_eq!(details.min_length, Some(12));
+                kadmin.delete_policy("default")?;
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn reset_passwords() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let polname = random_string(16);
+                let policy = Policy::builder(&polname)
+                    .password_min_length(32)
+                    .password_min_classes(5)
+                    .create(&kadmin)?;
+                let princnames: Vec<String> = (0..3).map(|_| random_string(16)).collect();
+                for princname in &princnames {
+                    Principal::builder(princname)
+                        .policy(Some(&polname))
+                        .create(&kadmin)?;
+                }
+                let generator = PasswordGenerator::from_policy(&policy);
+                let results = generator.reset_passwords(&kadmin, &princnames);
+                assert_eq!(results.len(), princnames.len());
+                for ((princname, password), expected) in results.into_iter().zip(&princnames) {
+                    assert_eq!(&princname, expected);
+                    let password = password?;
+                    assert_eq!(password.len(), 32);
+                    policy.validate_password(&password, Some(&princname), None)?;
+                }
+                Ok(())
+            }
+
             #[test]
             #[serial]
             fn modify_principal() -> Result<()> {
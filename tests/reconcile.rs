@@ -0,0 +1,106 @@
+//! Test realm reconciliation
+#![cfg(feature = "reconcile")]
+mod k5test;
+mod util;
+
+macro_rules! gen_tests {
+    ($libname:ident, $variant:ident) => {
+        #[cfg($libname)]
+        mod $libname {
+            use anyhow::Result;
+            use kadmin::{KAdm5Variant, KAdminImpl, reconcile::DesiredState};
+            use serial_test::serial;
+
+            use super::{
+                super::{k5test::K5Test, util::random_string},
+                *,
+            };
+
+            #[test]
+            #[serial]
+            fn plan_apply() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let prefix = random_string(8);
+                let state = DesiredState::from_toml(&format!(
+                    r#"
+                    managed = ["{prefix}-*"]
+                    prune = true
+
+                    [policies.{prefix}-policy]
+                    password_min_length = 12
+                    password_max_fail = 3
+                    password_lockout_duration = 600
+                    allowed_keysalts = "aes256-cts:normal"
+
+                    [principals.{prefix}-svc]
+                    policy = "{prefix}-policy"
+                    max_life = 3600
+
+                    [principals.{prefix}-svc.strings]
+                    session_enctypes = "aes256-cts"
+                    "#
+                ))?;
+
+                let plan = state.plan(&kadmin)?;
+                assert_eq!(plan.actions().len(), 3);
+                plan.apply(&kadmin)?;
+                let policy = kadmin.get_policy(&format!("{prefix}-policy"))?.unwrap();
+                assert_eq!(policy.password_max_fail(), 3);
+                assert_eq!(
+                    policy.password_lockout_duration(),
+                    Some(std::time::Duration::from_secs(600))
+                );
+                assert_eq!(policy.allowed_keysalts().unwrap().keysalts.len(), 1);
+                let princ = kadmin.get_principal(&format!("{prefix}-svc"))?.unwrap();
+                assert_eq!(princ.policy(), Some(format!("{prefix}-policy").as_str()));
+                assert_eq!(
+                    kadmin.principal_get_strings(&format!("{prefix}-svc"))?["session_enctypes"],
+                    "aes256-cts"
+                );
+                assert!(state.plan(&kadmin)?.is_empty());
+
+                let extra = format!("{prefix}-extra");
+                kadmin.add_principal(&kadmin::Principal::builder(&extra))?;
+                let plan = state.plan(&kadmin)?;
+                assert_eq!(plan.actions().len(), 1);
+                plan.apply(&kadmin)?;
+                assert!(!kadmin.principal_exists(&extra)?);
+                Ok(())
+            }
+
+            #[test]
+            #[serial]
+            fn out_of_scope() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let state = DesiredState::from_toml(
+                    r#"
+                    managed = ["svc/*"]
+                    [principals.alice]
+                    "#,
+                )?;
+                assert!(state.plan(&kadmin).is_err());
+                let state = DesiredState::from_toml("prune = true")?;
+                assert!(state.plan(&kadmin).is_err());
+                Ok(())
+            }
+        }
+    };
+}
+
+mod direct {
+    use kadmin::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
+
+mod sync {
+    use kadmin::sync::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
@@ -0,0 +1,80 @@
+//! Test recording and replaying kadm5 sessions
+#![cfg(feature = "replay")]
+mod k5test;
+mod util;
+
+macro_rules! gen_tests {
+    ($libname:ident, $variant:ident) => {
+        #[cfg($libname)]
+        mod $libname {
+            use anyhow::Result;
+            use kadmin::{
+                Error, KAdm5Variant, KAdminImpl, Principal,
+                replay::{Recorder, Replayer},
+            };
+            use serial_test::serial;
+
+            use super::{
+                super::{k5test::K5Test, util::random_string},
+                *,
+            };
+
+            #[test]
+            #[serial]
+            fn record_replay() -> Result<()> {
+                let realm = K5Test::new(KAdm5Variant::$variant)?;
+                let kadmin = KAdmin::builder(KAdm5Variant::$variant)
+                    .with_password(&realm.admin_princ()?, &realm.password("admin")?)?;
+                let path = std::env::temp_dir().join(format!("{}.jsonl", random_string(16)));
+                let princname = random_string(16);
+
+                let recorder = Recorder::new(kadmin, &path)?;
+                let princ = Principal::builder(&princname).create(&recorder)?;
+                recorder.principal_set_string(&princname, "key", Some("value"))?;
+                recorder.delete_principal(&princname)?;
+                assert!(
+                    recorder
+                        .delete_principal(&princname)
+                        .unwrap_err()
+                        .is_not_found()
+                );
+                drop(recorder);
+
+                let replayer = Replayer::open(&path)?;
+                std::fs::remove_file(&path)?;
+                assert_eq!(replayer.variant(), KAdm5Variant::$variant);
+                let replayed = Principal::builder(&princname).create(&replayer)?;
+                assert_eq!(replayed.name(), princ.name());
+                assert_eq!(replayed.kvno(), princ.kvno());
+                assert!(matches!(
+                    replayer.principal_set_string(&princname, "key", Some("other")),
+                    Err(Error::ReplayDivergence { .. })
+                ));
+                replayer.principal_set_string(&princname, "key", Some("value"))?;
+                replayer.delete_principal(&princname)?;
+                assert!(
+                    replayer
+                        .delete_principal(&princname)
+                        .unwrap_err()
+                        .is_not_found()
+                );
+                assert_eq!(replayer.remaining()?, 0);
+                Ok(())
+            }
+        }
+    };
+}
+
+mod direct {
+    use kadmin::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
+
+mod sync {
+    use kadmin::sync::KAdmin;
+
+    gen_tests!(mit_client, MitClient);
+    gen_tests!(mit_server, MitServer);
+}
@@ -9,3 +9,122 @@ pub(crate) fn random_string(len: usize) -> String {
         .map(char::from)
         .collect()
 }
+
+/// Capture `tracing` spans and events to assert on them
+#[cfg(feature = "tracing")]
+#[allow(dead_code)]
+pub(crate) mod capture {
+    use std::{
+        collections::HashMap,
+        fmt::Debug,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        Event, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+    };
+    use tracing_subscriber::{
+        Layer,
+        layer::{Context, SubscriberExt},
+        registry::LookupSpan,
+    };
+
+    /// Fields of a span or event, formatted with [`Debug`] except for strings
+    pub(crate) type Fields = HashMap<String, String>;
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_owned(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name().to_owned(), format!("{value:?}"));
+        }
+    }
+
+    /// Index of a span in [`Capture::spans`], stored in the span extensions
+    struct SpanIndex(usize);
+
+    /// Event captured by [`Capture`]
+    #[derive(Clone, Debug)]
+    pub(crate) struct CapturedEvent {
+        pub(crate) fields: Fields,
+        /// Name and fields of the span the event was emitted in
+        pub(crate) span: Option<(String, Fields)>,
+    }
+
+    /// Layer keeping every span and event in memory
+    #[derive(Clone, Default)]
+    pub(crate) struct Capture {
+        spans: Arc<Mutex<Vec<(String, Fields)>>>,
+        events: Arc<Mutex<Vec<CapturedEvent>>>,
+    }
+
+    impl Capture {
+        /// Subscriber feeding this [`Capture`], to be set as the default one of a test with
+        /// [`tracing::subscriber::set_default`]
+        ///
+        /// Spans opened on the test thread are captured even when they are entered or recorded
+        /// on executor threads.
+        pub(crate) fn subscriber(&self) -> impl Subscriber + Send + Sync + 'static {
+            tracing_subscriber::registry().with(self.clone())
+        }
+
+        /// Name and fields of captured spans
+        pub(crate) fn spans(&self) -> Vec<(String, Fields)> {
+            self.spans.lock().unwrap().clone()
+        }
+
+        /// Captured events
+        pub(crate) fn events(&self) -> Vec<CapturedEvent> {
+            self.events.lock().unwrap().clone()
+        }
+
+        fn index<S>(id: &Id, ctx: &Context<'_, S>) -> Option<usize>
+        where S: Subscriber + for<'a> LookupSpan<'a> {
+            let span = ctx.span(id)?;
+            let extensions = span.extensions();
+            extensions.get::<SpanIndex>().map(|index| index.0)
+        }
+    }
+
+    impl<S> Layer<S> for Capture
+    where S: Subscriber + for<'a> LookupSpan<'a>
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = Fields::new();
+            attrs.record(&mut Visitor(&mut fields));
+            let index = {
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((attrs.metadata().name().to_owned(), fields));
+                spans.len() - 1
+            };
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(SpanIndex(index));
+            }
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            if let Some(index) = Self::index(id, &ctx) {
+                values.record(&mut Visitor(&mut self.spans.lock().unwrap()[index].1));
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let mut fields = Fields::new();
+            event.record(&mut Visitor(&mut fields));
+            let span = ctx
+                .event_span(event)
+                .and_then(|span| Self::index(&span.id(), &ctx))
+                .map(|index| self.spans.lock().unwrap()[index].clone());
+            self.events
+                .lock()
+                .unwrap()
+                .push(CapturedEvent { fields, span });
+        }
+    }
+}